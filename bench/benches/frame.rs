@@ -0,0 +1,48 @@
+//! Benchmarks of the per-frame hot paths: checksum, PID calculation and signal decoding
+//!
+//! Run with `cargo bench` in this directory.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use lin_bus::frame::Frame;
+use lin_bus::{checksum, classic_checksum, PID};
+
+const DATA: [u8; 8] = [0xFF, 0x01, 0x80, 0x7F, 0x55, 0xAA, 0x00, 0xFE];
+
+fn bench_checksum(c: &mut Criterion) {
+    let pid = PID::TABLE[0x10];
+    c.bench_function("checksum 8 bytes", |b| {
+        b.iter(|| checksum(black_box(pid), black_box(&DATA)))
+    });
+    c.bench_function("checksum 2 bytes", |b| {
+        b.iter(|| checksum(black_box(pid), black_box(&DATA[..2])))
+    });
+    c.bench_function("classic checksum 8 bytes", |b| {
+        b.iter(|| classic_checksum(black_box(&DATA)))
+    });
+}
+
+fn bench_pid(c: &mut Criterion) {
+    c.bench_function("PID try_from_id", |b| {
+        b.iter(|| PID::try_from_id(black_box(0x2A)))
+    });
+    c.bench_function("PID new", |b| b.iter(|| PID::new(black_box(0x6A))));
+}
+
+fn bench_frame(c: &mut Criterion) {
+    let pid = PID::TABLE[0x10];
+    let frame = Frame::try_from_data(pid, &DATA).expect("valid frame");
+    c.bench_function("decode u16", |b| {
+        b.iter(|| black_box(&frame).try_decode::<u16>(black_box(5), 11))
+    });
+    c.bench_function("decode raw 64 bits", |b| {
+        b.iter(|| black_box(&frame).try_decode_raw(0, 64))
+    });
+    c.bench_function("from_data 8 bytes", |b| {
+        b.iter(|| Frame::try_from_data(black_box(pid), black_box(&DATA)))
+    });
+}
+
+criterion_group!(benches, bench_checksum, bench_pid, bench_frame);
+criterion_main!(benches);
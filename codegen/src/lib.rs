@@ -0,0 +1,310 @@
+//! Generates Rust source code from LDF files, intended to be used from build scripts:
+//!
+//! ```no_run
+//! // build.rs
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! lin_bus_codegen::generate_file("cluster.ldf", format!("{}/cluster.rs", out_dir)).unwrap();
+//! ```
+//!
+//! and then include the generated code with
+//! `include!(concat!(env!("OUT_DIR"), "/cluster.rs"));`.
+//!
+//! The generated code contains a `pid` module with the PID of every frame, a typed struct per
+//! unconditional frame with signal descriptors and accessors, and a `schedule` module with the
+//! schedule tables as slices of PIDs and delays.
+
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::{fs, io};
+
+use lin_bus::ldf::{Ldf, SignalValue, UnconditionalFrame};
+
+/// Convert an LDF identifier to `snake_case`
+fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if previous_lower {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+            previous_lower = false;
+        } else {
+            result.push(c);
+            previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        }
+    }
+    result
+}
+
+/// Convert an LDF identifier to `SCREAMING_SNAKE_CASE`
+fn screaming_snake_case(name: &str) -> String {
+    snake_case(name).to_ascii_uppercase()
+}
+
+/// Convert an LDF identifier to `CamelCase`
+fn camel_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut upper = true;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            result.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn generate_pids(ldf: &Ldf, out: &mut String) -> fmt::Result {
+    writeln!(out, "/// Protected identifiers of all frames")?;
+    writeln!(out, "pub mod pid {{")?;
+    for frame in &ldf.frames {
+        writeln!(
+            out,
+            "    pub const {}: lin_bus::PID = lin_bus::PID::from_id(0x{:02X});",
+            screaming_snake_case(&frame.name),
+            frame.id
+        )?;
+    }
+    for frame in &ldf.event_triggered_frames {
+        writeln!(
+            out,
+            "    pub const {}: lin_bus::PID = lin_bus::PID::from_id(0x{:02X});",
+            screaming_snake_case(&frame.name),
+            frame.id
+        )?;
+    }
+    writeln!(out, "}}")
+}
+
+fn generate_frame(ldf: &Ldf, frame: &UnconditionalFrame, out: &mut String) -> fmt::Result {
+    let name = camel_case(&frame.name);
+    writeln!(out)?;
+    writeln!(
+        out,
+        "/// Frame `{}` published by `{}`",
+        frame.name, frame.publisher
+    )?;
+    writeln!(out, "#[derive(Debug, PartialEq, Eq)]")?;
+    writeln!(out, "pub struct {}(pub lin_bus::Frame);", name)?;
+    writeln!(out)?;
+    writeln!(out, "impl {} {{", name)?;
+    writeln!(out, "    pub const ID: u8 = 0x{:02X};", frame.id)?;
+    writeln!(
+        out,
+        "    pub const PID: lin_bus::PID = lin_bus::PID::from_id(0x{:02X});",
+        frame.id
+    )?;
+    writeln!(out, "    pub const LENGTH: usize = {};", frame.length)?;
+    for signal in &frame.signals {
+        let size = ldf.signal(&signal.name).map_or(8, |s| s.size);
+        writeln!(
+            out,
+            "    pub const {}: lin_bus::Signal = lin_bus::Signal::new({}, {});",
+            screaming_snake_case(&signal.name),
+            signal.offset,
+            size
+        )?;
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "    /// Create the frame with the initial signal values"
+    )?;
+    writeln!(out, "    #[allow(unused_mut)]")?;
+    writeln!(out, "    pub fn new() -> {} {{", name)?;
+    writeln!(
+        out,
+        "        let mut frame = lin_bus::Frame::from_data(Self::PID, &[0u8; Self::LENGTH]);"
+    )?;
+    for signal in &frame.signals {
+        let init_value = ldf.signal(&signal.name).map(|s| &s.init_value);
+        match init_value {
+            Some(SignalValue::Scalar(value)) if *value != 0 => writeln!(
+                out,
+                "        frame.write_signal(&Self::{}, {});",
+                screaming_snake_case(&signal.name),
+                value
+            )?,
+            Some(SignalValue::ByteArray(bytes)) => {
+                for (i, byte) in bytes.iter().enumerate().filter(|(_, b)| **b != 0) {
+                    writeln!(
+                        out,
+                        "        frame.write_signal(&lin_bus::Signal::new({}, 8), {});",
+                        usize::from(signal.offset) + i * 8,
+                        byte
+                    )?;
+                }
+            }
+            _ => {}
+        }
+    }
+    writeln!(out, "        {}(frame)", name)?;
+    writeln!(out, "    }}")?;
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "    /// Wrap a received frame, returns `None` if the PID or length does not match"
+    )?;
+    writeln!(
+        out,
+        "    pub fn from_frame(frame: lin_bus::Frame) -> Option<{}> {{",
+        name
+    )?;
+    writeln!(
+        out,
+        "        if frame.get_pid() == Self::PID && frame.get_data().len() == Self::LENGTH {{"
+    )?;
+    writeln!(out, "            Some({}(frame))", name)?;
+    writeln!(out, "        }} else {{")?;
+    writeln!(out, "            None")?;
+    writeln!(out, "        }}")?;
+    writeln!(out, "    }}")?;
+
+    for signal in &frame.signals {
+        let constant = screaming_snake_case(&signal.name);
+        let accessor = snake_case(&signal.name);
+        writeln!(out)?;
+        writeln!(out, "    /// Raw value of signal `{}`", signal.name)?;
+        writeln!(out, "    pub fn {}(&self) -> u64 {{", accessor)?;
+        writeln!(out, "        self.0.read_signal(&Self::{})", constant)?;
+        writeln!(out, "    }}")?;
+        writeln!(out)?;
+        writeln!(out, "    /// Set the raw value of signal `{}`", signal.name)?;
+        writeln!(out, "    pub fn set_{}(&mut self, value: u64) {{", accessor)?;
+        writeln!(
+            out,
+            "        self.0.write_signal(&Self::{}, value)",
+            constant
+        )?;
+        writeln!(out, "    }}")?;
+    }
+    writeln!(out, "}}")?;
+
+    writeln!(out)?;
+    writeln!(out, "impl Default for {} {{", name)?;
+    writeln!(out, "    fn default() -> {} {{", name)?;
+    writeln!(out, "        {}::new()", name)?;
+    writeln!(out, "    }}")?;
+    writeln!(out, "}}")
+}
+
+fn generate_schedule_tables(ldf: &Ldf, out: &mut String) -> fmt::Result {
+    writeln!(out)?;
+    writeln!(
+        out,
+        "/// Schedule tables as slices of PIDs with the delay in ms until the next slot"
+    )?;
+    writeln!(out, "pub mod schedule {{")?;
+    for table in &ldf.schedule_tables {
+        writeln!(
+            out,
+            "    pub const {}: &[(lin_bus::PID, f32)] = &[",
+            screaming_snake_case(&table.name)
+        )?;
+        for (pid, delay) in table.slots(ldf) {
+            writeln!(
+                out,
+                "        (lin_bus::PID::from_id(0x{:02X}), {:?}),",
+                pid.get_id(),
+                delay
+            )?;
+        }
+        writeln!(out, "    ];")?;
+    }
+    writeln!(out, "}}")
+}
+
+/// Generate Rust source code for the given LDF
+pub fn generate(ldf: &Ldf) -> String {
+    let mut out = String::new();
+    // Writing into a `String` never fails
+    let _ = (|| -> fmt::Result {
+        writeln!(out, "// Generated by lin-bus-codegen, do not edit")?;
+        writeln!(out)?;
+        generate_pids(ldf, &mut out)?;
+        for frame in &ldf.frames {
+            generate_frame(ldf, frame, &mut out)?;
+        }
+        generate_schedule_tables(ldf, &mut out)
+    })();
+    out
+}
+
+/// Parse the LDF file at `input` and write the generated Rust code to `output`
+pub fn generate_file<P, Q>(input: P, output: Q) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let text = fs::read_to_string(input)?;
+    let ldf = lin_bus::ldf::parse(&text).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line {}: {}", e.line, e.message),
+        )
+    })?;
+    fs::write(output, generate(&ldf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LDF: &str = r#"
+LIN_description_file;
+LIN_protocol_version = "2.1";
+LIN_language_version = "2.1";
+LIN_speed = 19.2 kbps;
+Nodes {
+  Master: Gateway, 5 ms, 0.1 ms;
+  Slaves: Sensor;
+}
+Signals {
+  Temperature: 12, 0, Sensor, Gateway;
+  SensorStatus: 4, 3, Sensor, Gateway;
+}
+Frames {
+  SensorFrame: 0x10, Sensor, 2 {
+    Temperature, 0;
+    SensorStatus, 12;
+  }
+}
+Schedule_tables {
+  Normal {
+    SensorFrame delay 10 ms;
+    MasterReq delay 20 ms;
+  }
+}
+"#;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(snake_case("SensorFrame"), "sensor_frame");
+        assert_eq!(snake_case("Temp_Sensor1Value"), "temp_sensor1_value");
+        assert_eq!(screaming_snake_case("SensorFrame"), "SENSOR_FRAME");
+        assert_eq!(camel_case("sensor_frame"), "SensorFrame");
+    }
+
+    #[test]
+    fn test_generate() {
+        let code = generate(&lin_bus::ldf::parse(LDF).unwrap());
+        assert!(
+            code.contains("pub const SENSOR_FRAME: lin_bus::PID = lin_bus::PID::from_id(0x10);")
+        );
+        assert!(code.contains("pub struct SensorFrame(pub lin_bus::Frame);"));
+        assert!(code
+            .contains("pub const SENSOR_STATUS: lin_bus::Signal = lin_bus::Signal::new(12, 4);"));
+        assert!(code.contains("frame.write_signal(&Self::SENSOR_STATUS, 3);"));
+        assert!(code.contains("pub fn set_temperature(&mut self, value: u64)"));
+        assert!(code.contains("pub const NORMAL: &[(lin_bus::PID, f32)] = &["));
+        assert!(code.contains("(lin_bus::PID::from_id(0x3C), 20.0),"));
+    }
+}
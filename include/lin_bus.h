@@ -0,0 +1,47 @@
+/*
+ * C declarations of the `ffi` module of the lin-bus crate, see its documentation.
+ */
+#ifndef LIN_BUS_H
+#define LIN_BUS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define LIN_OK 0
+#define LIN_ERROR_NULL_POINTER (-1)
+#define LIN_ERROR_INVALID_ID (-2)
+#define LIN_ERROR_INVALID_PARITY (-3)
+#define LIN_ERROR_INVALID_LENGTH (-4)
+#define LIN_ERROR_INVALID_CHECKSUM_MODEL (-5)
+#define LIN_ERROR_CHECKSUM (-6)
+
+#define LIN_CHECKSUM_CLASSIC 0
+#define LIN_CHECKSUM_ENHANCED 1
+
+int32_t lin_pid_from_id(uint8_t id, uint8_t *pid);
+int32_t lin_id_from_pid(uint8_t pid, uint8_t *id);
+
+int32_t lin_checksum(uint8_t pid, uint8_t model, const uint8_t *data, size_t length,
+                     uint8_t *checksum);
+/* `response` has room for 9 bytes */
+int32_t lin_frame_build(uint8_t pid, uint8_t model, const uint8_t *data, size_t length,
+                        uint8_t *response, size_t *response_length);
+int32_t lin_frame_check(uint8_t pid, uint8_t model, const uint8_t *response, size_t length);
+
+/* Master request frames, `frame` has room for 8 bytes. The read by identifier and go-to-sleep
+ * requests require the `diagnostic` feature. */
+int32_t lin_diag_single_frame(uint8_t nad, uint8_t sid, const uint8_t *data, size_t length,
+                              uint8_t *frame);
+int32_t lin_diag_read_by_identifier(uint8_t nad, uint8_t identifier, uint16_t supplier_id,
+                                    uint16_t function_id, uint8_t *frame);
+int32_t lin_diag_go_to_sleep(uint8_t *frame);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LIN_BUS_H */
@@ -0,0 +1,85 @@
+//! Calls the API of `lin-bus` with the `panic-free` feature on inputs unknown to the compiler.
+//! The panic handler references an undefined symbol, so linking fails if any panic remains in
+//! the optimized binary.
+#![no_std]
+#![no_main]
+
+use core::panic::PanicInfo;
+use core::ptr::{read_volatile, write_volatile};
+use lin_bus::frame::diagnostic::{ProductId, SerialNumber};
+use lin_bus::frame::transport::{try_create_single_frame, NAD, PCI, SID};
+use lin_bus::{checksum, Frame, Signal, PID};
+
+// The C library starts the process and calls `main`
+#[link(name = "c")]
+extern "C" {}
+
+extern "C" {
+    /// Not defined anywhere
+    fn lin_bus_panic_free_failed() -> !;
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    unsafe { lin_bus_panic_free_failed() }
+}
+
+// Referenced by the unwind tables of the precompiled `core`, never called with `panic = "abort"`
+#[no_mangle]
+extern "C" fn rust_eh_personality() {}
+
+static mut INPUT: u8 = 0;
+static mut OUTPUT: u64 = 0;
+
+fn input() -> u8 {
+    unsafe { read_volatile(core::ptr::addr_of!(INPUT)) }
+}
+
+fn output(value: u64) {
+    unsafe { write_volatile(core::ptr::addr_of_mut!(OUTPUT), value) }
+}
+
+#[no_mangle]
+pub extern "C" fn main(_argc: i32, _argv: *const *const u8) -> i32 {
+    let mut data = [0u8; 16];
+    for byte in data.iter_mut() {
+        *byte = input();
+    }
+    let length = usize::from(input());
+    let data = data.get(..length).unwrap_or(&[]);
+
+    let pid = match PID::try_from_id(input()) {
+        Ok(pid) => pid,
+        Err(_) => return 1,
+    };
+    output(u64::from(checksum(pid, data)));
+
+    if let Ok(mut frame) = Frame::try_from_data(pid, data) {
+        output(frame.get_data().len() as u64);
+        let signal = Signal::new(input(), input());
+        if let Ok(raw) = frame.try_read_signal(&signal) {
+            output(raw);
+        }
+        if frame.try_write_signal(&signal, u64::from(input())).is_ok() {
+            output(frame.get_data_with_checksum().len() as u64);
+        }
+        if let Ok(value) = frame.try_decode::<u16>(length, usize::from(input())) {
+            output(u64::from(value));
+        }
+        let _ = frame.try_encode(usize::from(input()), length, u64::from(input()));
+    }
+
+    if let Ok(pci) = PCI::try_new_sf(input()) {
+        output(u64::from(pci.get_length()));
+    }
+    if let Ok(frame) = try_create_single_frame(pid, NAD(input()), SID(input()), data) {
+        output(frame.get_data().len() as u64);
+    }
+    if let Ok(product_id) = ProductId::try_from_bytes(data) {
+        output(u64::from(product_id.function_id));
+    }
+    if let Ok(serial_number) = SerialNumber::try_from_bytes(data) {
+        output(u64::from(serial_number.0));
+    }
+    0
+}
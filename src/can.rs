@@ -0,0 +1,441 @@
+//! Gateway between LIN and CAN
+//!
+//! `CanGateway` forwards frames between a LIN cluster and a CAN bus according to a table of
+//! `FrameMapping`s. A mapping connects a LIN frame and a CAN frame in one `Direction` and
+//! either copies the data as is or repacks individual signals with `SignalMapping`s, e.g. to
+//! move a signal to another offset, byte order or scaling. The `Forwarding` of a mapping
+//! decides whether the target frame is sent on every reception of the source frame, on a
+//! change of the mapped data or periodically with the last received data.
+//!
+//! Received frames are passed to `receive_lin` and `receive_can`, `poll` returns the next
+//! frame due on either bus. The CAN frames are of any type implementing `embedded_can::Frame`.
+//! LIN frames mapped from CAN are usually published in the slots of a `schedule::Scheduler`,
+//! which the gateway implements the `schedule::Handler` for, masters sending frames on demand
+//! take them from `poll`.
+//!
+//! ```
+//! use embedded_can::{Id, StandardId};
+//! use lin_bus::can::{CanGateway, Forwarding, FrameMapping, SignalMapping};
+//! use lin_bus::signal::{ByteOrder, Signal};
+//! use lin_bus::PID;
+//!
+//! // Move the 16 bit little endian value of the LIN frame to a big endian CAN signal
+//! static SIGNALS: [SignalMapping; 1] = [SignalMapping::new(
+//!     Signal::new(0, 16),
+//!     Signal::new(16, 16).with_byte_order(ByteOrder::BigEndian),
+//! )];
+//! let can_id = Id::Standard(StandardId::new(0x321).unwrap());
+//! let gateway = CanGateway::new([FrameMapping::lin_to_can(PID::TABLE[0x10], can_id, 4)
+//!     .with_forwarding(Forwarding::OnChange)
+//!     .with_signals(&SIGNALS)]);
+//! ```
+
+use embedded_can::{Frame as CanFrame, Id};
+
+use crate::frame::{Frame, PID};
+#[cfg(feature = "schedule")]
+use crate::schedule::Handler;
+use crate::signal::Signal;
+use crate::time::{Duration, Instant};
+
+/// Direction of a `FrameMapping`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    LinToCan,
+    CanToLin,
+}
+
+/// When a `FrameMapping` sends the target frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Forwarding {
+    /// On every reception of the source frame
+    OnReception,
+    /// When the mapped data differs from the data sent last
+    OnChange,
+    /// With the given period, once the source frame was received
+    Periodic(Duration),
+}
+
+/// Signal of the source frame repacked into a signal of the target frame. The raw value is
+/// copied, sign extended for signed sources, unless one of the signals has a scaling, then the
+/// physical value is converted.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SignalMapping {
+    pub source: Signal,
+    pub target: Signal,
+}
+
+impl SignalMapping {
+    pub const fn new(source: Signal, target: Signal) -> SignalMapping {
+        SignalMapping { source, target }
+    }
+
+    /// The raw value of the target signal for the raw value of the source signal
+    pub fn repack(&self, raw: u64) -> u64 {
+        if self.source.scaling.is_none() && self.target.scaling.is_none() {
+            return self.source.to_i64(raw) as u64 & self.target.mask();
+        }
+        self.target.from_physical(self.source.to_physical(raw))
+    }
+}
+
+/// Connection of a LIN frame and a CAN frame, see the module documentation
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FrameMapping<'a> {
+    pub lin: PID,
+    pub can: Id,
+    pub direction: Direction,
+    pub forwarding: Forwarding,
+    /// Data length of the target frame, at most 8 bytes
+    pub length: u8,
+    /// Signals repacked into the target frame, the data is copied as is if empty
+    pub signals: &'a [SignalMapping],
+}
+
+impl<'a> FrameMapping<'a> {
+    /// Mapping of the LIN frame to a CAN frame of `length` bytes, forwarded on reception
+    pub const fn lin_to_can(lin: PID, can: Id, length: u8) -> FrameMapping<'a> {
+        FrameMapping {
+            lin,
+            can,
+            direction: Direction::LinToCan,
+            forwarding: Forwarding::OnReception,
+            length,
+            signals: &[],
+        }
+    }
+
+    /// Mapping of the CAN frame to a LIN frame of `length` bytes, forwarded on reception
+    pub const fn can_to_lin(can: Id, lin: PID, length: u8) -> FrameMapping<'a> {
+        FrameMapping {
+            direction: Direction::CanToLin,
+            ..FrameMapping::lin_to_can(lin, can, length)
+        }
+    }
+
+    pub const fn with_forwarding(self, forwarding: Forwarding) -> Self {
+        Self { forwarding, ..self }
+    }
+
+    pub const fn with_signals(self, signals: &'a [SignalMapping]) -> Self {
+        Self { signals, ..self }
+    }
+
+    fn length(&self) -> usize {
+        usize::from(self.length.min(8))
+    }
+
+    /// Data of the target frame for the data of the source frame, based on the previous data
+    /// of the target frame
+    fn map(&self, source: &[u8], previous: [u8; 8]) -> [u8; 8] {
+        let length = self.length();
+        let mut data = previous;
+        if self.signals.is_empty() {
+            let copied = length.min(source.len());
+            data[..copied].copy_from_slice(&source[..copied]);
+            return data;
+        }
+        for mapping in self.signals {
+            // Signals missing in a short source frame keep their value
+            if let Ok(raw) = mapping.source.decode(source) {
+                let _ = mapping
+                    .target
+                    .insert(&mut data[..length], mapping.repack(raw));
+            }
+        }
+        data
+    }
+}
+
+/// Frame due on one of the buses
+#[derive(Debug, PartialEq)]
+pub enum Forward<F> {
+    Can(F),
+    Lin(Frame),
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Route {
+    data: [u8; 8],
+    received: bool,
+    pending: bool,
+    sent: Option<Instant>,
+}
+
+impl Route {
+    const IDLE: Route = Route {
+        data: [0; 8],
+        received: false,
+        pending: false,
+        sent: None,
+    };
+
+    fn is_due(&self, forwarding: Forwarding, now: Instant) -> bool {
+        match forwarding {
+            Forwarding::OnReception | Forwarding::OnChange => self.pending,
+            Forwarding::Periodic(period) => {
+                self.received
+                    && self
+                        .sent
+                        .map_or(true, |sent| now.duration_since(sent) >= period)
+            }
+        }
+    }
+}
+
+/// Forwarding of frames between LIN and CAN, see the module documentation
+#[derive(Clone, Debug)]
+pub struct CanGateway<'a, const N: usize> {
+    mappings: [FrameMapping<'a>; N],
+    routes: [Route; N],
+    /// Mapping checked first by `poll`, so all due frames get their turn
+    next: usize,
+}
+
+impl<'a, const N: usize> CanGateway<'a, N> {
+    pub const fn new(mappings: [FrameMapping<'a>; N]) -> CanGateway<'a, N> {
+        CanGateway {
+            mappings,
+            routes: [Route::IDLE; N],
+            next: 0,
+        }
+    }
+
+    pub fn mapping(&self, index: usize) -> &FrameMapping<'a> {
+        &self.mappings[index]
+    }
+
+    /// Update the mappings from a frame received on the LIN bus. Returns the number of
+    /// mappings with this frame as source.
+    pub fn receive_lin(&mut self, frame: &Frame) -> usize {
+        self.receive(
+            Direction::LinToCan,
+            |mapping| mapping.lin == frame.get_pid(),
+            frame.get_data(),
+        )
+    }
+
+    /// Update the mappings from a frame received on the CAN bus, remote frames are ignored.
+    /// Returns the number of mappings with this frame as source.
+    pub fn receive_can<F: CanFrame>(&mut self, frame: &F) -> usize {
+        if frame.is_remote_frame() {
+            return 0;
+        }
+        self.receive(
+            Direction::CanToLin,
+            |mapping| mapping.can == frame.id(),
+            frame.data(),
+        )
+    }
+
+    fn receive<S>(&mut self, direction: Direction, source: S, data: &[u8]) -> usize
+    where
+        S: Fn(&FrameMapping) -> bool,
+    {
+        let mut count = 0;
+        for (mapping, route) in self.mappings.iter().zip(self.routes.iter_mut()) {
+            if mapping.direction != direction || !source(mapping) {
+                continue;
+            }
+            let data = mapping.map(data, route.data);
+            let changed = !route.received || data != route.data;
+            route.data = data;
+            route.received = true;
+            route.pending |= match mapping.forwarding {
+                Forwarding::OnReception => true,
+                Forwarding::OnChange => changed,
+                Forwarding::Periodic(_) => false,
+            };
+            count += 1;
+        }
+        count
+    }
+
+    /// The next frame due at `now`, which is considered sent
+    pub fn poll<F: CanFrame>(&mut self, now: Instant) -> Option<Forward<F>> {
+        for offset in 0..N {
+            let index = (self.next + offset) % N;
+            let (mapping, route) = (&self.mappings[index], &mut self.routes[index]);
+            if !route.is_due(mapping.forwarding, now) {
+                continue;
+            }
+            route.pending = false;
+            route.sent = Some(now);
+            let data = &route.data[..mapping.length()];
+            let frame = match mapping.direction {
+                Direction::LinToCan => F::new(mapping.can, data).map(Forward::Can),
+                Direction::CanToLin => Frame::try_from_data(mapping.lin, data)
+                    .ok()
+                    .map(Forward::Lin),
+            };
+            if let Some(frame) = frame {
+                log!(trace, "gateway forwards mapping {}", index);
+                self.next = (index + 1) % N;
+                return Some(frame);
+            }
+        }
+        None
+    }
+}
+
+/// Publishes the LIN frames mapped from CAN with the last received data
+#[cfg(feature = "schedule")]
+impl<const N: usize> Handler for CanGateway<'_, N> {
+    fn publish(&mut self, pid: PID, data: &mut [u8]) {
+        for (mapping, route) in self.mappings.iter().zip(self.routes.iter_mut()) {
+            if mapping.direction == Direction::CanToLin && mapping.lin == pid && route.received {
+                let length = data.len().min(8);
+                data[..length].copy_from_slice(&route.data[..length]);
+                route.pending = false;
+            }
+        }
+    }
+
+    fn receive(&mut self, frame: &Frame) {
+        self.receive_lin(frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signal::ByteOrder;
+    use embedded_can::StandardId;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestFrame {
+        id: Id,
+        data: [u8; 8],
+        dlc: usize,
+        remote: bool,
+    }
+
+    impl CanFrame for TestFrame {
+        fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+            let mut frame = TestFrame::new_remote(id, data.len())?;
+            frame.data[..data.len()].copy_from_slice(data);
+            frame.remote = false;
+            Some(frame)
+        }
+
+        fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+            match dlc {
+                0..=8 => Some(TestFrame {
+                    id: id.into(),
+                    data: [0; 8],
+                    dlc,
+                    remote: true,
+                }),
+                _ => None,
+            }
+        }
+
+        fn is_extended(&self) -> bool {
+            matches!(self.id, Id::Extended(_))
+        }
+
+        fn is_remote_frame(&self) -> bool {
+            self.remote
+        }
+
+        fn id(&self) -> Id {
+            self.id
+        }
+
+        fn dlc(&self) -> usize {
+            self.dlc
+        }
+
+        fn data(&self) -> &[u8] {
+            &self.data[..self.dlc]
+        }
+    }
+
+    fn can_id(id: u16) -> Id {
+        Id::Standard(StandardId::new(id).unwrap())
+    }
+
+    fn can_frame(id: u16, data: &[u8]) -> Forward<TestFrame> {
+        Forward::Can(TestFrame::new(can_id(id), data).unwrap())
+    }
+
+    #[test]
+    fn test_lin_to_can() {
+        const LIN: PID = PID::from_id(0x10);
+        let signals = [
+            SignalMapping::new(
+                Signal::new(0, 16),
+                Signal::new(0, 16).with_byte_order(ByteOrder::BigEndian),
+            ),
+            SignalMapping::new(Signal::new(16, 4).signed(), Signal::new(16, 8)),
+            SignalMapping::new(
+                Signal::new(24, 8).with_scaling(0.5, 0.0),
+                Signal::new(24, 8).with_scaling(1.0, -40.0),
+            ),
+        ];
+        let mut gateway = CanGateway::new([
+            FrameMapping::lin_to_can(LIN, can_id(0x100), 4)
+                .with_forwarding(Forwarding::OnChange)
+                .with_signals(&signals),
+            FrameMapping::lin_to_can(LIN, can_id(0x101), 2),
+            FrameMapping::lin_to_can(PID::from_id(0x11), can_id(0x102), 1)
+                .with_forwarding(Forwarding::Periodic(Duration::from_millis(100))),
+        ]);
+        let now = Instant::from_micros(0);
+        assert_eq!(gateway.poll::<TestFrame>(now), None);
+
+        let frame = Frame::from_data(LIN, &[0x34, 0x12, 0x0F, 0x64]);
+        assert_eq!(gateway.receive_lin(&frame), 2);
+        assert_eq!(
+            gateway.poll(now),
+            Some(can_frame(0x100, &[0x12, 0x34, 0xFF, 0x5A]))
+        );
+        assert_eq!(gateway.poll(now), Some(can_frame(0x101, &[0x34, 0x12])));
+        assert_eq!(gateway.poll::<TestFrame>(now), None);
+
+        // Unchanged data is only forwarded by the mapping forwarding every reception
+        gateway.receive_lin(&frame);
+        assert_eq!(gateway.poll(now), Some(can_frame(0x101, &[0x34, 0x12])));
+        assert_eq!(gateway.poll::<TestFrame>(now), None);
+
+        gateway.receive_lin(&Frame::from_data(PID::from_id(0x11), &[0xAB]));
+        assert_eq!(gateway.poll(now), Some(can_frame(0x102, &[0xAB])));
+        assert_eq!(
+            gateway.poll::<TestFrame>(now + Duration::from_millis(99)),
+            None
+        );
+        assert_eq!(
+            gateway.poll(now + Duration::from_millis(100)),
+            Some(can_frame(0x102, &[0xAB]))
+        );
+    }
+
+    #[cfg(feature = "schedule")]
+    #[test]
+    fn test_can_to_lin() {
+        const LIN: PID = PID::from_id(0x20);
+        let mut gateway = CanGateway::new([FrameMapping::can_to_lin(can_id(0x200), LIN, 2)]);
+        let now = Instant::from_micros(0);
+
+        let mut data = [0xFF; 2];
+        gateway.publish(LIN, &mut data);
+        assert_eq!(data, [0xFF; 2]);
+
+        let remote = TestFrame::new_remote(can_id(0x200), 2).unwrap();
+        assert_eq!(gateway.receive_can(&remote), 0);
+        let frame = TestFrame::new(can_id(0x200), &[1, 2, 3]).unwrap();
+        assert_eq!(gateway.receive_can(&frame), 1);
+        assert_eq!(
+            gateway.poll::<TestFrame>(now),
+            Some(Forward::Lin(Frame::from_data(LIN, &[1, 2])))
+        );
+
+        gateway.receive_can(&frame);
+        gateway.publish(LIN, &mut data);
+        assert_eq!(data, [1, 2]);
+        // Published by the schedule, so not due anymore
+        assert_eq!(gateway.poll::<TestFrame>(now), None);
+    }
+}
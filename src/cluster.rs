@@ -0,0 +1,216 @@
+//! Runtime cluster configuration mapping frame IDs to their properties
+
+use crate::frame::{ChecksumModel, Frame, PID};
+use crate::{iso17987, j2602};
+
+/// The node publishing the response of a frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Publisher {
+    Master,
+    /// Slave node with the given index in the node list of the cluster
+    Slave(u8),
+}
+
+/// Properties of a single frame in the cluster
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FrameConfig {
+    pub id: u8,
+    pub data_length: u8,
+    pub checksum_model: ChecksumModel,
+    pub publisher: Publisher,
+}
+
+impl FrameConfig {
+    /// Create a frame configuration using the default checksum model of the ID
+    pub const fn new(id: u8, data_length: u8, publisher: Publisher) -> FrameConfig {
+        let checksum_model = if id >= iso17987::id::MASTER_REQUEST {
+            ChecksumModel::Classic
+        } else {
+            ChecksumModel::Enhanced
+        };
+        FrameConfig {
+            id,
+            data_length,
+            checksum_model,
+            publisher,
+        }
+    }
+
+    /// PID of the frame, the ID is masked to 6 bits
+    pub const fn pid(&self) -> PID {
+        PID::from_masked_id(self.id)
+    }
+
+    /// Returns the configuration with the given checksum model
+    pub const fn with_checksum_model(self, checksum_model: ChecksumModel) -> FrameConfig {
+        FrameConfig {
+            checksum_model,
+            ..self
+        }
+    }
+}
+
+/// Lookup table of all frames in a cluster. Can be built in a const context, e.g.
+///
+/// ```
+/// use lin_bus::cluster::{ClusterConfig, FrameConfig, Publisher};
+/// const CLUSTER: ClusterConfig = ClusterConfig::new(&[
+///     FrameConfig::new(0x10, 2, Publisher::Slave(0)),
+///     FrameConfig::new(0x11, 4, Publisher::Master),
+/// ]);
+/// assert_eq!(CLUSTER.data_length(0x10), Some(2));
+/// ```
+///
+/// Legacy LIN 1.3 clusters may encode the data length in the ID instead, see
+/// `with_id_encoded_length`, and SAE J2602 clusters follow the rules of `with_j2602`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ClusterConfig<'a> {
+    frames: &'a [FrameConfig],
+    id_encoded_length: bool,
+    j2602: bool,
+}
+
+impl<'a> ClusterConfig<'a> {
+    pub const fn new(frames: &'a [FrameConfig]) -> ClusterConfig<'a> {
+        ClusterConfig {
+            frames,
+            id_encoded_length: false,
+            j2602: false,
+        }
+    }
+
+    /// Returns the configuration of a LIN 1.3 cluster, in which frames that are not in the
+    /// table have the data length encoded in their ID (see `PID::encoded_data_length`) and
+    /// use the classic checksum. `frames` may be empty in that case.
+    pub const fn with_id_encoded_length(self) -> ClusterConfig<'a> {
+        ClusterConfig {
+            id_encoded_length: true,
+            ..self
+        }
+    }
+
+    /// Whether the data length of unknown frames is derived from the ID
+    pub const fn id_encoded_length(&self) -> bool {
+        self.id_encoded_length
+    }
+
+    /// Returns the configuration of a SAE J2602 cluster, see the `j2602` module. All frames
+    /// use the classic checksum, unknown frames have the data length encoded in their ID and the
+    /// reserved IDs 0x3E and 0x3F are unknown.
+    pub const fn with_j2602(self) -> ClusterConfig<'a> {
+        ClusterConfig {
+            id_encoded_length: true,
+            j2602: true,
+            ..self
+        }
+    }
+
+    /// Whether the cluster follows the J2602 rules
+    pub const fn is_j2602(&self) -> bool {
+        self.j2602
+    }
+
+    /// Get the configuration of the frame with the given ID
+    pub const fn frame(&self, id: u8) -> Option<&'a FrameConfig> {
+        let mut i = 0;
+        while i < self.frames.len() {
+            if self.frames[i].id == id {
+                return Some(&self.frames[i]);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Get the data length of the frame with the given ID, derived from the ID for unknown
+    /// frames if enabled by `with_id_encoded_length`
+    pub const fn data_length(&self, id: u8) -> Option<usize> {
+        if self.j2602 && !j2602::is_valid_id(id) {
+            return None;
+        }
+        match self.frame(id) {
+            Some(frame) => Some(frame.data_length as usize),
+            None if self.id_encoded_length && id < 64 => {
+                Some(PID::from_masked_id(id).encoded_data_length())
+            }
+            None => None,
+        }
+    }
+
+    /// Get the checksum model of the frame with the given ID, the classic checksum for unknown
+    /// frames if enabled by `with_id_encoded_length`
+    pub const fn checksum_model(&self, id: u8) -> Option<ChecksumModel> {
+        if self.j2602 {
+            return match self.data_length(id) {
+                Some(_) => Some(ChecksumModel::Classic),
+                None => None,
+            };
+        }
+        match self.frame(id) {
+            Some(frame) => Some(frame.checksum_model),
+            None if self.id_encoded_length && id < 64 => Some(ChecksumModel::Classic),
+            None => None,
+        }
+    }
+
+    /// Get the publisher of the frame with the given ID
+    pub const fn publisher(&self, id: u8) -> Option<Publisher> {
+        match self.frame(id) {
+            Some(frame) => Some(frame.publisher),
+            None => None,
+        }
+    }
+
+    /// Return all frame configurations
+    pub const fn frames(&self) -> &'a [FrameConfig] {
+        self.frames
+    }
+
+    /// The J2602 status byte of a message frame published by a slave, `None` for other frames
+    /// or if the cluster does not follow the J2602 rules
+    pub fn status(&self, frame: &Frame) -> Option<j2602::Status> {
+        let id = frame.get_pid().get_id();
+        match self.frame(id) {
+            Some(config) if self.j2602 && id <= j2602::MAX_MESSAGE_ID => match config.publisher {
+                Publisher::Slave(_) => j2602::Status::from_frame(frame),
+                Publisher::Master => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLUSTER: ClusterConfig = ClusterConfig::new(&[
+        FrameConfig::new(0x10, 2, Publisher::Slave(0)),
+        FrameConfig::new(0x11, 4, Publisher::Master).with_checksum_model(ChecksumModel::Classic),
+        FrameConfig::new(0x3C, 8, Publisher::Master),
+    ]);
+
+    #[test]
+    fn test_cluster_config() {
+        assert_eq!(CLUSTER.data_length(0x10), Some(2));
+        assert_eq!(CLUSTER.publisher(0x10), Some(Publisher::Slave(0)));
+        assert_eq!(CLUSTER.checksum_model(0x10), Some(ChecksumModel::Enhanced));
+        assert_eq!(CLUSTER.checksum_model(0x11), Some(ChecksumModel::Classic));
+        assert_eq!(CLUSTER.checksum_model(0x3C), Some(ChecksumModel::Classic));
+        assert_eq!(CLUSTER.frame(0x12), None);
+        assert_eq!(CLUSTER.frames().len(), 3);
+        assert_eq!(CLUSTER.data_length(0x20), None);
+    }
+
+    #[test]
+    fn test_id_encoded_length() {
+        let cluster = CLUSTER.with_id_encoded_length();
+        assert_eq!(cluster.data_length(0x10), Some(2));
+        assert_eq!(cluster.data_length(0x12), Some(2));
+        assert_eq!(cluster.data_length(0x20), Some(4));
+        assert_eq!(cluster.data_length(0x30), Some(8));
+        assert_eq!(cluster.checksum_model(0x10), Some(ChecksumModel::Enhanced));
+        assert_eq!(cluster.checksum_model(0x20), Some(ChecksumModel::Classic));
+        assert_eq!(cluster.data_length(0x40), None);
+    }
+}
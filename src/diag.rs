@@ -0,0 +1,798 @@
+//! Diagnostic master integrated with the schedule
+//!
+//! `DiagnosticManager` queues the diagnostic requests of the application and transfers them in
+//! master request (0x3C) and slave response (0x3D) slots, which it inserts into the execution
+//! of a `schedule::Scheduler`. The application submits a request, gets a `Ticket` and polls it
+//! for the response. The transport layer, i.e. segmented requests and responses and slaves
+//! answering with response pending, is handled by the manager. As all nodes share the
+//! diagnostic frames, the requests are transferred one after the other in the order of
+//! submission, `node_phase` tells the progress of the requests to a node.
+//!
+//! In `DiagnosticMode::Interleaved` a diagnostic slot follows each slot of the schedule table
+//! while a transfer is pending, in `DiagnosticMode::Only` the schedule table is suspended until
+//! all requests are done.
+//!
+//! `session::SessionTracker` keeps the diagnostic sessions of the slaves alive on top of the
+//! manager, `update::FirmwareUpdate` transfers firmware images to bootloaders and `dtc` reads
+//! and clears the fault memory. The counterpart on the slaves is `slave::Dispatcher`.
+//!
+//! ```
+//! use lin_bus::diag::{DiagnosticManager, Phase};
+//! use lin_bus::frame::transport::{NAD, SID};
+//!
+//! let mut manager = DiagnosticManager::<4>::new();
+//! let ticket = manager
+//!     .submit(NAD(0x10), SID(0xB2), &[0x00, 0xFF, 0x7F, 0xFF, 0xFF])
+//!     .unwrap();
+//! assert_eq!(manager.node_phase(NAD(0x10)), Some(Phase::Queued));
+//! assert!(manager.poll(ticket).is_pending());
+//! ```
+
+pub mod dtc;
+pub mod session;
+pub mod slave;
+pub mod update;
+
+use core::fmt;
+use core::task::Poll;
+
+use crate::frame::diagnostic::{MASTER_REQUEST_FRAME_PID, SLAVE_RESPONSE_FRAME_PID};
+use crate::frame::transport::{parse_response, ResponseError, NAD, RSID, SID};
+use crate::frame::{Frame, PID};
+use crate::iso17987::{nad, nrc};
+use crate::master::Response;
+#[cfg(feature = "schedule")]
+use crate::schedule::{Handler, Scheduler};
+use crate::Master;
+
+/// Maximum length of a request or response message, including the SID or RSID
+pub const MAX_MESSAGE_LENGTH: usize = 64;
+
+/// How diagnostic slots are inserted into the schedule, see the module documentation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DiagnosticMode {
+    /// A diagnostic slot after each slot of the schedule table
+    Interleaved,
+    /// Only diagnostic slots until all requests are done
+    Only,
+}
+
+impl Default for DiagnosticMode {
+    fn default() -> Self {
+        DiagnosticMode::Interleaved
+    }
+}
+
+/// Handle of a submitted request, see `DiagnosticManager::poll`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ticket(u32);
+
+/// Progress of a request
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Phase {
+    /// Waiting for the transfers of earlier requests
+    Queued,
+    /// Transmitting the master request frames
+    Request,
+    /// Polling the slave response
+    Response,
+    /// The slave answered with response pending and still processes the request
+    ResponsePending,
+    /// Receiving the consecutive frames of a segmented response
+    Segmented,
+    /// The transfer ended, the result is ready for `DiagnosticManager::poll`
+    Done,
+}
+
+/// Failed diagnostic request
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DiagnosticError {
+    /// The slave response is malformed or negative
+    Response(ResponseError),
+    /// No response in the allowed number of slave response slots
+    NoResponse,
+    /// A frame of the transfer failed on the bus, e.g. with an incomplete response or an
+    /// error of the driver, which was returned by `DiagnosticManager::run_slot`
+    Bus,
+    /// The ticket is not pending, because the request was cancelled or its result was
+    /// already taken
+    Cancelled,
+}
+
+impl fmt::Display for DiagnosticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticError::Response(e) => write!(f, "invalid response: {}", e),
+            DiagnosticError::NoResponse => f.write_str("no response"),
+            DiagnosticError::Bus => f.write_str("transfer failed on the bus"),
+            DiagnosticError::Cancelled => f.write_str("request is not pending"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DiagnosticError {}
+
+/// Request rejected by `DiagnosticManager::submit`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SubmitError {
+    /// All sessions hold pending requests or untaken results
+    Full,
+    /// The message of the given length exceeds `MAX_MESSAGE_LENGTH`
+    TooLong(usize),
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SubmitError::Full => f.write_str("no free diagnostic session"),
+            SubmitError::TooLong(length) => {
+                write!(f, "message of {} bytes exceeds the buffer", length)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SubmitError {}
+
+/// Positive response to a diagnostic request
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DiagnosticResponse {
+    pub nad: NAD,
+    pub rsid: RSID,
+    data: [u8; MAX_MESSAGE_LENGTH],
+    length: usize,
+}
+
+impl DiagnosticResponse {
+    /// The data following the RSID
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.length]
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Session {
+    ticket: Ticket,
+    nad: NAD,
+    sid: SID,
+    phase: Phase,
+    /// The request message and after its transmission the response message
+    buffer: [u8; MAX_MESSAGE_LENGTH],
+    length: usize,
+    /// Bytes of the message which were sent or received
+    position: usize,
+    sequence: u8,
+    empty_responses: u8,
+    response_nad: NAD,
+    outcome: Option<Result<RSID, DiagnosticError>>,
+}
+
+impl Session {
+    fn transfer<M: Master + ?Sized>(
+        &mut self,
+        master: &mut M,
+        max_empty_responses: u8,
+    ) -> Result<(), M::Error> {
+        let result = match self.phase {
+            Phase::Request => self.send(master),
+            _ => self.receive(master, max_empty_responses),
+        };
+        if result.is_err() {
+            self.finish(Err(DiagnosticError::Bus));
+        }
+        result
+    }
+
+    fn send<M: Master + ?Sized>(&mut self, master: &mut M) -> Result<(), M::Error> {
+        let frame = self.next_request_frame();
+        master.write_frame(&frame)?;
+        if self.position < self.length {
+            return Ok(());
+        }
+        self.position = 0;
+        self.length = 0;
+        if self.nad == nad::FUNCTIONAL {
+            // Functional requests are not answered
+            self.response_nad = self.nad;
+            self.finish(Ok(self.sid.positive_response()));
+        } else {
+            self.phase = Phase::Response;
+        }
+        Ok(())
+    }
+
+    fn next_request_frame(&mut self) -> Frame {
+        next_frame(
+            MASTER_REQUEST_FRAME_PID,
+            self.nad,
+            &self.buffer[..self.length],
+            &mut self.position,
+            &mut self.sequence,
+        )
+    }
+
+    fn receive<M: Master + ?Sized>(
+        &mut self,
+        master: &mut M,
+        max_empty_responses: u8,
+    ) -> Result<(), M::Error> {
+        let frame = match master.read_response(SLAVE_RESPONSE_FRAME_PID, 8)? {
+            Response::Complete(frame) => frame,
+            Response::NoResponse => {
+                self.empty_responses += 1;
+                if self.empty_responses >= max_empty_responses {
+                    self.finish(Err(DiagnosticError::NoResponse));
+                }
+                return Ok(());
+            }
+            Response::Partial(_) => {
+                self.finish(Err(DiagnosticError::Bus));
+                return Ok(());
+            }
+        };
+        self.empty_responses = 0;
+        let result = match (self.phase, frame.get_data()[1] >> 4) {
+            (Phase::Segmented, _) => self.push_consecutive(&frame),
+            (_, 1) => self.start_segmented(&frame),
+            _ => self.accept_single(&frame),
+        };
+        if let Err(e) = result {
+            self.finish(Err(DiagnosticError::Response(e)));
+        }
+        Ok(())
+    }
+
+    fn accept_single(&mut self, frame: &Frame) -> Result<(), ResponseError> {
+        match parse_response(frame, self.nad, self.sid) {
+            Ok(pdu) => {
+                self.buffer[..pdu.data.len()].copy_from_slice(pdu.data);
+                self.length = pdu.data.len();
+                self.response_nad = pdu.nad;
+                self.finish(Ok(pdu.rsid));
+                Ok(())
+            }
+            Err(ResponseError::Negative(nrc::RESPONSE_PENDING)) => {
+                self.phase = Phase::ResponsePending;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn start_segmented(&mut self, frame: &Frame) -> Result<(), ResponseError> {
+        let data = frame.get_data();
+        let response_nad = NAD(data[0]);
+        if !nad::is_slave(response_nad) || (self.nad != nad::BROADCAST && response_nad != self.nad)
+        {
+            return Err(ResponseError::UnexpectedNad(response_nad));
+        }
+        let length = usize::from(data[1] & 0x0F) << 8 | usize::from(data[2]);
+        if length <= 6 {
+            return Err(ResponseError::InvalidLength(length as u8));
+        }
+        if length > MAX_MESSAGE_LENGTH {
+            return Err(ResponseError::Overflow(length));
+        }
+        self.buffer[..5].copy_from_slice(&data[3..]);
+        self.length = length;
+        self.position = 5;
+        self.sequence = 1;
+        self.response_nad = response_nad;
+        self.phase = Phase::Segmented;
+        Ok(())
+    }
+
+    fn push_consecutive(&mut self, frame: &Frame) -> Result<(), ResponseError> {
+        let data = frame.get_data();
+        if NAD(data[0]) != self.response_nad {
+            return Err(ResponseError::UnexpectedNad(NAD(data[0])));
+        }
+        if data[1] >> 4 != 2 {
+            return Err(ResponseError::InvalidPci(data[1]));
+        }
+        if data[1] & 0x0F != self.sequence {
+            return Err(ResponseError::Sequence(data[1] & 0x0F));
+        }
+        self.sequence = (self.sequence + 1) & 0x0F;
+        let count = (self.length - self.position).min(6);
+        self.buffer[self.position..self.position + count].copy_from_slice(&data[2..2 + count]);
+        self.position += count;
+        if self.position < self.length {
+            return Ok(());
+        }
+        let message = &self.buffer[..self.length];
+        let rsid = RSID(message[0]);
+        if rsid.is_negative() {
+            return match message {
+                [_, rejected, code, ..] if *rejected == self.sid.0 => {
+                    Err(ResponseError::Negative(*code))
+                }
+                _ => Err(ResponseError::UnexpectedRsid(rsid)),
+            };
+        }
+        if !rsid.answers(self.sid) {
+            return Err(ResponseError::UnexpectedRsid(rsid));
+        }
+        self.buffer.copy_within(1..self.length, 0);
+        self.length -= 1;
+        self.finish(Ok(rsid));
+        Ok(())
+    }
+
+    fn finish(&mut self, outcome: Result<RSID, DiagnosticError>) {
+        if let Err(e) = outcome {
+            log!(
+                debug,
+                "diagnostic request {:#04x} to NAD {:#04x} failed: {}",
+                self.sid.0,
+                self.nad.0,
+                e
+            );
+        }
+        self.phase = Phase::Done;
+        self.outcome = Some(outcome);
+    }
+}
+
+/// The frame transporting `message` from `position` on, a single frame for messages of up to 6
+/// bytes, otherwise a first frame followed by consecutive frames numbered with `sequence`
+fn next_frame(
+    pid: PID,
+    nad: NAD,
+    message: &[u8],
+    position: &mut usize,
+    sequence: &mut u8,
+) -> Frame {
+    let mut data = [0xFFu8; 8];
+    data[0] = nad.0;
+    let (start, chunk) = if message.len() <= 6 {
+        data[1] = message.len() as u8;
+        (2, message)
+    } else if *position == 0 {
+        data[1] = 0x10 | (message.len() >> 8) as u8;
+        data[2] = message.len() as u8;
+        (3, &message[..5])
+    } else {
+        data[1] = 0x20 | *sequence;
+        *sequence = (*sequence + 1) & 0x0F;
+        let end = (*position + 6).min(message.len());
+        (2, &message[*position..end])
+    };
+    data[start..start + chunk.len()].copy_from_slice(chunk);
+    *position += chunk.len();
+    Frame::from_full_data(pid, data)
+}
+
+/// Queue and transport of diagnostic requests with `N` sessions, see the module documentation
+#[derive(Clone, Debug)]
+pub struct DiagnosticManager<const N: usize> {
+    sessions: [Option<Session>; N],
+    /// Index of the session being transferred
+    active: Option<usize>,
+    next_ticket: u32,
+    mode: DiagnosticMode,
+    slot_delay_us: u32,
+    max_empty_responses: u8,
+    /// Whether `run_slot` executes a diagnostic slot next in `DiagnosticMode::Interleaved`
+    #[cfg(feature = "schedule")]
+    diagnostic_due: bool,
+}
+
+impl<const N: usize> DiagnosticManager<N> {
+    /// Manager in `DiagnosticMode::Interleaved` with diagnostic slots of 10 ms, failing
+    /// requests after 3 slave response slots without response
+    pub const fn new() -> DiagnosticManager<N> {
+        DiagnosticManager {
+            sessions: [None; N],
+            active: None,
+            next_ticket: 0,
+            mode: DiagnosticMode::Interleaved,
+            slot_delay_us: 10_000,
+            max_empty_responses: 3,
+            #[cfg(feature = "schedule")]
+            diagnostic_due: false,
+        }
+    }
+
+    pub fn with_mode(self, mode: DiagnosticMode) -> Self {
+        Self { mode, ..self }
+    }
+
+    /// Time from the start of a diagnostic slot to the start of the next slot in µs, which
+    /// must exceed the P2min and STmin of the nodes
+    pub fn with_slot_delay(self, slot_delay_us: u32) -> Self {
+        Self {
+            slot_delay_us,
+            ..self
+        }
+    }
+
+    /// Number of consecutive slave response slots without response after which a request
+    /// fails with `DiagnosticError::NoResponse`
+    pub fn with_max_empty_responses(self, max_empty_responses: u8) -> Self {
+        Self {
+            max_empty_responses,
+            ..self
+        }
+    }
+
+    pub fn mode(&self) -> DiagnosticMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: DiagnosticMode) {
+        self.mode = mode;
+    }
+
+    /// Queue the request of service `sid` with `data` to `nad`. Requests of more than 5 data
+    /// bytes are segmented.
+    pub fn submit(&mut self, nad: NAD, sid: SID, data: &[u8]) -> Result<Ticket, SubmitError> {
+        let length = data.len() + 1;
+        if length > MAX_MESSAGE_LENGTH {
+            return Err(SubmitError::TooLong(length));
+        }
+        let free = self
+            .sessions
+            .iter_mut()
+            .find(|session| session.is_none())
+            .ok_or(SubmitError::Full)?;
+        let ticket = Ticket(self.next_ticket);
+        self.next_ticket = self.next_ticket.wrapping_add(1);
+        let mut buffer = [0u8; MAX_MESSAGE_LENGTH];
+        buffer[0] = sid.0;
+        buffer[1..length].copy_from_slice(data);
+        *free = Some(Session {
+            ticket,
+            nad,
+            sid,
+            phase: Phase::Queued,
+            buffer,
+            length,
+            position: 0,
+            sequence: 1,
+            empty_responses: 0,
+            response_nad: nad,
+            outcome: None,
+        });
+        Ok(ticket)
+    }
+
+    /// The result of the request, which is released when it is ready. Requests to the
+    /// functional NAD are not answered, they complete with an empty response after the
+    /// transmission.
+    pub fn poll(&mut self, ticket: Ticket) -> Poll<Result<DiagnosticResponse, DiagnosticError>> {
+        let slot = match self.sessions.iter_mut().find(|session| {
+            session
+                .as_ref()
+                .map_or(false, |session| session.ticket == ticket)
+        }) {
+            Some(slot) => slot,
+            None => return Poll::Ready(Err(DiagnosticError::Cancelled)),
+        };
+        let session = match slot {
+            Some(session) if session.phase == Phase::Done => *session,
+            _ => return Poll::Pending,
+        };
+        *slot = None;
+        Poll::Ready(match session.outcome {
+            Some(Ok(rsid)) => Ok(DiagnosticResponse {
+                nad: session.response_nad,
+                rsid,
+                data: session.buffer,
+                length: session.length,
+            }),
+            Some(Err(e)) => Err(e),
+            None => Err(DiagnosticError::Cancelled),
+        })
+    }
+
+    /// Drop the request, also if it is being transferred. Returns `false` if the ticket is
+    /// not pending.
+    pub fn cancel(&mut self, ticket: Ticket) -> bool {
+        let index = match self.sessions.iter().position(|session| {
+            session
+                .as_ref()
+                .map_or(false, |session| session.ticket == ticket)
+        }) {
+            Some(index) => index,
+            None => return false,
+        };
+        self.sessions[index] = None;
+        if self.active == Some(index) {
+            self.active = None;
+        }
+        true
+    }
+
+    /// Progress of the oldest pending request to `nad`, `None` without requests
+    pub fn node_phase(&self, nad: NAD) -> Option<Phase> {
+        self.sessions
+            .iter()
+            .flatten()
+            .filter(|session| session.nad == nad)
+            .min_by_key(|session| session.ticket)
+            .map(|session| session.phase)
+    }
+
+    /// Whether no request waits for a transfer
+    pub fn is_idle(&self) -> bool {
+        self.sessions
+            .iter()
+            .flatten()
+            .all(|session| session.phase == Phase::Done)
+    }
+
+    /// Execute the next slot, a diagnostic slot if one is due according to the mode and
+    /// otherwise the next slot of `scheduler`, and return its delay in µs
+    #[cfg(feature = "schedule")]
+    pub fn run_slot<M, H>(
+        &mut self,
+        scheduler: &mut Scheduler<'_>,
+        master: &mut M,
+        handler: &mut H,
+    ) -> Result<u32, M::Error>
+    where
+        M: Master + ?Sized,
+        H: Handler + ?Sized,
+    {
+        let due = match self.mode {
+            DiagnosticMode::Interleaved => self.diagnostic_due,
+            DiagnosticMode::Only => true,
+        };
+        if due && self.activate().is_some() {
+            self.diagnostic_due = false;
+            return self.run_diagnostic_slot(master);
+        }
+        self.diagnostic_due = true;
+        scheduler.run_slot(master, handler)
+    }
+
+    /// Transfer the next frame of the oldest request and return the slot delay in µs, 0 if no
+    /// request waits for a transfer. A failing frame ends the request with
+    /// `DiagnosticError::Bus`.
+    pub fn run_diagnostic_slot<M: Master + ?Sized>(
+        &mut self,
+        master: &mut M,
+    ) -> Result<u32, M::Error> {
+        let index = match self.activate() {
+            Some(index) => index,
+            None => return Ok(0),
+        };
+        let max_empty_responses = self.max_empty_responses;
+        let result = match &mut self.sessions[index] {
+            Some(session) => {
+                let result = session.transfer(master, max_empty_responses);
+                if session.phase == Phase::Done {
+                    self.active = None;
+                }
+                result
+            }
+            None => Ok(()),
+        };
+        result.map(|_| self.slot_delay_us)
+    }
+
+    /// Index of the session being transferred, the oldest queued one is started if there is
+    /// none
+    fn activate(&mut self) -> Option<usize> {
+        if self.active.is_none() {
+            self.active = self
+                .sessions
+                .iter()
+                .enumerate()
+                .filter_map(|(index, session)| session.as_ref().map(|session| (index, session)))
+                .filter(|(_, session)| session.phase == Phase::Queued)
+                .min_by_key(|(_, session)| session.ticket)
+                .map(|(index, _)| index);
+            if let Some(Some(session)) = self.active.map(|index| &mut self.sessions[index]) {
+                session.phase = Phase::Request;
+            }
+        }
+        self.active
+    }
+}
+
+impl<const N: usize> Default for DiagnosticManager<N> {
+    fn default() -> Self {
+        DiagnosticManager::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::mock::{Mock, Transaction};
+    use crate::driver::Error;
+    #[cfg(feature = "schedule")]
+    use crate::{
+        cluster::{FrameConfig, Publisher},
+        schedule::Slot,
+        PID,
+    };
+
+    fn request(data: [u8; 8]) -> Frame {
+        Frame::from_full_data(MASTER_REQUEST_FRAME_PID, data)
+    }
+
+    fn response(data: [u8; 8]) -> Frame {
+        Frame::from_full_data(SLAVE_RESPONSE_FRAME_PID, data)
+    }
+
+    #[cfg(feature = "schedule")]
+    #[test]
+    fn test_interleaved() {
+        const SLOTS: &[Slot] = &[Slot::new(
+            FrameConfig::new(0x11, 1, Publisher::Master),
+            5_000,
+        )];
+        let frame = Frame::from_data(PID::from_id(0x11), &[0x00]);
+        let read = request([0x10, 0x06, 0xB2, 0x00, 0xFF, 0x7F, 0xFF, 0xFF]);
+        let pending = response([0x10, 0x03, 0x7F, 0xB2, 0x78, 0xFF, 0xFF, 0xFF]);
+        let positive = response([0x10, 0x06, 0xF2, 0xB3, 0x00, 0x01, 0x10, 0x01]);
+        let expectations = [
+            Transaction::Header(PID::from_id(0x11)),
+            Transaction::Write(&frame.get_data_with_checksum()[..1]),
+            Transaction::Write(&frame.get_data_with_checksum()[1..]),
+            Transaction::Header(MASTER_REQUEST_FRAME_PID),
+            Transaction::Write(read.get_data_with_checksum()),
+            Transaction::Header(PID::from_id(0x11)),
+            Transaction::Write(&frame.get_data_with_checksum()[..1]),
+            Transaction::Write(&frame.get_data_with_checksum()[1..]),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::Read(pending.get_data_with_checksum()),
+            Transaction::Header(PID::from_id(0x11)),
+            Transaction::Write(&frame.get_data_with_checksum()[..1]),
+            Transaction::Write(&frame.get_data_with_checksum()[1..]),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::Read(positive.get_data_with_checksum()),
+            Transaction::Header(PID::from_id(0x11)),
+            Transaction::Write(&frame.get_data_with_checksum()[..1]),
+            Transaction::Write(&frame.get_data_with_checksum()[1..]),
+            Transaction::Header(PID::from_id(0x11)),
+            Transaction::Write(&frame.get_data_with_checksum()[..1]),
+            Transaction::Write(&frame.get_data_with_checksum()[1..]),
+        ];
+        let mut driver = Mock::new(&expectations);
+        let mut scheduler = Scheduler::new(SLOTS);
+        let mut handler = |_: PID, _: &mut [u8]| {};
+        let mut manager = DiagnosticManager::<2>::new();
+        let ticket = manager
+            .submit(NAD(0x10), SID(0xB2), &[0x00, 0xFF, 0x7F, 0xFF, 0xFF])
+            .unwrap();
+
+        let mut run = |manager: &mut DiagnosticManager<2>| {
+            manager.run_slot(&mut scheduler, &mut driver, &mut handler)
+        };
+        assert_eq!(run(&mut manager), Ok(5_000));
+        assert_eq!(run(&mut manager), Ok(10_000));
+        assert_eq!(manager.node_phase(NAD(0x10)), Some(Phase::Response));
+        assert_eq!(run(&mut manager), Ok(5_000));
+        assert_eq!(run(&mut manager), Ok(10_000));
+        assert_eq!(manager.node_phase(NAD(0x10)), Some(Phase::ResponsePending));
+        assert!(manager.poll(ticket).is_pending());
+        run(&mut manager).unwrap();
+        run(&mut manager).unwrap();
+        assert!(manager.is_idle());
+        run(&mut manager).unwrap();
+        run(&mut manager).unwrap();
+        driver.done();
+
+        let response = match manager.poll(ticket) {
+            Poll::Ready(Ok(response)) => response,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert_eq!((response.nad, response.rsid), (NAD(0x10), RSID(0xF2)));
+        assert_eq!(response.data(), &[0xB3, 0x00, 0x01, 0x10, 0x01]);
+        assert_eq!(manager.node_phase(NAD(0x10)), None);
+        assert_eq!(
+            manager.poll(ticket),
+            Poll::Ready(Err(DiagnosticError::Cancelled))
+        );
+    }
+
+    #[test]
+    fn test_segmented() {
+        let first = request([0x10, 0x10, 0x08, 0x22, 0x01, 0x02, 0x03, 0x04]);
+        let consecutive = request([0x10, 0x21, 0x05, 0x06, 0x07, 0xFF, 0xFF, 0xFF]);
+        let response_first = response([0x10, 0x10, 0x07, 0x62, 0x0A, 0x0B, 0x0C, 0x0D]);
+        let response_consecutive = response([0x10, 0x21, 0x0E, 0x0F, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let expectations = [
+            Transaction::Header(MASTER_REQUEST_FRAME_PID),
+            Transaction::Write(first.get_data_with_checksum()),
+            Transaction::Header(MASTER_REQUEST_FRAME_PID),
+            Transaction::Write(consecutive.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::ReadError(Error::NoResponse),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::Read(response_first.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::Read(response_consecutive.get_data_with_checksum()),
+        ];
+        let mut driver = Mock::new(&expectations);
+        let mut manager = DiagnosticManager::<1>::new().with_slot_delay(20_000);
+        let ticket = manager
+            .submit(NAD(0x10), SID(0x22), &[1, 2, 3, 4, 5, 6, 7])
+            .unwrap();
+        assert_eq!(
+            manager.submit(NAD(0x11), SID(0x22), &[]),
+            Err(SubmitError::Full)
+        );
+        for _ in 0..5 {
+            assert_eq!(manager.run_diagnostic_slot(&mut driver), Ok(20_000));
+        }
+        assert_eq!(manager.run_diagnostic_slot(&mut driver), Ok(0));
+        driver.done();
+        match manager.poll(ticket) {
+            Poll::Ready(Ok(response)) => {
+                assert_eq!(response.rsid, RSID(0x62));
+                assert_eq!(response.data(), &[0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F]);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "schedule")]
+    #[test]
+    fn test_failures() {
+        let read = request([0x10, 0x02, 0xB2, 0x01, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let negative = response([0x10, 0x03, 0x7F, 0xB2, 0x12, 0xFF, 0xFF, 0xFF]);
+        let expectations = [
+            Transaction::Header(MASTER_REQUEST_FRAME_PID),
+            Transaction::Write(read.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::ReadError(Error::NoResponse),
+            Transaction::Header(MASTER_REQUEST_FRAME_PID),
+            Transaction::Write(read.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::Read(negative.get_data_with_checksum()),
+            Transaction::Header(MASTER_REQUEST_FRAME_PID),
+            Transaction::Write(read.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::ReadError(Error::Timeout),
+        ];
+        let mut driver = Mock::new(&expectations);
+        let mut manager = DiagnosticManager::<4>::new()
+            .with_mode(DiagnosticMode::Only)
+            .with_max_empty_responses(1);
+        let mut tickets = [Ticket(0); 4];
+        for ticket in tickets.iter_mut() {
+            *ticket = manager.submit(NAD(0x10), SID(0xB2), &[0x01]).unwrap();
+        }
+        assert!(manager.cancel(tickets[3]));
+        assert!(!manager.cancel(tickets[3]));
+        let mut scheduler = Scheduler::new(&[]);
+        let mut handler = |_: PID, _: &mut [u8]| {};
+        for _ in 0..5 {
+            manager
+                .run_slot(&mut scheduler, &mut driver, &mut handler)
+                .unwrap();
+        }
+        assert_eq!(
+            manager.run_slot(&mut scheduler, &mut driver, &mut handler),
+            Err(Error::Timeout)
+        );
+        driver.done();
+        assert_eq!(
+            manager.poll(tickets[0]),
+            Poll::Ready(Err(DiagnosticError::NoResponse))
+        );
+        assert_eq!(
+            manager.poll(tickets[1]),
+            Poll::Ready(Err(DiagnosticError::Response(ResponseError::Negative(
+                0x12
+            ))))
+        );
+        assert_eq!(
+            manager.poll(tickets[2]),
+            Poll::Ready(Err(DiagnosticError::Bus))
+        );
+        assert!(manager.is_idle());
+    }
+}
@@ -0,0 +1,327 @@
+//! Reading and clearing the fault memory of slaves
+//!
+//! Slaves report their faults as diagnostic trouble codes (DTCs) with the read DTC information
+//! service of ISO 14229-1 (UDS). `read` submits a report of the DTCs matching a status mask,
+//! `poll_read` parses the response into a `DtcReport` of typed `Dtc` records. `clear` submits
+//! the clear diagnostic information service for a group of DTCs, `poll_clear` waits for the
+//! confirmation. The requests are transferred by a `DiagnosticManager` like all others.
+//!
+//! ```
+//! use lin_bus::diag::dtc::{self, DtcStatus};
+//! use lin_bus::diag::DiagnosticManager;
+//! use lin_bus::frame::transport::NAD;
+//!
+//! let mut manager = DiagnosticManager::<2>::new();
+//! let ticket = dtc::read(&mut manager, NAD(0x10), DtcStatus::CONFIRMED).unwrap();
+//! // The schedule transfers the request
+//! assert!(dtc::poll_read(&mut manager, ticket).is_pending());
+//! ```
+
+use core::fmt;
+use core::task::Poll;
+
+use super::{DiagnosticError, DiagnosticManager, DiagnosticResponse, SubmitError, Ticket};
+use crate::frame::transport::NAD;
+use crate::iso17987::sid;
+
+/// Sub-function of read DTC information reporting the DTCs matching a status mask
+pub const REPORT_DTC_BY_STATUS_MASK: u8 = 0x02;
+/// Group of `clear` selecting all DTCs
+pub const ALL_GROUPS: u32 = 0xFF_FFFF;
+
+/// Status byte of a DTC, also used as mask of `read`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(transparent)]
+pub struct DtcStatus(pub u8);
+
+impl DtcStatus {
+    pub const TEST_FAILED: DtcStatus = DtcStatus(0x01);
+    pub const TEST_FAILED_THIS_OPERATION_CYCLE: DtcStatus = DtcStatus(0x02);
+    pub const PENDING: DtcStatus = DtcStatus(0x04);
+    pub const CONFIRMED: DtcStatus = DtcStatus(0x08);
+    pub const TEST_NOT_COMPLETED_SINCE_LAST_CLEAR: DtcStatus = DtcStatus(0x10);
+    pub const TEST_FAILED_SINCE_LAST_CLEAR: DtcStatus = DtcStatus(0x20);
+    pub const TEST_NOT_COMPLETED_THIS_OPERATION_CYCLE: DtcStatus = DtcStatus(0x40);
+    pub const WARNING_INDICATOR_REQUESTED: DtcStatus = DtcStatus(0x80);
+    /// Mask of `read` matching all DTCs
+    pub const ANY: DtcStatus = DtcStatus(0xFF);
+
+    /// Whether all bits of `other` are set
+    pub const fn contains(self, other: DtcStatus) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn union(self, other: DtcStatus) -> DtcStatus {
+        DtcStatus(self.0 | other.0)
+    }
+
+    pub const fn test_failed(self) -> bool {
+        self.contains(DtcStatus::TEST_FAILED)
+    }
+
+    pub const fn pending(self) -> bool {
+        self.contains(DtcStatus::PENDING)
+    }
+
+    pub const fn confirmed(self) -> bool {
+        self.contains(DtcStatus::CONFIRMED)
+    }
+}
+
+/// Diagnostic trouble code with its status
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Dtc {
+    /// The 3 byte code, whose meaning is defined by the supplier
+    pub code: u32,
+    pub status: DtcStatus,
+}
+
+impl Dtc {
+    /// Decode a record of 3 bytes of code, most significant first, and the status
+    pub const fn from_bytes(bytes: [u8; 4]) -> Dtc {
+        Dtc {
+            code: u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]),
+            status: DtcStatus(bytes[3]),
+        }
+    }
+
+    pub const fn to_bytes(self) -> [u8; 4] {
+        let code = self.code.to_be_bytes();
+        [code[1], code[2], code[3], self.status.0]
+    }
+}
+
+impl fmt::Display for Dtc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:06X} status={:#04X}", self.code, self.status.0)
+    }
+}
+
+/// Failed read of the DTCs
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DtcError {
+    Diagnostic(DiagnosticError),
+    /// The positive response does not hold a report of the given length
+    Malformed(usize),
+}
+
+impl fmt::Display for DtcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DtcError::Diagnostic(e) => fmt::Display::fmt(e, f),
+            DtcError::Malformed(length) => write!(f, "malformed DTC report of {} bytes", length),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DtcError {}
+
+impl From<DiagnosticError> for DtcError {
+    fn from(e: DiagnosticError) -> Self {
+        DtcError::Diagnostic(e)
+    }
+}
+
+/// DTCs reported by a slave
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DtcReport {
+    response: DiagnosticResponse,
+}
+
+impl DtcReport {
+    /// Parse the positive response to a report by status mask
+    pub fn from_response(response: DiagnosticResponse) -> Result<DtcReport, DtcError> {
+        let data = response.data();
+        if data.len() < 2 || data[0] != REPORT_DTC_BY_STATUS_MASK || (data.len() - 2) % 4 != 0 {
+            return Err(DtcError::Malformed(data.len()));
+        }
+        Ok(DtcReport { response })
+    }
+
+    pub fn nad(&self) -> NAD {
+        self.response.nad
+    }
+
+    /// The status bits supported by the slave
+    pub fn availability(&self) -> DtcStatus {
+        DtcStatus(self.response.data()[1])
+    }
+
+    pub fn len(&self) -> usize {
+        self.records().len() / 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<Dtc> {
+        self.records()
+            .get(4 * index..4 * index + 4)
+            .map(|record| Dtc::from_bytes([record[0], record[1], record[2], record[3]]))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Dtc> + '_ {
+        (0..self.len()).filter_map(move |index| self.get(index))
+    }
+
+    fn records(&self) -> &[u8] {
+        &self.response.data()[2..]
+    }
+}
+
+/// Submit a report of the DTCs whose status matches any bit of `mask`
+pub fn read<const N: usize>(
+    manager: &mut DiagnosticManager<N>,
+    nad: NAD,
+    mask: DtcStatus,
+) -> Result<Ticket, SubmitError> {
+    manager.submit(
+        nad,
+        sid::READ_DTC_INFORMATION,
+        &[REPORT_DTC_BY_STATUS_MASK, mask.0],
+    )
+}
+
+/// The report of a request submitted by `read`
+pub fn poll_read<const N: usize>(
+    manager: &mut DiagnosticManager<N>,
+    ticket: Ticket,
+) -> Poll<Result<DtcReport, DtcError>> {
+    manager
+        .poll(ticket)
+        .map(|response| DtcReport::from_response(response?))
+}
+
+/// Submit clearing the DTCs of `group`, the 3 byte code of a group or `ALL_GROUPS`
+pub fn clear<const N: usize>(
+    manager: &mut DiagnosticManager<N>,
+    nad: NAD,
+    group: u32,
+) -> Result<Ticket, SubmitError> {
+    let group = group.to_be_bytes();
+    manager.submit(
+        nad,
+        sid::CLEAR_DIAGNOSTIC_INFORMATION,
+        &[group[1], group[2], group[3]],
+    )
+}
+
+/// The confirmation of a request submitted by `clear`
+pub fn poll_clear<const N: usize>(
+    manager: &mut DiagnosticManager<N>,
+    ticket: Ticket,
+) -> Poll<Result<(), DiagnosticError>> {
+    manager.poll(ticket).map(|response| response.map(|_| ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::mock::{Mock, Transaction};
+    use crate::frame::diagnostic::{MASTER_REQUEST_FRAME_PID, SLAVE_RESPONSE_FRAME_PID};
+    use crate::frame::transport::RSID;
+    use crate::frame::PID;
+    use crate::Frame;
+
+    #[test]
+    fn test_read_and_clear() {
+        let frames = [
+            (true, [0x10, 0x03, 0x19, 0x02, 0x08, 0xFF, 0xFF, 0xFF]),
+            (false, [0x10, 0x10, 0x0B, 0x59, 0x02, 0x7F, 0x12, 0x34]),
+            (false, [0x10, 0x21, 0x56, 0x2F, 0x9A, 0xBC, 0xDE, 0x08]),
+            (true, [0x10, 0x04, 0x14, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+            (false, [0x10, 0x01, 0x54, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+        ];
+        let frames = frames.map(|(request, data)| {
+            let pid = match request {
+                true => MASTER_REQUEST_FRAME_PID,
+                false => SLAVE_RESPONSE_FRAME_PID,
+            };
+            (request, Frame::from_full_data(pid, data))
+        });
+        let mut expectations = [Transaction::Header(PID::from_id(0)); 10];
+        for (i, (request, frame)) in frames.iter().enumerate() {
+            expectations[2 * i] = Transaction::Header(frame.get_pid());
+            expectations[2 * i + 1] = match request {
+                true => Transaction::Write(frame.get_data_with_checksum()),
+                false => Transaction::Read(frame.get_data_with_checksum()),
+            };
+        }
+        let mut driver = Mock::new(&expectations);
+        let mut manager = DiagnosticManager::<1>::new();
+
+        let ticket = read(&mut manager, NAD(0x10), DtcStatus::CONFIRMED).unwrap();
+        let report = loop {
+            match poll_read(&mut manager, ticket) {
+                Poll::Ready(report) => break report.unwrap(),
+                Poll::Pending => manager.run_diagnostic_slot(&mut driver).unwrap(),
+            };
+        };
+        assert_eq!(report.nad(), NAD(0x10));
+        assert_eq!(report.availability(), DtcStatus(0x7F));
+        assert_eq!(report.len(), 2);
+        let mut dtcs = report.iter();
+        let dtc = dtcs.next().unwrap();
+        assert_eq!(dtc.code, 0x12_3456);
+        assert!(dtc.status.test_failed() && dtc.status.confirmed());
+        assert_eq!(
+            dtc.status,
+            DtcStatus::TEST_FAILED
+                .union(DtcStatus::TEST_FAILED_THIS_OPERATION_CYCLE)
+                .union(DtcStatus::PENDING)
+                .union(DtcStatus::CONFIRMED)
+                .union(DtcStatus::TEST_FAILED_SINCE_LAST_CLEAR)
+        );
+        assert_eq!(
+            dtcs.next(),
+            Some(Dtc {
+                code: 0x9A_BCDE,
+                status: DtcStatus::CONFIRMED
+            })
+        );
+        assert_eq!(dtcs.next(), None);
+
+        let ticket = clear(&mut manager, NAD(0x10), ALL_GROUPS).unwrap();
+        let result = loop {
+            match poll_clear(&mut manager, ticket) {
+                Poll::Ready(result) => break result,
+                Poll::Pending => manager.run_diagnostic_slot(&mut driver).unwrap(),
+            };
+        };
+        assert_eq!(result, Ok(()));
+        driver.done();
+    }
+
+    #[test]
+    fn test_report() {
+        let response = |data: &[u8]| {
+            let mut response = DiagnosticResponse {
+                nad: NAD(0x10),
+                rsid: RSID(0x59),
+                data: [0; super::super::MAX_MESSAGE_LENGTH],
+                length: data.len(),
+            };
+            response.data[..data.len()].copy_from_slice(data);
+            response
+        };
+
+        let report = DtcReport::from_response(response(&[0x02, 0x09])).unwrap();
+        assert!(report.is_empty());
+        assert_eq!(report.get(0), None);
+        for data in [&[0x02][..], &[0x01, 0x09], &[0x02, 0x09, 0x01, 0x02, 0x03]] {
+            assert_eq!(
+                DtcReport::from_response(response(data)),
+                Err(DtcError::Malformed(data.len()))
+            );
+        }
+
+        let dtc = Dtc::from_bytes([0xC1, 0x23, 0x45, 0x09]);
+        assert_eq!(dtc.to_bytes(), [0xC1, 0x23, 0x45, 0x09]);
+        assert!(!dtc.status.pending());
+    }
+}
@@ -0,0 +1,359 @@
+//! Diagnostic sessions of the slaves
+//!
+//! Slaves supporting ISO 14229-1 (UDS) services over LIN, e.g. for flashing or end of line
+//! configuration, run them in a non-default session. The slave returns to the default session
+//! when it receives no request for the S3 time of 5 s, which silently disables the services
+//! of the session. `SessionTracker` keeps the session of each slave, changes it with the
+//! diagnostic session control service and sends tester present requests to slaves in a
+//! non-default session when the application did not send a request for the keep-alive
+//! interval. The requests are transferred by a `DiagnosticManager`, the transitions of the
+//! sessions are reported by `SessionTracker::poll`.
+//!
+//! ```
+//! use lin_bus::diag::session::{DiagnosticSession, SessionTracker};
+//! use lin_bus::diag::DiagnosticManager;
+//! use lin_bus::frame::transport::NAD;
+//! use lin_bus::time::Instant;
+//!
+//! let mut manager = DiagnosticManager::<4>::new();
+//! let mut sessions = SessionTracker::<2>::new();
+//! let now = Instant::from_micros(0);
+//! sessions
+//!     .request_session(&mut manager, NAD(0x10), DiagnosticSession::Extended, now)
+//!     .unwrap();
+//! // The session changes when the slave confirmed it
+//! assert_eq!(sessions.session(NAD(0x10)), DiagnosticSession::Default);
+//! assert_eq!(sessions.poll(&mut manager, now), None);
+//! ```
+
+use core::task::Poll;
+
+use super::{DiagnosticError, DiagnosticManager, SubmitError, Ticket};
+use crate::frame::transport::{NAD, SID};
+use crate::iso17987::sid;
+use crate::time::{Duration, Instant};
+
+/// Interval of the tester present requests, well below the S3 time of 5 s
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_millis(2_000);
+
+/// Diagnostic session of ISO 14229-1
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DiagnosticSession {
+    Default,
+    Programming,
+    Extended,
+    /// Vehicle manufacturer or supplier specific session
+    Other(u8),
+}
+
+impl From<u8> for DiagnosticSession {
+    fn from(byte: u8) -> DiagnosticSession {
+        match byte {
+            0x01 => DiagnosticSession::Default,
+            0x02 => DiagnosticSession::Programming,
+            0x03 => DiagnosticSession::Extended,
+            b => DiagnosticSession::Other(b),
+        }
+    }
+}
+
+impl From<DiagnosticSession> for u8 {
+    fn from(session: DiagnosticSession) -> u8 {
+        match session {
+            DiagnosticSession::Default => 0x01,
+            DiagnosticSession::Programming => 0x02,
+            DiagnosticSession::Extended => 0x03,
+            DiagnosticSession::Other(b) => b,
+        }
+    }
+}
+
+/// Transition of the session of a slave, see `SessionTracker::poll`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SessionEvent {
+    /// The slave confirmed the change of the session
+    Changed {
+        nad: NAD,
+        from: DiagnosticSession,
+        to: DiagnosticSession,
+    },
+    /// The slave rejected or did not answer the session control request, the session is
+    /// unchanged
+    Rejected {
+        nad: NAD,
+        requested: DiagnosticSession,
+        error: DiagnosticError,
+    },
+    /// The tester present request failed, the slave is assumed to have returned to the
+    /// default session
+    Lost {
+        nad: NAD,
+        session: DiagnosticSession,
+        error: DiagnosticError,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Request {
+    Control(DiagnosticSession),
+    KeepAlive,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Node {
+    nad: NAD,
+    session: DiagnosticSession,
+    last_request: Instant,
+    request: Option<(Ticket, Request)>,
+}
+
+/// Sessions of up to `N` slaves, see the module documentation
+#[derive(Clone, Debug)]
+pub struct SessionTracker<const N: usize> {
+    nodes: [Option<Node>; N],
+    keep_alive: Duration,
+}
+
+impl<const N: usize> SessionTracker<N> {
+    /// Tracker sending tester present requests with `DEFAULT_KEEP_ALIVE`
+    pub const fn new() -> SessionTracker<N> {
+        SessionTracker {
+            nodes: [None; N],
+            keep_alive: DEFAULT_KEEP_ALIVE,
+        }
+    }
+
+    /// Time since the submission of the last request to a slave after which a tester present
+    /// request is sent. It must leave a margin to the S3 time for the transfer of the request.
+    pub fn with_keep_alive(self, keep_alive: Duration) -> Self {
+        Self { keep_alive, ..self }
+    }
+
+    /// The session of the slave, `DiagnosticSession::Default` for slaves without a confirmed
+    /// session change
+    pub fn session(&self, nad: NAD) -> DiagnosticSession {
+        self.node(nad)
+            .map_or(DiagnosticSession::Default, |node| node.session)
+    }
+
+    /// Submit the diagnostic session control request to change the session of the slave,
+    /// the result is reported by `poll`. Fails with `SubmitError::Full` if the sessions of `N`
+    /// other slaves are tracked.
+    pub fn request_session<const M: usize>(
+        &mut self,
+        manager: &mut DiagnosticManager<M>,
+        nad: NAD,
+        session: DiagnosticSession,
+        now: Instant,
+    ) -> Result<Ticket, SubmitError> {
+        let index = match self.nodes.iter().position(|node| is_node(node, nad)) {
+            Some(index) => index,
+            None => self
+                .nodes
+                .iter()
+                .position(Option::is_none)
+                .ok_or(SubmitError::Full)?,
+        };
+        let ticket = manager.submit(nad, sid::DIAGNOSTIC_SESSION_CONTROL, &[session.into()])?;
+        let node = self.nodes[index].get_or_insert(Node {
+            nad,
+            session: DiagnosticSession::Default,
+            last_request: now,
+            request: None,
+        });
+        node.last_request = now;
+        node.request = Some((ticket, Request::Control(session)));
+        Ok(ticket)
+    }
+
+    /// Submit a request of the application, which also keeps the session of the slave alive
+    pub fn submit<const M: usize>(
+        &mut self,
+        manager: &mut DiagnosticManager<M>,
+        nad: NAD,
+        sid: SID,
+        data: &[u8],
+        now: Instant,
+    ) -> Result<Ticket, SubmitError> {
+        let ticket = manager.submit(nad, sid, data)?;
+        if let Some(node) = self.nodes.iter_mut().flatten().find(|node| node.nad == nad) {
+            node.last_request = now;
+        }
+        Ok(ticket)
+    }
+
+    /// Collect the results of the session requests and submit the due tester present
+    /// requests. Returns the next transition of a session, call it until it returns `None`.
+    pub fn poll<const M: usize>(
+        &mut self,
+        manager: &mut DiagnosticManager<M>,
+        now: Instant,
+    ) -> Option<SessionEvent> {
+        let keep_alive = self.keep_alive;
+        for slot in self.nodes.iter_mut() {
+            let node = match slot {
+                Some(node) => node,
+                None => continue,
+            };
+            let event = match node.request {
+                Some((ticket, request)) => match manager.poll(ticket) {
+                    Poll::Pending => None,
+                    Poll::Ready(result) => {
+                        node.request = None;
+                        complete(node, request, result.map(|_| ()))
+                    }
+                },
+                None if node.session != DiagnosticSession::Default
+                    && now.duration_since(node.last_request) >= keep_alive =>
+                {
+                    // A full queue is retried on the next poll
+                    if let Ok(ticket) = manager.submit(node.nad, sid::TESTER_PRESENT, &[0x00]) {
+                        log!(trace, "tester present to NAD {:#04x}", node.nad.0);
+                        node.last_request = now;
+                        node.request = Some((ticket, Request::KeepAlive));
+                    }
+                    None
+                }
+                None => None,
+            };
+            if node.session == DiagnosticSession::Default && node.request.is_none() {
+                *slot = None;
+            }
+            if event.is_some() {
+                return event;
+            }
+        }
+        None
+    }
+
+    fn node(&self, nad: NAD) -> Option<&Node> {
+        self.nodes.iter().flatten().find(|node| node.nad == nad)
+    }
+}
+
+impl<const N: usize> Default for SessionTracker<N> {
+    fn default() -> Self {
+        SessionTracker::new()
+    }
+}
+
+fn is_node(node: &Option<Node>, nad: NAD) -> bool {
+    node.as_ref().map_or(false, |node| node.nad == nad)
+}
+
+/// Apply the result of a request to the session of the node
+fn complete(
+    node: &mut Node,
+    request: Request,
+    result: Result<(), DiagnosticError>,
+) -> Option<SessionEvent> {
+    let nad = node.nad;
+    let from = node.session;
+    match (request, result) {
+        (Request::Control(to), Ok(())) => {
+            node.session = to;
+            log!(debug, "NAD {:#04x} changed to session {:?}", nad.0, to);
+            Some(SessionEvent::Changed { nad, from, to })
+        }
+        (Request::Control(requested), Err(error)) => Some(SessionEvent::Rejected {
+            nad,
+            requested,
+            error,
+        }),
+        (Request::KeepAlive, Ok(())) => None,
+        (Request::KeepAlive, Err(error)) => {
+            node.session = DiagnosticSession::Default;
+            log!(debug, "NAD {:#04x} lost session {:?}", nad.0, from);
+            Some(SessionEvent::Lost {
+                nad,
+                session: from,
+                error,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::mock::{Mock, Transaction};
+    use crate::driver::Error;
+    use crate::frame::diagnostic::{MASTER_REQUEST_FRAME_PID, SLAVE_RESPONSE_FRAME_PID};
+    use crate::Frame;
+
+    #[test]
+    fn test_session_tracker() {
+        let control = Frame::from_full_data(
+            MASTER_REQUEST_FRAME_PID,
+            [0x10, 0x02, 0x10, 0x03, 0xFF, 0xFF, 0xFF, 0xFF],
+        );
+        let confirmation = Frame::from_full_data(
+            SLAVE_RESPONSE_FRAME_PID,
+            [0x10, 0x06, 0x50, 0x03, 0x00, 0x32, 0x01, 0xF4],
+        );
+        let tester_present = Frame::from_full_data(
+            MASTER_REQUEST_FRAME_PID,
+            [0x10, 0x02, 0x3E, 0x00, 0xFF, 0xFF, 0xFF, 0xFF],
+        );
+        let expectations = [
+            Transaction::Header(MASTER_REQUEST_FRAME_PID),
+            Transaction::Write(control.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::Read(confirmation.get_data_with_checksum()),
+            Transaction::Header(MASTER_REQUEST_FRAME_PID),
+            Transaction::Write(tester_present.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::ReadError(Error::NoResponse),
+        ];
+        let mut driver = Mock::new(&expectations);
+        let mut manager = DiagnosticManager::<2>::new().with_max_empty_responses(1);
+        let mut sessions = SessionTracker::<1>::new();
+        let at = Instant::from_micros;
+        let nad = NAD(0x10);
+
+        sessions
+            .request_session(&mut manager, nad, DiagnosticSession::Extended, at(0))
+            .unwrap();
+        assert_eq!(
+            sessions.request_session(&mut manager, NAD(0x11), DiagnosticSession::Extended, at(0)),
+            Err(SubmitError::Full)
+        );
+        manager.run_diagnostic_slot(&mut driver).unwrap();
+        manager.run_diagnostic_slot(&mut driver).unwrap();
+        assert_eq!(
+            sessions.poll(&mut manager, at(1_000)),
+            Some(SessionEvent::Changed {
+                nad,
+                from: DiagnosticSession::Default,
+                to: DiagnosticSession::Extended
+            })
+        );
+        assert_eq!(sessions.session(nad), DiagnosticSession::Extended);
+
+        // Requests of the application postpone the tester present
+        let ticket = sessions
+            .submit(&mut manager, nad, SID(0x22), &[0xF1, 0x90], at(1_000_000))
+            .unwrap();
+        manager.cancel(ticket);
+        assert_eq!(sessions.poll(&mut manager, at(2_500_000)), None);
+        assert!(manager.is_idle());
+        assert_eq!(sessions.poll(&mut manager, at(3_000_000)), None);
+        assert!(!manager.is_idle());
+        manager.run_diagnostic_slot(&mut driver).unwrap();
+        manager.run_diagnostic_slot(&mut driver).unwrap();
+        driver.done();
+        assert_eq!(
+            sessions.poll(&mut manager, at(3_100_000)),
+            Some(SessionEvent::Lost {
+                nad,
+                session: DiagnosticSession::Extended,
+                error: DiagnosticError::NoResponse
+            })
+        );
+        assert_eq!(sessions.session(nad), DiagnosticSession::Default);
+        assert_eq!(sessions.poll(&mut manager, at(9_000_000)), None);
+        assert!(manager.is_idle());
+    }
+}
@@ -0,0 +1,480 @@
+//! Diagnostic services of a slave node
+//!
+//! `Dispatcher` processes the master request frames (0x3C) received by a slave: it reassembles
+//! segmented requests, routes them by their SID to the registered `Service`s and provides the
+//! frames of the response for the following slave response headers (0x3D). A custom service
+//! is one `Service` implementation, `ReadByIdentifier` and `AssignNad` implement the
+//! identification and node configuration services of ISO 17987-3.
+//!
+//! ```
+//! use lin_bus::diag::slave::{Dispatcher, Node, ReadByIdentifier, Service};
+//! use lin_bus::frame::diagnostic::{self, Identifier, ProductId, SerialNumber};
+//! use lin_bus::frame::transport::NAD;
+//!
+//! let product_id = ProductId {
+//!     supplier_id: 0x00B3,
+//!     function_id: 0x1001,
+//!     variant: 0x01,
+//! };
+//! let mut read_by_identifier = ReadByIdentifier::new(SerialNumber(42));
+//! let mut services: [&mut dyn Service; 1] = [&mut read_by_identifier];
+//! let mut dispatcher = Dispatcher::new(Node::new(NAD(0x10), product_id), &mut services);
+//!
+//! dispatcher.receive(&diagnostic::create_read_by_identifier_frame(
+//!     NAD(0x10),
+//!     Identifier::SerialNumber,
+//!     0x00B3,
+//!     0x1001,
+//! ));
+//! let response = dispatcher.respond().unwrap();
+//! assert_eq!(response.get_data(), &[0x10, 0x05, 0xF2, 42, 0, 0, 0, 0xFF]);
+//! assert_eq!(dispatcher.respond(), None);
+//! ```
+
+use super::{next_frame, MAX_MESSAGE_LENGTH};
+use crate::frame::diagnostic::{
+    Identifier, ProductId, ReadByIdentifierRequest, SerialNumber, MASTER_REQUEST_FRAME_PID,
+    SLAVE_RESPONSE_FRAME_PID,
+};
+use crate::frame::transport::{NAD, SID};
+use crate::frame::Frame;
+use crate::iso17987::{nad, nrc, sid, wildcard};
+
+/// Identity of the slave node, shared by the services
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Node {
+    /// The configured NAD, which the node answers to
+    pub nad: NAD,
+    /// The NAD before the node configuration, see `AssignNad`
+    pub initial_nad: NAD,
+    pub product_id: ProductId,
+}
+
+impl Node {
+    pub const fn new(nad: NAD, product_id: ProductId) -> Node {
+        Node {
+            nad,
+            initial_nad: nad,
+            product_id,
+        }
+    }
+}
+
+/// Complete master request passed to a `Service`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Request<'a> {
+    pub nad: NAD,
+    pub sid: SID,
+    /// The data following the SID
+    pub data: &'a [u8],
+}
+
+impl Request<'_> {
+    /// Whether the request is sent to the NAD of the node or to the broadcast or functional
+    /// NAD
+    pub fn is_for(&self, node: &Node) -> bool {
+        self.nad == node.nad || self.nad == nad::BROADCAST || self.nad == nad::FUNCTIONAL
+    }
+}
+
+/// Result of a `Service`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Reply {
+    /// Positive response with the given number of bytes of the response data
+    Positive(usize),
+    /// Negative response with the negative response code
+    Negative(u8),
+    /// The request is not answered, e.g. because it addresses another node
+    Ignore,
+}
+
+/// Handler of the requests of one service
+pub trait Service {
+    /// The SID of the requests passed to `handle`
+    fn sid(&self) -> SID;
+
+    /// Process the request and write the data of a positive response following the RSID into
+    /// `response`
+    fn handle(&mut self, node: &mut Node, request: &Request, response: &mut [u8]) -> Reply;
+}
+
+/// Reader of user defined identifiers, which writes the data of the identifier into the
+/// buffer and returns its length or `None` for unsupported identifiers
+pub type ReadUserDefined = fn(u8, &mut [u8]) -> Option<usize>;
+
+/// Read by identifier service, answering the product identification, the serial number and
+/// the user defined identifiers of an optional `ReadUserDefined` function
+#[derive(Copy, Clone)]
+pub struct ReadByIdentifier {
+    serial_number: SerialNumber,
+    user_defined: Option<ReadUserDefined>,
+}
+
+/// Shows whether a `ReadUserDefined` function is set, Rust 1.57 does not implement `Debug`
+/// for function pointers with references
+impl core::fmt::Debug for ReadByIdentifier {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("ReadByIdentifier")
+            .field("serial_number", &self.serial_number)
+            .field("user_defined", &self.user_defined.is_some())
+            .finish()
+    }
+}
+
+impl ReadByIdentifier {
+    pub fn new(serial_number: SerialNumber) -> ReadByIdentifier {
+        ReadByIdentifier {
+            serial_number,
+            user_defined: None,
+        }
+    }
+
+    pub fn with_user_defined(self, read: ReadUserDefined) -> Self {
+        Self {
+            user_defined: Some(read),
+            ..self
+        }
+    }
+}
+
+impl Service for ReadByIdentifier {
+    fn sid(&self) -> SID {
+        sid::READ_BY_IDENTIFIER
+    }
+
+    fn handle(&mut self, node: &mut Node, request: &Request, response: &mut [u8]) -> Reply {
+        let request = match request.data {
+            [identifier, supplier_low, supplier_high, function_low, function_high] => {
+                ReadByIdentifierRequest {
+                    nad: request.nad,
+                    identifier: Identifier::from(*identifier),
+                    supplier_id: u16::from_le_bytes([*supplier_low, *supplier_high]),
+                    function_id: u16::from_le_bytes([*function_low, *function_high]),
+                }
+            }
+            _ if request.nad == node.nad => return Reply::Negative(nrc::INCORRECT_MESSAGE_LENGTH),
+            _ => return Reply::Ignore,
+        };
+        if !request.addresses(node.nad, &node.product_id) {
+            return Reply::Ignore;
+        }
+        let length = match request.identifier {
+            Identifier::LINProductIdentification => {
+                response[..5].copy_from_slice(&node.product_id.to_bytes());
+                Some(5)
+            }
+            Identifier::SerialNumber => {
+                response[..4].copy_from_slice(&self.serial_number.to_bytes());
+                Some(4)
+            }
+            Identifier::UserDefined(identifier) => self
+                .user_defined
+                .and_then(|read| read(identifier, response)),
+            Identifier::Reserved(_) => None,
+        };
+        length.map_or(Reply::Negative(nrc::REQUEST_OUT_OF_RANGE), Reply::Positive)
+    }
+}
+
+/// Assign NAD service, changing the NAD of the node addressed with its initial NAD and
+/// product identification
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AssignNad;
+
+impl Service for AssignNad {
+    fn sid(&self) -> SID {
+        sid::ASSIGN_NAD
+    }
+
+    fn handle(&mut self, node: &mut Node, request: &Request, _response: &mut [u8]) -> Reply {
+        let (supplier_id, function_id, new_nad) = match request.data {
+            [supplier_low, supplier_high, function_low, function_high, new_nad] => (
+                u16::from_le_bytes([*supplier_low, *supplier_high]),
+                u16::from_le_bytes([*function_low, *function_high]),
+                NAD(*new_nad),
+            ),
+            _ => return Reply::Ignore,
+        };
+        let addressed = request.nad == node.initial_nad || request.nad == wildcard::NAD;
+        if !addressed
+            || !node.product_id.matches(supplier_id, function_id)
+            || !nad::is_slave(new_nad)
+        {
+            return Reply::Ignore;
+        }
+        log!(
+            debug,
+            "NAD changed from {:#04x} to {:#04x}",
+            node.nad.0,
+            new_nad.0
+        );
+        node.nad = new_nad;
+        Reply::Positive(0)
+    }
+}
+
+/// Router of the diagnostic requests of a slave, see the module documentation
+pub struct Dispatcher<'a> {
+    node: Node,
+    services: &'a mut [&'a mut dyn Service],
+    /// The request being received and after its processing the response being sent
+    buffer: [u8; MAX_MESSAGE_LENGTH],
+    length: usize,
+    position: usize,
+    sequence: u8,
+    /// NAD of the request being received or of the response
+    nad: NAD,
+    receiving: bool,
+    responding: bool,
+}
+
+impl<'a> Dispatcher<'a> {
+    pub fn new(node: Node, services: &'a mut [&'a mut dyn Service]) -> Dispatcher<'a> {
+        Dispatcher {
+            node,
+            services,
+            buffer: [0u8; MAX_MESSAGE_LENGTH],
+            length: 0,
+            position: 0,
+            sequence: 0,
+            nad: node.nad,
+            receiving: false,
+            responding: false,
+        }
+    }
+
+    /// The identity of the node, updated by the node configuration services
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+
+    /// Whether a response waits for slave response headers
+    pub fn has_response(&self) -> bool {
+        self.responding
+    }
+
+    /// Process a frame received by the node, frames other than master requests are ignored.
+    /// A new request discards the pending response.
+    pub fn receive(&mut self, frame: &Frame) {
+        let data = frame.get_data();
+        if frame.get_pid() != MASTER_REQUEST_FRAME_PID || data.len() != 8 {
+            return;
+        }
+        let (request_nad, pci) = (NAD(data[0]), data[1]);
+        if request_nad == nad::GO_TO_SLEEP {
+            return;
+        }
+        match pci >> 4 {
+            0 if (1..=6).contains(&pci) => {
+                self.responding = false;
+                self.receiving = false;
+                let length = usize::from(pci);
+                self.buffer[..length].copy_from_slice(&data[2..2 + length]);
+                self.length = length;
+                self.dispatch(request_nad);
+            }
+            1 => {
+                self.responding = false;
+                let length = usize::from(pci & 0x0F) << 8 | usize::from(data[2]);
+                self.receiving = length > 6 && length <= MAX_MESSAGE_LENGTH;
+                if !self.receiving {
+                    log!(debug, "request of {} bytes ignored", length);
+                    return;
+                }
+                self.buffer[..5].copy_from_slice(&data[3..]);
+                self.nad = request_nad;
+                self.length = length;
+                self.position = 5;
+                self.sequence = 1;
+            }
+            2 if self.receiving => {
+                if request_nad != self.nad || pci & 0x0F != self.sequence {
+                    self.receiving = false;
+                    return;
+                }
+                self.sequence = (self.sequence + 1) & 0x0F;
+                let count = (self.length - self.position).min(6);
+                self.buffer[self.position..self.position + count]
+                    .copy_from_slice(&data[2..2 + count]);
+                self.position += count;
+                if self.position == self.length {
+                    self.receiving = false;
+                    self.dispatch(request_nad);
+                }
+            }
+            _ => self.receiving = false,
+        }
+    }
+
+    /// The next frame of the response for a slave response header, `None` if the node does
+    /// not respond
+    pub fn respond(&mut self) -> Option<Frame> {
+        if !self.responding {
+            return None;
+        }
+        let frame = next_frame(
+            SLAVE_RESPONSE_FRAME_PID,
+            self.nad,
+            &self.buffer[..self.length],
+            &mut self.position,
+            &mut self.sequence,
+        );
+        self.responding = self.position < self.length;
+        Some(frame)
+    }
+
+    /// Pass the request in the buffer to its service and prepare the response
+    fn dispatch(&mut self, request_nad: NAD) {
+        let mut message = [0u8; MAX_MESSAGE_LENGTH];
+        message[..self.length].copy_from_slice(&self.buffer[..self.length]);
+        let request = Request {
+            nad: request_nad,
+            sid: SID(message[0]),
+            data: &message[1..self.length],
+        };
+        // The response is sent with the NAD before a node configuration
+        let response_nad = self.node.nad;
+        let reply = match self
+            .services
+            .iter_mut()
+            .find(|service| service.sid() == request.sid)
+        {
+            Some(service) => service.handle(&mut self.node, &request, &mut self.buffer[1..]),
+            None if request_nad == self.node.nad => Reply::Negative(nrc::SERVICE_NOT_SUPPORTED),
+            None => Reply::Ignore,
+        };
+        self.length = match reply {
+            _ if request_nad == nad::FUNCTIONAL => return,
+            Reply::Positive(length) => {
+                self.buffer[0] = request.sid.positive_response().0;
+                length.min(MAX_MESSAGE_LENGTH - 1) + 1
+            }
+            Reply::Negative(code) => {
+                self.buffer[..3].copy_from_slice(&[sid::NEGATIVE_RESPONSE, request.sid.0, code]);
+                3
+            }
+            Reply::Ignore => return,
+        };
+        self.nad = response_nad;
+        self.position = 0;
+        self.sequence = 1;
+        self.responding = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::diagnostic::create_read_by_identifier_frame;
+
+    const PRODUCT_ID: ProductId = ProductId {
+        supplier_id: 0x00B3,
+        function_id: 0x1001,
+        variant: 0x01,
+    };
+
+    fn request(data: [u8; 8]) -> Frame {
+        Frame::from_full_data(MASTER_REQUEST_FRAME_PID, data)
+    }
+
+    /// Service answering the data of the request in reverse order
+    struct Reverse;
+
+    impl Service for Reverse {
+        fn sid(&self) -> SID {
+            SID(0x22)
+        }
+
+        fn handle(&mut self, node: &mut Node, request: &Request, response: &mut [u8]) -> Reply {
+            if !request.is_for(node) {
+                return Reply::Ignore;
+            }
+            for (byte, data) in response.iter_mut().zip(request.data.iter().rev()) {
+                *byte = *data;
+            }
+            Reply::Positive(request.data.len())
+        }
+    }
+
+    #[test]
+    fn test_dispatcher() {
+        let mut read_by_identifier =
+            ReadByIdentifier::new(SerialNumber(1)).with_user_defined(|identifier, data| {
+                data[..2].copy_from_slice(&[identifier, 0xAA]);
+                Some(2)
+            });
+        let mut assign_nad = AssignNad;
+        let mut reverse = Reverse;
+        let mut services: [&mut dyn Service; 3] =
+            [&mut read_by_identifier, &mut assign_nad, &mut reverse];
+        let mut dispatcher = Dispatcher::new(Node::new(NAD(0x10), PRODUCT_ID), &mut services);
+
+        // Wildcard product identification
+        dispatcher.receive(&create_read_by_identifier_frame(
+            NAD(0x7F),
+            Identifier::LINProductIdentification,
+            0x7FFF,
+            0xFFFF,
+        ));
+        assert_eq!(
+            dispatcher.respond().unwrap().get_data(),
+            &[0x10, 0x06, 0xF2, 0xB3, 0x00, 0x01, 0x10, 0x01]
+        );
+        dispatcher.receive(&create_read_by_identifier_frame(
+            NAD(0x10),
+            Identifier::UserDefined(33),
+            0x00B3,
+            0x1001,
+        ));
+        assert_eq!(
+            dispatcher.respond().unwrap().get_data(),
+            &[0x10, 0x03, 0xF2, 33, 0xAA, 0xFF, 0xFF, 0xFF]
+        );
+        dispatcher.receive(&create_read_by_identifier_frame(
+            NAD(0x10),
+            Identifier::Reserved(2),
+            0x00B3,
+            0x1001,
+        ));
+        assert_eq!(
+            dispatcher.respond().unwrap().get_data(),
+            &[0x10, 0x03, 0x7F, 0xB2, 0x31, 0xFF, 0xFF, 0xFF]
+        );
+
+        // Segmented request and response
+        dispatcher.receive(&request([0x10, 0x10, 0x08, 0x22, 1, 2, 3, 4]));
+        assert!(!dispatcher.has_response());
+        dispatcher.receive(&request([0x10, 0x21, 5, 6, 7, 0xFF, 0xFF, 0xFF]));
+        assert_eq!(
+            dispatcher.respond().unwrap().get_data(),
+            &[0x10, 0x10, 0x08, 0x62, 7, 6, 5, 4]
+        );
+        assert_eq!(
+            dispatcher.respond().unwrap().get_data(),
+            &[0x10, 0x21, 3, 2, 1, 0xFF, 0xFF, 0xFF]
+        );
+        assert_eq!(dispatcher.respond(), None);
+
+        // Unknown services and other nodes
+        dispatcher.receive(&request([0x10, 0x01, 0x19, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]));
+        assert_eq!(
+            dispatcher.respond().unwrap().get_data(),
+            &[0x10, 0x03, 0x7F, 0x19, 0x11, 0xFF, 0xFF, 0xFF]
+        );
+        dispatcher.receive(&request([0x11, 0x01, 0x19, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]));
+        dispatcher.receive(&request([0x7E, 0x02, 0x22, 0x01, 0xFF, 0xFF, 0xFF, 0xFF]));
+        assert_eq!(dispatcher.respond(), None);
+
+        // The response to the assign NAD is sent with the initial NAD
+        dispatcher.receive(&request([0x10, 0x06, 0xB0, 0xB3, 0x00, 0xFF, 0xFF, 0x20]));
+        assert_eq!(dispatcher.node().nad, NAD(0x20));
+        assert_eq!(
+            dispatcher.respond().unwrap().get_data(),
+            &[0x10, 0x01, 0xF0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+        dispatcher.receive(&request([0x10, 0x01, 0x19, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]));
+        assert_eq!(dispatcher.respond(), None);
+    }
+}
@@ -0,0 +1,347 @@
+//! Firmware update of slaves with a bootloader
+//!
+//! `FirmwareUpdate` transfers an image with the download services of ISO 14229-1 (UDS): request
+//! download negotiates the memory range and the maximum block length, transfer data streams
+//! the image in blocks numbered with a sequence counter, which the slave echoes, and request
+//! transfer exit completes the transfer. Bootloaders with vendor specific services are served
+//! by replacing the SIDs with `UpdateServices` and passing the parameters of the transfer
+//! exit, e.g. a checksum of the image, with `FirmwareUpdate::with_exit_data`.
+//!
+//! The requests are transferred by a `DiagnosticManager`, usually in `DiagnosticMode::Only`
+//! to use the whole bandwidth of the bus. Most bootloaders only accept the download in the
+//! programming session, see `session::SessionTracker`.
+//!
+//! ```
+//! use lin_bus::diag::update::FirmwareUpdate;
+//! use lin_bus::diag::DiagnosticManager;
+//! use lin_bus::frame::transport::NAD;
+//!
+//! static IMAGE: [u8; 1024] = [0xFF; 1024];
+//!
+//! let mut manager = DiagnosticManager::<2>::new();
+//! let mut update = FirmwareUpdate::new(NAD(0x10), 0x0800_0000, &IMAGE);
+//! // Submits the request download, the schedule transfers it
+//! assert!(update.poll(&mut manager).is_pending());
+//! assert_eq!(update.progress(), (0, 1024));
+//! ```
+
+use core::fmt;
+use core::task::Poll;
+
+use super::{DiagnosticError, DiagnosticManager, DiagnosticResponse, Ticket, MAX_MESSAGE_LENGTH};
+use crate::frame::transport::{NAD, SID};
+use crate::iso17987::sid;
+
+/// SIDs of the services of the transfer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UpdateServices {
+    pub request_download: SID,
+    pub transfer_data: SID,
+    pub transfer_exit: SID,
+}
+
+impl UpdateServices {
+    /// The services of ISO 14229-1
+    pub const UDS: UpdateServices = UpdateServices {
+        request_download: sid::REQUEST_DOWNLOAD,
+        transfer_data: sid::TRANSFER_DATA,
+        transfer_exit: sid::REQUEST_TRANSFER_EXIT,
+    };
+}
+
+impl Default for UpdateServices {
+    fn default() -> Self {
+        UpdateServices::UDS
+    }
+}
+
+/// Step of the transfer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Stage {
+    /// Request download
+    Download,
+    /// Transfer data
+    Transfer,
+    /// Request transfer exit
+    Exit,
+    /// The transfer completed
+    Done,
+}
+
+/// Failed firmware update
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UpdateError {
+    /// The request of the stage failed
+    Rejected {
+        stage: Stage,
+        error: DiagnosticError,
+    },
+    /// The maximum block length of the slave is malformed or leaves no room for data
+    BlockLength(usize),
+    /// The slave confirmed a block with a wrong sequence counter
+    Sequence { expected: u8, received: u8 },
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UpdateError::Rejected { stage, error } => write!(f, "{:?} failed: {}", stage, error),
+            UpdateError::BlockLength(length) => write!(f, "invalid block length {}", length),
+            UpdateError::Sequence { expected, received } => write!(
+                f,
+                "block {} confirmed instead of block {}",
+                received, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UpdateError {}
+
+/// Transfer of a firmware image to a slave, see the module documentation
+#[derive(Clone, Debug)]
+pub struct FirmwareUpdate<'a> {
+    nad: NAD,
+    address: u32,
+    image: &'a [u8],
+    exit_data: &'a [u8],
+    services: UpdateServices,
+    stage: Stage,
+    /// Image bytes per transfer data request
+    block_length: usize,
+    /// Image bytes confirmed by the slave
+    position: usize,
+    sequence: u8,
+    ticket: Option<Ticket>,
+    error: Option<UpdateError>,
+}
+
+impl<'a> FirmwareUpdate<'a> {
+    /// Update transferring `image` to the memory starting at `address` of the slave
+    pub const fn new(nad: NAD, address: u32, image: &'a [u8]) -> FirmwareUpdate<'a> {
+        FirmwareUpdate {
+            nad,
+            address,
+            image,
+            exit_data: &[],
+            services: UpdateServices::UDS,
+            stage: Stage::Download,
+            block_length: 0,
+            position: 0,
+            sequence: 1,
+            ticket: None,
+            error: None,
+        }
+    }
+
+    pub fn with_services(self, services: UpdateServices) -> Self {
+        Self { services, ..self }
+    }
+
+    /// Parameters of the request transfer exit
+    pub fn with_exit_data(self, exit_data: &'a [u8]) -> Self {
+        Self { exit_data, ..self }
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Bytes of the image confirmed by the slave and the length of the image
+    pub fn progress(&self) -> (usize, usize) {
+        (self.position, self.image.len())
+    }
+
+    /// Collect the response of the outstanding request and submit the next one. Ready when
+    /// the slave confirmed the transfer exit or a request failed.
+    pub fn poll<const N: usize>(
+        &mut self,
+        manager: &mut DiagnosticManager<N>,
+    ) -> Poll<Result<(), UpdateError>> {
+        if let Some(e) = self.error {
+            return Poll::Ready(Err(e));
+        }
+        if let Some(ticket) = self.ticket {
+            let response = match manager.poll(ticket) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(response) => response,
+            };
+            self.ticket = None;
+            let result = response
+                .map_err(|error| UpdateError::Rejected {
+                    stage: self.stage,
+                    error,
+                })
+                .and_then(|response| self.advance(&response));
+            if let Err(e) = result {
+                log!(
+                    debug,
+                    "firmware update of NAD {:#04x} failed: {}",
+                    self.nad.0,
+                    e
+                );
+                self.error = Some(e);
+                return Poll::Ready(Err(e));
+            }
+        }
+        if self.stage == Stage::Done {
+            return Poll::Ready(Ok(()));
+        }
+        // A full queue is retried on the next poll
+        self.ticket = self.submit(manager);
+        Poll::Pending
+    }
+
+    fn submit<const N: usize>(&self, manager: &mut DiagnosticManager<N>) -> Option<Ticket> {
+        let mut data = [0u8; MAX_MESSAGE_LENGTH - 1];
+        let (sid, length) = match self.stage {
+            Stage::Download => {
+                // Uncompressed and unencrypted, 4 bytes of address and size
+                data[..2].copy_from_slice(&[0x00, 0x44]);
+                data[2..6].copy_from_slice(&self.address.to_be_bytes());
+                data[6..10].copy_from_slice(&(self.image.len() as u32).to_be_bytes());
+                (self.services.request_download, 10)
+            }
+            Stage::Transfer => {
+                let end = (self.position + self.block_length).min(self.image.len());
+                let block = &self.image[self.position..end];
+                data[0] = self.sequence;
+                data[1..=block.len()].copy_from_slice(block);
+                (self.services.transfer_data, block.len() + 1)
+            }
+            Stage::Exit | Stage::Done => {
+                let length = self.exit_data.len().min(data.len());
+                data[..length].copy_from_slice(&self.exit_data[..length]);
+                (self.services.transfer_exit, length)
+            }
+        };
+        manager.submit(self.nad, sid, &data[..length]).ok()
+    }
+
+    fn advance(&mut self, response: &DiagnosticResponse) -> Result<(), UpdateError> {
+        match self.stage {
+            Stage::Download => {
+                self.block_length = block_length(response.data())?;
+                self.stage = next_transfer_stage(self.image.len(), 0);
+            }
+            Stage::Transfer => {
+                let received = response.data().first().copied().unwrap_or(0);
+                if received != self.sequence {
+                    return Err(UpdateError::Sequence {
+                        expected: self.sequence,
+                        received,
+                    });
+                }
+                self.sequence = self.sequence.wrapping_add(1);
+                self.position = (self.position + self.block_length).min(self.image.len());
+                self.stage = next_transfer_stage(self.image.len(), self.position);
+            }
+            Stage::Exit | Stage::Done => self.stage = Stage::Done,
+        }
+        Ok(())
+    }
+}
+
+fn next_transfer_stage(length: usize, position: usize) -> Stage {
+    if position < length {
+        Stage::Transfer
+    } else {
+        Stage::Exit
+    }
+}
+
+/// Image bytes per transfer data request from the response to the request download, whose
+/// maximum block length includes the SID and the sequence counter
+fn block_length(data: &[u8]) -> Result<usize, UpdateError> {
+    let count = data.first().map_or(0, |format| usize::from(format >> 4));
+    let bytes = match data.get(1..=count) {
+        Some(bytes) if (1..=4).contains(&count) => bytes,
+        _ => return Err(UpdateError::BlockLength(0)),
+    };
+    let maximum = bytes
+        .iter()
+        .fold(0usize, |length, byte| length << 8 | usize::from(*byte));
+    if maximum < 3 {
+        return Err(UpdateError::BlockLength(maximum));
+    }
+    Ok(maximum.min(MAX_MESSAGE_LENGTH) - 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::mock::{Mock, Transaction};
+    use crate::frame::diagnostic::{MASTER_REQUEST_FRAME_PID, SLAVE_RESPONSE_FRAME_PID};
+    use crate::frame::PID;
+    use crate::Frame;
+
+    #[test]
+    fn test_firmware_update() {
+        let frames = [
+            (true, [0x10, 0x10, 0x0B, 0x34, 0x00, 0x44, 0x10, 0x00]),
+            (true, [0x10, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0A]),
+            (false, [0x10, 0x04, 0x74, 0x20, 0x00, 0x06, 0xFF, 0xFF]),
+            (true, [0x10, 0x06, 0x36, 0x01, 0x00, 0x01, 0x02, 0x03]),
+            (false, [0x10, 0x02, 0x76, 0x01, 0xFF, 0xFF, 0xFF, 0xFF]),
+            (true, [0x10, 0x06, 0x36, 0x02, 0x04, 0x05, 0x06, 0x07]),
+            (false, [0x10, 0x02, 0x76, 0x02, 0xFF, 0xFF, 0xFF, 0xFF]),
+            (true, [0x10, 0x04, 0x36, 0x03, 0x08, 0x09, 0xFF, 0xFF]),
+            (false, [0x10, 0x02, 0x76, 0x03, 0xFF, 0xFF, 0xFF, 0xFF]),
+            (true, [0x10, 0x03, 0x37, 0xAB, 0xCD, 0xFF, 0xFF, 0xFF]),
+            (false, [0x10, 0x01, 0x77, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+        ];
+        let frames = frames.map(|(request, data)| {
+            let pid = match request {
+                true => MASTER_REQUEST_FRAME_PID,
+                false => SLAVE_RESPONSE_FRAME_PID,
+            };
+            (request, Frame::from_full_data(pid, data))
+        });
+        let mut expectations = [Transaction::Header(PID::from_id(0)); 22];
+        for (i, (request, frame)) in frames.iter().enumerate() {
+            expectations[2 * i] = Transaction::Header(frame.get_pid());
+            expectations[2 * i + 1] = match request {
+                true => Transaction::Write(frame.get_data_with_checksum()),
+                false => Transaction::Read(frame.get_data_with_checksum()),
+            };
+        }
+        let mut driver = Mock::new(&expectations);
+        let mut manager = DiagnosticManager::<1>::new();
+        let image = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let mut update =
+            FirmwareUpdate::new(NAD(0x10), 0x1000_0000, &image).with_exit_data(&[0xAB, 0xCD]);
+
+        let result = loop {
+            match update.poll(&mut manager) {
+                Poll::Ready(result) => break result,
+                Poll::Pending => manager.run_diagnostic_slot(&mut driver).unwrap(),
+            };
+        };
+        assert_eq!(result, Ok(()));
+        assert_eq!(update.stage(), Stage::Done);
+        driver.done();
+        assert_eq!(update.progress(), (10, 10));
+    }
+
+    #[test]
+    fn test_block_length() {
+        assert_eq!(
+            block_length(&[0x20, 0x01, 0x02]),
+            Ok(MAX_MESSAGE_LENGTH - 2)
+        );
+        assert_eq!(block_length(&[0x10, 0x08]), Ok(6));
+        assert_eq!(
+            block_length(&[0x10, 0x02]),
+            Err(UpdateError::BlockLength(2))
+        );
+        assert_eq!(
+            block_length(&[0x20, 0x01]),
+            Err(UpdateError::BlockLength(0))
+        );
+        assert_eq!(block_length(&[0x00]), Err(UpdateError::BlockLength(0)));
+    }
+}
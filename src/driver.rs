@@ -1,11 +1,598 @@
 //! Trait for a hardware driver to implement
+//!
+//! Ready-made drivers are available behind features:
+//!
+//! * `uart::UartMaster` for UARTs implementing `embedded-io` (feature `embedded-io`)
+//! * `hal::HalMaster` for `embedded-hal-nb` serial ports with a GPIO break (feature `hal`)
+//! * `embassy::EmbassyMaster` for async embassy UARTs (feature `embassy`)
+//! * `serial::SerialPortMaster` for serial ports of desktop operating systems
+//!   (feature `serial-port`)
+//! * `sllin::SllinMaster` for USB-LIN adapters with an ASCII protocol (feature `embedded-io`)
+//! * `peripheral::PeripheralDriver` for UARTs with a hardware LIN mode
+//! * `loopback::Loopback` feeding written bytes back, for tests without hardware
+//! * `mock::Mock` checking the bus traffic against expectations, for unit tests
+//! * `fault::FaultInjector` wrapping another driver to test error handling
+//! * `spaced::SpacedMaster` wrapping another driver to insert inter-byte spaces
+//! * `echo::EchoMaster` wrapping another driver to discard the echo of the transceiver
+//! * `virtual_bus::VirtualBus` connecting a master to simulated slaves (feature `std`)
+//!
+//! Commercial adapters like Baby-LIN or PCAN-USB Pro are only accessible through proprietary
+//! vendor libraries, which are not part of this crate. Their frame based APIs can be wrapped by
+//! implementing the frame level `crate::Master` trait, like `sllin::SllinMaster` does.
+use crate::frame::ChecksumAccumulator;
+use crate::iso17987::timing;
+use crate::ldf::LinSpeed;
+use crate::time::{Delay, Duration, Instant};
 pub use crate::Error;
 use crate::PID;
+use core::task::Poll;
 
+pub mod echo;
+#[cfg(feature = "embassy")]
+pub mod embassy;
+#[cfg(any(test, not(feature = "panic-free")))]
+pub mod fault;
+#[cfg(feature = "hal")]
+pub mod hal;
+#[cfg(any(test, not(feature = "panic-free")))]
+pub mod loopback;
+#[cfg(any(test, not(feature = "panic-free")))]
+pub mod mock;
+pub mod peripheral;
+#[cfg(feature = "serial-port")]
+pub mod serial;
+#[cfg(feature = "embedded-io")]
+pub mod sllin;
+pub mod spaced;
+#[cfg(feature = "embedded-io")]
+pub mod uart;
+#[cfg(feature = "std")]
+pub mod virtual_bus;
+
+/// Value of the sync field following the break of every frame header
+pub const SYNC_BYTE: u8 = 0x55;
+
+/// Byte which results in a dominant pulse of 8 bit times when transmitted by a UART, which is
+/// longer than the 250 µs minimum of a wakeup signal at 19200 bit/s
+pub const WAKEUP_BYTE: u8 = 0x80;
+
+/// Length of the dominant pulse of the wakeup signal, between `timing::WAKEUP_MIN_US` and
+/// `timing::WAKEUP_MAX_US`
+///
+/// Drivers without a configured pulse send the `WAKEUP_BYTE` at the nominal baud rate.
+/// Drivers switching the baud rate generate the pulse with `send_wakeup_by_baud_switch`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeupPulse(Duration);
+
+impl WakeupPulse {
+    /// Fails with `Error::InvalidConfig` if `duration` is outside of the allowed range
+    pub const fn try_new(duration: Duration) -> Result<WakeupPulse, Error> {
+        let us = duration.as_micros();
+        if us < timing::WAKEUP_MIN_US as u64 || us > timing::WAKEUP_MAX_US as u64 {
+            return Err(Error::InvalidConfig);
+        }
+        Ok(WakeupPulse(duration))
+    }
+
+    /// Pulse of `bits` bit times at `speed`, fails with `Error::InvalidConfig` if the length is
+    /// outside of the allowed range
+    pub const fn try_from_bits(bits: u32, speed: LinSpeed) -> Result<WakeupPulse, Error> {
+        WakeupPulse::try_new(timing::bits(bits, speed))
+    }
+
+    pub const fn duration(self) -> Duration {
+        self.0
+    }
+
+    /// Baud rate at which the 8 dominant bit times of the `WAKEUP_BYTE` last the pulse length
+    pub const fn baud_rate(self) -> u32 {
+        (8_000_000 / self.0.as_micros()) as u32
+    }
+}
+
+/// Send the wakeup signal with the length `pulse` by transmitting the `WAKEUP_BYTE` at
+/// `WakeupPulse::baud_rate`. The baud rate of the bus `speed` is restored afterwards, also if
+/// the transmission failed.
+pub fn send_wakeup_by_baud_switch<U>(
+    uart: &mut U,
+    speed: LinSpeed,
+    pulse: WakeupPulse,
+) -> Result<(), Error>
+where
+    U: SetBaudRate + WriteByte,
+{
+    uart.set_baud_rate(pulse.baud_rate())?;
+    let result = uart.write_byte(WAKEUP_BYTE);
+    uart.set_baud_rate(speed.bits_per_second())?;
+    result
+}
+
+/// UARTs which can change their baud rate at runtime, e.g. to generate the break by sending a
+/// byte at a lower baud rate
+pub trait SetBaudRate {
+    /// Set the baud rate in bit/s, pending bytes have been transmitted before
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error>;
+}
+
+/// UARTs which can transmit a single byte
+pub trait WriteByte {
+    /// Transmit `byte` and block until it has been sent completely
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error>;
+}
+
+/// Length of the break field and the break delimiter in nominal bit times
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BreakConfig {
+    break_bits: u32,
+    delimiter_bits: u32,
+}
+
+impl BreakConfig {
+    /// The minimum break of 13 bit times with a delimiter of one bit time
+    pub const MINIMUM: BreakConfig = BreakConfig {
+        break_bits: timing::BREAK_BITS,
+        delimiter_bits: timing::BREAK_DELIMITER_BITS,
+    };
+
+    /// The break of 0x00 transmitted at half the baud rate, 18 bit times with a delimiter of 2
+    /// bit times
+    pub const HALF_BAUD_RATE: BreakConfig = BreakConfig {
+        break_bits: 18,
+        delimiter_bits: 2,
+    };
+
+    /// Fails with `Error::InvalidConfig` if the break or the delimiter are shorter than the
+    /// minimum of the standard
+    pub const fn try_new(break_bits: u32, delimiter_bits: u32) -> Result<BreakConfig, Error> {
+        if break_bits < timing::BREAK_BITS || delimiter_bits < timing::BREAK_DELIMITER_BITS {
+            return Err(Error::InvalidConfig);
+        }
+        Ok(BreakConfig::new_unchecked(break_bits, delimiter_bits))
+    }
+
+    /// Create the configuration without checking the minimum lengths, e.g. for test tools
+    /// generating too short breaks
+    pub const fn new_unchecked(break_bits: u32, delimiter_bits: u32) -> BreakConfig {
+        BreakConfig {
+            break_bits,
+            delimiter_bits,
+        }
+    }
+
+    pub const fn break_bits(self) -> u32 {
+        self.break_bits
+    }
+
+    pub const fn delimiter_bits(self) -> u32 {
+        self.delimiter_bits
+    }
+
+    /// Baud rate at which the 9 dominant bits of 0x00, the start bit and the data bits, last
+    /// the break at the bus `speed`. The delimiter is the stop bit at this baud rate, i.e. the
+    /// ninth part of the break.
+    pub const fn baud_rate(self, speed: LinSpeed) -> u32 {
+        let break_bits = if self.break_bits == 0 {
+            1
+        } else {
+            self.break_bits
+        };
+        (speed.bits_per_second() as u64 * 9 / break_bits as u64) as u32
+    }
+}
+
+impl Default for BreakConfig {
+    fn default() -> BreakConfig {
+        BreakConfig::MINIMUM
+    }
+}
+
+/// Generate a break on a UART without break support by transmitting 0x00 at half of the baud
+/// rate of the bus `speed`. This results in a dominant level of 18 nominal bit times followed
+/// by a break delimiter of 2 bit times. The baud rate of the bus is restored afterwards, also
+/// if the transmission failed.
+pub fn send_break_by_baud_switch<U>(uart: &mut U, speed: LinSpeed) -> Result<(), Error>
+where
+    U: SetBaudRate + WriteByte,
+{
+    send_configured_break_by_baud_switch(uart, speed, BreakConfig::HALF_BAUD_RATE)
+}
+
+/// Generate the break `config` like `send_break_by_baud_switch` by transmitting 0x00 at
+/// `BreakConfig::baud_rate`. Delimiters longer than the stop bit at this baud rate are not
+/// generated, they require e.g. a break generated with a GPIO.
+pub fn send_configured_break_by_baud_switch<U>(
+    uart: &mut U,
+    speed: LinSpeed,
+    config: BreakConfig,
+) -> Result<(), Error>
+where
+    U: SetBaudRate + WriteByte,
+{
+    uart.set_baud_rate(config.baud_rate(speed))?;
+    let result = uart.write_byte(0x00);
+    uart.set_baud_rate(speed.bits_per_second())?;
+    result
+}
+
+/// Timeout of the reception of a response, measured from the end of the header
+///
+/// Only `serial::SerialPortMaster` applies it. The other drivers wait as long as their UART or
+/// adapter does, e.g. for the timeout of `hal::TimeoutSerial`, which can be derived from
+/// `timing::max_response_time` as well.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResponseTimeout {
+    /// The maximum response time of the frame, 140 % of the nominal time of the data and
+    /// checksum bytes at the bus speed, see `timing::max_response_time`
+    Auto,
+    /// The same timeout for all frames
+    Fixed(Duration),
+}
+
+impl ResponseTimeout {
+    /// The timeout of a response with `data_length` data bytes at `speed`
+    pub const fn duration(self, data_length: usize, speed: LinSpeed) -> Duration {
+        match self {
+            ResponseTimeout::Auto => timing::max_response_time(data_length, speed),
+            ResponseTimeout::Fixed(timeout) => timeout,
+        }
+    }
+}
+
+impl Default for ResponseTimeout {
+    fn default() -> ResponseTimeout {
+        ResponseTimeout::Auto
+    }
+}
+
+/// Gaps inserted on transmission, e.g. for slow slaves which can not receive the bytes of a
+/// frame back to back
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InterByteSpace {
+    /// Space between the break delimiter, the sync field and the PID
+    pub header: Duration,
+    /// Space between two bytes of the response
+    pub response: Duration,
+}
+
+impl InterByteSpace {
+    /// Back to back transmission
+    pub const NONE: InterByteSpace = InterByteSpace {
+        header: Duration::ZERO,
+        response: Duration::ZERO,
+    };
+
+    /// Whether the response spaces fit into the response tolerance of a frame with
+    /// `data_length` data bytes, see `timing::inter_byte_budget`
+    pub fn fits(&self, data_length: usize, speed: LinSpeed) -> bool {
+        let spaces = Duration::from_micros(self.response.as_micros() * data_length as u64);
+        spaces <= timing::inter_byte_budget(data_length, speed)
+    }
+}
+
+/// Send the header of `pid` like the default `Master::send_header`, waiting `space` between
+/// the fields. The driver has to implement `Master::send_break`.
+pub fn send_header_spaced<M, D>(
+    master: &mut M,
+    pid: PID,
+    space: Duration,
+    delay: &mut D,
+) -> Result<(), M::Error>
+where
+    M: Master + ?Sized,
+    D: Delay + ?Sized,
+{
+    master.send_break()?;
+    delay.delay(space);
+    master.send_sync()?;
+    delay.delay(space);
+    master.send_pid(pid)
+}
+
+/// Write `data` byte by byte, waiting `space` between two bytes
+pub fn write_spaced<M, D>(
+    master: &mut M,
+    data: &[u8],
+    space: Duration,
+    delay: &mut D,
+) -> Result<(), M::Error>
+where
+    M: Master + ?Sized,
+    D: Delay + ?Sized,
+{
+    for (i, byte) in data.iter().enumerate() {
+        if i > 0 {
+            delay.delay(space);
+        }
+        master.write(core::slice::from_ref(byte))?;
+    }
+    Ok(())
+}
+
+/// Control of a LIN transceiver with sleep support, e.g. through the EN pin of TJA1021 or
+/// TJA1027 style transceivers
+pub trait Transceiver {
+    /// Switch to normal mode
+    fn enable(&mut self) -> Result<(), Error>;
+    /// Switch to the low power sleep mode
+    fn sleep(&mut self) -> Result<(), Error>;
+    /// Leave the sleep mode because of a local wakeup request, the transceiver is in normal
+    /// mode afterwards
+    fn wake(&mut self) -> Result<(), Error>;
+
+    /// Report a physical fault detected by the transceiver, like `Error::BusStuckDominant` or
+    /// `Error::NoBusSupply`. The default implementation is for transceivers without fault
+    /// detection.
+    fn check_fault(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Optional capability of drivers which timestamp the bus traffic, e.g. with a hardware timer
+/// or the UART interrupts. The instants are of a driver specific time base.
+pub trait Timestamping {
+    /// Time at which the break of the last header started
+    fn header_timestamp(&self) -> Option<Instant>;
+    /// Time at which the last byte of the last response was transmitted or received
+    fn response_timestamp(&self) -> Option<Instant>;
+}
+
+/// A driver has to implement either `send_header` or `send_break`. The default `send_header`
+/// sends the break followed by the sync byte and the PID written with `write`, so e.g. UART
+/// based drivers only need to implement the break.
 pub trait Master {
     type Error: Into<crate::Error> + From<crate::Error>;
     fn send_wakeup(&mut self) -> Result<(), Self::Error>;
-    fn send_header(&mut self, pid: PID) -> Result<(), Self::Error>;
+
+    /// Send the complete frame header, i.e. break, sync field and PID
+    fn send_header(&mut self, pid: PID) -> Result<(), Self::Error> {
+        self.send_break()?;
+        self.send_sync()?;
+        self.send_pid(pid)
+    }
+
+    /// Send the break field. Only needs to be implemented if the default `send_header` is used,
+    /// fails with `Error::PhysicalBus` otherwise.
+    fn send_break(&mut self) -> Result<(), Self::Error> {
+        Err(Error::PhysicalBus.into())
+    }
+
+    /// Send the sync field
+    fn send_sync(&mut self) -> Result<(), Self::Error> {
+        self.write(&[SYNC_BYTE])
+    }
+
+    /// Send the protected identifier field
+    fn send_pid(&mut self, pid: PID) -> Result<(), Self::Error> {
+        self.write(&[pid.get()])
+    }
+
+    /// Read the response into `buf`. If the response ends early, fails with
+    /// `Error::NoResponse` or `Error::Partial` with the received bytes at the start of `buf`.
     fn read(&mut self, buf: &mut [u8]) -> Result<(), Self::Error>;
     fn write(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Read the response like `read` and fold its checksum into `accumulator` while the bytes
+    /// arrive. Returns `true` if the received checksum is correct, the master layer then skips
+    /// its own verification. The default implementation returns `false` for drivers which do
+    /// not verify checksums.
+    fn read_verified(
+        &mut self,
+        buf: &mut [u8],
+        _accumulator: ChecksumAccumulator,
+    ) -> Result<bool, Self::Error> {
+        self.read(buf).map(|()| false)
+    }
+}
+
+/// Asynchronous variant of `Master` for drivers used with async executors
+#[cfg(feature = "async")]
+#[cfg_attr(feature = "async", allow(async_fn_in_trait))]
+pub trait AsyncMaster {
+    type Error: Into<crate::Error> + From<crate::Error>;
+    async fn send_wakeup(&mut self) -> Result<(), Self::Error>;
+
+    /// Send the complete frame header, i.e. break, sync field and PID
+    async fn send_header(&mut self, pid: PID) -> Result<(), Self::Error> {
+        self.send_break().await?;
+        self.send_sync().await?;
+        self.send_pid(pid).await
+    }
+
+    /// Send the break field. Only needs to be implemented if the default `send_header` is used,
+    /// fails with `Error::PhysicalBus` otherwise.
+    async fn send_break(&mut self) -> Result<(), Self::Error> {
+        Err(Error::PhysicalBus.into())
+    }
+
+    /// Send the sync field
+    async fn send_sync(&mut self) -> Result<(), Self::Error> {
+        self.write(&[SYNC_BYTE]).await
+    }
+
+    /// Send the protected identifier field
+    async fn send_pid(&mut self, pid: PID) -> Result<(), Self::Error> {
+        self.write(&[pid.get()]).await
+    }
+
+    async fn read(&mut self, buf: &mut [u8]) -> Result<(), Self::Error>;
+    async fn write(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// See `Master::read_verified`
+    async fn read_verified(
+        &mut self,
+        buf: &mut [u8],
+        _accumulator: ChecksumAccumulator,
+    ) -> Result<bool, Self::Error> {
+        self.read(buf).await.map(|()| false)
+    }
+}
+
+/// Event observed on the bus by a `Monitor`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BusEvent {
+    /// A break field, usually reported by UARTs as framing error with the data byte 0x00
+    Break,
+    /// A received byte
+    Byte(u8),
+    /// The bus was recessive for longer than the inter-byte space, e.g. after a missing or
+    /// incomplete response
+    Idle,
+}
+
+/// Receive-only driver, e.g. a UART connected to the RX line of a transceiver, used to observe
+/// the bus without taking part in the communication
+pub trait Monitor {
+    type Error: Into<crate::Error> + From<crate::Error>;
+
+    /// Block until the next event on the bus
+    fn next_event(&mut self) -> Result<BusEvent, Self::Error>;
+}
+
+/// Driver handing complete transfers to DMA or an interrupt handler instead of blocking
+///
+/// A transfer consists of the header followed by the response including the checksum. It is
+/// started with `start_write` or `start_read` and then polled with `poll_complete` until it is
+/// ready, e.g. from the DMA transfer complete interrupt. Only one transfer can be in progress.
+pub trait SplitMaster {
+    type Error: Into<crate::Error> + From<crate::Error>;
+
+    /// Start sending the header of `pid` followed by `data`, which includes the checksum
+    fn start_write(&mut self, pid: PID, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Start sending the header of `pid` and receiving a response of `length` bytes including
+    /// the checksum
+    fn start_read(&mut self, pid: PID, length: usize) -> Result<(), Self::Error>;
+
+    /// Check whether the transfer is complete. The response of a completed read is copied
+    /// into `buf`, which has the length passed to `start_read`.
+    fn poll_complete(&mut self, buf: &mut [u8]) -> Poll<Result<(), Self::Error>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Driver recording written bytes, a break is recorded as 0x00
+    #[derive(Default)]
+    struct Recorder {
+        written: [u8; 8],
+        len: usize,
+    }
+
+    impl Master for Recorder {
+        type Error = Error;
+
+        fn send_wakeup(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn send_break(&mut self) -> Result<(), Error> {
+            self.write(&[0x00])
+        }
+
+        fn read(&mut self, _buf: &mut [u8]) -> Result<(), Error> {
+            Err(Error::Timeout)
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.written[self.len..self.len + data.len()].copy_from_slice(data);
+            self.len += data.len();
+            Ok(())
+        }
+    }
+
+    /// UART recording bytes with the baud rate they were sent at
+    #[derive(Default)]
+    struct Uart {
+        baud_rate: u32,
+        written: Option<(u8, u32)>,
+    }
+
+    impl SetBaudRate for Uart {
+        fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+            self.baud_rate = baud_rate;
+            Ok(())
+        }
+    }
+
+    impl WriteByte for Uart {
+        fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+            self.written = Some((byte, self.baud_rate));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_send_break_by_baud_switch() {
+        let mut uart = Uart::default();
+        send_break_by_baud_switch(&mut uart, LinSpeed::BAUD_19200).unwrap();
+        assert_eq!(uart.written, Some((0x00, 9600)));
+        assert_eq!(uart.baud_rate, 19200);
+    }
+
+    #[test]
+    fn test_response_timeout() {
+        let timeout = ResponseTimeout::default();
+        assert_eq!(
+            timeout.duration(8, LinSpeed(19200)),
+            Duration::from_micros(6_562)
+        );
+        assert_eq!(
+            timeout.duration(8, LinSpeed(9600)),
+            Duration::from_micros(13_125)
+        );
+        let timeout = ResponseTimeout::Fixed(Duration::from_millis(20));
+        assert_eq!(
+            timeout.duration(1, LinSpeed(19200)),
+            Duration::from_millis(20)
+        );
+    }
+
+    #[test]
+    fn test_break_config() {
+        let config = BreakConfig::try_new(26, 2).unwrap();
+        let mut uart = Uart::default();
+        send_configured_break_by_baud_switch(&mut uart, LinSpeed::BAUD_19200, config).unwrap();
+        assert_eq!(uart.written, Some((0x00, 6646)));
+        assert_eq!(uart.baud_rate, 19200);
+        assert_eq!(
+            BreakConfig::HALF_BAUD_RATE.baud_rate(LinSpeed::BAUD_19200),
+            9600
+        );
+        assert_eq!(BreakConfig::try_new(12, 1), Err(Error::InvalidConfig));
+        assert_eq!(BreakConfig::try_new(13, 0), Err(Error::InvalidConfig));
+        assert_eq!(BreakConfig::new_unchecked(10, 1).break_bits(), 10);
+    }
+
+    #[test]
+    fn test_wakeup_pulse() {
+        let pulse = WakeupPulse::try_new(Duration::from_millis(1)).unwrap();
+        assert_eq!(pulse.baud_rate(), 8_000);
+        let mut uart = Uart::default();
+        send_wakeup_by_baud_switch(&mut uart, LinSpeed::BAUD_19200, pulse).unwrap();
+        assert_eq!(uart.written, Some((WAKEUP_BYTE, 8_000)));
+        assert_eq!(uart.baud_rate, 19200);
+
+        let pulse = WakeupPulse::try_from_bits(96, LinSpeed(19200)).unwrap();
+        assert_eq!(pulse.duration(), Duration::from_micros(5_000));
+        assert_eq!(
+            WakeupPulse::try_from_bits(4, LinSpeed(19200)),
+            Err(Error::InvalidConfig)
+        );
+        assert_eq!(
+            WakeupPulse::try_new(Duration::from_micros(5_001)),
+            Err(Error::InvalidConfig)
+        );
+    }
+
+    #[test]
+    fn test_default_send_header() {
+        let mut driver = Recorder::default();
+        driver.send_header(PID::from_id(0x10)).unwrap();
+        assert_eq!(&driver.written[..driver.len], &[0x00, 0x55, 0x50]);
+    }
 }
@@ -0,0 +1,243 @@
+//! Driver wrapper discarding the echo of the transceiver
+//!
+//! Single-wire transceivers return every transmitted byte on RX, so a byte-oriented driver
+//! receives the header and the published responses of the master before the responses of
+//! the slaves. `EchoMaster` wraps such a driver and reads back the `Echo` after every
+//! transmission: the echo of the break is discarded, as it is usually received with a
+//! framing error, the echo of the other bytes is compared to the transmitted bytes and a
+//! mismatch is reported as `Error::Collision`. A missing echo is reported as
+//! `Error::PhysicalBus`. With `Echo::None` all calls are passed through, so the same
+//! application code runs on hardware with and without echo.
+//!
+//! `uart::UartMaster` reads back the echo itself, see `UartMaster::with_echo`.
+
+use super::{Error, Master, SYNC_BYTE};
+use crate::frame::{ChecksumAccumulator, PID};
+
+/// Bytes returned by the UART because of the echo of the transceiver
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Echo {
+    /// The UART does not return transmitted bytes
+    None,
+    /// Transmitted bytes are returned, the break is not
+    Data,
+    /// Transmitted bytes are returned and the break as one byte, usually with a framing error
+    BreakAndData,
+}
+
+/// Driver wrapper, see the module documentation
+pub struct EchoMaster<M> {
+    driver: M,
+    echo: Echo,
+}
+
+impl<M: Master> EchoMaster<M> {
+    pub fn new(driver: M, echo: Echo) -> EchoMaster<M> {
+        EchoMaster { driver, echo }
+    }
+
+    pub fn echo(&self) -> Echo {
+        self.echo
+    }
+
+    /// Release the wrapped driver
+    pub fn free(self) -> M {
+        self.driver
+    }
+
+    /// Read the echo of `data` and compare it
+    fn check_echo(&mut self, data: &[u8]) -> Result<(), M::Error> {
+        if self.echo == Echo::None {
+            return Ok(());
+        }
+        let mut buf = [0u8; 9];
+        for chunk in data.chunks(buf.len()) {
+            let echo = &mut buf[..chunk.len()];
+            self.read_echo(echo)?;
+            if echo != chunk {
+                return Err(Error::Collision.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Discard the echo of a break or a byte transmitted at another baud rate, which may be
+    /// received with a framing error
+    fn skip_echo(&mut self) -> Result<(), M::Error> {
+        match self.read_echo(&mut [0u8]) {
+            Err(e) => match e.into() {
+                Error::Framing => Ok(()),
+                e => Err(e.into()),
+            },
+            Ok(()) => Ok(()),
+        }
+    }
+
+    fn read_echo(&mut self, buf: &mut [u8]) -> Result<(), M::Error> {
+        self.driver.read(buf).map_err(|e| match e.into() {
+            Error::NoResponse | Error::Partial(_) => Error::PhysicalBus.into(),
+            e => e.into(),
+        })
+    }
+}
+
+impl<M: Master> Master for EchoMaster<M> {
+    type Error = M::Error;
+
+    fn send_wakeup(&mut self) -> Result<(), M::Error> {
+        self.driver.send_wakeup()?;
+        match self.echo {
+            Echo::None => Ok(()),
+            Echo::Data | Echo::BreakAndData => self.skip_echo(),
+        }
+    }
+
+    /// The header of the wrapped driver is read back at once, so also drivers implementing
+    /// `send_header` themselves are supported
+    fn send_header(&mut self, pid: PID) -> Result<(), M::Error> {
+        self.driver.send_header(pid)?;
+        if self.echo == Echo::BreakAndData {
+            self.skip_echo()?;
+        }
+        self.check_echo(&[SYNC_BYTE, pid.get()])
+    }
+
+    fn send_break(&mut self) -> Result<(), M::Error> {
+        self.driver.send_break()?;
+        match self.echo {
+            Echo::BreakAndData => self.skip_echo(),
+            Echo::None | Echo::Data => Ok(()),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), M::Error> {
+        self.driver.read(buf)
+    }
+
+    fn read_verified(
+        &mut self,
+        buf: &mut [u8],
+        accumulator: ChecksumAccumulator,
+    ) -> Result<bool, M::Error> {
+        self.driver.read_verified(buf, accumulator)
+    }
+
+    /// With echo, fails with `Error::Collision` if the echo differs from `data`
+    fn write(&mut self, data: &[u8]) -> Result<(), M::Error> {
+        self.driver.write(data)?;
+        self.check_echo(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frame;
+
+    /// Driver with a receive queue, which optionally echoes the transmitted bytes
+    #[derive(Default)]
+    struct Driver {
+        echo: bool,
+        /// Whether the echo of the break is received with a framing error
+        break_framing: bool,
+        received: [Option<u8>; 32],
+        len: usize,
+        written: usize,
+    }
+
+    impl Driver {
+        fn push(&mut self, byte: Option<u8>) {
+            self.received[self.len] = byte;
+            self.len += 1;
+        }
+    }
+
+    impl Master for Driver {
+        type Error = Error;
+
+        fn send_wakeup(&mut self) -> Result<(), Error> {
+            if self.echo {
+                self.push(None);
+            }
+            Ok(())
+        }
+
+        fn send_break(&mut self) -> Result<(), Error> {
+            if self.echo {
+                self.push(if self.break_framing { None } else { Some(0x00) });
+            }
+            Ok(())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                if self.len == 0 {
+                    return Err(Error::incomplete_response(i));
+                }
+                let received = self.received[0];
+                self.received.copy_within(1.., 0);
+                self.len -= 1;
+                *byte = received.ok_or(Error::Framing)?;
+            }
+            Ok(())
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.written += data.len();
+            if self.echo {
+                data.iter().for_each(|byte| self.push(Some(*byte)));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_echo() {
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        // The echo of the break is received as 0x00 or with a framing error
+        for break_framing in [false, true] {
+            let driver = Driver {
+                echo: true,
+                break_framing,
+                ..Default::default()
+            };
+            let mut master = EchoMaster::new(driver, Echo::BreakAndData);
+            crate::Master::write_frame(&mut master, &frame).unwrap();
+            Master::send_wakeup(&mut master).unwrap();
+            master.driver.push(Some(0x03));
+            let mut buf = [0u8; 1];
+            Master::read(&mut master, &mut buf).unwrap();
+            assert_eq!(buf, [0x03]);
+            assert_eq!(master.driver.len, 0);
+        }
+
+        // Without the echo of the break, e.g. for a break generated by a GPIO
+        let mut master = EchoMaster::new(Driver::default(), Echo::Data);
+        Master::send_break(&mut master).unwrap();
+        master.driver.echo = true;
+        Master::write(&mut master, &[0x55, 0x50]).unwrap();
+        assert_eq!(master.driver.len, 0);
+    }
+
+    #[test]
+    fn test_no_echo() {
+        let mut master = EchoMaster::new(Driver::default(), Echo::None);
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        crate::Master::write_frame(&mut master, &frame).unwrap();
+        Master::send_wakeup(&mut master).unwrap();
+        assert_eq!(master.free().written, 5);
+    }
+
+    #[test]
+    fn test_echo_mismatch() {
+        let mut master = EchoMaster::new(Driver::default(), Echo::Data);
+        master.driver.push(Some(0x54));
+        assert_eq!(Master::send_sync(&mut master), Err(Error::Collision));
+        assert_eq!(Master::send_sync(&mut master), Err(Error::PhysicalBus));
+        master.driver.push(Some(0x55));
+        assert_eq!(
+            Master::send_header(&mut master, PID::from_id(0x10)),
+            Err(Error::PhysicalBus)
+        );
+    }
+}
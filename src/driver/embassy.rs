@@ -0,0 +1,236 @@
+//! Async LIN master driver for embassy UARTs
+//!
+//! Works with any UART implementing the `embedded-io-async` traits, like the `BufferedUart` of
+//! the embassy HALs. As there is no common API to change the baud rate, the UART has to
+//! implement `SetBaudRate`, which usually forwards to the `set_config` or `set_baudrate` method
+//! of the HAL.
+//!
+//! The break is generated by transmitting 0x00 at half the nominal baud rate, resulting in a
+//! dominant level of 18 bit times followed by a delimiter of 2 bit times. The wakeup signal is
+//! a `WAKEUP_BYTE` at the nominal baud rate, or at a lower one for a pulse configured with
+//! `EmbassyMaster::with_wakeup_pulse`. The UART must not return the bytes it transmitted
+//! itself.
+
+use embedded_io_async::{Read, Write};
+
+use super::uart::map_error;
+use super::{AsyncMaster, BreakConfig, SetBaudRate, WakeupPulse, WAKEUP_BYTE};
+use crate::ldf::LinSpeed;
+use crate::Error;
+
+/// Async LIN master on top of an embassy UART, see the module documentation
+pub struct EmbassyMaster<U> {
+    uart: U,
+    speed: LinSpeed,
+    wakeup_pulse: Option<WakeupPulse>,
+    break_config: BreakConfig,
+}
+
+impl<U> EmbassyMaster<U>
+where
+    U: Read + Write + SetBaudRate,
+{
+    /// Create the driver for a bus at `speed`, the UART must already be configured to it
+    pub fn new(uart: U, speed: LinSpeed) -> EmbassyMaster<U> {
+        EmbassyMaster {
+            uart,
+            speed,
+            wakeup_pulse: None,
+            break_config: BreakConfig::HALF_BAUD_RATE,
+        }
+    }
+
+    /// Returns the driver sending the wakeup signal with the length `pulse` by transmitting
+    /// the `WAKEUP_BYTE` at `WakeupPulse::baud_rate`
+    pub fn with_wakeup_pulse(self, pulse: WakeupPulse) -> EmbassyMaster<U> {
+        EmbassyMaster {
+            wakeup_pulse: Some(pulse),
+            ..self
+        }
+    }
+
+    /// Returns the driver sending breaks of the given length by transmitting 0x00 at
+    /// `BreakConfig::baud_rate`, the default is `BreakConfig::HALF_BAUD_RATE`
+    pub fn with_break(self, config: BreakConfig) -> EmbassyMaster<U> {
+        EmbassyMaster {
+            break_config: config,
+            ..self
+        }
+    }
+
+    /// Release the UART
+    pub fn free(self) -> U {
+        self.uart
+    }
+}
+
+impl<U> AsyncMaster for EmbassyMaster<U>
+where
+    U: Read + Write + SetBaudRate,
+{
+    type Error = Error;
+
+    async fn send_wakeup(&mut self) -> Result<(), Error> {
+        let pulse = match self.wakeup_pulse {
+            Some(pulse) => pulse,
+            None => return self.write(&[WAKEUP_BYTE]).await,
+        };
+        self.uart.flush().await.map_err(map_error)?;
+        self.uart.set_baud_rate(pulse.baud_rate())?;
+        let result = self.write(&[WAKEUP_BYTE]).await;
+        self.uart.set_baud_rate(self.speed.bits_per_second())?;
+        result
+    }
+
+    async fn send_break(&mut self) -> Result<(), Error> {
+        self.uart.flush().await.map_err(map_error)?;
+        self.uart
+            .set_baud_rate(self.break_config.baud_rate(self.speed))?;
+        let result = self.write(&[0x00]).await;
+        self.uart.set_baud_rate(self.speed.bits_per_second())?;
+        result
+    }
+
+    /// Reading fails with `Error::NoResponse` or `Error::Partial` if the UART times out or
+    /// reports the end of the input before the buffer is full
+    async fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let mut received = 0;
+        while received < buf.len() {
+            match self
+                .uart
+                .read(&mut buf[received..])
+                .await
+                .map_err(map_error)
+            {
+                Ok(0) | Err(Error::Timeout) => return Err(Error::incomplete_response(received)),
+                Ok(n) => received += n,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.uart.write_all(data).await.map_err(map_error)?;
+        self.uart.flush().await.map_err(map_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AsyncMaster, Frame, PID};
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+    use embedded_io_async::{ErrorKind, ErrorType};
+
+    /// Poll a future which never returns `Pending` to completion
+    fn block_on<F: Future>(future: F) -> F::Output {
+        const VTABLE: RawWakerVTable = RawWakerVTable::new(
+            |_| RawWaker::new(core::ptr::null(), &VTABLE),
+            |_| {},
+            |_| {},
+            |_| {},
+        );
+        let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) };
+        match pin!(future).poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("Future is pending"),
+        }
+    }
+
+    /// UART recording written bytes with the baud rate they were sent at
+    #[derive(Default)]
+    struct Uart {
+        baud_rate: u32,
+        written: [(u8, u32); 8],
+        len: usize,
+    }
+
+    impl ErrorType for Uart {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Uart {
+        async fn read(&mut self, _buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            Ok(0)
+        }
+    }
+
+    impl Write for Uart {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+            for byte in buf {
+                self.written[self.len] = (*byte, self.baud_rate);
+                self.len += 1;
+            }
+            Ok(buf.len())
+        }
+    }
+
+    impl SetBaudRate for Uart {
+        fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+            self.baud_rate = baud_rate;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let uart = Uart {
+            baud_rate: 19200,
+            ..Default::default()
+        };
+        let mut master = EmbassyMaster::new(uart, LinSpeed(19200));
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01]);
+        block_on(master.write_frame(&frame)).unwrap();
+        let uart = master.free();
+        assert_eq!(
+            &uart.written[..uart.len],
+            &[
+                (0x00, 9600),
+                (0x55, 19200),
+                (0x50, 19200),
+                (0x01, 19200),
+                (0xAE, 19200)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wakeup_pulse() {
+        let uart = Uart {
+            baud_rate: 19200,
+            ..Default::default()
+        };
+        let pulse = WakeupPulse::try_new(crate::time::Duration::from_millis(2)).unwrap();
+        let mut master = EmbassyMaster::new(uart, LinSpeed(19200)).with_wakeup_pulse(pulse);
+        block_on(master.send_wakeup()).unwrap();
+        let uart = master.free();
+        assert_eq!(&uart.written[..uart.len], &[(WAKEUP_BYTE, 4_000)]);
+        assert_eq!(uart.baud_rate, 19200);
+    }
+
+    #[test]
+    fn test_break_config() {
+        let uart = Uart {
+            baud_rate: 19200,
+            ..Default::default()
+        };
+        let config = BreakConfig::try_new(27, 3).unwrap();
+        let mut master = EmbassyMaster::new(uart, LinSpeed(19200)).with_break(config);
+        block_on(crate::driver::AsyncMaster::send_break(&mut master)).unwrap();
+        let uart = master.free();
+        assert_eq!(&uart.written[..uart.len], &[(0x00, 6400)]);
+        assert_eq!(uart.baud_rate, 19200);
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let mut master = EmbassyMaster::new(Uart::default(), LinSpeed(19200));
+        assert_eq!(
+            block_on(master.read_frame(PID::from_id(0x10), 1)),
+            Err(Error::NoResponse)
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Driver wrapper injecting faults for tests of error handling
+//!
+//! `FaultInjector` wraps another driver, e.g. a `Loopback`, and applies one `Fault` of a
+//! script to every frame, starting with the next header. Frames after the end of the script
+//! are passed through unchanged.
+//!
+//! ```
+//! use lin_bus::driver::fault::{Fault, FaultInjector};
+//! use lin_bus::driver::loopback::Loopback;
+//! use lin_bus::{driver, Master, PID};
+//!
+//! let pid = PID::from_id(0x10);
+//! let mut loopback = Loopback::new();
+//! loopback.set_response(pid, &[0x01]);
+//! let mut driver = FaultInjector::new(loopback, &[Fault::CorruptChecksum, Fault::None]);
+//! assert!(matches!(
+//!     driver.read_frame(pid, 1),
+//!     Err(driver::Error::Checksum(_))
+//! ));
+//! assert!(driver.read_frame(pid, 1).is_ok());
+//! ```
+
+use super::{Error, Master};
+use crate::frame::PID;
+
+/// Fault applied to a single frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Transfer the frame unchanged
+    None,
+    /// Invert the least significant bit of the checksum
+    CorruptChecksum,
+    /// Drop the given number of bytes at the end of the response, so it is incomplete
+    DropBytes(usize),
+    /// Delay the response past the timeout of the master
+    DelayResponse,
+    /// Send the header with a wrong parity bit, so no slave responds
+    FlipParity,
+}
+
+/// Driver wrapper, see the module documentation
+pub struct FaultInjector<'a, D> {
+    driver: D,
+    script: &'a [Fault],
+    current: Fault,
+}
+
+impl<'a, D: Master> FaultInjector<'a, D> {
+    pub fn new(driver: D, script: &'a [Fault]) -> FaultInjector<'a, D> {
+        FaultInjector {
+            driver,
+            script,
+            current: Fault::None,
+        }
+    }
+
+    /// Faults for the frames which were not sent yet
+    pub fn remaining(&self) -> &'a [Fault] {
+        self.script
+    }
+
+    /// Release the wrapped driver
+    pub fn free(self) -> D {
+        self.driver
+    }
+}
+
+impl<D: Master> Master for FaultInjector<'_, D> {
+    type Error = D::Error;
+
+    fn send_wakeup(&mut self) -> Result<(), D::Error> {
+        self.driver.send_wakeup()
+    }
+
+    fn send_header(&mut self, pid: PID) -> Result<(), D::Error> {
+        self.current = match self.script.split_first() {
+            Some((fault, rest)) => {
+                self.script = rest;
+                *fault
+            }
+            None => Fault::None,
+        };
+        match self.current {
+            Fault::FlipParity => self.driver.send_header(PID(pid.get() ^ 0x40)),
+            _ => self.driver.send_header(pid),
+        }
+    }
+
+    /// The response is always read from the wrapped driver, also if the fault discards it
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), D::Error> {
+        self.driver.read(buf)?;
+        match self.current {
+            Fault::None => Ok(()),
+            Fault::CorruptChecksum => {
+                if let Some(checksum) = buf.last_mut() {
+                    *checksum ^= 0x01;
+                }
+                Ok(())
+            }
+            Fault::DropBytes(0) => Ok(()),
+            Fault::DropBytes(n) => {
+                Err(Error::incomplete_response(buf.len().saturating_sub(n)).into())
+            }
+            Fault::DelayResponse | Fault::FlipParity => Err(Error::NoResponse.into()),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), D::Error> {
+        match self.current {
+            Fault::CorruptChecksum if !data.is_empty() => {
+                let mut buf = [0u8; 9];
+                let buf = &mut buf[..data.len().min(9)];
+                buf.copy_from_slice(&data[..buf.len()]);
+                buf[buf.len() - 1] ^= 0x01;
+                self.driver.write(buf)
+            }
+            Fault::DropBytes(n) => self.driver.write(&data[..data.len().saturating_sub(n)]),
+            _ => self.driver.write(data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::loopback::Loopback;
+    use crate::{Frame, Master};
+
+    #[test]
+    fn test_read_faults() {
+        let pid = PID::from_id(0x10);
+        let mut loopback = Loopback::new();
+        loopback.set_response(pid, &[0x01]);
+        let script = [
+            Fault::DropBytes(1),
+            Fault::DelayResponse,
+            Fault::FlipParity,
+            Fault::None,
+        ];
+        let mut driver = FaultInjector::new(loopback, &script);
+        assert_eq!(driver.read_frame(pid, 1), Err(Error::Partial(1)));
+        assert_eq!(driver.read_frame(pid, 1), Err(Error::NoResponse));
+        assert_eq!(driver.read_frame(pid, 1), Err(Error::NoResponse));
+        assert_eq!(driver.remaining(), &[Fault::None]);
+        assert!(driver.read_frame(pid, 1).is_ok());
+        assert!(driver.read_frame(pid, 1).is_ok());
+        assert!(driver.free().pending().is_empty());
+    }
+
+    #[test]
+    fn test_write_faults() {
+        let pid = PID::from_id(0x11);
+        let frame = Frame::from_data(pid, &[0x01, 0x02]);
+        let mut driver = FaultInjector::new(
+            Loopback::new(),
+            &[Fault::CorruptChecksum, Fault::DropBytes(2)],
+        );
+        driver.write_frame(&frame).unwrap();
+        let checksum = frame.get_checksum() ^ 0x01;
+        assert_eq!(driver.driver.pending(), &[0x01, 0x02, checksum]);
+        driver.write_frame(&frame).unwrap();
+        assert_eq!(driver.driver.pending(), &[0x01, 0x02, checksum, 0x01]);
+    }
+}
@@ -0,0 +1,471 @@
+//! Adapter for `embedded-hal` serial peripherals with a GPIO driving the break
+//!
+//! Covers the common setup of a UART whose TX line can additionally be pulled dominant by a
+//! spare GPIO. The serial port implements the non-blocking `embedded-hal-nb` traits and is
+//! wrapped in `NbSerial` to be used with the generic `UartMaster`. Instead of the `PinBreak`,
+//! any other `BreakGenerator`, e.g. one switching the baud rate, can be combined with `NbSerial`
+//! and `UartMaster` directly.
+//!
+//! `NbSerial` waits for a byte without a limit. Wrapped in `TimeoutSerial` instead, a read fails
+//! with `Error::Timeout` if no byte is received within the timeout measured with a
+//! `time::Clock`, so a missing response does not block the master.
+//!
+//! ```no_run
+//! # fn example<S, P, D>(serial: S, pin: P, delay: D) -> Result<(), lin_bus::Error>
+//! # where
+//! #     S: embedded_hal_nb::serial::Read + embedded_hal_nb::serial::Write,
+//! #     P: embedded_hal::digital::OutputPin,
+//! #     D: embedded_hal::delay::DelayNs,
+//! # {
+//! use lin_bus::driver::hal;
+//! use lin_bus::ldf::LinSpeed;
+//! use lin_bus::{Master, PID};
+//!
+//! let mut master = hal::try_master(serial, pin, delay, LinSpeed(19200))?;
+//! let frame = master.read_frame(PID::try_from_id(0x10)?, 2)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_nb::nb::{self, block};
+use embedded_hal_nb::serial;
+
+use super::uart::{BreakGenerator, UartMaster};
+use super::{BreakConfig, Transceiver};
+use crate::iso17987::timing;
+use crate::ldf::LinSpeed;
+use crate::time::{Clock, Duration};
+use crate::Error;
+
+/// Error of a serial port wrapped in `NbSerial`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NbSerialError<E>(pub E);
+
+impl<E: serial::Error> embedded_io::Error for NbSerialError<E> {
+    /// Framing, parity and noise errors are mapped to `InvalidData`, i.e. `Error::Framing`
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self.0.kind() {
+            serial::ErrorKind::FrameFormat
+            | serial::ErrorKind::Parity
+            | serial::ErrorKind::Noise => embedded_io::ErrorKind::InvalidData,
+            _ => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+impl<E: core::fmt::Debug> core::fmt::Display for NbSerialError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "serial port error: {:?}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: core::fmt::Debug> std::error::Error for NbSerialError<E> {}
+
+/// Blocking `embedded-io` wrapper for a non-blocking `embedded-hal-nb` serial port
+pub struct NbSerial<S>(pub S);
+
+impl<S: serial::ErrorType> embedded_io::ErrorType for NbSerial<S> {
+    type Error = NbSerialError<S::Error>;
+}
+
+impl<S: serial::Read> embedded_io::Read for NbSerial<S> {
+    /// Blocks until one byte is received
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        match buf.first_mut() {
+            Some(byte) => {
+                *byte = block!(self.0.read()).map_err(NbSerialError)?;
+                Ok(1)
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+impl<S: serial::Write> embedded_io::Write for NbSerial<S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for byte in buf {
+            block!(self.0.write(*byte)).map_err(NbSerialError)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        block!(self.0.flush()).map_err(NbSerialError)
+    }
+}
+
+/// Error of a serial port wrapped in `TimeoutSerial`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeoutSerialError<E> {
+    Serial(E),
+    /// No byte was received within the timeout
+    Timeout,
+}
+
+impl<E: serial::Error> embedded_io::Error for TimeoutSerialError<E> {
+    /// A timeout is mapped to `TimedOut`, i.e. `Error::Timeout`, errors of the serial port like
+    /// in `NbSerialError`
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            TimeoutSerialError::Serial(error) => {
+                embedded_io::Error::kind(&NbSerialError(error.kind()))
+            }
+            TimeoutSerialError::Timeout => embedded_io::ErrorKind::TimedOut,
+        }
+    }
+}
+
+impl<E: core::fmt::Debug> core::fmt::Display for TimeoutSerialError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            TimeoutSerialError::Serial(error) => write!(f, "serial port error: {:?}", error),
+            TimeoutSerialError::Timeout => f.write_str("timeout"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: core::fmt::Debug> std::error::Error for TimeoutSerialError<E> {}
+
+/// Blocking `embedded-io` wrapper for a non-blocking `embedded-hal-nb` serial port, failing
+/// reads after a timeout
+pub struct TimeoutSerial<S, C> {
+    serial: S,
+    clock: C,
+    timeout: Duration,
+}
+
+impl<S, C: Clock> TimeoutSerial<S, C> {
+    /// Wrap `serial`, a read fails if no byte is received within `timeout`
+    pub fn new(serial: S, clock: C, timeout: Duration) -> TimeoutSerial<S, C> {
+        TimeoutSerial {
+            serial,
+            clock,
+            timeout,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Release the serial port and the clock
+    pub fn free(self) -> (S, C) {
+        (self.serial, self.clock)
+    }
+}
+
+impl<S: serial::ErrorType, C> embedded_io::ErrorType for TimeoutSerial<S, C> {
+    type Error = TimeoutSerialError<S::Error>;
+}
+
+impl<S: serial::Read, C: Clock> embedded_io::Read for TimeoutSerial<S, C> {
+    /// Blocks until one byte is received or the timeout elapsed
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let byte = match buf.first_mut() {
+            Some(byte) => byte,
+            None => return Ok(0),
+        };
+        let deadline = self.clock.now() + self.timeout;
+        loop {
+            match self.serial.read() {
+                Ok(received) => {
+                    *byte = received;
+                    return Ok(1);
+                }
+                Err(nb::Error::Other(error)) => return Err(TimeoutSerialError::Serial(error)),
+                Err(nb::Error::WouldBlock) if self.clock.now() >= deadline => {
+                    return Err(TimeoutSerialError::Timeout)
+                }
+                Err(nb::Error::WouldBlock) => {}
+            }
+        }
+    }
+}
+
+impl<S: serial::Write, C> embedded_io::Write for TimeoutSerial<S, C> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for byte in buf {
+            block!(self.serial.write(*byte)).map_err(TimeoutSerialError::Serial)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        block!(self.serial.flush()).map_err(TimeoutSerialError::Serial)
+    }
+}
+
+/// Generates the break by pulling the bus dominant with a GPIO for 13 bit times, followed by
+/// the break delimiter of one bit time, or for the times of a configured `BreakConfig`
+pub struct PinBreak<P, D> {
+    pin: P,
+    delay: D,
+    bit_time_ns: u32,
+    config: BreakConfig,
+}
+
+impl<P: OutputPin, D: DelayNs> PinBreak<P, D> {
+    /// Create the break generator, the pin is set to the recessive (high) level
+    pub fn new(mut pin: P, delay: D, speed: LinSpeed) -> Result<PinBreak<P, D>, Error> {
+        pin.set_high().map_err(|_| Error::PhysicalBus)?;
+        Ok(PinBreak {
+            pin,
+            delay,
+            bit_time_ns: timing::bit_time_ns(speed),
+            config: BreakConfig::MINIMUM,
+        })
+    }
+
+    /// Returns the generator with the given break and delimiter length, the minimum of 13 and
+    /// one bit times by default
+    pub fn with_config(self, config: BreakConfig) -> PinBreak<P, D> {
+        PinBreak { config, ..self }
+    }
+
+    /// Release the pin and the delay
+    pub fn free(self) -> (P, D) {
+        (self.pin, self.delay)
+    }
+}
+
+impl<U, P: OutputPin, D: DelayNs> BreakGenerator<U> for PinBreak<P, D> {
+    fn send_break(&mut self, _uart: &mut U) -> Result<(), Error> {
+        self.pin.set_low().map_err(|_| Error::PhysicalBus)?;
+        let bits = self.config.break_bits();
+        self.delay.delay_ns(bits.saturating_mul(self.bit_time_ns));
+        self.pin.set_high().map_err(|_| Error::PhysicalBus)?;
+        let bits = self.config.delimiter_bits();
+        self.delay.delay_ns(bits.saturating_mul(self.bit_time_ns));
+        Ok(())
+    }
+}
+
+/// Transceiver controlled by its EN pin: high selects normal mode and low selects sleep mode.
+/// A local wakeup switches back to normal mode, the master then sends the wakeup signal.
+pub struct PinTransceiver<P> {
+    enable: P,
+}
+
+impl<P: OutputPin> PinTransceiver<P> {
+    pub fn new(enable: P) -> PinTransceiver<P> {
+        PinTransceiver { enable }
+    }
+
+    /// Release the EN pin
+    pub fn free(self) -> P {
+        self.enable
+    }
+}
+
+impl<P: OutputPin> Transceiver for PinTransceiver<P> {
+    fn enable(&mut self) -> Result<(), Error> {
+        self.enable.set_high().map_err(|_| Error::PhysicalBus)
+    }
+
+    fn sleep(&mut self) -> Result<(), Error> {
+        self.enable.set_low().map_err(|_| Error::PhysicalBus)
+    }
+
+    fn wake(&mut self) -> Result<(), Error> {
+        self.enable()
+    }
+}
+
+/// LIN master on an `embedded-hal-nb` serial port with a GPIO driven break
+pub type HalMaster<S, P, D> = UartMaster<NbSerial<S>, PinBreak<P, D>>;
+
+/// Create a `HalMaster` for the bus running at `speed`
+///
+/// # Panics
+///
+/// Panics if the pin can not be set to the recessive level, see `try_master`.
+#[cfg(any(test, not(feature = "panic-free")))]
+pub fn master<S, P, D>(serial: S, pin: P, delay: D, speed: LinSpeed) -> HalMaster<S, P, D>
+where
+    S: serial::Read + serial::Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    try_master(serial, pin, delay, speed).expect("Failed to set break pin")
+}
+
+/// Create a `HalMaster` for the bus running at `speed`, fails with `Error::PhysicalBus` if the
+/// pin can not be set to the recessive level
+pub fn try_master<S, P, D>(
+    serial: S,
+    pin: P,
+    delay: D,
+    speed: LinSpeed,
+) -> Result<HalMaster<S, P, D>, Error>
+where
+    S: serial::Read + serial::Write,
+    P: OutputPin,
+    D: DelayNs,
+{
+    let break_generator = PinBreak::new(pin, delay, speed)?;
+    Ok(UartMaster::new(NbSerial(serial), break_generator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use embedded_hal_nb::nb;
+
+    /// Records pin levels and delays as one event log
+    #[derive(Default)]
+    struct Log {
+        events: [(char, u32); 8],
+        len: usize,
+    }
+
+    impl Log {
+        fn push(&mut self, event: (char, u32)) {
+            self.events[self.len] = event;
+            self.len += 1;
+        }
+    }
+
+    struct Pin<'a>(&'a core::cell::RefCell<Log>);
+
+    impl embedded_hal::digital::ErrorType for Pin<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for Pin<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(('L', 0));
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().push(('H', 0));
+            Ok(())
+        }
+    }
+
+    struct Delay<'a>(&'a core::cell::RefCell<Log>);
+
+    impl DelayNs for Delay<'_> {
+        fn delay_ns(&mut self, ns: u32) {
+            self.0.borrow_mut().push(('D', ns));
+        }
+    }
+
+    /// Serial port echoing written bytes
+    #[derive(Default)]
+    struct Serial {
+        byte: Option<u8>,
+    }
+
+    impl serial::ErrorType for Serial {
+        type Error = serial::ErrorKind;
+    }
+
+    impl serial::Read for Serial {
+        fn read(&mut self) -> nb::Result<u8, serial::ErrorKind> {
+            self.byte
+                .take()
+                .ok_or(nb::Error::Other(serial::ErrorKind::Overrun))
+        }
+    }
+
+    impl serial::Write for Serial {
+        fn write(&mut self, byte: u8) -> nb::Result<(), serial::ErrorKind> {
+            self.byte = Some(byte);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), serial::ErrorKind> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_pin_break() {
+        let log = core::cell::RefCell::new(Log::default());
+        let mut master = master(Serial::default(), Pin(&log), Delay(&log), LinSpeed(20000));
+        crate::driver::Master::send_break(&mut master).unwrap();
+        let log = log.borrow();
+        assert_eq!(
+            &log.events[..log.len],
+            &[('H', 0), ('L', 0), ('D', 650_000), ('H', 0), ('D', 50_000)]
+        );
+    }
+
+    #[test]
+    fn test_pin_break_config() {
+        let log = core::cell::RefCell::new(Log::default());
+        let config = BreakConfig::try_new(20, 2).unwrap();
+        let mut generator = PinBreak::new(Pin(&log), Delay(&log), LinSpeed(20000))
+            .unwrap()
+            .with_config(config);
+        BreakGenerator::<()>::send_break(&mut generator, &mut ()).unwrap();
+        let log = log.borrow();
+        assert_eq!(
+            &log.events[..log.len],
+            &[
+                ('H', 0),
+                ('L', 0),
+                ('D', 1_000_000),
+                ('H', 0),
+                ('D', 100_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pin_transceiver() {
+        let log = core::cell::RefCell::new(Log::default());
+        let mut transceiver = PinTransceiver::new(Pin(&log));
+        transceiver.sleep().unwrap();
+        transceiver.wake().unwrap();
+        let log = log.borrow();
+        assert_eq!(&log.events[..log.len], &[('L', 0), ('H', 0)]);
+    }
+
+    #[test]
+    fn test_serial() {
+        use embedded_io::{Read, Write};
+        let mut serial = NbSerial(Serial::default());
+        serial.write_all(&[0x42]).unwrap();
+        let mut buf = [0u8; 2];
+        assert_eq!(serial.read(&mut buf), Ok(1));
+        assert_eq!(buf[0], 0x42);
+        assert_eq!(
+            serial.read(&mut buf),
+            Err(NbSerialError(serial::ErrorKind::Overrun))
+        );
+    }
+
+    /// Serial port never receiving a byte
+    struct Silent;
+
+    impl serial::ErrorType for Silent {
+        type Error = serial::ErrorKind;
+    }
+
+    impl serial::Read for Silent {
+        fn read(&mut self) -> nb::Result<u8, serial::ErrorKind> {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    #[test]
+    fn test_timeout_serial() {
+        use embedded_io::{Error as _, Read};
+        let clock = crate::time::tests::TestClock {
+            step: 100,
+            ..Default::default()
+        };
+        let mut serial = TimeoutSerial::new(Silent, clock, Duration::from_millis(1));
+        let error = serial.read(&mut [0u8; 1]).unwrap_err();
+        assert_eq!(error, TimeoutSerialError::Timeout);
+        assert_eq!(error.kind(), embedded_io::ErrorKind::TimedOut);
+        assert_eq!(serial.free().1.now, 1_100);
+    }
+}
@@ -0,0 +1,222 @@
+//! Loopback driver for tests of higher layers without hardware
+//!
+//! Bytes written by the master are fed back to subsequent reads, like on a single-wire bus
+//! without slaves. Responses can be injected per PID and are queued whenever the header of
+//! the frame is sent, so `Master::read_frame` returns them.
+//!
+//! ```
+//! use lin_bus::driver::loopback::Loopback;
+//! use lin_bus::{Master, PID};
+//!
+//! let mut driver = Loopback::new();
+//! driver.set_response(PID::from_id(0x10), &[0x01, 0x02]);
+//! let frame = driver.read_frame(PID::from_id(0x10), 2).unwrap();
+//! assert_eq!(frame.get_data(), &[0x01, 0x02]);
+//! ```
+
+use super::{Error, Master};
+use crate::frame::{ChecksumModel, PID};
+
+/// Capacity of the buffer holding looped back bytes
+pub const BUFFER_SIZE: usize = 64;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Response {
+    bytes: [u8; 9],
+    len: usize,
+}
+
+/// Loopback driver, see the module documentation
+#[derive(Clone, Debug)]
+pub struct Loopback {
+    responses: [Option<Response>; 64],
+    buffer: [u8; BUFFER_SIZE],
+    len: usize,
+    last_pid: Option<PID>,
+    wakeups: usize,
+}
+
+impl Loopback {
+    pub fn new() -> Loopback {
+        Loopback {
+            responses: [None; 64],
+            buffer: [0u8; BUFFER_SIZE],
+            len: 0,
+            last_pid: None,
+            wakeups: 0,
+        }
+    }
+
+    /// Respond to headers of `pid` with `data` followed by the checksum of the default model of
+    /// the PID
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` exceeds 8 bytes.
+    pub fn set_response(&mut self, pid: PID, data: &[u8]) {
+        self.set_response_with_checksum_model(pid, data, pid.checksum_model());
+    }
+
+    /// Respond to headers of `pid` with `data` followed by the checksum of `checksum_model`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` exceeds 8 bytes.
+    pub fn set_response_with_checksum_model(
+        &mut self,
+        pid: PID,
+        data: &[u8],
+        checksum_model: ChecksumModel,
+    ) {
+        assert!(data.len() <= 8, "Maximum data length is 8 bytes");
+        let mut bytes = [0u8; 9];
+        bytes[..data.len()].copy_from_slice(data);
+        bytes[data.len()] = checksum_model.calculate(pid, data);
+        self.set_raw_response(pid, &bytes[..=data.len()]);
+    }
+
+    /// Respond to headers of `pid` with `bytes` as they are, e.g. to inject a wrong checksum or
+    /// a short response
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` exceeds 9 bytes.
+    pub fn set_raw_response(&mut self, pid: PID, bytes: &[u8]) {
+        assert!(bytes.len() <= 9, "Maximum response length is 9 bytes");
+        let mut response = Response {
+            bytes: [0u8; 9],
+            len: bytes.len(),
+        };
+        response.bytes[..bytes.len()].copy_from_slice(bytes);
+        self.responses[usize::from(pid.get_id())] = Some(response);
+    }
+
+    /// Remove the response of `pid`, headers of it are not answered anymore
+    pub fn clear_response(&mut self, pid: PID) {
+        self.responses[usize::from(pid.get_id())] = None;
+    }
+
+    /// PID of the last sent header
+    pub fn last_pid(&self) -> Option<PID> {
+        self.last_pid
+    }
+
+    /// Number of sent wakeup signals
+    pub fn wakeups(&self) -> usize {
+        self.wakeups
+    }
+
+    /// Bytes which were not read yet
+    pub fn pending(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+        let end = self.len + data.len();
+        if end > BUFFER_SIZE {
+            return Err(Error::PhysicalBus);
+        }
+        self.buffer[self.len..end].copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl Default for Loopback {
+    fn default() -> Self {
+        Loopback::new()
+    }
+}
+
+impl Master for Loopback {
+    type Error = Error;
+
+    fn send_wakeup(&mut self) -> Result<(), Error> {
+        self.wakeups += 1;
+        Ok(())
+    }
+
+    /// Queues the injected response of `pid`, the header itself is not looped back
+    fn send_header(&mut self, pid: PID) -> Result<(), Error> {
+        self.last_pid = Some(pid);
+        match self.responses[usize::from(pid.get_id())] {
+            Some(response) => self.feed(&response.bytes[..response.len]),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails with `Error::NoResponse` or `Error::Partial` if less bytes than requested are
+    /// pending, the pending bytes are consumed in that case
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        if buf.len() > self.len {
+            let received = self.len;
+            buf[..received].copy_from_slice(&self.buffer[..received]);
+            self.len = 0;
+            return Err(Error::incomplete_response(received));
+        }
+        buf.copy_from_slice(&self.buffer[..buf.len()]);
+        self.buffer.copy_within(buf.len()..self.len, 0);
+        self.len -= buf.len();
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.feed(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::master::{PartialResponse, Response};
+    use crate::{Frame, Master};
+
+    #[test]
+    fn test_loopback() {
+        let mut driver = Loopback::new();
+        let pid = PID::from_id(0x11);
+        let frame = Frame::from_data(pid, &[0x01, 0x02]);
+        driver.write_frame(&frame).unwrap();
+        assert_eq!(driver.last_pid(), Some(pid));
+        assert_eq!(driver.pending(), frame.get_data_with_checksum());
+
+        let mut buf = [0u8; 3];
+        crate::driver::Master::read(&mut driver, &mut buf).unwrap();
+        assert_eq!(&buf, frame.get_data_with_checksum());
+        assert_eq!(driver.read_frame(pid, 2), Err(Error::NoResponse));
+    }
+
+    #[test]
+    fn test_injected_responses() {
+        let mut driver = Loopback::new();
+        let pid = PID::from_id(0x10);
+        driver.set_raw_response(pid, &[0x01, 0x00]);
+        match driver.read_frame(pid, 1) {
+            Err(Error::Checksum(mismatch)) => {
+                assert_eq!(mismatch.pid, pid);
+                assert_eq!(mismatch.expected, 0xAE);
+                assert_eq!(mismatch.received, 0x00);
+                assert_eq!(mismatch.response(), &[0x01, 0x00]);
+            }
+            result => panic!("Unexpected {:?}", result),
+        }
+
+        driver.set_response_with_checksum_model(pid, &[0x01], ChecksumModel::Classic);
+        let frame = driver
+            .read_frame_with_checksum_model(pid, 1, ChecksumModel::Classic)
+            .unwrap();
+        assert_eq!(frame.get_checksum(), 0xFE);
+
+        driver.set_raw_response(pid, &[0x01]);
+        assert_eq!(driver.read_frame(pid, 1), Err(Error::Partial(1)));
+        assert_eq!(
+            driver.read_response(pid, 1),
+            Ok(Response::Partial(PartialResponse::new(pid, &[0x01])))
+        );
+
+        driver.clear_response(pid);
+        assert_eq!(driver.read_frame(pid, 1), Err(Error::NoResponse));
+        assert_eq!(driver.read_response(pid, 1), Ok(Response::NoResponse));
+        assert!(driver.pending().is_empty());
+    }
+}
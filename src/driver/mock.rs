@@ -0,0 +1,152 @@
+//! Mock driver checking the bus traffic against a list of expectations
+//!
+//! Every call of the driver consumes the next expected `Transaction` and panics if it does not
+//! match. `Mock::done` panics if expectations are left, so a test fails if the code under test
+//! does less than expected.
+//!
+//! ```
+//! use lin_bus::driver::mock::{Mock, Transaction};
+//! use lin_bus::{Frame, Master, PID};
+//!
+//! let pid = PID::from_id(0x10);
+//! let expectations = [
+//!     Transaction::Header(pid),
+//!     Transaction::Read(&[0x01, 0xAE]),
+//! ];
+//! let mut driver = Mock::new(&expectations);
+//! assert_eq!(driver.read_frame(pid, 1), Ok(Frame::from_data(pid, &[0x01])));
+//! driver.done();
+//! ```
+
+use super::{Error, Master};
+use crate::frame::PID;
+
+/// Expected driver call
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Transaction<'a> {
+    /// `send_wakeup`
+    Wakeup,
+    /// `send_header` with the given PID
+    Header(PID),
+    /// `write` with exactly these bytes
+    Write(&'a [u8]),
+    /// `read`, filling the buffer with these bytes, whose length must match the buffer
+    Read(&'a [u8]),
+    /// `read` failing with the error
+    ReadError(Error),
+}
+
+/// Mock driver, see the module documentation
+#[derive(Clone, Debug)]
+pub struct Mock<'a> {
+    expectations: &'a [Transaction<'a>],
+}
+
+impl<'a> Mock<'a> {
+    pub fn new(expectations: &'a [Transaction<'a>]) -> Mock<'a> {
+        Mock { expectations }
+    }
+
+    /// Expectations which were not consumed yet
+    pub fn remaining(&self) -> &'a [Transaction<'a>] {
+        self.expectations
+    }
+
+    /// Assert that all expectations were consumed
+    ///
+    /// # Panics
+    ///
+    /// Panics if expectations are left.
+    pub fn done(&self) {
+        assert!(
+            self.expectations.is_empty(),
+            "Unconsumed expectations: {:?}",
+            self.expectations
+        );
+    }
+
+    fn next(&mut self, call: &str) -> Transaction<'a> {
+        match self.expectations.split_first() {
+            Some((expectation, rest)) => {
+                self.expectations = rest;
+                *expectation
+            }
+            None => panic!("Unexpected {}, no expectations left", call),
+        }
+    }
+}
+
+impl Master for Mock<'_> {
+    type Error = Error;
+
+    fn send_wakeup(&mut self) -> Result<(), Error> {
+        match self.next("wakeup") {
+            Transaction::Wakeup => Ok(()),
+            expected => panic!("Expected {:?}, got wakeup", expected),
+        }
+    }
+
+    fn send_header(&mut self, pid: PID) -> Result<(), Error> {
+        match self.next("header") {
+            Transaction::Header(expected) if expected == pid => Ok(()),
+            expected => panic!("Expected {:?}, got header {:?}", expected, pid),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        match self.next("read") {
+            Transaction::Read(data) if data.len() == buf.len() => {
+                buf.copy_from_slice(data);
+                Ok(())
+            }
+            Transaction::ReadError(error) => Err(error),
+            expected => panic!("Expected {:?}, got read of {} bytes", expected, buf.len()),
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        match self.next("write") {
+            Transaction::Write(expected) if expected == data => Ok(()),
+            expected => panic!("Expected {:?}, got write of {:?}", expected, data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Frame, Master};
+
+    #[test]
+    fn test_expectations() {
+        let pid = PID::from_id(0x11);
+        let frame = Frame::from_data(pid, &[0x01]);
+        let expectations = [
+            Transaction::Wakeup,
+            Transaction::Header(pid),
+            Transaction::Write(frame.get_data_with_checksum()),
+            Transaction::Header(pid),
+            Transaction::ReadError(Error::Timeout),
+        ];
+        let mut driver = Mock::new(&expectations);
+        driver.send_wakeup().unwrap();
+        driver.write_frame(&frame).unwrap();
+        assert_eq!(driver.remaining().len(), 2);
+        assert_eq!(driver.read_frame(pid, 1), Err(Error::Timeout));
+        driver.done();
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected Header")]
+    fn test_unexpected_call() {
+        let expectations = [Transaction::Header(PID::from_id(0x10))];
+        let mut driver = Mock::new(&expectations);
+        let _ = driver.send_wakeup();
+    }
+
+    #[test]
+    #[should_panic(expected = "Unconsumed expectations")]
+    fn test_unconsumed_expectations() {
+        Mock::new(&[Transaction::Wakeup]).done();
+    }
+}
@@ -0,0 +1,312 @@
+//! Helpers for UART peripherals with a hardware LIN mode
+//!
+//! Peripherals like the STM32 USART in LIN mode generate the break on request and flag a
+//! detected break, which a plain UART can only guess from a framing error. A HAL exposes these
+//! capabilities by implementing `LinPeripheral`, `PeripheralDriver` then turns the peripheral
+//! into a master driver and a bus `Monitor`.
+//!
+//! Nodes processing headers in the receive interrupt can feed the break flag and the received
+//! bytes into a `HeaderFifo`, which collects the PIDs of complete headers for the application.
+
+use super::{BusEvent, Error, Master, Monitor, SYNC_BYTE};
+use crate::frame::{ChecksumAccumulator, PID};
+
+/// Hardware hooks of a UART peripheral with LIN mode
+pub trait LinPeripheral {
+    /// Request a break from the hardware and block until it was transmitted
+    fn send_break(&mut self) -> Result<(), Error>;
+
+    /// Return whether a break was detected since the last call and clear the flag
+    fn break_detected(&mut self) -> bool;
+
+    /// Transmit a byte and block until it was sent
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error>;
+
+    /// Block until a byte is received. Fails with `Error::Timeout` if no byte is received
+    /// within the response timeout. The 0x00 byte received with a break is returned like other
+    /// bytes, usually with a framing error which is ignored.
+    fn read_byte(&mut self) -> Result<u8, Error>;
+}
+
+/// Master driver and bus monitor for a `LinPeripheral`
+pub struct PeripheralDriver<P> {
+    peripheral: P,
+}
+
+impl<P: LinPeripheral> PeripheralDriver<P> {
+    pub fn new(peripheral: P) -> PeripheralDriver<P> {
+        PeripheralDriver { peripheral }
+    }
+
+    /// Release the peripheral
+    pub fn free(self) -> P {
+        self.peripheral
+    }
+
+    /// Receive the response byte following `received` bytes
+    fn receive(&mut self, received: usize) -> Result<u8, Error> {
+        self.peripheral.read_byte().map_err(|e| match e {
+            Error::Timeout => Error::incomplete_response(received),
+            e => e,
+        })
+    }
+}
+
+impl<P: LinPeripheral> Master for PeripheralDriver<P> {
+    type Error = Error;
+
+    fn send_wakeup(&mut self) -> Result<(), Error> {
+        self.peripheral.write_byte(super::WAKEUP_BYTE)
+    }
+
+    fn send_break(&mut self) -> Result<(), Error> {
+        self.peripheral.send_break()
+    }
+
+    /// Fails with `Error::NoResponse` or `Error::Partial` if the peripheral times out before
+    /// the buffer is full
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        for (received, byte) in buf.iter_mut().enumerate() {
+            *byte = self.receive(received)?;
+        }
+        Ok(())
+    }
+
+    /// Folds the checksum while the bytes are received
+    fn read_verified(
+        &mut self,
+        buf: &mut [u8],
+        mut accumulator: ChecksumAccumulator,
+    ) -> Result<bool, Error> {
+        let data_length = buf.len().saturating_sub(1);
+        for (received, byte) in buf.iter_mut().enumerate() {
+            *byte = self.receive(received)?;
+            if received < data_length {
+                accumulator.push(*byte);
+            }
+        }
+        Ok(buf.last() == Some(&accumulator.finish()))
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        for byte in data {
+            self.peripheral.write_byte(*byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl<P: LinPeripheral> Monitor for PeripheralDriver<P> {
+    type Error = Error;
+
+    /// A timeout of the peripheral is reported as `BusEvent::Idle`
+    fn next_event(&mut self) -> Result<BusEvent, Error> {
+        match self.peripheral.read_byte() {
+            Ok(_) if self.peripheral.break_detected() => Ok(BusEvent::Break),
+            Ok(byte) => Ok(BusEvent::Byte(byte)),
+            Err(Error::Timeout) => Ok(BusEvent::Idle),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum HeaderState {
+    Idle,
+    Sync,
+    Pid,
+}
+
+/// FIFO of the PIDs of received headers, filled from the receive interrupt
+#[derive(Clone, Debug)]
+pub struct HeaderFifo<const N: usize> {
+    state: HeaderState,
+    pids: [PID; N],
+    start: usize,
+    len: usize,
+    overflows: usize,
+    invalid: usize,
+}
+
+impl<const N: usize> HeaderFifo<N> {
+    pub const fn new() -> HeaderFifo<N> {
+        HeaderFifo {
+            state: HeaderState::Idle,
+            pids: [PID(0x80); N],
+            start: 0,
+            len: 0,
+            overflows: 0,
+            invalid: 0,
+        }
+    }
+
+    /// Signal a detected break, the following bytes are decoded as header
+    pub fn on_break(&mut self) {
+        self.state = HeaderState::Sync;
+    }
+
+    /// Process a received byte, returns `true` if the byte completed a header
+    pub fn on_byte(&mut self, byte: u8) -> bool {
+        match self.state {
+            HeaderState::Sync if byte == SYNC_BYTE => {
+                self.state = HeaderState::Pid;
+                false
+            }
+            HeaderState::Pid => {
+                self.state = HeaderState::Idle;
+                match PID::new(byte) {
+                    Ok(pid) => self.push(pid),
+                    Err(_) => {
+                        self.invalid += 1;
+                        false
+                    }
+                }
+            }
+            // Response bytes and the 0x00 byte of the break are ignored
+            HeaderState::Sync if byte == 0x00 => false,
+            _ => {
+                self.state = HeaderState::Idle;
+                false
+            }
+        }
+    }
+
+    /// Take the oldest PID
+    pub fn pop(&mut self) -> Option<PID> {
+        if self.len == 0 {
+            return None;
+        }
+        let pid = self.pids[self.start];
+        self.start = (self.start + 1) % N;
+        self.len -= 1;
+        Some(pid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of headers dropped because the FIFO was full
+    pub fn overflows(&self) -> usize {
+        self.overflows
+    }
+
+    /// Number of headers with a wrong PID parity
+    pub fn invalid(&self) -> usize {
+        self.invalid
+    }
+
+    fn push(&mut self, pid: PID) -> bool {
+        if self.len == N {
+            self.overflows += 1;
+            return false;
+        }
+        self.pids[(self.start + self.len) % N] = pid;
+        self.len += 1;
+        true
+    }
+}
+
+impl<const N: usize> Default for HeaderFifo<N> {
+    fn default() -> Self {
+        HeaderFifo::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Master;
+
+    /// Peripheral with a prepared receive buffer, a break is received as 0x00 with the flag
+    #[derive(Default)]
+    struct Usart {
+        written: [u8; 8],
+        written_len: usize,
+        received: &'static [(u8, bool)],
+        break_flag: bool,
+    }
+
+    impl LinPeripheral for Usart {
+        fn send_break(&mut self) -> Result<(), Error> {
+            self.write_byte(0x00)
+        }
+
+        fn break_detected(&mut self) -> bool {
+            core::mem::replace(&mut self.break_flag, false)
+        }
+
+        fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+            self.written[self.written_len] = byte;
+            self.written_len += 1;
+            Ok(())
+        }
+
+        fn read_byte(&mut self) -> Result<u8, Error> {
+            let (&(byte, is_break), rest) = self.received.split_first().ok_or(Error::Timeout)?;
+            self.received = rest;
+            self.break_flag |= is_break;
+            Ok(byte)
+        }
+    }
+
+    #[test]
+    fn test_master() {
+        let usart = Usart {
+            received: &[(0x01, false), (0xAE, false)],
+            ..Default::default()
+        };
+        let mut driver = PeripheralDriver::new(usart);
+        let frame = driver.read_frame(PID::from_id(0x10), 1).unwrap();
+        assert_eq!(frame.get_data(), &[0x01]);
+        let usart = driver.free();
+        assert_eq!(&usart.written[..usart.written_len], &[0x00, 0x55, 0x50]);
+
+        let usart = Usart {
+            received: &[(0x01, false), (0xAF, false)],
+            ..Default::default()
+        };
+        let mut driver = PeripheralDriver::new(usart);
+        assert!(matches!(
+            driver.read_frame(PID::from_id(0x10), 1),
+            Err(Error::Checksum(_))
+        ));
+    }
+
+    #[test]
+    fn test_monitor() {
+        let usart = Usart {
+            received: &[(0x00, true), (0x55, false)],
+            ..Default::default()
+        };
+        let mut driver = PeripheralDriver::new(usart);
+        assert_eq!(driver.next_event(), Ok(BusEvent::Break));
+        assert_eq!(driver.next_event(), Ok(BusEvent::Byte(0x55)));
+        assert_eq!(driver.next_event(), Ok(BusEvent::Idle));
+    }
+
+    #[test]
+    fn test_header_fifo() {
+        let mut fifo = HeaderFifo::<2>::new();
+        for pid in [0x50, 0x12, 0xD3, 0x11] {
+            fifo.on_break();
+            assert!(!fifo.on_byte(0x00));
+            assert!(!fifo.on_byte(SYNC_BYTE));
+            fifo.on_byte(pid);
+            fifo.on_byte(0x01);
+        }
+        fifo.on_break();
+        fifo.on_byte(0x54);
+        fifo.on_byte(0x50);
+        assert_eq!(fifo.len(), 2);
+        assert_eq!(fifo.invalid(), 1);
+        assert_eq!(fifo.overflows(), 1);
+        assert_eq!(fifo.pop(), Some(PID::from_id(0x10)));
+        assert_eq!(fifo.pop(), Some(PID::from_id(0x13)));
+        assert_eq!(fifo.pop(), None);
+    }
+}
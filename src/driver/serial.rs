@@ -0,0 +1,192 @@
+//! LIN master driver for serial ports of desktop operating systems
+//!
+//! Built on the `serialport` crate and intended for host tools, e.g. with a USB serial adapter
+//! connected to a LIN transceiver. The break is generated by transmitting 0x00 at half the
+//! nominal baud rate. Echoed bytes of the transceiver have to be filtered by the adapter.
+//!
+//! Reading a response times out after the maximum response time of the frame at the bus speed
+//! unless a fixed timeout is set with `SerialPortMaster::with_response_timeout`.
+
+use std::boxed::Box;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use serialport::SerialPort;
+
+use super::{
+    send_configured_break_by_baud_switch, send_wakeup_by_baud_switch, BreakConfig, Master,
+    ResponseTimeout, SetBaudRate, WakeupPulse, WriteByte, WAKEUP_BYTE,
+};
+use crate::ldf::LinSpeed;
+use crate::Error;
+
+fn map_error(error: io::Error) -> Error {
+    match error.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::UnexpectedEof => {
+            Error::Timeout
+        }
+        _ => Error::PhysicalBus,
+    }
+}
+
+/// LIN master on a `serialport::SerialPort`, see the module documentation
+pub struct SerialPortMaster {
+    port: Box<dyn SerialPort>,
+    speed: LinSpeed,
+    wakeup_pulse: Option<WakeupPulse>,
+    break_config: BreakConfig,
+    response_timeout: ResponseTimeout,
+}
+
+impl SerialPortMaster {
+    /// Open the serial port at `path`, e.g. `/dev/ttyUSB0` or `COM3`, for a bus at `speed`
+    pub fn open(path: &str, speed: LinSpeed) -> serialport::Result<Self> {
+        let timeout = ResponseTimeout::Auto.duration(8, speed);
+        let port = serialport::new(path, speed.bits_per_second())
+            .timeout(timeout.into())
+            .open()?;
+        Ok(SerialPortMaster::new(port, speed))
+    }
+
+    /// Use an already opened port, which must be configured to `speed` with 8 data bits, no
+    /// parity and one stop bit
+    pub fn new(port: Box<dyn SerialPort>, speed: LinSpeed) -> SerialPortMaster {
+        SerialPortMaster {
+            port,
+            speed,
+            wakeup_pulse: None,
+            break_config: BreakConfig::HALF_BAUD_RATE,
+            response_timeout: ResponseTimeout::Auto,
+        }
+    }
+
+    /// Returns the driver sending the wakeup signal with the length `pulse`, see
+    /// `send_wakeup_by_baud_switch`
+    pub fn with_wakeup_pulse(self, pulse: WakeupPulse) -> SerialPortMaster {
+        SerialPortMaster {
+            wakeup_pulse: Some(pulse),
+            ..self
+        }
+    }
+
+    /// Returns the driver reading responses with the given timeout instead of
+    /// `ResponseTimeout::Auto`
+    pub fn with_response_timeout(self, timeout: ResponseTimeout) -> SerialPortMaster {
+        SerialPortMaster {
+            response_timeout: timeout,
+            ..self
+        }
+    }
+
+    /// Returns the driver sending breaks of the given length instead of the default
+    /// `BreakConfig::HALF_BAUD_RATE`, see `send_configured_break_by_baud_switch`
+    pub fn with_break(self, config: BreakConfig) -> SerialPortMaster {
+        SerialPortMaster {
+            break_config: config,
+            ..self
+        }
+    }
+
+    /// Release the serial port
+    pub fn free(self) -> Box<dyn SerialPort> {
+        self.port
+    }
+}
+
+impl SetBaudRate for SerialPortMaster {
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+        self.port
+            .set_baud_rate(baud_rate)
+            .map_err(|_| Error::PhysicalBus)
+    }
+}
+
+impl WriteByte for SerialPortMaster {
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        self.write(&[byte])
+    }
+}
+
+impl Master for SerialPortMaster {
+    type Error = Error;
+
+    fn send_wakeup(&mut self) -> Result<(), Error> {
+        match self.wakeup_pulse {
+            Some(pulse) => send_wakeup_by_baud_switch(self, self.speed, pulse),
+            None => self.write(&[WAKEUP_BYTE]),
+        }
+    }
+
+    fn send_break(&mut self) -> Result<(), Error> {
+        send_configured_break_by_baud_switch(self, self.speed, self.break_config)
+    }
+
+    /// Fails with `Error::NoResponse` or `Error::Partial` if the buffer is not full when the
+    /// response timeout elapsed
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let timeout = self
+            .response_timeout
+            .duration(buf.len().saturating_sub(1), self.speed);
+        let deadline = Instant::now() + Duration::from(timeout);
+        let mut received = 0;
+        while received < buf.len() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::incomplete_response(received));
+            }
+            self.port
+                .set_timeout(remaining)
+                .map_err(|_| Error::PhysicalBus)?;
+            match self.port.read(&mut buf[received..]).map_err(map_error) {
+                Ok(0) | Err(Error::Timeout) => return Err(Error::incomplete_response(received)),
+                Ok(n) => received += n,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.port.write_all(data).map_err(map_error)?;
+        self.port.flush().map_err(map_error)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{Frame, PID};
+    use serialport::TTYPort;
+
+    #[test]
+    fn test_pseudo_terminal() {
+        let (master, mut slave) = TTYPort::pair().unwrap();
+        let mut master = SerialPortMaster::new(Box::new(master), LinSpeed(19200));
+        slave.set_timeout(Duration::from_millis(100)).unwrap();
+
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01]);
+        crate::Master::write_frame(&mut master, &frame).unwrap();
+        let mut buf = [0u8; 5];
+        slave.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0x00, 0x55, 0x50, 0x01, 0xAE]);
+
+        slave.write_all(&[0x01, 0xAE]).unwrap();
+        let frame = crate::Master::read_frame(&mut master, PID::from_id(0x10), 1).unwrap();
+        assert_eq!(frame.get_data(), &[0x01]);
+    }
+
+    #[test]
+    fn test_response_timeout() {
+        let (master, mut slave) = TTYPort::pair().unwrap();
+        let mut master = SerialPortMaster::new(Box::new(master), LinSpeed(19200));
+        slave.set_timeout(Duration::from_millis(100)).unwrap();
+
+        let start = Instant::now();
+        assert_eq!(
+            crate::Master::read_frame(&mut master, PID::from_id(0x10), 8),
+            Err(Error::NoResponse)
+        );
+        // The maximum response time of 8 data bytes is 6.6 ms at 19200 bit/s
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+}
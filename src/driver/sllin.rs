@@ -0,0 +1,332 @@
+//! Driver for USB-LIN adapters using an slLIN/SLCAN style ASCII protocol
+//!
+//! The adapter transmits complete frames, so `SllinMaster` implements the frame level
+//! `Master` trait directly. Every command and response is a line terminated by `\r`:
+//!
+//! * `tIIILDD..` publishes a frame with the 3 digit hex frame ID `III`, the data length `L` and
+//!   `L` hex encoded data bytes from the master. The adapter acknowledges with an empty line.
+//! * `rIIIL` sends the header of frame `III` and requests a response of `L` bytes, which the
+//!   adapter returns as `tIIILDD..` line.
+//! * `W` sends a wakeup signal and is acknowledged with an empty line.
+//!
+//! The adapter answers with the BEL character (0x07) instead of a line on errors, e.g. if no
+//! slave responded. Checksums are handled by the adapter.
+//!
+//! Host tools can use the adapter with the `FromStd` wrapper of the `embedded-io-adapters`
+//! crate.
+
+use embedded_io::{Read, ReadExactError, Write};
+
+use super::uart::map_error;
+use crate::frame::{ChecksumModel, Frame, FrameError, PID};
+use crate::master::{check_response_length, Master, PartialResponse, Response};
+use crate::Error;
+
+/// Character sent by the adapter instead of a line in case of an error
+pub const BEL: u8 = 0x07;
+
+/// Maximal length of a command or response line including the terminating `\r`
+pub const MAX_LINE_LENGTH: usize = 22;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+fn hex_value(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn encode_id_and_length(command: u8, id: u8, data_length: usize, line: &mut [u8]) {
+    line[0] = command;
+    line[1] = b'0';
+    line[2] = HEX_DIGITS[usize::from(id >> 4)];
+    line[3] = HEX_DIGITS[usize::from(id & 0xF)];
+    line[4] = HEX_DIGITS[data_length];
+}
+
+/// Encode the command publishing `frame`, returns the used part of `line`
+pub fn encode_frame<'a>(frame: &Frame, line: &'a mut [u8; MAX_LINE_LENGTH]) -> &'a [u8] {
+    encode_data(frame.get_pid(), frame.get_data(), line)
+}
+
+/// Encode the command publishing at most 8 bytes of `data`
+fn encode_data<'a>(pid: PID, data: &[u8], line: &'a mut [u8; MAX_LINE_LENGTH]) -> &'a [u8] {
+    encode_id_and_length(b't', pid.get_id(), data.len(), line);
+    for (i, byte) in data.iter().enumerate() {
+        line[5 + 2 * i] = HEX_DIGITS[usize::from(byte >> 4)];
+        line[6 + 2 * i] = HEX_DIGITS[usize::from(byte & 0xF)];
+    }
+    let end = 5 + 2 * data.len();
+    line[end] = b'\r';
+    &line[..=end]
+}
+
+/// Encode the command requesting `data_length` bytes from a slave, returns the used part of
+/// `line`
+///
+/// # Panics
+///
+/// Panics if `data_length` exceeds 8 bytes, see `try_encode_request`.
+#[cfg(any(test, not(feature = "panic-free")))]
+pub fn encode_request(pid: PID, data_length: usize, line: &mut [u8; MAX_LINE_LENGTH]) -> &[u8] {
+    try_encode_request(pid, data_length, line).expect("Maximum data length is 8 bytes")
+}
+
+/// Encode the command requesting `data_length` bytes from a slave, returns the used part of
+/// `line` or fails with `FrameError::DataTooLong` if `data_length` exceeds 8 bytes
+pub fn try_encode_request(
+    pid: PID,
+    data_length: usize,
+    line: &mut [u8; MAX_LINE_LENGTH],
+) -> Result<&[u8], FrameError> {
+    if data_length > 8 {
+        return Err(FrameError::DataTooLong(data_length));
+    }
+    encode_id_and_length(b'r', pid.get_id(), data_length, line);
+    line[5] = b'\r';
+    Ok(&line[..6])
+}
+
+/// Decode a `tIIILDD..` line without the terminating `\r` into a frame using `checksum_model`
+pub fn decode_frame(line: &[u8], checksum_model: ChecksumModel) -> Option<Frame> {
+    let (&command, rest) = line.split_first()?;
+    if command != b't' || rest.len() < 4 {
+        return None;
+    }
+    let digits = |s: &[u8]| {
+        s.iter().try_fold(0usize, |acc, d| {
+            Some(acc * 16 + usize::from(hex_value(*d)?))
+        })
+    };
+    let id = digits(&rest[..3])?;
+    let data_length = digits(&rest[3..4])?;
+    let hex = &rest[4..];
+    if id > 0x3F || data_length > 8 || hex.len() != 2 * data_length {
+        return None;
+    }
+    let mut data = [0u8; 8];
+    for (byte, digits) in data.iter_mut().zip(hex.chunks(2)) {
+        *byte = hex_value(digits[0])? << 4 | hex_value(digits[1])?;
+    }
+    Frame::try_from_data_with_checksum_model(
+        PID::from_masked_id(id as u8),
+        &data[..data_length],
+        checksum_model,
+    )
+    .ok()
+}
+
+/// LIN master talking to an ASCII protocol adapter, see the module documentation
+pub struct SllinMaster<P> {
+    port: P,
+}
+
+impl<P: Read + Write> SllinMaster<P> {
+    pub fn new(port: P) -> SllinMaster<P> {
+        SllinMaster { port }
+    }
+
+    /// Release the port
+    pub fn free(self) -> P {
+        self.port
+    }
+
+    fn send(&mut self, line: &[u8]) -> Result<(), Error> {
+        self.port.write_all(line).map_err(map_error)?;
+        self.port.flush().map_err(map_error)
+    }
+
+    /// Read the next line into `line` and return its length without the `\r`. Returns
+    /// `Ok(None)` if the adapter reported an error.
+    fn receive(&mut self, line: &mut [u8; MAX_LINE_LENGTH]) -> Result<Option<usize>, Error> {
+        for (length, slot) in line.iter_mut().enumerate() {
+            let mut byte = [0u8];
+            self.port.read_exact(&mut byte).map_err(|e| match e {
+                ReadExactError::UnexpectedEof => Error::Timeout,
+                ReadExactError::Other(e) => map_error(e),
+            })?;
+            match byte[0] {
+                BEL => return Ok(None),
+                b'\r' => return Ok(Some(length)),
+                byte => *slot = byte,
+            }
+        }
+        Err(Error::PhysicalBus)
+    }
+
+    /// Wait for the acknowledge of a command
+    fn acknowledge(&mut self) -> Result<(), Error> {
+        let mut line = [0u8; MAX_LINE_LENGTH];
+        match self.receive(&mut line)? {
+            Some(0) => Ok(()),
+            _ => Err(Error::PhysicalBus),
+        }
+    }
+}
+
+impl<P: Read + Write> Master for SllinMaster<P> {
+    type Error = Error;
+
+    fn send_wakeup(&mut self) -> Result<(), Error> {
+        self.send(b"W\r")?;
+        self.acknowledge()
+    }
+
+    fn write_frame(&mut self, frame: &Frame) -> Result<(), Error> {
+        let mut line = [0u8; MAX_LINE_LENGTH];
+        let command = encode_frame(frame, &mut line);
+        self.send(command)?;
+        self.acknowledge()
+    }
+
+    fn read_frame(&mut self, pid: PID, data_length: usize) -> Result<Frame, Error> {
+        self.read_frame_with_checksum_model(pid, data_length, pid.checksum_model())
+    }
+
+    /// Fails with `Error::NoResponse` if the adapter reports that no slave responded
+    fn read_frame_with_checksum_model(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+        checksum_model: ChecksumModel,
+    ) -> Result<Frame, Error> {
+        check_response_length(pid, data_length)?;
+        let mut line = [0u8; MAX_LINE_LENGTH];
+        let command = try_encode_request(pid, data_length, &mut line)?;
+        self.send(command)?;
+        let length = self.receive(&mut line)?.ok_or(Error::NoResponse)?;
+        decode_frame(&line[..length], checksum_model)
+            .filter(|frame| frame.get_pid() == pid && frame.get_data().len() == data_length)
+            .ok_or(Error::PhysicalBus)
+    }
+
+    /// The adapter verifies the checksum, so a shorter response is only detected if the adapter
+    /// forwards it
+    fn read_response(&mut self, pid: PID, data_length: usize) -> Result<Response, Error> {
+        check_response_length(pid, data_length)?;
+        let mut line = [0u8; MAX_LINE_LENGTH];
+        let command = try_encode_request(pid, data_length, &mut line)?;
+        self.send(command)?;
+        let length = match self.receive(&mut line)? {
+            Some(length) => length,
+            None => return Ok(Response::NoResponse),
+        };
+        match decode_frame(&line[..length], pid.checksum_model()) {
+            Some(frame) if frame.get_pid() == pid && frame.get_data().len() == data_length => {
+                Ok(Response::Complete(frame))
+            }
+            Some(frame) if frame.get_pid() == pid && frame.get_data().len() < data_length => Ok(
+                Response::Partial(PartialResponse::new(pid, frame.get_data())),
+            ),
+            _ => Err(Error::PhysicalBus),
+        }
+    }
+
+    /// The adapter calculates the checksum itself, so `checksum` is not used
+    fn write_payload(&mut self, pid: PID, data: &[u8], _checksum: u8) -> Result<(), Error> {
+        check_response_length(pid, data.len())?;
+        let mut line = [0u8; MAX_LINE_LENGTH];
+        let command = encode_data(pid, data, &mut line);
+        self.send(command)?;
+        self.acknowledge()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_io::{ErrorKind, ErrorType};
+
+    #[test]
+    fn test_encode() {
+        let mut line = [0u8; MAX_LINE_LENGTH];
+        let frame = Frame::from_data(PID::from_id(0x30), &[0x7F, 0x06, 0xB2]);
+        assert_eq!(encode_frame(&frame, &mut line), b"t03037F06B2\r");
+        assert_eq!(encode_request(PID::from_id(0x10), 8, &mut line), b"r0108\r");
+    }
+
+    #[test]
+    fn test_decode() {
+        let frame = decode_frame(b"t01020aFF", ChecksumModel::Enhanced).unwrap();
+        assert_eq!(frame, Frame::from_data(PID::from_id(0x10), &[0x0A, 0xFF]));
+        assert_eq!(decode_frame(b"t0102", ChecksumModel::Enhanced), None);
+        assert_eq!(decode_frame(b"t0401", ChecksumModel::Enhanced), None);
+        assert_eq!(decode_frame(b"r0100", ChecksumModel::Enhanced), None);
+        assert_eq!(decode_frame(b"t0101GG", ChecksumModel::Enhanced), None);
+    }
+
+    /// Adapter with a prepared reply, recording the last command
+    struct Port {
+        command: [u8; MAX_LINE_LENGTH],
+        command_len: usize,
+        reply: &'static [u8],
+    }
+
+    impl ErrorType for Port {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Port {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            let len = buf.len().min(self.reply.len());
+            buf[..len].copy_from_slice(&self.reply[..len]);
+            self.reply = &self.reply[len..];
+            Ok(len)
+        }
+    }
+
+    impl Write for Port {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+            self.command[..buf.len()].copy_from_slice(buf);
+            self.command_len = buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+    }
+
+    fn master(reply: &'static [u8]) -> SllinMaster<Port> {
+        SllinMaster::new(Port {
+            command: [0; MAX_LINE_LENGTH],
+            command_len: 0,
+            reply,
+        })
+    }
+
+    #[test]
+    fn test_read_frame() {
+        let mut master = master(b"t0102ABCD\r\x07");
+        let pid = PID::from_id(0x10);
+        let frame = master.read_frame(pid, 2).unwrap();
+        assert_eq!(frame.get_data(), &[0xAB, 0xCD]);
+        assert_eq!(master.read_frame(pid, 2), Err(Error::NoResponse));
+        let port = master.free();
+        assert_eq!(&port.command[..port.command_len], b"r0102\r");
+
+        let mut master = self::master(b"t0101AB\r\x07");
+        let partial = PartialResponse::new(pid, &[0xAB]);
+        assert_eq!(master.read_response(pid, 2), Ok(Response::Partial(partial)));
+        assert_eq!(master.read_response(pid, 2), Ok(Response::NoResponse));
+        let port = master.free();
+        assert_eq!(&port.command[..port.command_len], b"r0102\r");
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let mut master = master(b"\r\x07");
+        let frame = Frame::from_data(PID::from_id(0x11), &[0x01]);
+        assert_eq!(master.write_frame(&frame), Ok(()));
+        assert_eq!(master.write_frame(&frame), Err(Error::PhysicalBus));
+
+        let mut master = self::master(b"\r");
+        let pid = PID::from_id(0x11);
+        assert_eq!(master.write_payload(pid, &[0x01], 0x00), Ok(()));
+        let port = master.free();
+        assert_eq!(&port.command[..port.command_len], b"t011101\r");
+    }
+}
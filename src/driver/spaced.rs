@@ -0,0 +1,166 @@
+//! Driver wrapper inserting inter-byte spaces on transmission
+//!
+//! Some slaves can not receive the fields of a frame back to back. `SpacedMaster` wraps a
+//! driver and waits the configured `InterByteSpace` between the header fields and between the
+//! bytes of a published response. Without a space the calls are passed through, so drivers
+//! implementing `Master::send_header` themselves only support response spaces.
+
+use super::{send_header_spaced, write_spaced, InterByteSpace, Master};
+use crate::frame::{ChecksumAccumulator, PID};
+use crate::time::{Delay, Duration};
+
+/// Driver wrapper, see the module documentation
+pub struct SpacedMaster<M, D> {
+    driver: M,
+    delay: D,
+    space: InterByteSpace,
+}
+
+impl<M: Master, D: Delay> SpacedMaster<M, D> {
+    pub fn new(driver: M, delay: D, space: InterByteSpace) -> SpacedMaster<M, D> {
+        SpacedMaster {
+            driver,
+            delay,
+            space,
+        }
+    }
+
+    pub fn space(&self) -> InterByteSpace {
+        self.space
+    }
+
+    pub fn set_space(&mut self, space: InterByteSpace) {
+        self.space = space;
+    }
+
+    /// Release the wrapped driver and the delay
+    pub fn free(self) -> (M, D) {
+        (self.driver, self.delay)
+    }
+}
+
+impl<M: Master, D: Delay> Master for SpacedMaster<M, D> {
+    type Error = M::Error;
+
+    fn send_wakeup(&mut self) -> Result<(), M::Error> {
+        self.driver.send_wakeup()
+    }
+
+    fn send_header(&mut self, pid: PID) -> Result<(), M::Error> {
+        match self.space.header {
+            Duration::ZERO => self.driver.send_header(pid),
+            space => send_header_spaced(&mut self.driver, pid, space, &mut self.delay),
+        }
+    }
+
+    fn send_break(&mut self) -> Result<(), M::Error> {
+        self.driver.send_break()
+    }
+
+    fn send_sync(&mut self) -> Result<(), M::Error> {
+        self.driver.send_sync()
+    }
+
+    fn send_pid(&mut self, pid: PID) -> Result<(), M::Error> {
+        self.driver.send_pid(pid)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), M::Error> {
+        self.driver.read(buf)
+    }
+
+    fn read_verified(
+        &mut self,
+        buf: &mut [u8],
+        accumulator: ChecksumAccumulator,
+    ) -> Result<bool, M::Error> {
+        self.driver.read_verified(buf, accumulator)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), M::Error> {
+        match self.space.response {
+            Duration::ZERO => self.driver.write(data),
+            space => write_spaced(&mut self.driver, data, space, &mut self.delay),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::Error;
+    use crate::ldf::LinSpeed;
+    use crate::time::tests::TestClock;
+    use crate::Frame;
+
+    /// Driver counting the calls of `send_break` and `write`
+    #[derive(Default)]
+    struct Driver {
+        breaks: usize,
+        writes: usize,
+        written: [u8; 16],
+        len: usize,
+    }
+
+    impl Master for Driver {
+        type Error = Error;
+
+        fn send_wakeup(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn send_break(&mut self) -> Result<(), Error> {
+            self.breaks += 1;
+            Ok(())
+        }
+
+        fn read(&mut self, _buf: &mut [u8]) -> Result<(), Error> {
+            Err(Error::NoResponse)
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.written[self.len..self.len + data.len()].copy_from_slice(data);
+            self.len += data.len();
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_spaced_master() {
+        let space = InterByteSpace {
+            header: Duration::from_micros(100),
+            response: Duration::from_micros(50),
+        };
+        let mut master = SpacedMaster::new(Driver::default(), TestClock::default(), space);
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        crate::Master::write_frame(&mut master, &frame).unwrap();
+        let (driver, clock) = master.free();
+        assert_eq!(driver.breaks, 1);
+        // Sync, PID and three response bytes
+        assert_eq!(driver.writes, 5);
+        assert_eq!(
+            &driver.written[..driver.len],
+            &[0x55, 0x50, 0x01, 0x02, 0xAC]
+        );
+        assert_eq!((clock.now, clock.delays), (2 * 100 + 2 * 50, 4));
+    }
+
+    #[test]
+    fn test_no_space() {
+        let mut master = SpacedMaster::new(
+            Driver::default(),
+            TestClock::default(),
+            InterByteSpace::NONE,
+        );
+        master.write(&[0x01, 0x02]).unwrap();
+        let (driver, clock) = master.free();
+        assert_eq!(driver.writes, 1);
+        assert_eq!(clock.delays, 0);
+        assert!(!InterByteSpace {
+            response: Duration::from_millis(1),
+            ..InterByteSpace::NONE
+        }
+        .fits(8, LinSpeed(19200)));
+    }
+}
@@ -0,0 +1,375 @@
+//! Generic LIN master driver for UARTs implementing the `embedded-io` traits
+//!
+//! UARTs can not generate the break field on their own, so the break is delegated to a
+//! `BreakGenerator` strategy, e.g. a closure which toggles a GPIO or switches the baud rate.
+//! The sync field, PID and response are transmitted as normal UART bytes.
+//!
+//! Single-wire transceivers return every transmitted byte on RX. The `Echo` setting of the
+//! master tells which bytes are echoed, they are read back and compared to the transmitted
+//! bytes before the slave response is read. A mismatch is reported as `Error::Collision`.
+
+use embedded_io::{ErrorKind, Read, ReadExactError, Write};
+
+use super::{
+    send_configured_break_by_baud_switch, BreakConfig, Master, SetBaudRate, WakeupPulse, WriteByte,
+    WAKEUP_BYTE,
+};
+use crate::ldf::LinSpeed;
+use crate::Error;
+
+/// Strategy to generate the break field of a frame header on the UART `U`
+pub trait BreakGenerator<U> {
+    /// Generate a break of at least 13 bit times. Pending bytes have been flushed before.
+    fn send_break(&mut self, uart: &mut U) -> Result<(), Error>;
+}
+
+impl<U, F> BreakGenerator<U> for F
+where
+    F: FnMut(&mut U) -> Result<(), Error>,
+{
+    fn send_break(&mut self, uart: &mut U) -> Result<(), Error> {
+        self(uart)
+    }
+}
+
+/// Generates the break by transmitting 0x00 at a lower baud rate, see
+/// `send_configured_break_by_baud_switch`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BaudSwitchBreak {
+    pub speed: LinSpeed,
+    pub config: BreakConfig,
+}
+
+impl BaudSwitchBreak {
+    /// The break at half the baud rate, `BreakConfig::HALF_BAUD_RATE`
+    pub fn new(speed: LinSpeed) -> BaudSwitchBreak {
+        BaudSwitchBreak {
+            speed,
+            config: BreakConfig::HALF_BAUD_RATE,
+        }
+    }
+}
+
+/// Adapter providing `WriteByte` for a UART
+struct Bytes<'a, U>(&'a mut U);
+
+impl<U: SetBaudRate> SetBaudRate for Bytes<'_, U> {
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+        self.0.set_baud_rate(baud_rate)
+    }
+}
+
+impl<U: Write> WriteByte for Bytes<'_, U> {
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error> {
+        self.0.write_all(&[byte]).map_err(map_error)?;
+        self.0.flush().map_err(map_error)
+    }
+}
+
+impl<U: SetBaudRate + Write> BreakGenerator<U> for BaudSwitchBreak {
+    fn send_break(&mut self, uart: &mut U) -> Result<(), Error> {
+        send_configured_break_by_baud_switch(&mut Bytes(uart), self.speed, self.config)
+    }
+}
+
+/// Map an `embedded-io` error to `Error::Timeout`, `Error::Framing` or `Error::PhysicalBus`
+pub fn map_error<E: embedded_io::Error>(error: E) -> Error {
+    match error.kind() {
+        ErrorKind::TimedOut => Error::Timeout,
+        ErrorKind::InvalidData => Error::Framing,
+        _ => Error::PhysicalBus,
+    }
+}
+
+pub use super::echo::Echo;
+
+/// Wakeup signal generated by transmitting the `WAKEUP_BYTE` at a lower baud rate
+struct WakeupSwitch<U> {
+    pulse: WakeupPulse,
+    speed: LinSpeed,
+    set_baud_rate: fn(&mut U, u32) -> Result<(), Error>,
+}
+
+/// LIN master driver on top of a UART, see the module documentation
+pub struct UartMaster<U, B> {
+    uart: U,
+    break_generator: B,
+    echo: Echo,
+    wakeup: Option<WakeupSwitch<U>>,
+}
+
+impl<U, B> UartMaster<U, B>
+where
+    U: Read + Write,
+    B: BreakGenerator<U>,
+{
+    /// Create the driver for a UART without echo
+    pub fn new(uart: U, break_generator: B) -> UartMaster<U, B> {
+        UartMaster {
+            uart,
+            break_generator,
+            echo: Echo::None,
+            wakeup: None,
+        }
+    }
+
+    /// Returns the driver reading back the given echo
+    pub fn with_echo(self, echo: Echo) -> UartMaster<U, B> {
+        UartMaster { echo, ..self }
+    }
+
+    /// Read back the echo of `data` and compare it
+    fn check_echo(&mut self, data: &[u8]) -> Result<(), Error> {
+        let mut buf = [0u8; 9];
+        for chunk in data.chunks(buf.len()) {
+            let echo = &mut buf[..chunk.len()];
+            self.uart.read_exact(echo).map_err(|e| match e {
+                ReadExactError::UnexpectedEof => Error::PhysicalBus,
+                ReadExactError::Other(e) => map_error(e),
+            })?;
+            if echo != chunk {
+                return Err(Error::Collision);
+            }
+        }
+        Ok(())
+    }
+
+    /// Release the UART and the break generator
+    pub fn free(self) -> (U, B) {
+        (self.uart, self.break_generator)
+    }
+}
+
+impl<U, B> UartMaster<U, B>
+where
+    U: Read + Write + SetBaudRate,
+    B: BreakGenerator<U>,
+{
+    /// Returns the driver sending the wakeup signal with the length `pulse` by transmitting
+    /// the `WAKEUP_BYTE` at `WakeupPulse::baud_rate`, the UART runs at `speed` otherwise
+    pub fn with_wakeup_pulse(self, pulse: WakeupPulse, speed: LinSpeed) -> UartMaster<U, B> {
+        let wakeup = WakeupSwitch {
+            pulse,
+            speed,
+            set_baud_rate: U::set_baud_rate,
+        };
+        UartMaster {
+            wakeup: Some(wakeup),
+            ..self
+        }
+    }
+}
+
+impl<U, B> Master for UartMaster<U, B>
+where
+    U: Read + Write,
+    B: BreakGenerator<U>,
+{
+    type Error = Error;
+
+    fn send_wakeup(&mut self) -> Result<(), Error> {
+        let (pulse, speed, set_baud_rate) = match &self.wakeup {
+            Some(wakeup) => (wakeup.pulse, wakeup.speed, wakeup.set_baud_rate),
+            None => return self.write(&[WAKEUP_BYTE]),
+        };
+        self.uart.flush().map_err(map_error)?;
+        set_baud_rate(&mut self.uart, pulse.baud_rate())?;
+        let result = self.write(&[WAKEUP_BYTE]);
+        set_baud_rate(&mut self.uart, speed.bits_per_second())?;
+        result
+    }
+
+    fn send_break(&mut self) -> Result<(), Error> {
+        self.uart.flush().map_err(map_error)?;
+        self.break_generator.send_break(&mut self.uart)?;
+        if self.echo == Echo::BreakAndData {
+            // The echoed break is expected to have a framing error
+            if let Err(ReadExactError::UnexpectedEof) = self.uart.read_exact(&mut [0u8]) {
+                return Err(Error::PhysicalBus);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reading fails with `Error::NoResponse` or `Error::Partial` if the UART times out or
+    /// reports the end of the input before the buffer is full
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let mut received = 0;
+        while received < buf.len() {
+            match self.uart.read(&mut buf[received..]).map_err(map_error) {
+                Ok(0) | Err(Error::Timeout) => return Err(Error::incomplete_response(received)),
+                Ok(n) => received += n,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// With echo, fails with `Error::Collision` if the echo differs from `data`
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.uart.write_all(data).map_err(map_error)?;
+        self.uart.flush().map_err(map_error)?;
+        match self.echo {
+            Echo::None => Ok(()),
+            Echo::Data | Echo::BreakAndData => self.check_echo(data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Frame, PID};
+    use embedded_io::ErrorType;
+
+    /// UART which records written bytes and returns prepared response bytes
+    #[derive(Default)]
+    struct Uart {
+        written: [u8; 16],
+        written_len: usize,
+        response: [u8; 16],
+        response_len: usize,
+        baud_rate: u32,
+        echo: bool,
+    }
+
+    impl SetBaudRate for Uart {
+        fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), Error> {
+            self.baud_rate = baud_rate;
+            Ok(())
+        }
+    }
+
+    impl ErrorType for Uart {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Uart {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            let len = buf.len().min(self.response_len);
+            buf[..len].copy_from_slice(&self.response[..len]);
+            self.response.copy_within(len.., 0);
+            self.response_len -= len;
+            Ok(len)
+        }
+    }
+
+    impl Write for Uart {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+            let end = self.written_len + buf.len();
+            self.written[self.written_len..end].copy_from_slice(buf);
+            self.written_len = end;
+            if self.echo {
+                let end = self.response_len + buf.len();
+                self.response[self.response_len..end].copy_from_slice(buf);
+                self.response_len = end;
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+    }
+
+    /// Record the break as 0x00 byte
+    fn record_break(uart: &mut Uart) -> Result<(), Error> {
+        uart.write_all(&[0x00]).map_err(map_error)
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let mut master = UartMaster::new(Uart::default(), record_break);
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        crate::Master::write_frame(&mut master, &frame).unwrap();
+        let (uart, _) = master.free();
+        assert_eq!(
+            &uart.written[..uart.written_len],
+            &[0x00, 0x55, 0x50, 0x01, 0x02, 0xAC]
+        );
+    }
+
+    #[test]
+    fn test_read_frame() {
+        let mut uart = Uart::default();
+        uart.response[..3].copy_from_slice(&[0x01, 0x02, 0xAC]);
+        uart.response_len = 3;
+        let mut master = UartMaster::new(uart, record_break);
+        let frame = crate::Master::read_frame(&mut master, PID::from_id(0x10), 2).unwrap();
+        assert_eq!(frame.get_data(), &[0x01, 0x02]);
+
+        assert_eq!(
+            crate::Master::read_frame(&mut master, PID::from_id(0x10), 2),
+            Err(Error::NoResponse)
+        );
+        master.uart.response[0] = 0x01;
+        master.uart.response_len = 1;
+        assert_eq!(
+            crate::Master::read_frame(&mut master, PID::from_id(0x10), 2),
+            Err(Error::Partial(1))
+        );
+    }
+
+    #[test]
+    fn test_echo() {
+        let uart = Uart {
+            echo: true,
+            ..Default::default()
+        };
+        let mut master = UartMaster::new(uart, record_break).with_echo(Echo::BreakAndData);
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        crate::Master::write_frame(&mut master, &frame).unwrap();
+        let (uart, _) = master.free();
+        assert_eq!(uart.response_len, 0);
+
+        // A break generated by a GPIO is not echoed
+        let pin_break = |_: &mut Uart| Ok(());
+        let mut master = UartMaster::new(uart, pin_break).with_echo(Echo::Data);
+        Master::send_header(&mut master, PID::from_id(0x10)).unwrap();
+        master.uart.response[..3].copy_from_slice(&[0x01, 0x02, 0xAC]);
+        master.uart.response_len = 3;
+        let mut buf = [0u8; 3];
+        Master::read(&mut master, &mut buf).unwrap();
+        assert_eq!(buf, [0x01, 0x02, 0xAC]);
+    }
+
+    #[test]
+    fn test_echo_mismatch() {
+        let mut uart = Uart::default();
+        uart.response[0] = 0x54;
+        uart.response_len = 1;
+        let mut master = UartMaster::new(uart, record_break).with_echo(Echo::Data);
+        assert_eq!(Master::send_sync(&mut master), Err(Error::Collision));
+        assert_eq!(Master::send_sync(&mut master), Err(Error::PhysicalBus));
+    }
+
+    #[test]
+    fn test_baud_switch_break() {
+        let mut uart = Uart::default();
+        let mut break_generator = BaudSwitchBreak::new(LinSpeed(19200));
+        break_generator.send_break(&mut uart).unwrap();
+        assert_eq!(&uart.written[..uart.written_len], &[0x00]);
+        assert_eq!(uart.baud_rate, 19200);
+    }
+
+    #[test]
+    fn test_wakeup() {
+        let mut master = UartMaster::new(Uart::default(), record_break);
+        Master::send_wakeup(&mut master).unwrap();
+        let (uart, _) = master.free();
+        assert_eq!(&uart.written[..uart.written_len], &[WAKEUP_BYTE]);
+
+        let pulse = WakeupPulse::try_new(crate::time::Duration::from_millis(1)).unwrap();
+        let mut master = UartMaster::new(uart, record_break)
+            .with_wakeup_pulse(pulse, LinSpeed(19200))
+            .with_echo(Echo::Data);
+        master.uart.echo = true;
+        Master::send_wakeup(&mut master).unwrap();
+        let (uart, _) = master.free();
+        assert_eq!(
+            &uart.written[..uart.written_len],
+            &[WAKEUP_BYTE, WAKEUP_BYTE]
+        );
+        assert_eq!(uart.baud_rate, 19200);
+    }
+}
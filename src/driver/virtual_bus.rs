@@ -0,0 +1,290 @@
+//! In-memory bus connecting a master to simulated slave nodes
+//!
+//! `VirtualBus` is a master driver for integration tests of complete applications on a
+//! desktop. The headers sent by the master are answered by the attached `Slave`s:
+//!
+//! * If no slave responds, reading the response times out.
+//! * If several slaves respond, e.g. to an event triggered frame, the responses collide. Like
+//!   on a real bus the dominant level wins, so the master receives the bitwise AND of the
+//!   responses, which usually has a wrong checksum.
+//! * Frames with a correct checksum are passed to all slaves, including those published by the
+//!   master.
+//!
+//! The bus keeps a virtual time advancing with the nominal duration of every header, byte and
+//! wakeup signal, which is available through `driver::Timestamping`.
+
+use std::boxed::Box;
+use std::vec::Vec;
+
+use super::{Error, Master, Timestamping, WakeupPulse};
+use crate::frame::{Frame, PID};
+use crate::iso17987::timing;
+use crate::ldf::LinSpeed;
+use crate::time::{Duration, Instant};
+
+/// Simulated slave node attached to a `VirtualBus`
+pub trait Slave {
+    /// Response to a header of `pid`, or `None` if the slave does not publish the frame
+    fn respond(&mut self, pid: PID) -> Option<Frame>;
+
+    /// Called for every frame transmitted with a correct checksum
+    fn receive(&mut self, _frame: &Frame) {}
+}
+
+impl<F: FnMut(PID) -> Option<Frame>> Slave for F {
+    fn respond(&mut self, pid: PID) -> Option<Frame> {
+        self(pid)
+    }
+}
+
+/// Virtual bus, see the module documentation
+pub struct VirtualBus {
+    slaves: Vec<Box<dyn Slave>>,
+    speed: LinSpeed,
+    wakeup_pulse: Duration,
+    time: u64,
+    header_time: Option<u64>,
+    response_time: Option<u64>,
+    pid: Option<PID>,
+    response: Vec<u8>,
+    written: Vec<u8>,
+    collisions: usize,
+}
+
+impl VirtualBus {
+    pub fn new(speed: LinSpeed) -> VirtualBus {
+        VirtualBus {
+            slaves: Vec::new(),
+            speed,
+            wakeup_pulse: Duration::from_micros(timing::WAKEUP_MIN_US as u64),
+            time: 0,
+            header_time: None,
+            response_time: None,
+            pid: None,
+            response: Vec::new(),
+            written: Vec::new(),
+            collisions: 0,
+        }
+    }
+
+    /// Returns the bus with wakeup signals of the length `pulse`, the minimum length by default
+    pub fn with_wakeup_pulse(self, pulse: WakeupPulse) -> VirtualBus {
+        VirtualBus {
+            wakeup_pulse: pulse.duration(),
+            ..self
+        }
+    }
+
+    /// Attach a slave and return its index
+    pub fn add_slave(&mut self, slave: Box<dyn Slave>) -> usize {
+        self.slaves.push(slave);
+        self.slaves.len() - 1
+    }
+
+    /// The slave with the given index
+    pub fn slave(&mut self, index: usize) -> Option<&mut (dyn Slave + 'static)> {
+        self.slaves.get_mut(index).map(|slave| slave.as_mut())
+    }
+
+    /// Current virtual time in µs
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Let the bus idle for `duration` µs, e.g. the rest of a schedule slot
+    pub fn advance(&mut self, duration: u64) {
+        self.time += duration;
+    }
+
+    /// Number of colliding responses
+    pub fn collisions(&self) -> usize {
+        self.collisions
+    }
+
+    fn advance_bytes(&mut self, count: usize) {
+        self.time += timing::bytes(count, self.speed).as_micros();
+    }
+
+    /// Pass the frame to the slaves if `bytes` is a response with a correct checksum
+    fn deliver(&mut self, bytes: &[u8]) {
+        let pid = match self.pid {
+            Some(pid) => pid,
+            None => return,
+        };
+        let (data, checksum) = match bytes.split_last() {
+            Some((checksum, data)) => (data, *checksum),
+            None => return,
+        };
+        let frame = match Frame::try_from_data(pid, data) {
+            Ok(frame) => frame,
+            Err(_) => return,
+        };
+        if frame.get_checksum() == checksum {
+            for slave in &mut self.slaves {
+                slave.receive(&frame);
+            }
+        }
+    }
+}
+
+impl Master for VirtualBus {
+    type Error = Error;
+
+    fn send_wakeup(&mut self) -> Result<(), Error> {
+        self.time += self.wakeup_pulse.as_micros();
+        Ok(())
+    }
+
+    fn send_header(&mut self, pid: PID) -> Result<(), Error> {
+        self.header_time = Some(self.time);
+        self.response_time = None;
+        self.time += timing::header_time(self.speed).as_micros();
+        self.pid = Some(pid);
+        self.written.clear();
+        self.response.clear();
+        let mut responses = 0;
+        for slave in &mut self.slaves {
+            if let Some(frame) = slave.respond(pid) {
+                let bytes = frame.get_data_with_checksum();
+                if responses == 0 {
+                    self.response.extend_from_slice(bytes);
+                } else {
+                    for (i, byte) in bytes.iter().enumerate() {
+                        match self.response.get_mut(i) {
+                            Some(received) => *received &= byte,
+                            None => self.response.push(*byte),
+                        }
+                    }
+                }
+                responses += 1;
+            }
+        }
+        if responses > 1 {
+            self.collisions += 1;
+        }
+        Ok(())
+    }
+
+    /// Fails with `Error::NoResponse` or `Error::Partial` if the slaves sent less bytes than
+    /// requested
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        if buf.len() > self.response.len() {
+            let received = self.response.len();
+            buf[..received].copy_from_slice(&self.response);
+            self.advance_bytes(received);
+            self.response.clear();
+            return Err(Error::incomplete_response(received));
+        }
+        let bytes: Vec<u8> = self.response.drain(..buf.len()).collect();
+        buf.copy_from_slice(&bytes);
+        self.advance_bytes(bytes.len());
+        self.response_time = Some(self.time);
+        self.deliver(&bytes);
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.advance_bytes(data.len());
+        self.response_time = Some(self.time);
+        self.written.extend_from_slice(data);
+        let written = core::mem::take(&mut self.written);
+        self.deliver(&written);
+        self.written = written;
+        Ok(())
+    }
+}
+
+impl Timestamping for VirtualBus {
+    fn header_timestamp(&self) -> Option<Instant> {
+        self.header_time.map(Instant::from_micros)
+    }
+
+    fn response_timestamp(&self) -> Option<Instant> {
+        self.response_time.map(Instant::from_micros)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::master::TimestampedMaster;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Slave publishing a fixed response for one PID and counting received frames
+    struct Node {
+        pid: PID,
+        data: &'static [u8],
+        received: Rc<Cell<usize>>,
+    }
+
+    impl Slave for Node {
+        fn respond(&mut self, pid: PID) -> Option<Frame> {
+            if pid == self.pid {
+                Some(Frame::from_data(pid, self.data))
+            } else {
+                None
+            }
+        }
+
+        fn receive(&mut self, _frame: &Frame) {
+            self.received.set(self.received.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_virtual_bus() {
+        let received = Rc::new(Cell::new(0));
+        let mut bus = VirtualBus::new(LinSpeed(20000));
+        bus.add_slave(Box::new(Node {
+            pid: PID::from_id(0x10),
+            data: &[0x01],
+            received: received.clone(),
+        }));
+        bus.add_slave(Box::new(|pid: PID| {
+            if pid.get_id() == 0x3A || pid.get_id() == 0x11 {
+                Some(Frame::from_data(pid, &[0x02]))
+            } else {
+                None
+            }
+        }));
+        bus.add_slave(Box::new(|pid: PID| {
+            if pid.get_id() == 0x3A {
+                Some(Frame::from_data(pid, &[0x04]))
+            } else {
+                None
+            }
+        }));
+
+        let frame = crate::Master::read_frame(&mut bus, PID::from_id(0x10), 1).unwrap();
+        assert_eq!(frame.get_data(), &[0x01]);
+        assert_eq!(bus.header_timestamp(), Some(Instant::from_micros(0)));
+        assert_eq!(bus.time(), 2700);
+        assert_eq!(received.get(), 1);
+
+        let frame = Frame::from_data(PID::from_id(0x20), &[0x03]);
+        let timestamps = bus.write_frame_timestamped(&frame).unwrap();
+        assert_eq!(timestamps.frame_time(), Some(Duration::from_micros(2700)));
+        assert_eq!(received.get(), 2);
+
+        assert!(matches!(
+            crate::Master::read_frame(&mut bus, PID::from_id(0x3A), 1),
+            Err(Error::Checksum(_))
+        ));
+        assert_eq!(bus.collisions(), 1);
+        assert_eq!(received.get(), 2);
+
+        assert_eq!(
+            crate::Master::read_frame(&mut bus, PID::from_id(0x12), 1),
+            Err(Error::NoResponse)
+        );
+    }
+
+    #[test]
+    fn test_wakeup_pulse() {
+        let pulse = WakeupPulse::try_new(Duration::from_millis(1)).unwrap();
+        let mut bus = VirtualBus::new(LinSpeed(20000)).with_wakeup_pulse(pulse);
+        crate::Master::send_wakeup(&mut bus).unwrap();
+        assert_eq!(bus.time(), 1_000);
+    }
+}
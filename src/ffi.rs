@@ -0,0 +1,342 @@
+//! C ABI for firmware written in C (feature `ffi`)
+//!
+//! The functions allow existing C code to use the protocol logic of the crate, e.g. the PID
+//! and checksum calculation, during a step by step migration to Rust. The declarations for C
+//! are in `include/lin_bus.h`. The functions are exported when the crate is linked into a
+//! `staticlib`. For firmware this is a small crate depending on `lin-bus` with the `ffi` feature
+//! that provides the `#[panic_handler]`. On hosts the library is built with
+//! `cargo rustc --release --features ffi,std --crate-type staticlib`.
+//!
+//! All functions return `LIN_OK` or a negative error code and write their results to the
+//! output pointers. Null pointers are reported with `LIN_ERROR_NULL_POINTER`, except for data
+//! pointers with the length 0. The functions never panic.
+
+use core::slice;
+
+use crate::frame::{ChecksumModel, Frame, FrameError, PID};
+
+/// Success
+pub const LIN_OK: i32 = 0;
+/// A pointer is null
+pub const LIN_ERROR_NULL_POINTER: i32 = -1;
+/// The frame ID is 64 or larger
+pub const LIN_ERROR_INVALID_ID: i32 = -2;
+/// The parity bits of the PID are wrong
+pub const LIN_ERROR_INVALID_PARITY: i32 = -3;
+/// The data length is not valid for the frame or PDU
+pub const LIN_ERROR_INVALID_LENGTH: i32 = -4;
+/// The checksum model is neither `LIN_CHECKSUM_CLASSIC` nor `LIN_CHECKSUM_ENHANCED`
+pub const LIN_ERROR_INVALID_CHECKSUM_MODEL: i32 = -5;
+/// The checksum of a response is wrong
+pub const LIN_ERROR_CHECKSUM: i32 = -6;
+
+/// Classic checksum over the data bytes only
+pub const LIN_CHECKSUM_CLASSIC: u8 = 0;
+/// Enhanced checksum over the PID and the data bytes
+pub const LIN_CHECKSUM_ENHANCED: u8 = 1;
+
+fn status(error: FrameError) -> i32 {
+    match error {
+        FrameError::InvalidId(_) => LIN_ERROR_INVALID_ID,
+        FrameError::InvalidParity(_) => LIN_ERROR_INVALID_PARITY,
+        _ => LIN_ERROR_INVALID_LENGTH,
+    }
+}
+
+fn checksum_model(model: u8) -> Result<ChecksumModel, i32> {
+    match model {
+        LIN_CHECKSUM_CLASSIC => Ok(ChecksumModel::Classic),
+        LIN_CHECKSUM_ENHANCED => Ok(ChecksumModel::Enhanced),
+        _ => Err(LIN_ERROR_INVALID_CHECKSUM_MODEL),
+    }
+}
+
+/// Slice of `length` bytes at `data`, which may be null for an empty slice
+unsafe fn input<'a>(data: *const u8, length: usize) -> Result<&'a [u8], i32> {
+    match (data.is_null(), length) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(LIN_ERROR_NULL_POINTER),
+        (false, _) => Ok(slice::from_raw_parts(data, length)),
+    }
+}
+
+unsafe fn output<T: Copy>(pointer: *mut T, value: T) -> i32 {
+    match pointer.as_mut() {
+        Some(pointer) => {
+            *pointer = value;
+            LIN_OK
+        }
+        None => LIN_ERROR_NULL_POINTER,
+    }
+}
+
+/// Copy `bytes` to the buffer at `out`, which has room for `N` bytes
+unsafe fn output_bytes<const N: usize>(out: *mut u8, bytes: &[u8]) -> i32 {
+    match (out as *mut [u8; N]).as_mut() {
+        Some(out) => {
+            out[..bytes.len()].copy_from_slice(bytes);
+            LIN_OK
+        }
+        None => LIN_ERROR_NULL_POINTER,
+    }
+}
+
+fn frame(pid: u8, model: u8, data: &[u8]) -> Result<Frame, i32> {
+    let pid = PID::new(pid).map_err(status)?;
+    Frame::try_from_data_with_checksum_model(pid, data, checksum_model(model)?).map_err(status)
+}
+
+/// Calculate the PID with the parity bits of the frame ID `id`
+///
+/// # Safety
+///
+/// `pid` must be null or point to a writable byte.
+#[no_mangle]
+pub unsafe extern "C" fn lin_pid_from_id(id: u8, pid: *mut u8) -> i32 {
+    match PID::try_from_id(id) {
+        Ok(value) => output(pid, value.get()),
+        Err(error) => status(error),
+    }
+}
+
+/// Check the parity bits of `pid` and get its frame ID
+///
+/// # Safety
+///
+/// `id` must be null or point to a writable byte.
+#[no_mangle]
+pub unsafe extern "C" fn lin_id_from_pid(pid: u8, id: *mut u8) -> i32 {
+    match PID::new(pid) {
+        Ok(value) => output(id, value.get_id()),
+        Err(error) => status(error),
+    }
+}
+
+/// Calculate the checksum of `length` bytes at `data` for the frame with the PID `pid`
+///
+/// # Safety
+///
+/// `data` must be null or point to `length` readable bytes, `checksum` must be null or point
+/// to a writable byte.
+#[no_mangle]
+pub unsafe extern "C" fn lin_checksum(
+    pid: u8,
+    model: u8,
+    data: *const u8,
+    length: usize,
+    checksum: *mut u8,
+) -> i32 {
+    let result = input(data, length).and_then(|data| frame(pid, model, data));
+    match result {
+        Ok(frame) => output(checksum, frame.get_checksum()),
+        Err(error) => error,
+    }
+}
+
+/// Build the response of a frame, i.e. the data followed by the checksum, in the buffer of 9
+/// bytes at `response` and store its length in `response_length`
+///
+/// # Safety
+///
+/// `data` must be null or point to `length` readable bytes, `response` must be null or point
+/// to 9 writable bytes and `response_length` must be null or point to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn lin_frame_build(
+    pid: u8,
+    model: u8,
+    data: *const u8,
+    length: usize,
+    response: *mut u8,
+    response_length: *mut usize,
+) -> i32 {
+    let frame = match input(data, length).and_then(|data| frame(pid, model, data)) {
+        Ok(frame) => frame,
+        Err(error) => return error,
+    };
+    let bytes = frame.get_data_with_checksum();
+    if response_length.is_null() {
+        return LIN_ERROR_NULL_POINTER;
+    }
+    match output_bytes::<9>(response, bytes) {
+        LIN_OK => output(response_length, bytes.len()),
+        error => error,
+    }
+}
+
+/// Check the checksum of a received response of `length` bytes including the checksum
+///
+/// # Safety
+///
+/// `response` must be null or point to `length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn lin_frame_check(
+    pid: u8,
+    model: u8,
+    response: *const u8,
+    length: usize,
+) -> i32 {
+    let (checksum, data) = match input(response, length).map(<[u8]>::split_last) {
+        Ok(Some((checksum, data))) => (*checksum, data),
+        Ok(None) => return LIN_ERROR_INVALID_LENGTH,
+        Err(error) => return error,
+    };
+    match frame(pid, model, data) {
+        Ok(frame) if frame.get_checksum() == checksum => LIN_OK,
+        Ok(_) => LIN_ERROR_CHECKSUM,
+        Err(error) => error,
+    }
+}
+
+/// Build the 8 data bytes of a single frame PDU of the master request frame with 1 to 5 bytes
+/// at `data`
+///
+/// # Safety
+///
+/// `data` must be null or point to `length` readable bytes, `frame` must be null or point to
+/// 8 writable bytes.
+#[cfg(feature = "transport")]
+#[no_mangle]
+pub unsafe extern "C" fn lin_diag_single_frame(
+    nad: u8,
+    sid: u8,
+    data: *const u8,
+    length: usize,
+    frame: *mut u8,
+) -> i32 {
+    use crate::frame::transport::{try_create_single_frame, NAD, SID};
+    use crate::iso17987::id;
+
+    let data = match input(data, length) {
+        Ok(data) => data,
+        Err(error) => return error,
+    };
+    let pid = PID::from_masked_id(id::MASTER_REQUEST);
+    match try_create_single_frame(pid, NAD(nad), SID(sid), data) {
+        Ok(pdu) => output_bytes::<8>(frame, pdu.get_data()),
+        Err(error) => status(error),
+    }
+}
+
+/// Build the 8 data bytes of a read by identifier request
+///
+/// # Safety
+///
+/// `frame` must be null or point to 8 writable bytes.
+#[cfg(feature = "diagnostic")]
+#[no_mangle]
+pub unsafe extern "C" fn lin_diag_read_by_identifier(
+    nad: u8,
+    identifier: u8,
+    supplier_id: u16,
+    function_id: u16,
+    frame: *mut u8,
+) -> i32 {
+    use crate::frame::diagnostic::create_read_by_identifier_frame;
+    use crate::frame::transport::NAD;
+
+    let request =
+        create_read_by_identifier_frame(NAD(nad), identifier.into(), supplier_id, function_id);
+    output_bytes::<8>(frame, request.get_data())
+}
+
+/// Build the 8 data bytes of the go-to-sleep command
+///
+/// # Safety
+///
+/// `frame` must be null or point to 8 writable bytes.
+#[cfg(feature = "diagnostic")]
+#[no_mangle]
+pub unsafe extern "C" fn lin_diag_go_to_sleep(frame: *mut u8) -> i32 {
+    output_bytes::<8>(
+        frame,
+        crate::frame::diagnostic::GO_TO_SLEEP_FRAME.get_data(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ptr;
+
+    #[test]
+    fn test_pid() {
+        let mut value = 0;
+        unsafe {
+            assert_eq!(lin_pid_from_id(0x23, &mut value), LIN_OK);
+            assert_eq!(value, 0xA3);
+            assert_eq!(lin_id_from_pid(0xA3, &mut value), LIN_OK);
+            assert_eq!(value, 0x23);
+            assert_eq!(lin_pid_from_id(0x40, &mut value), LIN_ERROR_INVALID_ID);
+            assert_eq!(lin_id_from_pid(0x23, &mut value), LIN_ERROR_INVALID_PARITY);
+            assert_eq!(
+                lin_pid_from_id(0x23, ptr::null_mut()),
+                LIN_ERROR_NULL_POINTER
+            );
+        }
+    }
+
+    #[test]
+    fn test_frame() {
+        let data = [0x01, 0x02];
+        let mut checksum = 0;
+        let mut response = [0u8; 9];
+        let mut length = 0;
+        unsafe {
+            let result = lin_checksum(0x50, LIN_CHECKSUM_ENHANCED, data.as_ptr(), 2, &mut checksum);
+            assert_eq!(result, LIN_OK);
+            assert_eq!(checksum, 0xAC);
+            let result = lin_checksum(0x50, 2, data.as_ptr(), 2, &mut checksum);
+            assert_eq!(result, LIN_ERROR_INVALID_CHECKSUM_MODEL);
+            let result = lin_checksum(0x50, LIN_CHECKSUM_CLASSIC, ptr::null(), 2, &mut checksum);
+            assert_eq!(result, LIN_ERROR_NULL_POINTER);
+
+            let result = lin_frame_build(
+                0x50,
+                LIN_CHECKSUM_ENHANCED,
+                data.as_ptr(),
+                2,
+                response.as_mut_ptr(),
+                &mut length,
+            );
+            assert_eq!(result, LIN_OK);
+            assert_eq!(&response[..length], &[0x01, 0x02, 0xAC]);
+
+            let check = |response: &[u8]| {
+                lin_frame_check(
+                    0x50,
+                    LIN_CHECKSUM_ENHANCED,
+                    response.as_ptr(),
+                    response.len(),
+                )
+            };
+            assert_eq!(check(&[0x01, 0x02, 0xAC]), LIN_OK);
+            assert_eq!(check(&[0x01, 0x02, 0xAD]), LIN_ERROR_CHECKSUM);
+            assert_eq!(check(&[]), LIN_ERROR_INVALID_LENGTH);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_diagnostic() {
+        let mut frame = [0u8; 8];
+        unsafe {
+            let data = [0x00, 0x01];
+            let result = lin_diag_single_frame(0x0A, 0xB2, data.as_ptr(), 2, frame.as_mut_ptr());
+            assert_eq!(result, LIN_OK);
+            assert_eq!(frame, [0x0A, 0x03, 0xB2, 0x00, 0x01, 0xFF, 0xFF, 0xFF]);
+            let result = lin_diag_single_frame(0x0A, 0xB2, ptr::null(), 0, frame.as_mut_ptr());
+            assert_eq!(result, LIN_ERROR_INVALID_LENGTH);
+
+            let result = lin_diag_read_by_identifier(0x0A, 0, 0x1234, 0x5678, frame.as_mut_ptr());
+            assert_eq!(result, LIN_OK);
+            assert_eq!(frame, [0x0A, 0x06, 0xB2, 0x00, 0x34, 0x12, 0x78, 0x56]);
+
+            assert_eq!(lin_diag_go_to_sleep(frame.as_mut_ptr()), LIN_OK);
+            assert_eq!(frame, [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+            assert_eq!(
+                lin_diag_go_to_sleep(ptr::null_mut()),
+                LIN_ERROR_NULL_POINTER
+            );
+        }
+    }
+}
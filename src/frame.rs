@@ -1,32 +1,133 @@
 //! LIN bus frame definitions
 
-use crate::ldf::NodeAttributes;
-use bitfield::BitRange;
-use byteorder::{ByteOrder, LittleEndian};
-use core::mem::size_of;
-use num_traits::{PrimInt, Unsigned};
+use crate::signal::Signal;
+use core::fmt;
+
+/// Invalid input for the construction or decoding of a frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameError {
+    /// The frame ID is 64 or larger
+    InvalidId(u8),
+    /// The parity bits of the PID byte are wrong
+    InvalidParity(u8),
+    /// The frame data exceeds 8 bytes
+    DataTooLong(usize),
+    /// The requested bits are not within the frame data
+    OutOfRange,
+    /// The requested bits do not fit into the output type
+    TypeTooSmall,
+    /// The payload of a single frame must contain between 1 and 5 bytes
+    InvalidPayloadLength(usize),
+    /// The data length differs from the length mandated for the frame ID, see
+    /// `iso17987::known_format`
+    WrongLength { id: u8, expected: usize },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::InvalidId(id) => write!(f, "ID {} is not less than 64", id),
+            FrameError::InvalidParity(pid) => write!(f, "invalid parity bits in PID {:#04X}", pid),
+            FrameError::DataTooLong(length) => {
+                write!(f, "data of {} bytes exceeds 8 bytes", length)
+            }
+            FrameError::OutOfRange => f.write_str("bits are not within the frame data"),
+            FrameError::TypeTooSmall => f.write_str("output type not big enough"),
+            FrameError::InvalidPayloadLength(length) => {
+                write!(f, "single frame payload of {} bytes", length)
+            }
+            FrameError::WrongLength { id, expected } => {
+                write!(f, "frame {:#04X} must have {} data bytes", id, expected)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrameError {}
+
+/// How parsers of received data treat reserved or invalid fields
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParsePolicy {
+    /// Reject data with any reserved or invalid field, e.g. in production masters
+    Strict,
+    /// Accept data as far as it can be interpreted and flag the deviation, e.g. in bench tools
+    /// which need to see malformed traffic
+    Lenient,
+}
+
+/// Fail if the data length differs from the length mandated for the ID of `pid`
+pub(crate) const fn check_known_length(pid: PID, data_length: usize) -> Result<(), FrameError> {
+    match crate::iso17987::known_format(pid.get_id()) {
+        Some((expected, _)) if expected != data_length => Err(FrameError::WrongLength {
+            id: pid.get_id(),
+            expected,
+        }),
+        _ => Ok(()),
+    }
+}
 
 /// Protected ID which is a 6 bit ID with two parity bits
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(transparent)]
-pub struct PID(u8);
+pub struct PID(pub(crate) u8);
 
 impl PID {
-    /// Creates a new PID object with given PID
-    pub const fn new(pid: u8) -> Result<PID, &'static str> {
-        let correct_pid = PID::from_id(pid & 0b0011_1111);
+    /// The PIDs of all 64 IDs, indexed by ID, so headers can be built and checked without
+    /// computing the parity bits
+    pub const TABLE: [PID; 64] = {
+        let mut table = [PID(0); 64];
+        let mut id = 0;
+        while id < 64 {
+            table[id as usize] = PID::compute(id);
+            id += 1;
+        }
+        table
+    };
+
+    /// Creates a new PID object from a raw PID byte, e.g. received from the bus, verifying
+    /// its parity bits
+    pub const fn new(pid: u8) -> Result<PID, FrameError> {
+        let correct_pid = PID::from_masked_id(pid);
         if correct_pid.0 == pid {
             Ok(correct_pid)
         } else {
-            Err("Invalid parity bits")
+            Err(FrameError::InvalidParity(pid))
         }
     }
 
     /// Calculate the PID from an ID.
     /// P0 = ID0 ⊕ ID1 ⊕ ID2 ⊕ ID4
     /// P1 = ¬(ID1 ⊕ ID3 ⊕ ID4 ⊕ ID5)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ID is 64 or larger, see `try_from_id`.
+    #[cfg(any(test, not(feature = "panic-free")))]
     pub const fn from_id(id: u8) -> PID {
-        assert!(id < 64, "ID must be less than 64");
+        match PID::try_from_id(id) {
+            Ok(pid) => pid,
+            Err(_) => panic!("ID must be less than 64"),
+        }
+    }
+
+    /// Calculate the PID from an ID, fails if the ID is 64 or larger
+    pub const fn try_from_id(id: u8) -> Result<PID, FrameError> {
+        if id >= 64 {
+            return Err(FrameError::InvalidId(id));
+        }
+        Ok(PID::from_masked_id(id))
+    }
+
+    /// Look up the PID of the lower 6 bits of `id`
+    pub(crate) const fn from_masked_id(id: u8) -> PID {
+        PID::TABLE[(id & 0b0011_1111) as usize]
+    }
+
+    /// Calculate the PID of a 6 bit ID
+    const fn compute(id: u8) -> PID {
         // count parity bits and check if they are even odd
         let p0 = (id & 0b1_0111).count_ones() as u8 & 0b1;
         let p1 = ((id & 0b11_1010).count_ones() as u8 + 1) & 0b1;
@@ -43,85 +144,481 @@ impl PID {
         self.0 & 0b0011_1111
     }
 
+    /// Data length encoded in the ID bits 4 and 5 by LIN 1.3 and earlier: 2 bytes for the IDs
+    /// 0..=31, 4 bytes for 32..=47 and 8 bytes for 48..=63
+    pub const fn encoded_data_length(self) -> usize {
+        match (self.0 >> 4) & 0b11 {
+            0 | 1 => 2,
+            2 => 4,
+            _ => 8,
+        }
+    }
+
     /// Return if the associated frame uses the classic checksum (diagnostic IDs 60 and 61 or
     /// special use IDs 62, 63)
     pub const fn uses_classic_checksum(self) -> bool {
-        self.get_id() >= 60
+        self.get_id() >= crate::iso17987::id::MASTER_REQUEST
+    }
+
+    /// Return the checksum model used by the associated frame in a LIN 2.x cluster
+    pub const fn checksum_model(self) -> ChecksumModel {
+        if self.uses_classic_checksum() {
+            ChecksumModel::Classic
+        } else {
+            ChecksumModel::Enhanced
+        }
+    }
+}
+
+impl TryFrom<u8> for PID {
+    type Error = FrameError;
+
+    /// See `PID::new`
+    fn try_from(pid: u8) -> Result<PID, FrameError> {
+        PID::new(pid)
+    }
+}
+
+impl From<PID> for u8 {
+    fn from(pid: PID) -> u8 {
+        pid.0
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PID {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "PID({=u8:#04x}, ID {=u8:#04x})", self.0, self.get_id())
+    }
+}
+
+/// Shows the ID, the PID byte and whether the parity bits are correct, e.g.
+/// `id=0x23 pid=0xA3 parity=ok`
+impl fmt::Display for PID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parity = match PID::new(self.0) {
+            Ok(_) => "ok",
+            Err(_) => "error",
+        };
+        write!(
+            f,
+            "id={:#04X} pid={:#04X} parity={}",
+            self.get_id(),
+            self.0,
+            parity
+        )
+    }
+}
+
+/// Serialized as the PID byte, the parity bits are checked when deserializing
+#[cfg(feature = "serde")]
+impl serde::Serialize for PID {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PID {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<PID, D::Error> {
+        let pid = u8::deserialize(deserializer)?;
+        PID::new(pid).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The checksum model used for a frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChecksumModel {
+    /// LIN 1.3 checksum over the data bytes only. Used for the diagnostic frames and for all
+    /// frames of LIN 1.3 slaves.
+    Classic,
+    /// LIN 2.x checksum over the PID and the data bytes
+    Enhanced,
+}
+
+impl ChecksumModel {
+    /// Calculate the checksum of `data` according to the model
+    pub const fn calculate(self, pid: PID, data: &[u8]) -> u8 {
+        match self {
+            ChecksumModel::Classic => classic_checksum(data),
+            ChecksumModel::Enhanced => checksum(pid, data),
+        }
     }
 }
 
 /// Calculate the LIN V2.1 "enhanced" checksum. It is defined as "The inverted eight bit sum with
 /// carry. Eight bit sum with carry is equivalent to sum all values and subtract 255 every time the
 /// sum is greater or equal to 256"
-pub fn checksum(pid: PID, data: &[u8]) -> u8 {
-    let sum = data.iter().fold(u16::from(pid.0), |sum, v| {
-        let sum = sum + u16::from(*v);
-        if sum >= 256 {
-            sum - 255
-        } else {
-            sum
-        }
-    });
+pub const fn checksum(pid: PID, data: &[u8]) -> u8 {
+    // Adding all bytes first and folding the carries once at the end gives the same result
+    // as subtracting 255 after every addition, without a branch per byte
+    let mut sum = pid.0 as u32;
+    let mut i = 0;
+    while i < data.len() {
+        sum += data[i] as u32;
+        i += 1;
+    }
+    while sum > 0xFF {
+        sum = (sum & 0xFF) + (sum >> 8);
+    }
     !(sum as u8)
 }
 
 /// Calculate the LIN V1.3 "classic" checksum. It is defined as "Checksum calculation over the data
 /// bytes only"
-pub fn classic_checksum(data: &[u8]) -> u8 {
+pub const fn classic_checksum(data: &[u8]) -> u8 {
     checksum(PID(0u8), data)
 }
 
-#[derive(Debug, Eq, PartialEq)]
+/// Checksum folded over the bytes of a response while they are received, e.g. in the receive
+/// interrupt of a driver, instead of walking the buffered response afterwards
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChecksumAccumulator {
+    sum: u16,
+}
+
+impl ChecksumAccumulator {
+    /// Start the checksum of a response of `pid` with the default checksum model of the PID
+    pub const fn new(pid: PID) -> ChecksumAccumulator {
+        ChecksumAccumulator::with_checksum_model(pid, pid.checksum_model())
+    }
+
+    pub const fn with_checksum_model(
+        pid: PID,
+        checksum_model: ChecksumModel,
+    ) -> ChecksumAccumulator {
+        let sum = match checksum_model {
+            ChecksumModel::Classic => 0,
+            ChecksumModel::Enhanced => pid.0 as u16,
+        };
+        ChecksumAccumulator { sum }
+    }
+
+    /// Add a received data byte
+    pub fn push(&mut self, byte: u8) {
+        self.sum = add_with_carry(self.sum, byte);
+    }
+
+    /// The checksum of the data bytes pushed so far
+    pub const fn finish(self) -> u8 {
+        !(self.sum as u8)
+    }
+}
+
+/// Eight bit sum with carry of the checksum calculation
+const fn add_with_carry(sum: u16, byte: u8) -> u16 {
+    let sum = sum + byte as u16;
+    if sum >= 256 {
+        sum - 255
+    } else {
+        sum
+    }
+}
+
+/// Details of a received response with a wrong checksum
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    pub pid: PID,
+    /// Checksum calculated from the received data
+    pub expected: u8,
+    /// Checksum received from the bus
+    pub received: u8,
+    response: [u8; 9],
+    length: usize,
+}
+
+impl ChecksumMismatch {
+    /// The raw response including the received checksum
+    pub fn response(&self) -> &[u8] {
+        &self.response[..self.length]
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ChecksumMismatch {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "ChecksumMismatch {{ pid: {}, expected: {=u8:#04x}, received: {=u8:#04x}, response: {=[u8]:02x} }}",
+            self.pid,
+            self.expected,
+            self.received,
+            self.response()
+        )
+    }
+}
+
+/// Unsigned integer types which frame data can be decoded into, see `Frame::decode`
+pub trait Unsigned: Copy {
+    /// Size of the type in bits
+    const BITS: usize;
+
+    /// Truncate a raw value to the type
+    fn from_raw(raw: u64) -> Self;
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Unsigned for $t {
+                const BITS: usize = <$t>::BITS as usize;
+
+                fn from_raw(raw: u64) -> Self {
+                    raw as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned!(u8, u16, u32, u64);
+
+/// Mask of the lower `length` bits
+const fn bit_mask(length: usize) -> u64 {
+    if length >= 64 {
+        u64::MAX
+    } else {
+        (1 << length) - 1
+    }
+}
+
+/// The first 8 bytes of `buffer` as little endian integer
+const fn read_le(buffer: &[u8; 9]) -> u64 {
+    let mut num = 0;
+    let mut i = 0;
+    while i < 8 {
+        num |= (buffer[i] as u64) << (8 * i);
+        i += 1;
+    }
+    num
+}
+
+/// LIN frame with up to 8 data bytes and the checksum
+///
+/// The layout is kept compact, a `Frame` never exceeds `Frame::MAX_SIZE` bytes, so queues and
+/// caches of frames fit into the RAM of small microcontrollers.
+#[derive(Eq, PartialEq)]
 pub struct Frame {
     pub(crate) pid: PID,
     pub(crate) buffer: [u8; 9],
-    pub(crate) data_length: usize,
+    pub(crate) data_length: u8,
+    pub(crate) checksum_model: ChecksumModel,
 }
 
+// Fails the build if a change of `Frame` breaks the guarantee of `Frame::MAX_SIZE`
+const _: () = assert!(core::mem::size_of::<Frame>() <= Frame::MAX_SIZE);
+
 impl Frame {
-    /// Creates a LIN frame from the PID and data. Calculates and adds checksum accordingly
-    pub fn from_data(pid: PID, data: &[u8]) -> Frame {
-        assert!(data.len() <= 8, "Maximum data is 8 bytes");
+    /// Upper bound of `size_of::<Frame>()` on all targets
+    pub const MAX_SIZE: usize = 12;
+
+    /// Creates a LIN frame from the PID and data. Calculates and adds checksum accordingly.
+    /// Usable in constant expressions, so fixed frames can be placed in a `static`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data exceeds 8 bytes, see `try_from_data`.
+    #[cfg(any(test, not(feature = "panic-free")))]
+    pub const fn from_data(pid: PID, data: &[u8]) -> Frame {
+        Frame::from_data_with_checksum_model(pid, data, pid.checksum_model())
+    }
+
+    /// Creates a LIN frame from the PID and data, fails if the data exceeds 8 bytes
+    pub const fn try_from_data(pid: PID, data: &[u8]) -> Result<Frame, FrameError> {
+        Frame::try_from_data_with_checksum_model(pid, data, pid.checksum_model())
+    }
+
+    /// Creates a LIN frame from the PID and data using the given checksum model. Use this to
+    /// force the classic checksum for frames of LIN 1.3 slaves in mixed clusters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data exceeds 8 bytes, see `try_from_data_with_checksum_model`.
+    #[cfg(any(test, not(feature = "panic-free")))]
+    pub const fn from_data_with_checksum_model(
+        pid: PID,
+        data: &[u8],
+        checksum_model: ChecksumModel,
+    ) -> Frame {
+        match Frame::try_from_data_with_checksum_model(pid, data, checksum_model) {
+            Ok(frame) => frame,
+            Err(FrameError::WrongLength { .. }) => panic!("Wrong data length for the frame ID"),
+            Err(_) => panic!("Maximum data is 8 bytes"),
+        }
+    }
+
+    /// Creates a LIN frame with 8 data bytes, which can not fail
+    #[cfg(feature = "transport")]
+    pub(crate) const fn from_full_data(pid: PID, data: [u8; 8]) -> Frame {
         let mut buffer = [0u8; 9];
-        buffer[0..data.len()].clone_from_slice(data);
-        buffer[data.len()] = {
-            if pid.uses_classic_checksum() {
-                classic_checksum(&buffer[0..data.len()])
-            } else {
-                checksum(pid, &buffer[0..data.len()])
-            }
-        };
+        let mut i = 0;
+        while i < 8 {
+            buffer[i] = data[i];
+            i += 1;
+        }
+        buffer[8] = pid.checksum_model().calculate(pid, &data);
         Frame {
             pid,
             buffer,
-            data_length: data.len(),
+            data_length: 8,
+            checksum_model: pid.checksum_model(),
+        }
+    }
+
+    /// Creates a LIN frame from the PID and data using the given checksum model, fails if the
+    /// data exceeds 8 bytes
+    pub const fn try_from_data_with_checksum_model(
+        pid: PID,
+        data: &[u8],
+        checksum_model: ChecksumModel,
+    ) -> Result<Frame, FrameError> {
+        if data.len() > 8 {
+            return Err(FrameError::DataTooLong(data.len()));
+        }
+        if let Err(e) = check_known_length(pid, data.len()) {
+            return Err(e);
+        }
+        let mut buffer = [0u8; 9];
+        let mut i = 0;
+        while i < data.len() {
+            buffer[i] = data[i];
+            i += 1;
+        }
+        buffer[data.len()] = checksum_model.calculate(pid, data);
+        Ok(Frame {
+            pid,
+            buffer,
+            data_length: data.len() as u8,
+            checksum_model,
+        })
+    }
+
+    /// Length of the data, the constructors never exceed 8 bytes. Stating the bound lets the
+    /// compiler remove the bounds checks of `buffer`, which the `panic-free` feature relies on.
+    pub(crate) const fn data_len(&self) -> usize {
+        if self.data_length > 8 {
+            8
+        } else {
+            self.data_length as usize
         }
     }
 
     /// Access the data from the frame
     pub fn get_data(&self) -> &[u8] {
-        &self.buffer[0..self.data_length]
+        &self.buffer[0..self.data_len()]
     }
 
     /// Decode frame data
-    pub fn decode<T>(&self, offset: usize, length: usize) -> T
-    where
-        T: PrimInt + Unsigned,
-        u64: BitRange<T>,
-    {
-        assert!(
-            (offset + length) <= self.data_length * 8,
-            "Not enough data available"
-        );
-        assert!(length <= size_of::<T>() * 8, "Output type not big enough");
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bits are not within the data or do not fit into `T`, see `try_decode`.
+    #[cfg(any(test, not(feature = "panic-free")))]
+    pub fn decode<T: Unsigned>(&self, offset: usize, length: usize) -> T {
+        match self.try_decode(offset, length) {
+            Ok(value) => value,
+            Err(FrameError::TypeTooSmall) => panic!("Output type not big enough"),
+            Err(_) => panic!("Not enough data available"),
+        }
+    }
+
+    /// Decode `length` bits of the frame data starting at bit `offset`
+    pub fn try_decode<T: Unsigned>(&self, offset: usize, length: usize) -> Result<T, FrameError> {
+        let raw = self.try_decode_raw(offset, length)?;
+        if length > T::BITS {
+            return Err(FrameError::TypeTooSmall);
+        }
+        Ok(T::from_raw(raw))
+    }
+
+    /// Decode `length` bits of the frame data starting at bit `offset` into a `u64`, usable in
+    /// constant expressions
+    pub const fn try_decode_raw(&self, offset: usize, length: usize) -> Result<u64, FrameError> {
+        if length == 0 || offset + length > self.data_len() * 8 {
+            return Err(FrameError::OutOfRange);
+        }
+        Ok((read_le(&self.buffer) >> offset) & bit_mask(length))
+    }
 
-        let num = LittleEndian::read_u64(&self.buffer[0..8]);
-        num.bit_range(offset + length - 1, offset)
+    /// Encode the lower `length` bits of `value` into the frame data starting at bit `offset`
+    /// and update the checksum
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bits are not within the data, see `try_encode`.
+    #[cfg(any(test, not(feature = "panic-free")))]
+    pub fn encode(&mut self, offset: usize, length: usize, value: u64) {
+        self.try_encode(offset, length, value)
+            .expect("Not enough data available")
+    }
+
+    /// Encode the lower `length` bits of `value` into the frame data starting at bit `offset`
+    /// and update the checksum, fails if the bits are not within the data
+    pub fn try_encode(
+        &mut self,
+        offset: usize,
+        length: usize,
+        value: u64,
+    ) -> Result<(), FrameError> {
+        if length == 0 || offset + length > self.data_len() * 8 {
+            return Err(FrameError::OutOfRange);
+        }
+        let mask = bit_mask(length) << offset;
+        let num = (read_le(&self.buffer) & !mask) | ((value << offset) & mask);
+        let data_length = self.data_len();
+        self.buffer[0..data_length].copy_from_slice(&num.to_le_bytes()[0..data_length]);
+        self.update_checksum();
+        Ok(())
+    }
+
+    /// Read the raw value of a signal from the frame data
+    ///
+    /// # Panics
+    ///
+    /// Panics if the signal does not fit into the frame, see `try_read_signal`.
+    #[cfg(any(test, not(feature = "panic-free")))]
+    pub fn read_signal(&self, signal: &Signal) -> u64 {
+        self.try_read_signal(signal)
+            .expect("Signal does not fit into frame")
+    }
+
+    /// Read the raw value of a signal from the frame data, fails if the signal does not fit
+    /// into the frame
+    pub fn try_read_signal(&self, signal: &Signal) -> Result<u64, FrameError> {
+        signal.decode(self.get_data())
+    }
+
+    /// Write the raw value of a signal into the frame data and update the checksum
+    ///
+    /// # Panics
+    ///
+    /// Panics if the signal does not fit into the frame, see `try_write_signal`.
+    #[cfg(any(test, not(feature = "panic-free")))]
+    pub fn write_signal(&mut self, signal: &Signal, raw: u64) {
+        self.try_write_signal(signal, raw)
+            .expect("Signal does not fit into frame")
+    }
+
+    /// Write the raw value of a signal into the frame data and update the checksum, fails if
+    /// the signal does not fit into the frame
+    pub fn try_write_signal(&mut self, signal: &Signal, raw: u64) -> Result<(), FrameError> {
+        let data_length = self.data_len();
+        signal.insert(&mut self.buffer[0..data_length], raw)?;
+        self.update_checksum();
+        Ok(())
+    }
+
+    fn update_checksum(&mut self) {
+        self.buffer[self.data_len()] = self
+            .checksum_model
+            .calculate(self.pid, &self.buffer[0..self.data_len()]);
     }
 
     /// Get the checksum from the frame
     pub fn get_checksum(&self) -> u8 {
-        self.buffer[self.data_length]
+        self.buffer[self.data_len()]
     }
 
     /// Get the PID from the frame
@@ -129,31 +626,209 @@ impl Frame {
         self.pid
     }
 
+    /// Get the checksum model used for the frame
+    pub fn get_checksum_model(&self) -> ChecksumModel {
+        self.checksum_model
+    }
+
     /// Get the serialized bytes to write to the driver
     pub fn get_data_with_checksum(&self) -> &[u8] {
-        &self.buffer[0..=self.data_length]
+        &self.buffer[0..=self.data_len()]
+    }
+
+    /// Check the checksum of a frame whose buffer was filled with a received response
+    pub(crate) fn verify_checksum(self) -> Result<Frame, ChecksumMismatch> {
+        self.check_checksum().map(|()| self)
+    }
+
+    /// Check the checksum like `verify_checksum` without moving the frame
+    pub(crate) fn check_checksum(&self) -> Result<(), ChecksumMismatch> {
+        let expected = self
+            .checksum_model
+            .calculate(self.pid, &self.buffer[0..self.data_len()]);
+        let received = self.buffer[self.data_len()];
+        if expected == received {
+            Ok(())
+        } else {
+            Err(ChecksumMismatch {
+                pid: self.pid,
+                expected,
+                received,
+                response: self.buffer,
+                length: self.data_len() + 1,
+            })
+        }
+    }
+}
+
+/// Serialized as PID, data and checksum model, the checksum is calculated when deserializing
+#[cfg(feature = "serde")]
+mod serde_frame {
+    use super::{ChecksumModel, Frame, PID};
+    use core::fmt;
+    use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+    impl Serialize for Frame {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut frame = serializer.serialize_struct("Frame", 3)?;
+            frame.serialize_field("pid", &self.pid)?;
+            frame.serialize_field("data", self.get_data())?;
+            frame.serialize_field("checksum_model", &self.checksum_model)?;
+            frame.end()
+        }
+    }
+
+    /// Up to 8 data bytes
+    struct Data([u8; 8], usize);
+
+    impl<'de> Deserialize<'de> for Data {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Data, D::Error> {
+            deserializer.deserialize_seq(DataVisitor)
+        }
+    }
+
+    struct DataVisitor;
+
+    impl<'de> Visitor<'de> for DataVisitor {
+        type Value = Data;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("up to 8 data bytes")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Data, A::Error> {
+            let mut data = Data([0; 8], 0);
+            while let Some(byte) = seq.next_element()? {
+                if data.1 == 8 {
+                    return Err(de::Error::invalid_length(9, &self));
+                }
+                data.0[data.1] = byte;
+                data.1 += 1;
+            }
+            Ok(data)
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Data, E> {
+            if bytes.len() > 8 {
+                return Err(E::invalid_length(bytes.len(), &self));
+            }
+            let mut data = Data([0; 8], bytes.len());
+            data.0[..bytes.len()].copy_from_slice(bytes);
+            Ok(data)
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename = "Frame")]
+    struct Fields {
+        pid: PID,
+        data: Data,
+        checksum_model: ChecksumModel,
+    }
+
+    impl<'de> Deserialize<'de> for Frame {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Frame, D::Error> {
+            let Fields {
+                pid,
+                data,
+                checksum_model,
+            } = Fields::deserialize(deserializer)?;
+            Frame::try_from_data_with_checksum_model(pid, &data.0[..data.1], checksum_model)
+                .map_err(de::Error::custom)
+        }
+    }
+}
+
+/// Shows the data instead of the whole buffer
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Frame")
+            .field("pid", &self.pid)
+            .field("data", &self.get_data())
+            .field("checksum", &self.get_checksum())
+            .field("checksum_model", &self.checksum_model)
+            .finish()
+    }
+}
+
+/// Shows the ID, the length, the data in hex and the checksum verdict, e.g.
+/// `id=0x10 len=2 data=01 02 checksum=0xAC ok`
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "id={:#04X} len={} data=",
+            self.pid.get_id(),
+            self.data_len()
+        )?;
+        fmt_hex(self.get_data(), f)?;
+        write!(f, " checksum={:#04X}", self.get_checksum())?;
+        match self.check_checksum() {
+            Ok(()) => f.write_str(" ok"),
+            Err(mismatch) => write!(f, " expected={:#04X}", mismatch.expected),
+        }
+    }
+}
+
+/// Write `bytes` as hex separated by spaces, `-` if empty
+fn fmt_hex(bytes: &[u8], f: &mut fmt::Formatter) -> fmt::Result {
+    if bytes.is_empty() {
+        return f.write_str("-");
+    }
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            f.write_str(" ")?;
+        }
+        write!(f, "{:02X}", byte)?;
+    }
+    Ok(())
+}
+
+/// Shows the decoded frame instead of the raw buffer
+#[cfg(feature = "defmt")]
+impl defmt::Format for Frame {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Frame {{ pid: {}, data: {=[u8]:02x}, checksum: {=u8:#04x}, {} }}",
+            self.pid,
+            self.get_data(),
+            self.get_checksum(),
+            self.checksum_model
+        )
     }
 }
 
 /// Implements the transport layer of LIN. The units that are transported in a transport layer
 /// frame are called PDU (Packet Data Unit)
+#[cfg(feature = "transport")]
 pub mod transport {
-    use super::{Frame, PID};
+    use super::{fmt, Frame, FrameError, ParsePolicy, PID};
+    use crate::iso17987::{id, nad, nrc, sid};
+    #[cfg(feature = "ldf")]
+    use crate::ldf::NodeAttributes;
+    use crate::ldf::{P2Min, STMin};
+    use crate::time::{Duration, Instant, Timer};
 
     /// NAD is the address of the slave node being addressed in a request, i.e. only slave nodes
     /// have an address. NAD is also used to indicate the source of a response.
     #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     #[repr(transparent)]
     pub struct NAD(pub u8);
 
     /// The PCI (Protocol Control Information) contains the transport layer flow control
     /// information.
     #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     #[repr(transparent)]
     pub struct PCI(u8);
 
     /// Type of the `PCI` byte
     #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub enum PCIType {
         /// Single Frame
         SF = 0,
@@ -165,71 +840,879 @@ pub mod transport {
         Invalid,
     }
 
-    impl PCI {
-        /// Create a `PCI` with type `PCIType::SF` and the given length
-        pub const fn new_sf(length: u8) -> PCI {
-            assert!(length <= 6, "Maximum length for single frame is 6");
-            PCI(length)
+    impl PCI {
+        /// Create a `PCI` with type `PCIType::SF` and the given length
+        ///
+        /// # Panics
+        ///
+        /// Panics if the length exceeds 6, see `try_new_sf`.
+        #[cfg(any(test, not(feature = "panic-free")))]
+        pub const fn new_sf(length: u8) -> PCI {
+            match PCI::try_new_sf(length) {
+                Ok(pci) => pci,
+                Err(_) => panic!("Maximum length for single frame is 6"),
+            }
+        }
+
+        /// Create a `PCI` with type `PCIType::SF` and the given length, fails if the length
+        /// exceeds 6
+        pub const fn try_new_sf(length: u8) -> Result<PCI, FrameError> {
+            if length > 6 {
+                return Err(FrameError::InvalidPayloadLength(length as usize));
+            }
+            Ok(PCI(length))
+        }
+
+        /// Get the `PCIType` of the PCI
+        pub const fn get_type(self) -> PCIType {
+            match self.0 >> 4 {
+                0 => PCIType::SF,
+                1 => PCIType::FF,
+                2 => PCIType::CF,
+                _ => PCIType::Invalid,
+            }
+        }
+
+        /// Get the length field of the `PCI`
+        pub const fn get_length(self) -> u8 {
+            self.0 & 0x0F
+        }
+    }
+
+    /// The Service Identifier (SID) specifies the request that shall be performed by the slave
+    /// node addressed.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[repr(transparent)]
+    pub struct SID(pub u8);
+
+    impl SID {
+        /// The RSID of a positive response to the service
+        pub const fn positive_response(self) -> RSID {
+            RSID(self.0.wrapping_add(sid::POSITIVE_RESPONSE_OFFSET))
+        }
+
+        /// The standard service, `None` for other SIDs
+        pub const fn service(self) -> Option<ServiceId> {
+            ServiceId::from_sid(self)
+        }
+    }
+
+    /// The Response Service Identifier (RSID) specifies the contents of the response.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[repr(transparent)]
+    pub struct RSID(pub u8);
+
+    impl RSID {
+        /// The RSID of all negative responses
+        pub const NEGATIVE: RSID = RSID(sid::NEGATIVE_RESPONSE);
+
+        pub const fn is_negative(self) -> bool {
+            self.0 == sid::NEGATIVE_RESPONSE
+        }
+
+        /// The SID of the request answered by the positive response, `None` for negative
+        /// responses and RSIDs below the offset of positive responses
+        pub const fn request(self) -> Option<SID> {
+            match self.0.checked_sub(sid::POSITIVE_RESPONSE_OFFSET) {
+                Some(request) if !self.is_negative() => Some(SID(request)),
+                _ => None,
+            }
+        }
+
+        /// The standard service of the request answered by the positive response
+        pub const fn service(self) -> Option<ServiceId> {
+            match self.request() {
+                Some(request) => request.service(),
+                None => None,
+            }
+        }
+
+        /// Whether this is the RSID of a positive response to `sid`
+        pub const fn answers(self, sid: SID) -> bool {
+            !self.is_negative() && self.0 == sid.positive_response().0
+        }
+    }
+
+    /// The standard node configuration, identification and diagnostic services, whose SIDs
+    /// are defined in `iso17987::sid`
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum ServiceId {
+        AssignNad,
+        /// Obsolete since LIN 2.1
+        AssignFrameIdentifier,
+        ReadByIdentifier,
+        ConditionalChangeNad,
+        DataDump,
+        AssignNadViaSnpd,
+        SaveConfiguration,
+        AssignFrameIdentifierRange,
+        DiagnosticSessionControl,
+        RequestDownload,
+        TransferData,
+        RequestTransferExit,
+        TesterPresent,
+        ClearDiagnosticInformation,
+        ReadDtcInformation,
+    }
+
+    impl ServiceId {
+        pub const ALL: [ServiceId; 15] = [
+            ServiceId::AssignNad,
+            ServiceId::AssignFrameIdentifier,
+            ServiceId::ReadByIdentifier,
+            ServiceId::ConditionalChangeNad,
+            ServiceId::DataDump,
+            ServiceId::AssignNadViaSnpd,
+            ServiceId::SaveConfiguration,
+            ServiceId::AssignFrameIdentifierRange,
+            ServiceId::DiagnosticSessionControl,
+            ServiceId::RequestDownload,
+            ServiceId::TransferData,
+            ServiceId::RequestTransferExit,
+            ServiceId::TesterPresent,
+            ServiceId::ClearDiagnosticInformation,
+            ServiceId::ReadDtcInformation,
+        ];
+
+        pub const fn from_sid(sid: SID) -> Option<ServiceId> {
+            match sid {
+                sid::ASSIGN_NAD => Some(ServiceId::AssignNad),
+                sid::ASSIGN_FRAME_IDENTIFIER => Some(ServiceId::AssignFrameIdentifier),
+                sid::READ_BY_IDENTIFIER => Some(ServiceId::ReadByIdentifier),
+                sid::CONDITIONAL_CHANGE_NAD => Some(ServiceId::ConditionalChangeNad),
+                sid::DATA_DUMP => Some(ServiceId::DataDump),
+                sid::ASSIGN_NAD_VIA_SNPD => Some(ServiceId::AssignNadViaSnpd),
+                sid::SAVE_CONFIGURATION => Some(ServiceId::SaveConfiguration),
+                sid::ASSIGN_FRAME_IDENTIFIER_RANGE => Some(ServiceId::AssignFrameIdentifierRange),
+                sid::DIAGNOSTIC_SESSION_CONTROL => Some(ServiceId::DiagnosticSessionControl),
+                sid::REQUEST_DOWNLOAD => Some(ServiceId::RequestDownload),
+                sid::TRANSFER_DATA => Some(ServiceId::TransferData),
+                sid::REQUEST_TRANSFER_EXIT => Some(ServiceId::RequestTransferExit),
+                sid::TESTER_PRESENT => Some(ServiceId::TesterPresent),
+                sid::CLEAR_DIAGNOSTIC_INFORMATION => Some(ServiceId::ClearDiagnosticInformation),
+                sid::READ_DTC_INFORMATION => Some(ServiceId::ReadDtcInformation),
+                _ => None,
+            }
+        }
+
+        pub const fn sid(self) -> SID {
+            match self {
+                ServiceId::AssignNad => sid::ASSIGN_NAD,
+                ServiceId::AssignFrameIdentifier => sid::ASSIGN_FRAME_IDENTIFIER,
+                ServiceId::ReadByIdentifier => sid::READ_BY_IDENTIFIER,
+                ServiceId::ConditionalChangeNad => sid::CONDITIONAL_CHANGE_NAD,
+                ServiceId::DataDump => sid::DATA_DUMP,
+                ServiceId::AssignNadViaSnpd => sid::ASSIGN_NAD_VIA_SNPD,
+                ServiceId::SaveConfiguration => sid::SAVE_CONFIGURATION,
+                ServiceId::AssignFrameIdentifierRange => sid::ASSIGN_FRAME_IDENTIFIER_RANGE,
+                ServiceId::DiagnosticSessionControl => sid::DIAGNOSTIC_SESSION_CONTROL,
+                ServiceId::RequestDownload => sid::REQUEST_DOWNLOAD,
+                ServiceId::TransferData => sid::TRANSFER_DATA,
+                ServiceId::RequestTransferExit => sid::REQUEST_TRANSFER_EXIT,
+                ServiceId::TesterPresent => sid::TESTER_PRESENT,
+                ServiceId::ClearDiagnosticInformation => sid::CLEAR_DIAGNOSTIC_INFORMATION,
+                ServiceId::ReadDtcInformation => sid::READ_DTC_INFORMATION,
+            }
+        }
+
+        /// The RSID of a positive response to the service
+        pub const fn positive_response(self) -> RSID {
+            self.sid().positive_response()
+        }
+
+        pub const fn name(self) -> &'static str {
+            match self {
+                ServiceId::AssignNad => "assign NAD",
+                ServiceId::AssignFrameIdentifier => "assign frame identifier",
+                ServiceId::ReadByIdentifier => "read by identifier",
+                ServiceId::ConditionalChangeNad => "conditional change NAD",
+                ServiceId::DataDump => "data dump",
+                ServiceId::AssignNadViaSnpd => "assign NAD via SNPD",
+                ServiceId::SaveConfiguration => "save configuration",
+                ServiceId::AssignFrameIdentifierRange => "assign frame identifier range",
+                ServiceId::DiagnosticSessionControl => "diagnostic session control",
+                ServiceId::RequestDownload => "request download",
+                ServiceId::TransferData => "transfer data",
+                ServiceId::RequestTransferExit => "request transfer exit",
+                ServiceId::TesterPresent => "tester present",
+                ServiceId::ClearDiagnosticInformation => "clear diagnostic information",
+                ServiceId::ReadDtcInformation => "read DTC information",
+            }
+        }
+    }
+
+    impl From<ServiceId> for SID {
+        fn from(service: ServiceId) -> SID {
+            service.sid()
+        }
+    }
+
+    /// Create a single frame (CF) PDU
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is empty or exceeds 5 bytes, see `try_create_single_frame`.
+    #[cfg(any(test, not(feature = "panic-free")))]
+    pub fn create_single_frame(pid: PID, nad: NAD, sid: SID, data: &[u8]) -> Frame {
+        try_create_single_frame(pid, nad, sid, data)
+            .expect("A single frame must contain between 0 and 5 bytes")
+    }
+
+    /// Create a single frame (CF) PDU, fails if `data` is empty or exceeds 5 bytes
+    pub fn try_create_single_frame(
+        pid: PID,
+        nad: NAD,
+        sid: SID,
+        data: &[u8],
+    ) -> Result<Frame, FrameError> {
+        if data.is_empty() || data.len() > 5 {
+            return Err(FrameError::InvalidPayloadLength(data.len()));
+        }
+        // If a PDU is not completely filled the unused bytes shall be filled with 0xFF.
+        let mut frame_data = [0xFFu8; 8];
+        frame_data[0] = nad.0;
+        frame_data[1] = PCI::try_new_sf(data.len() as u8 + 1)?.0;
+        frame_data[2] = sid.0;
+        frame_data[3..data.len() + 3].clone_from_slice(data);
+        Ok(Frame::from_full_data(pid, frame_data))
+    }
+
+    /// Create a single frame PDU with the maximum of 5 data bytes, which can not fail
+    #[cfg(feature = "diagnostic")]
+    pub(crate) fn create_full_single_frame(pid: PID, nad: NAD, sid: SID, data: [u8; 5]) -> Frame {
+        let [d0, d1, d2, d3, d4] = data;
+        Frame::from_full_data(pid, [nad.0, 6, sid.0, d0, d1, d2, d3, d4])
+    }
+
+    /// Positive single frame response of a slave, see `parse_response`
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct ResponsePdu<'a> {
+        pub nad: NAD,
+        pub rsid: RSID,
+        /// The data following the RSID, without the padding
+        pub data: &'a [u8],
+        /// The first deviation accepted by `ParsePolicy::Lenient`
+        pub deviation: Option<ResponseError>,
+    }
+
+    /// Malformed or unexpected slave response
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum ResponseError {
+        /// The frame is not a slave response frame
+        WrongFrame(PID),
+        /// The NAD is not a slave node address or differs from the addressed node
+        UnexpectedNad(NAD),
+        /// The PCI does not fit the position in the response, e.g. a consecutive frame first
+        InvalidPci(u8),
+        /// The length in the PCI is 0 or exceeds the data
+        InvalidLength(u8),
+        /// An unused byte is not filled with 0xFF
+        InvalidPadding(u8),
+        /// The RSID does not belong to the outstanding request
+        UnexpectedRsid(RSID),
+        /// The slave rejected the request with the negative response code
+        Negative(u8),
+        /// A consecutive frame has the given sequence number instead of the next one
+        Sequence(u8),
+        /// The segmented response of the given length does not fit into the buffer
+        Overflow(usize),
+    }
+
+    /// Shows the NAD in hex and the name of reserved NADs, e.g. `0x7F (broadcast)`
+    impl fmt::Display for NAD {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:#04X}", self.0)?;
+            match *self {
+                nad::GO_TO_SLEEP => f.write_str(" (go-to-sleep)"),
+                nad::FUNCTIONAL => f.write_str(" (functional)"),
+                nad::BROADCAST => f.write_str(" (broadcast)"),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    /// Shows the SID in hex and the name of the service, e.g. `0xB2 (read by identifier)`
+    impl fmt::Display for SID {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:#04X}", self.0)?;
+            match sid::name(*self) {
+                Some(name) => write!(f, " ({})", name),
+                None => Ok(()),
+            }
+        }
+    }
+
+    /// Shows the RSID in hex and the service it responds to, e.g.
+    /// `0xF2 (read by identifier positive)`
+    impl fmt::Display for RSID {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:#04X}", self.0)?;
+            if self.is_negative() {
+                return f.write_str(" (negative)");
+            }
+            match self.service() {
+                Some(service) => write!(f, " ({} positive)", service.name()),
+                None => Ok(()),
+            }
+        }
+    }
+
+    /// Service byte of a decoded diagnostic frame
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum PduService {
+        Request(SID),
+        /// Positive response, or a negative response without SID and NRC
+        Response(RSID),
+        /// Negative response rejecting the request with the negative response code
+        Negative {
+            sid: SID,
+            nrc: u8,
+        },
+    }
+
+    /// Content of a master request or slave response frame, see `Frame::decode_pdu`
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct DecodedPdu<'a> {
+        /// Master request or slave response
+        pub request: bool,
+        pub nad: NAD,
+        pub pci: PCI,
+        /// Message length of valid single and first frames
+        pub length: Option<usize>,
+        /// Sequence number of consecutive frames
+        pub sequence: Option<u8>,
+        /// Service of single and first frames
+        pub service: Option<PduService>,
+        /// Identifier of read by identifier requests
+        pub identifier: Option<u8>,
+        /// The payload following the decoded fields, without the padding of single frames
+        pub data: &'a [u8],
+    }
+
+    impl DecodedPdu<'_> {
+        pub fn is_go_to_sleep(&self) -> bool {
+            self.request && self.nad == nad::GO_TO_SLEEP
+        }
+
+        /// Name of the class of the read by identifier identifier, e.g. `serial number`
+        pub fn identifier_name(&self) -> Option<&'static str> {
+            self.identifier.map(|identifier| match identifier {
+                0 => "product identification",
+                1 => "serial number",
+                32..=63 => "user defined",
+                _ => "reserved",
+            })
+        }
+    }
+
+    /// Decoded diagnostic frame, see `Frame::display_pdu`
+    pub struct PduDisplay<'a>(&'a Frame);
+
+    impl Frame {
+        /// Decode a master request or slave response frame into its transport layer fields
+        /// and service, `None` for other frames. The service and identifier are decoded from
+        /// single and first frames, the continuation of a message in consecutive frames is
+        /// returned as data.
+        pub fn decode_pdu(&self) -> Option<DecodedPdu<'_>> {
+            let request = match self.get_pid().get_id() {
+                id::MASTER_REQUEST => true,
+                id::SLAVE_RESPONSE => false,
+                _ => return None,
+            };
+            let bytes = self.get_data();
+            let (nad, pci) = match bytes {
+                [nad, pci, _, ..] => (NAD(*nad), PCI(*pci)),
+                _ => return None,
+            };
+            let mut pdu = DecodedPdu {
+                request,
+                nad,
+                pci,
+                length: None,
+                sequence: None,
+                service: None,
+                identifier: None,
+                data: &bytes[2..],
+            };
+            let message = match pci.get_type() {
+                PCIType::SF
+                    if (1..=6).contains(&pci.get_length())
+                        && usize::from(pci.get_length()) + 2 <= bytes.len() =>
+                {
+                    let length = usize::from(pci.get_length());
+                    pdu.length = Some(length);
+                    &bytes[2..2 + length]
+                }
+                PCIType::FF => {
+                    pdu.length = Some(usize::from(pci.get_length()) << 8 | usize::from(bytes[2]));
+                    &bytes[3..]
+                }
+                PCIType::CF => {
+                    pdu.sequence = Some(pci.get_length());
+                    return Some(pdu);
+                }
+                _ => return Some(pdu),
+            };
+            let (service, data) = match (request, message) {
+                (_, []) => return Some(pdu),
+                (true, [service, data @ ..]) => (PduService::Request(SID(*service)), data),
+                (false, [sid::NEGATIVE_RESPONSE, rejected, nrc, data @ ..]) => (
+                    PduService::Negative {
+                        sid: SID(*rejected),
+                        nrc: *nrc,
+                    },
+                    data,
+                ),
+                (false, [service, data @ ..]) => (PduService::Response(RSID(*service)), data),
+            };
+            pdu.service = Some(service);
+            pdu.data = data;
+            if let (PduService::Request(sid::READ_BY_IDENTIFIER), [identifier, data @ ..]) =
+                (service, data)
+            {
+                pdu.identifier = Some(*identifier);
+                pdu.data = data;
+            }
+            Some(pdu)
+        }
+
+        /// Format a master request or slave response frame with the decoded NAD, PCI and
+        /// service, e.g. `request NAD=0x10 SF len=2 SID=0xB2 (read by identifier)
+        /// identifier=0 (product identification)`. Other frames are formatted like the
+        /// `Display` implementation of `Frame`.
+        pub fn display_pdu(&self) -> PduDisplay<'_> {
+            PduDisplay(self)
+        }
+    }
+
+    impl fmt::Display for PduDisplay<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self.0.decode_pdu() {
+                Some(pdu) => fmt::Display::fmt(&pdu, f),
+                None => fmt::Display::fmt(self.0, f),
+            }
+        }
+    }
+
+    /// Formats the decoded fields, see `Frame::display_pdu`
+    impl fmt::Display for DecodedPdu<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            if self.is_go_to_sleep() {
+                return f.write_str("go-to-sleep");
+            }
+            let direction = if self.request { "request" } else { "response" };
+            write!(f, "{} NAD={} ", direction, self.nad)?;
+            match (self.pci.get_type(), self.length, self.sequence) {
+                (PCIType::SF, Some(length), _) => write!(f, "SF len={}", length)?,
+                (PCIType::FF, Some(length), _) => write!(f, "FF len={}", length)?,
+                (PCIType::CF, _, Some(sequence)) => write!(f, "CF seq={}", sequence)?,
+                _ => write!(f, "PCI={:#04X}", self.pci.0)?,
+            }
+            match self.service {
+                Some(PduService::Request(sid)) => write!(f, " SID={}", sid)?,
+                Some(PduService::Response(rsid)) => write!(f, " RSID={}", rsid)?,
+                Some(PduService::Negative { sid, nrc }) => {
+                    write!(f, " RSID={} SID={} NRC={:#04X}", RSID::NEGATIVE, sid, nrc)?;
+                    if let Some(name) = nrc::name(nrc) {
+                        write!(f, " ({})", name)?;
+                    }
+                }
+                None => {}
+            }
+            if let (Some(identifier), Some(name)) = (self.identifier, self.identifier_name()) {
+                write!(f, " identifier={} ({})", identifier, name)?;
+            }
+            if self.data.is_empty() {
+                return Ok(());
+            }
+            f.write_str(" data=")?;
+            super::fmt_hex(self.data, f)
+        }
+    }
+
+    impl fmt::Display for ResponseError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                ResponseError::WrongFrame(pid) => {
+                    write!(f, "frame {:#04X} is not a slave response", pid.get_id())
+                }
+                ResponseError::UnexpectedNad(nad) => write!(f, "unexpected NAD {:#04X}", nad.0),
+                ResponseError::InvalidPci(pci) => write!(f, "invalid PCI {:#04X}", pci),
+                ResponseError::InvalidLength(length) => {
+                    write!(f, "invalid single frame length {}", length)
+                }
+                ResponseError::InvalidPadding(byte) => write!(f, "invalid padding {:#04X}", byte),
+                ResponseError::UnexpectedRsid(rsid) => {
+                    write!(f, "unexpected RSID {:#04X}", rsid.0)
+                }
+                ResponseError::Negative(nrc) => {
+                    write!(f, "negative response with code {:#04X}", nrc)
+                }
+                ResponseError::Sequence(sequence) => {
+                    write!(f, "unexpected sequence number {}", sequence)
+                }
+                ResponseError::Overflow(length) => {
+                    write!(f, "response of {} bytes exceeds the buffer", length)
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl std::error::Error for ResponseError {}
+
+    /// Validate a slave response to the request of service `sid` sent to `nad` with
+    /// `ParsePolicy::Strict` and return its content. The NAD of the response must be a slave
+    /// node address, which equals `nad` unless the request was sent to the broadcast address.
+    pub fn parse_response(
+        frame: &Frame,
+        nad: NAD,
+        sid: SID,
+    ) -> Result<ResponsePdu<'_>, ResponseError> {
+        parse_response_with_policy(frame, nad, sid, ParsePolicy::Strict)
+    }
+
+    /// Validate a slave response like `parse_response`. With `ParsePolicy::Lenient` an
+    /// unexpected NAD, a length exceeding the data and invalid padding are accepted and
+    /// reported in `ResponsePdu::deviation`.
+    pub fn parse_response_with_policy(
+        frame: &Frame,
+        nad: NAD,
+        sid: SID,
+        policy: ParsePolicy,
+    ) -> Result<ResponsePdu<'_>, ResponseError> {
+        let mut deviation = None;
+        let mut deviate = |error| match policy {
+            ParsePolicy::Strict => Err(error),
+            ParsePolicy::Lenient => {
+                deviation = deviation.or(Some(error));
+                Ok(())
+            }
+        };
+        if frame.get_pid().get_id() != id::SLAVE_RESPONSE {
+            return Err(ResponseError::WrongFrame(frame.get_pid()));
+        }
+        let (response_nad, pci, rsid, data) = match frame.get_data() {
+            [response_nad, pci, rsid, data @ ..] => (NAD(*response_nad), PCI(*pci), *rsid, data),
+            _ => return Err(ResponseError::InvalidLength(0)),
+        };
+        if !nad::is_slave(response_nad) || (nad != nad::BROADCAST && response_nad != nad) {
+            deviate(ResponseError::UnexpectedNad(response_nad))?;
+        }
+        if pci.get_type() != PCIType::SF {
+            return Err(ResponseError::InvalidPci(pci.0));
+        }
+        let length = usize::from(pci.get_length());
+        if length == 0 {
+            return Err(ResponseError::InvalidLength(0));
+        }
+        if length > data.len() + 1 {
+            deviate(ResponseError::InvalidLength(pci.get_length()))?;
+        }
+        let (data, padding) = data.split_at((length - 1).min(data.len()));
+        if let Some(byte) = padding.iter().find(|byte| **byte != 0xFF) {
+            deviate(ResponseError::InvalidPadding(*byte))?;
+        }
+        if rsid == sid::NEGATIVE_RESPONSE {
+            return match data {
+                [rejected, nrc, ..] if *rejected == sid.0 => Err(ResponseError::Negative(*nrc)),
+                _ => Err(ResponseError::UnexpectedRsid(RSID(rsid))),
+            };
+        }
+        let rsid = RSID(rsid);
+        if !rsid.answers(sid) {
+            return Err(ResponseError::UnexpectedRsid(rsid));
+        }
+        Ok(ResponsePdu {
+            nad: response_nad,
+            rsid,
+            data,
+            deviation,
+        })
+    }
+
+    /// Read the slave response frame into `frame` and validate it like `parse_response`. The
+    /// returned PDU borrows the data of `frame`, so the response is not copied. The outer
+    /// result holds the errors of the bus, the inner one the validation of the response.
+    pub fn receive_response<'a, M: crate::Master>(
+        master: &mut M,
+        frame: &'a mut Frame,
+        nad: NAD,
+        sid: SID,
+    ) -> Result<Result<ResponsePdu<'a>, ResponseError>, M::Error> {
+        master.read_frame_into(PID::from_masked_id(id::SLAVE_RESPONSE), 8, frame)?;
+        Ok(parse_and_log(frame, nad, sid))
+    }
+
+    /// Reassembles a segmented slave response, i.e. a first frame followed by consecutive
+    /// frames, into a buffer. The buffer holds the message starting with the RSID.
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct Reassembly<'b> {
+        buffer: &'b mut [u8],
+        nad: NAD,
+        length: usize,
+        received: usize,
+        sequence: u8,
+    }
+
+    impl<'b> Reassembly<'b> {
+        /// Start the reassembly with the first frame `frame`, fails if it is not a first frame
+        /// or the announced length exceeds `buffer`
+        pub fn start(buffer: &'b mut [u8], frame: &Frame) -> Result<Reassembly<'b>, ResponseError> {
+            let (nad, pci, length, data) = match frame.get_data() {
+                [nad, pci, length, data @ ..] if data.len() == 5 => {
+                    (NAD(*nad), PCI(*pci), *length, data)
+                }
+                _ => return Err(ResponseError::InvalidLength(0)),
+            };
+            if pci.get_type() != PCIType::FF {
+                return Err(ResponseError::InvalidPci(pci.0));
+            }
+            let length = usize::from(pci.get_length()) << 8 | usize::from(length);
+            if length <= data.len() + 1 {
+                return Err(ResponseError::InvalidLength(length as u8));
+            }
+            if length > buffer.len() {
+                return Err(ResponseError::Overflow(length));
+            }
+            buffer[..data.len()].copy_from_slice(data);
+            Ok(Reassembly {
+                buffer,
+                nad,
+                length,
+                received: data.len(),
+                sequence: 1,
+            })
+        }
+
+        /// Add the next consecutive frame, returns whether the response is complete
+        pub fn push(&mut self, frame: &Frame) -> Result<bool, ResponseError> {
+            let (nad, pci, data) = match frame.get_data() {
+                [nad, pci, data @ ..] if data.len() == 6 => (NAD(*nad), PCI(*pci), data),
+                _ => return Err(ResponseError::InvalidLength(0)),
+            };
+            if nad != self.nad {
+                return Err(ResponseError::UnexpectedNad(nad));
+            }
+            if pci.get_type() != PCIType::CF {
+                return Err(ResponseError::InvalidPci(pci.0));
+            }
+            if pci.get_length() != self.sequence {
+                return Err(ResponseError::Sequence(pci.get_length()));
+            }
+            self.sequence = (self.sequence + 1) & 0x0F;
+            let count = data.len().min(self.length - self.received);
+            self.buffer[self.received..self.received + count].copy_from_slice(&data[..count]);
+            self.received += count;
+            Ok(self.is_complete())
+        }
+
+        pub fn is_complete(&self) -> bool {
+            self.received == self.length
         }
 
-        /// Get the `PCIType` of the PCI
-        pub const fn get_type(self) -> PCIType {
-            match self.0 >> 4 {
-                0 => PCIType::SF,
-                1 => PCIType::FF,
-                2 => PCIType::CF,
-                _ => PCIType::Invalid,
+        /// The NAD of the first frame
+        pub fn nad(&self) -> NAD {
+            self.nad
+        }
+
+        /// The received part of the message
+        pub fn message(&self) -> &[u8] {
+            &self.buffer[..self.received]
+        }
+
+        /// Release the buffer, with the length of the received part of the message
+        pub fn finish(self) -> (&'b [u8], NAD) {
+            let Reassembly {
+                buffer,
+                received,
+                nad,
+                ..
+            } = self;
+            (&buffer[..received], nad)
+        }
+    }
+
+    /// Read a slave response of any length into `buffer` and validate it like
+    /// `receive_response`. A single frame response is copied into `buffer`, a segmented one is
+    /// reassembled from its consecutive frames. The data of the returned PDU is in `buffer`.
+    pub fn receive_segmented_response<'b, M: crate::Master>(
+        master: &mut M,
+        buffer: &'b mut [u8],
+        nad: NAD,
+        sid: SID,
+    ) -> Result<Result<ResponsePdu<'b>, ResponseError>, M::Error> {
+        let pid = PID::from_masked_id(id::SLAVE_RESPONSE);
+        let mut frame = master.read_frame(pid, 8)?;
+        if PCI(frame.get_data()[1]).get_type() != PCIType::FF {
+            let pdu = match parse_and_log(&frame, nad, sid) {
+                Ok(pdu) => pdu,
+                Err(e) => return Ok(Err(e)),
+            };
+            if pdu.data.len() > buffer.len() {
+                return Ok(Err(ResponseError::Overflow(pdu.data.len())));
+            }
+            let (response_nad, rsid, deviation) = (pdu.nad, pdu.rsid, pdu.deviation);
+            let length = pdu.data.len();
+            buffer[..length].copy_from_slice(pdu.data);
+            return Ok(Ok(ResponsePdu {
+                nad: response_nad,
+                rsid,
+                data: &buffer[..length],
+                deviation,
+            }));
+        }
+        let mut reassembly = match Reassembly::start(buffer, &frame) {
+            Ok(reassembly) => reassembly,
+            Err(e) => return Ok(Err(e)),
+        };
+        let response_nad = reassembly.nad();
+        if !nad::is_slave(response_nad) || (nad != nad::BROADCAST && response_nad != nad) {
+            return Ok(Err(ResponseError::UnexpectedNad(response_nad)));
+        }
+        while !reassembly.is_complete() {
+            frame = master.read_frame(pid, 8)?;
+            if let Err(e) = reassembly.push(&frame) {
+                log!(debug, "segmented response rejected: {}", e);
+                return Ok(Err(e));
             }
         }
+        let (message, nad) = reassembly.finish();
+        let rsid = RSID(message[0]);
+        if !rsid.answers(sid) {
+            return Ok(Err(ResponseError::UnexpectedRsid(rsid)));
+        }
+        Ok(Ok(ResponsePdu {
+            nad,
+            rsid,
+            data: &message[1..],
+            deviation: None,
+        }))
+    }
 
-        /// Get the length field of the `PCI`
-        pub const fn get_length(self) -> u8 {
-            self.0 & 0x0F
+    fn parse_and_log(frame: &Frame, nad: NAD, sid: SID) -> Result<ResponsePdu<'_>, ResponseError> {
+        let result = parse_response(frame, nad, sid);
+        if let Err(e) = result {
+            log!(
+                debug,
+                "slave response to SID {:#04x} rejected: {}",
+                sid.0,
+                e
+            );
         }
+        result
     }
 
-    /// The Service Identifier (SID) specifies the request that shall be performed by the slave
-    /// node addressed.
+    /// Enforces the diagnostic timing of a slave node on the master: the slave response is
+    /// polled P2min after the end of the master request and consecutive transport frames are
+    /// separated by at least STmin
     #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-    #[repr(transparent)]
-    pub struct SID(pub u8);
+    pub struct DiagnosticTiming {
+        p2_min: Duration,
+        st_min: Duration,
+        request_end: Option<Instant>,
+        frame_end: Option<Instant>,
+    }
 
-    /// The Response Service Identifier (RSID) specifies the contents of the response.
-    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-    #[repr(transparent)]
-    pub struct RSID(pub u8);
+    impl DiagnosticTiming {
+        pub const fn new(p2_min: P2Min, st_min: STMin) -> DiagnosticTiming {
+            DiagnosticTiming {
+                p2_min: Duration::from_micros(p2_min.as_us() as u64),
+                st_min: Duration::from_micros(st_min.as_us() as u64),
+                request_end: None,
+                frame_end: None,
+            }
+        }
 
-    /// Create a single frame (CF) PDU
-    pub fn create_single_frame(pid: PID, nad: NAD, sid: SID, data: &[u8]) -> Frame {
-        assert!(
-            !data.is_empty() && data.len() <= 5,
-            "A single frame must contain between 0 and 5 bytes"
-        );
-        // If a PDU is not completely filled the unused bytes shall be filled with 0xFF.
-        let mut frame_data = [0xFFu8; 8];
-        frame_data[0] = nad.0;
-        frame_data[1] = PCI::new_sf(data.len() as u8 + 1).0;
-        frame_data[2] = sid.0;
-        frame_data[3..data.len() + 3].clone_from_slice(data);
-        Frame::from_data(pid, &frame_data)
+        /// The timing configured in the node attributes
+        #[cfg(feature = "ldf")]
+        pub fn from_node_attributes(attributes: &NodeAttributes) -> DiagnosticTiming {
+            DiagnosticTiming::new(attributes.p2_min, attributes.st_min)
+        }
+
+        /// Send the master request `frame` at least STmin after the previous transport frame
+        pub fn send_request<M, T>(
+            &mut self,
+            master: &mut M,
+            timer: &mut T,
+            frame: &Frame,
+        ) -> Result<(), M::Error>
+        where
+            M: crate::Master,
+            T: Timer + ?Sized,
+        {
+            self.wait(timer, self.frame_end, self.st_min);
+            let result = master.write_frame(frame);
+            let now = timer.now();
+            self.frame_end = Some(now);
+            self.request_end = Some(now);
+            result
+        }
+
+        /// Read and validate the slave response like `receive_response` at least P2min after
+        /// the last request and STmin after the previous transport frame
+        pub fn receive_response<'a, M, T>(
+            &mut self,
+            master: &mut M,
+            timer: &mut T,
+            frame: &'a mut Frame,
+            nad: NAD,
+            sid: SID,
+        ) -> Result<Result<ResponsePdu<'a>, ResponseError>, M::Error>
+        where
+            M: crate::Master,
+            T: Timer + ?Sized,
+        {
+            self.wait(timer, self.request_end, self.p2_min);
+            self.wait(timer, self.frame_end, self.st_min);
+            let result = receive_response(master, frame, nad, sid);
+            self.frame_end = Some(timer.now());
+            result
+        }
+
+        fn wait<T: Timer + ?Sized>(&self, timer: &mut T, since: Option<Instant>, time: Duration) {
+            if let Some(deadline) = since.and_then(|since| since.checked_add(time)) {
+                timer.delay_until(deadline);
+            }
+        }
     }
 }
 
 /// Implements the LIN diagnostics methods.
+#[cfg(feature = "diagnostic")]
 pub mod diagnostic {
-    use super::transport::{create_single_frame, NAD, SID};
-    use super::{ByteOrder, Frame, LittleEndian, PID};
+    use super::transport::{
+        create_full_single_frame, receive_segmented_response, ResponseError, NAD, SID,
+    };
+    use super::{Frame, FrameError, PID};
+    use crate::iso17987::{id, nad, sid, wildcard};
+    #[cfg(feature = "ldf")]
+    use crate::ldf::NodeAttributes;
+
+    pub const MASTER_REQUEST_FRAME_ID: u8 = id::MASTER_REQUEST;
+    pub const SLAVE_RESPONSE_FRAME_ID: u8 = id::SLAVE_RESPONSE;
 
-    pub const MASTER_REQUEST_FRAME_ID: u8 = 0x3C;
-    pub const SLAVE_RESPONSE_FRAME_ID: u8 = 0x3D;
+    pub const MASTER_REQUEST_FRAME_PID: PID = PID::from_masked_id(MASTER_REQUEST_FRAME_ID);
+    pub const SLAVE_RESPONSE_FRAME_PID: PID = PID::from_masked_id(SLAVE_RESPONSE_FRAME_ID);
 
-    pub const MASTER_REQUEST_FRAME_PID: PID = PID::from_id(0x3C);
-    pub const SLAVE_RESPONSE_FRAME_PID: PID = PID::from_id(0x3D);
+    pub const READ_BY_IDENTIFIER_SID: SID = sid::READ_BY_IDENTIFIER;
 
-    pub const READ_BY_IDENTIFIER_SID: SID = SID(0xB2);
+    /// Supplier ID of a request addressing the nodes of all suppliers
+    pub const WILDCARD_SUPPLIER_ID: u16 = wildcard::SUPPLIER_ID;
+    /// Function ID of a request addressing the nodes of all functions
+    pub const WILDCARD_FUNCTION_ID: u16 = wildcard::FUNCTION_ID;
 
     #[repr(u8)]
     /// Identifiers used for the Read by identifer
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     pub enum Identifier {
         /// See also `ProductId`
         LINProductIdentification,
@@ -241,6 +1724,23 @@ pub mod diagnostic {
         Reserved(u8),
     }
 
+    impl Identifier {
+        pub const fn is_user_defined(self) -> bool {
+            matches!(self, Identifier::UserDefined(_))
+        }
+
+        /// Length of the response data of the standard identifiers, user defined identifiers
+        /// have node specific lengths and are transported with segmented responses if they
+        /// exceed 5 bytes
+        pub const fn response_length(self) -> Option<usize> {
+            match self {
+                Identifier::LINProductIdentification => Some(5),
+                Identifier::SerialNumber => Some(4),
+                Identifier::UserDefined(_) | Identifier::Reserved(_) => None,
+            }
+        }
+    }
+
     impl From<u8> for Identifier {
         fn from(byte: u8) -> Identifier {
             match byte {
@@ -265,37 +1765,109 @@ pub mod diagnostic {
 
     /// Holds the LIN slave node product identification
     #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ProductId {
         pub supplier_id: u16,
         pub function_id: u16,
         pub variant: u8,
     }
 
+    impl ProductId {
+        /// Decode the product identification from the first 5 bytes of `data`, fails if
+        /// `data` is shorter
+        pub fn try_from_bytes(data: &[u8]) -> Result<ProductId, FrameError> {
+            if data.len() < 5 {
+                return Err(FrameError::InvalidPayloadLength(data.len()));
+            }
+            Ok(ProductId {
+                supplier_id: u16::from_le_bytes([data[0], data[1]]),
+                function_id: u16::from_le_bytes([data[2], data[3]]),
+                variant: data[4],
+            })
+        }
+
+        /// Whether a request with `supplier_id` and `function_id` addresses the node, each of
+        /// them matches if it is equal or the wildcard
+        pub const fn matches(&self, supplier_id: u16, function_id: u16) -> bool {
+            (supplier_id == WILDCARD_SUPPLIER_ID || supplier_id == self.supplier_id)
+                && (function_id == WILDCARD_FUNCTION_ID || function_id == self.function_id)
+        }
+
+        /// Encode the product identification like in the read by identifier response
+        pub const fn to_bytes(self) -> [u8; 5] {
+            let [supplier_low, supplier_high] = self.supplier_id.to_le_bytes();
+            let [function_low, function_high] = self.function_id.to_le_bytes();
+            [
+                supplier_low,
+                supplier_high,
+                function_low,
+                function_high,
+                self.variant,
+            ]
+        }
+    }
+
+    impl From<ProductId> for [u8; 5] {
+        fn from(product_id: ProductId) -> [u8; 5] {
+            product_id.to_bytes()
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `data` is shorter than 5 bytes, see `ProductId::try_from_bytes`.
+    #[cfg(any(test, not(feature = "panic-free")))]
     impl From<&[u8]> for ProductId {
         fn from(data: &[u8]) -> ProductId {
-            assert!(data.len() >= 5, "We require at least 4 data bytes");
-            ProductId {
-                supplier_id: LittleEndian::read_u16(&data[0..2]),
-                function_id: LittleEndian::read_u16(&data[2..4]),
-                variant: data[4],
-            }
+            ProductId::try_from_bytes(data).expect("We require at least 5 data bytes")
         }
     }
 
     #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     #[repr(transparent)]
     pub struct SerialNumber(pub u32);
 
+    impl SerialNumber {
+        /// Decode the serial number from the first 4 bytes of `data`, fails if `data` is
+        /// shorter
+        pub fn try_from_bytes(data: &[u8]) -> Result<SerialNumber, FrameError> {
+            if data.len() < 4 {
+                return Err(FrameError::InvalidPayloadLength(data.len()));
+            }
+            Ok(SerialNumber(u32::from_le_bytes([
+                data[0], data[1], data[2], data[3],
+            ])))
+        }
+
+        /// Encode the serial number like in the read by identifier response
+        pub const fn to_bytes(self) -> [u8; 4] {
+            self.0.to_le_bytes()
+        }
+    }
+
+    impl From<SerialNumber> for [u8; 4] {
+        fn from(serial_number: SerialNumber) -> [u8; 4] {
+            serial_number.to_bytes()
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `data` is shorter than 4 bytes, see `SerialNumber::try_from_bytes`.
+    #[cfg(any(test, not(feature = "panic-free")))]
     impl From<&[u8]> for SerialNumber {
         fn from(data: &[u8]) -> SerialNumber {
-            assert!(data.len() >= 4, "We require at least 4 data bytes");
-            SerialNumber(LittleEndian::read_u32(data))
+            SerialNumber::try_from_bytes(data).expect("We require at least 4 data bytes")
         }
     }
 
     /// Create a read by identifier `Frame` from `NodeAttributes`
+    #[cfg(feature = "ldf")]
     pub fn create_read_by_identifier_frame_from_node_attributes(
-        node_attributes: super::NodeAttributes,
+        node_attributes: NodeAttributes,
         identifier: Identifier,
     ) -> Frame {
         create_read_by_identifier_frame(
@@ -306,18 +1878,19 @@ pub mod diagnostic {
         )
     }
 
-    /// Create a read by identifier frame
+    /// Create a read by identifier frame. Use `WILDCARD_SUPPLIER_ID` and `WILDCARD_FUNCTION_ID`
+    /// together with the broadcast NAD to discover unknown nodes.
     pub fn create_read_by_identifier_frame(
         nad: NAD,
         identifier: Identifier,
         supplier_id: u16,
         function_id: u16,
     ) -> Frame {
-        create_single_frame(
+        create_full_single_frame(
             MASTER_REQUEST_FRAME_PID,
             nad,
             READ_BY_IDENTIFIER_SID,
-            &[
+            [
                 identifier.into(),
                 (supplier_id & 0xFF) as u8,
                 (supplier_id >> 8) as u8,
@@ -327,29 +1900,122 @@ pub mod diagnostic {
         )
     }
 
-    pub fn create_read_lin_product_identification_frame(
-        node_attributes: super::NodeAttributes,
-    ) -> Frame {
+    /// Read by identifier request received by a slave
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct ReadByIdentifierRequest {
+        pub nad: NAD,
+        pub identifier: Identifier,
+        pub supplier_id: u16,
+        pub function_id: u16,
+    }
+
+    impl ReadByIdentifierRequest {
+        /// Decode a master request frame, `None` if it is no read by identifier request
+        pub fn from_frame(frame: &Frame) -> Option<ReadByIdentifierRequest> {
+            let data = frame.get_data();
+            if frame.get_pid() != MASTER_REQUEST_FRAME_PID
+                || data.len() != 8
+                || data[1] != 0x06
+                || data[2] != READ_BY_IDENTIFIER_SID.0
+            {
+                return None;
+            }
+            Some(ReadByIdentifierRequest {
+                nad: NAD(data[0]),
+                identifier: Identifier::from(data[3]),
+                supplier_id: u16::from_le_bytes([data[4], data[5]]),
+                function_id: u16::from_le_bytes([data[6], data[7]]),
+            })
+        }
+
+        /// Whether the request addresses the slave with `nad` and `product_id`, the NAD
+        /// matches if it is equal or the broadcast NAD and the IDs if they are equal or the
+        /// wildcards
+        pub fn addresses(&self, nad: NAD, product_id: &ProductId) -> bool {
+            (self.nad == nad || self.nad == wildcard::NAD)
+                && product_id.matches(self.supplier_id, self.function_id)
+        }
+    }
+
+    /// Send the read by identifier request and return the response data, which is read into
+    /// `buffer`. Responses longer than 5 bytes, e.g. of user defined identifiers, are
+    /// reassembled from their consecutive frames, see `transport::receive_segmented_response`.
+    pub fn read_by_identifier<'b, M: crate::Master>(
+        master: &mut M,
+        nad: NAD,
+        identifier: Identifier,
+        supplier_id: u16,
+        function_id: u16,
+        buffer: &'b mut [u8],
+    ) -> Result<Result<&'b [u8], ResponseError>, M::Error> {
+        let request = create_read_by_identifier_frame(nad, identifier, supplier_id, function_id);
+        master.write_frame(&request)?;
+        let result = receive_segmented_response(master, buffer, nad, READ_BY_IDENTIFIER_SID)?;
+        Ok(result.map(|pdu| pdu.data))
+    }
+
+    #[cfg(feature = "ldf")]
+    pub fn create_read_lin_product_identification_frame(node_attributes: NodeAttributes) -> Frame {
         create_read_by_identifier_frame_from_node_attributes(
             node_attributes,
             Identifier::LINProductIdentification,
         )
     }
 
-    pub fn create_read_serial_number_frame(node_attributes: super::NodeAttributes) -> Frame {
+    #[cfg(feature = "ldf")]
+    pub fn create_read_serial_number_frame(node_attributes: NodeAttributes) -> Frame {
         create_read_by_identifier_frame_from_node_attributes(
             node_attributes,
             Identifier::SerialNumber,
         )
     }
+
+    /// Create the go-to-sleep command, a master request with the first data byte 0x00
+    pub const fn create_go_to_sleep_frame() -> Frame {
+        Frame::from_full_data(
+            MASTER_REQUEST_FRAME_PID,
+            [nad::GO_TO_SLEEP.0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+        )
+    }
+
+    /// The go-to-sleep command, built at compile time
+    pub static GO_TO_SLEEP_FRAME: Frame = create_go_to_sleep_frame();
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "diagnostic")]
     use super::diagnostic::*;
+    #[cfg(feature = "transport")]
     use super::transport::*;
     use super::*;
 
+    struct Buffer {
+        data: [u8; 160],
+        length: usize,
+    }
+
+    impl fmt::Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.data[self.length..self.length + s.len()].copy_from_slice(s.as_bytes());
+            self.length += s.len();
+            Ok(())
+        }
+    }
+
+    fn assert_display(value: impl fmt::Display, expected: &str) {
+        let mut buffer = Buffer {
+            data: [0; 160],
+            length: 0,
+        };
+        fmt::write(&mut buffer, format_args!("{}", value)).unwrap();
+        assert_eq!(
+            core::str::from_utf8(&buffer.data[..buffer.length]).unwrap(),
+            expected
+        );
+    }
+
     struct CheckSumTestData<'a> {
         pid: PID,
         data: &'a [u8],
@@ -380,6 +2046,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_checksum_accumulator() {
+        let pid = PID::from_id(0x10);
+        let data = [0xFF, 0xFF, 0x01, 0x80];
+        let mut accumulator = ChecksumAccumulator::new(pid);
+        let mut classic = ChecksumAccumulator::with_checksum_model(pid, ChecksumModel::Classic);
+        for byte in data {
+            accumulator.push(byte);
+            classic.push(byte);
+        }
+        assert_eq!(accumulator.finish(), checksum(pid, &data));
+        assert_eq!(classic.finish(), classic_checksum(&data));
+        assert_eq!(ChecksumAccumulator::new(pid).finish(), checksum(pid, &[]));
+
+        // The folded sum of `checksum` must match the per-byte carry of the accumulator
+        for seed in 0..=255u8 {
+            let pid = PID::from_id(seed & 0x3F);
+            let data = [seed, !seed, 0xFF, seed.wrapping_mul(7), 0xFF, seed];
+            let mut accumulator =
+                ChecksumAccumulator::with_checksum_model(pid, ChecksumModel::Enhanced);
+            for byte in data {
+                accumulator.push(byte);
+            }
+            assert_eq!(accumulator.finish(), checksum(pid, &data));
+        }
+    }
+
     #[test]
     fn test_classic_checksum() {
         let test_data = [
@@ -422,7 +2115,10 @@ mod tests {
 
     #[test]
     fn test_invalid_pid_new() {
-        assert_eq!(Err("Invalid parity bits"), PID::new(0x07));
+        assert_eq!(Err(FrameError::InvalidParity(0x07)), PID::new(0x07));
+        assert_eq!(PID::try_from(0x07), Err(FrameError::InvalidParity(0x07)));
+        assert_eq!(PID::try_from(0x50), Ok(PID::from_id(0x10)));
+        assert_eq!(u8::from(PID::from_id(0x10)), 0x50);
     }
 
     #[test]
@@ -452,6 +2148,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_checksum_model_override() {
+        let pid = PID::from_id(0x10);
+        let data = [0x01, 0x02];
+        let frame = Frame::from_data(pid, &data);
+        assert_eq!(frame.get_checksum_model(), ChecksumModel::Enhanced);
+        assert_eq!(frame.get_checksum(), checksum(pid, &data));
+
+        let frame = Frame::from_data_with_checksum_model(pid, &data, ChecksumModel::Classic);
+        assert_eq!(frame.get_checksum_model(), ChecksumModel::Classic);
+        assert_eq!(frame.get_checksum(), classic_checksum(&data));
+    }
+
     #[test]
     #[should_panic]
     fn test_pid_from_id_panic() {
@@ -459,13 +2168,286 @@ mod tests {
     }
 
     #[test]
+    fn test_pid_table() {
+        for id in 0..64 {
+            assert_eq!(PID::TABLE[usize::from(id)], PID::compute(id));
+        }
+        assert_eq!(PID::TABLE[0x3C], PID(0x3C));
+        assert_eq!(PID::TABLE[0x3D], PID(0x7D));
+    }
+
+    #[test]
+    fn test_frame_size() {
+        assert_eq!(core::mem::size_of::<Frame>(), Frame::MAX_SIZE);
+        assert_eq!(core::mem::align_of::<Frame>(), 1);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_display(PID::from_id(0x23), "id=0x23 pid=0xA3 parity=ok");
+        assert_display(PID(0x23), "id=0x23 pid=0x23 parity=error");
+
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        assert_display(&frame, "id=0x10 len=2 data=01 02 checksum=0xAC ok");
+        let mut corrupted = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        corrupted.buffer[2] = 0x00;
+        assert_display(
+            &corrupted,
+            "id=0x10 len=2 data=01 02 checksum=0x00 expected=0xAC",
+        );
+        assert_display(
+            Frame::from_data(PID::from_id(0x10), &[]),
+            "id=0x10 len=0 data=- checksum=0xAF ok",
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "transport")]
+    fn test_service_id() {
+        use crate::iso17987::sid;
+
+        for service in ServiceId::ALL {
+            assert_eq!(ServiceId::from_sid(service.sid()), Some(service));
+            assert_eq!(service.sid().service(), Some(service));
+            assert_eq!(service.positive_response().service(), Some(service));
+            assert!(service.positive_response().answers(service.into()));
+            assert_eq!(sid::name(service.sid()), Some(service.name()));
+        }
+        assert_eq!(SID(0x22).service(), None);
+        assert_eq!(
+            SID::from(ServiceId::ReadByIdentifier),
+            sid::READ_BY_IDENTIFIER
+        );
+        assert_eq!(RSID(0xF2).request(), Some(sid::READ_BY_IDENTIFIER));
+        assert_eq!(RSID(0x20).request(), None);
+        assert!(RSID::NEGATIVE.is_negative());
+        assert_eq!(RSID::NEGATIVE.request(), None);
+        assert_eq!(RSID::NEGATIVE.service(), None);
+        assert!(!RSID(0xF2).answers(sid::ASSIGN_NAD));
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_decode_pdu() {
+        use crate::iso17987::{nrc, sid};
+
+        let request =
+            create_read_by_identifier_frame(NAD(0x10), Identifier::SerialNumber, 0x00B3, 0x1001);
+        let pdu = request.decode_pdu().unwrap();
+        assert!(pdu.request && !pdu.is_go_to_sleep());
+        assert_eq!(pdu.nad, NAD(0x10));
+        assert_eq!(pdu.pci.get_type(), PCIType::SF);
+        assert_eq!(pdu.length, Some(6));
+        assert_eq!(
+            pdu.service,
+            Some(PduService::Request(sid::READ_BY_IDENTIFIER))
+        );
+        assert_eq!(pdu.identifier, Some(1));
+        assert_eq!(pdu.identifier_name(), Some("serial number"));
+        assert_eq!(pdu.data, &[0xB3, 0x00, 0x01, 0x10]);
+
+        let response = Frame::from_data(
+            SLAVE_RESPONSE_FRAME_PID,
+            &[0x10, 0x03, 0x7F, 0x36, 0x78, 0xFF, 0xFF, 0xFF],
+        );
+        let pdu = response.decode_pdu().unwrap();
+        assert!(!pdu.request);
+        assert_eq!(
+            pdu.service,
+            Some(PduService::Negative {
+                sid: sid::TRANSFER_DATA,
+                nrc: nrc::RESPONSE_PENDING
+            })
+        );
+        assert!(pdu.data.is_empty());
+
+        let consecutive = Frame::from_data(
+            MASTER_REQUEST_FRAME_PID,
+            &[0x10, 0x2F, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06],
+        );
+        let pdu = consecutive.decode_pdu().unwrap();
+        assert_eq!(
+            (pdu.length, pdu.sequence, pdu.service),
+            (None, Some(15), None)
+        );
+        assert_eq!(pdu.data, &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        assert!(GO_TO_SLEEP_FRAME.decode_pdu().unwrap().is_go_to_sleep());
+        assert_eq!(
+            Frame::from_data(PID::from_id(0x10), &[0x01]).decode_pdu(),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_display_pdu() {
+        let request = create_read_by_identifier_frame(
+            NAD(0x10),
+            Identifier::LINProductIdentification,
+            0x00B3,
+            0x1001,
+        );
+        assert_display(
+            request.display_pdu(),
+            "request NAD=0x10 SF len=6 SID=0xB2 (read by identifier) \
+             identifier=0 (product identification) data=B3 00 01 10",
+        );
+        let response = Frame::from_data(
+            SLAVE_RESPONSE_FRAME_PID,
+            &[0x10, 0x03, 0x7F, 0xB2, 0x12, 0xFF, 0xFF, 0xFF],
+        );
+        assert_display(
+            response.display_pdu(),
+            "response NAD=0x10 SF len=3 RSID=0x7F (negative) \
+             SID=0xB2 (read by identifier) NRC=0x12 (subfunction not supported)",
+        );
+        let response = Frame::from_data(
+            SLAVE_RESPONSE_FRAME_PID,
+            &[0x10, 0x06, 0xF2, 0xB3, 0x00, 0x01, 0x10, 0x00],
+        );
+        assert_display(
+            response.display_pdu(),
+            "response NAD=0x10 SF len=6 RSID=0xF2 (read by identifier positive) \
+             data=B3 00 01 10 00",
+        );
+        assert_display(NAD(0x7F), "0x7F (broadcast)");
+        assert_display(GO_TO_SLEEP_FRAME.display_pdu(), "go-to-sleep");
+        let first = Frame::from_data(
+            SLAVE_RESPONSE_FRAME_PID,
+            &[0x10, 0x10, 0x0B, 0x59, 0x02, 0x7F, 0x12, 0x34],
+        );
+        assert_display(
+            first.display_pdu(),
+            "response NAD=0x10 FF len=11 RSID=0x59 (read DTC information positive) \
+             data=02 7F 12 34",
+        );
+        let consecutive = Frame::from_data(
+            SLAVE_RESPONSE_FRAME_PID,
+            &[0x10, 0x21, 0x56, 0x2F, 0x9A, 0xBC, 0xDE, 0x08],
+        );
+        assert_display(
+            consecutive.display_pdu(),
+            "response NAD=0x10 CF seq=1 data=56 2F 9A BC DE 08",
+        );
+        let invalid = Frame::from_data(
+            MASTER_REQUEST_FRAME_PID,
+            &[0x10, 0x07, 0xB2, 0x00, 0xFF, 0xFF, 0xFF, 0xFF],
+        );
+        assert_display(
+            invalid.display_pdu(),
+            "request NAD=0x10 PCI=0x07 data=B2 00 FF FF FF FF",
+        );
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        assert_display(
+            frame.display_pdu(),
+            "id=0x10 len=2 data=01 02 checksum=0xAC ok",
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let frame = Frame::from_data_with_checksum_model(
+            PID::from_id(0x10),
+            &[0x01, 0x02],
+            ChecksumModel::Classic,
+        );
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(
+            json,
+            r#"{"pid":80,"data":[1,2],"checksum_model":"Classic"}"#
+        );
+        assert_eq!(serde_json::from_str::<Frame>(&json).unwrap(), frame);
+
+        assert!(serde_json::from_str::<PID>("81").is_err());
+        let too_long = r#"{"pid":80,"data":[1,2,3,4,5,6,7,8,9],"checksum_model":"Classic"}"#;
+        assert!(serde_json::from_str::<Frame>(too_long).is_err());
+    }
+
+    #[test]
+    fn test_const_frames() {
+        static FRAME: Frame = Frame::from_data(PID::from_id(0x10), &[0x01]);
+        const CLASSIC: Frame = Frame::from_data_with_checksum_model(
+            PID::from_id(0x10),
+            &[0x01],
+            ChecksumModel::Classic,
+        );
+        const TOO_LONG: Result<Frame, FrameError> =
+            Frame::try_from_data(PID::from_id(0x10), &[0; 9]);
+        assert_eq!(FRAME.get_data_with_checksum(), &[0x01, 0xAE]);
+        assert_eq!(CLASSIC.get_checksum(), 0xFE);
+        assert_eq!(TOO_LONG, Err(FrameError::DataTooLong(9)));
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_go_to_sleep_frame() {
+        assert_eq!(GO_TO_SLEEP_FRAME, create_go_to_sleep_frame());
+        assert_eq!(GO_TO_SLEEP_FRAME.get_data()[0], 0x00);
+    }
+
+    #[test]
+    fn test_try_constructors() {
+        assert_eq!(PID::try_from_id(0x10), Ok(PID::from_id(0x10)));
+        assert_eq!(PID::try_from_id(64), Err(FrameError::InvalidId(64)));
+        let pid = PID::from_id(0x10);
+        assert_eq!(
+            Frame::try_from_data(pid, &[0; 9]),
+            Err(FrameError::DataTooLong(9))
+        );
+        assert_eq!(
+            Frame::try_from_data(PID::from_id(0x3C), &[0x7F, 0x06, 0xB2]),
+            Err(FrameError::WrongLength {
+                id: 0x3C,
+                expected: 8
+            })
+        );
+        let frame = Frame::try_from_data(pid, &[0x55, 0xDD]).unwrap();
+        assert_eq!(frame.try_decode::<u8>(8, 8), Ok(0xDD));
+        assert_eq!(frame.try_decode::<u8>(8, 9), Err(FrameError::OutOfRange));
+        assert_eq!(frame.try_decode::<u8>(0, 16), Err(FrameError::TypeTooSmall));
+        assert_eq!(frame.try_decode_raw(4, 8), Ok(0xD5));
+        let mut frame = frame;
+        assert_eq!(frame.try_encode(12, 5, 0), Err(FrameError::OutOfRange));
+        frame.try_encode(4, 8, 0x1A3).unwrap();
+        assert_eq!(frame.get_data(), &[0x35, 0xDA]);
+        assert_eq!(frame, Frame::from_data(pid, &[0x35, 0xDA]));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_error_display() {
+        use std::string::ToString;
+        assert_eq!(
+            FrameError::DataTooLong(9).to_string(),
+            "data of 9 bytes exceeds 8 bytes"
+        );
+        assert_eq!(
+            crate::Error::Partial(2).to_string(),
+            "incomplete response, received 2 bytes"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "transport")]
     fn test_pci() {
         let pci = PCI::new_sf(5);
         assert_eq!(pci.get_type(), PCIType::SF);
         assert_eq!(pci.get_length(), 5);
+        assert_eq!(PCI::try_new_sf(7), Err(FrameError::InvalidPayloadLength(7)));
+        assert_eq!(
+            transport::try_create_single_frame(
+                PID::from_id(0x1),
+                transport::NAD(0x2),
+                transport::SID(0x03),
+                &[],
+            ),
+            Err(FrameError::InvalidPayloadLength(0))
+        );
     }
 
     #[test]
+    #[cfg(feature = "diagnostic")]
     fn test_transport_frame() {
         struct TestData {
             pid: PID,
@@ -495,12 +2477,13 @@ mod tests {
             let frame = transport::create_single_frame(d.pid, d.nad, d.sid, d.data);
             assert_eq!(frame.get_pid(), d.pid);
             assert_eq!(frame.get_data(), d.frame_data);
-            assert_eq!(frame.data_length, 8);
+            assert_eq!(frame.data_len(), 8);
         }
     }
 
     #[test]
     #[should_panic]
+    #[cfg(feature = "transport")]
     fn test_transport_frame_without_data() {
         transport::create_single_frame(
             PID::from_id(0x1),
@@ -512,6 +2495,7 @@ mod tests {
 
     #[test]
     #[should_panic]
+    #[cfg(feature = "transport")]
     fn test_transport_frame_with_too_much_data() {
         transport::create_single_frame(
             PID::from_id(0x1),
@@ -522,6 +2506,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "diagnostic")]
     fn test_create_read_by_identifier_frame() {
         const LIN_ID_SERIAL_REQ_PAYLOAD: &[u8] = &[0x10, 0x06, 0xB2, 0x01, 0xB3, 0x00, 0x01, 0x10];
 
@@ -534,13 +2519,54 @@ mod tests {
 
         assert_eq!(frame.get_pid(), diagnostic::MASTER_REQUEST_FRAME_PID);
         assert_eq!(frame.get_data(), LIN_ID_SERIAL_REQ_PAYLOAD);
-        assert_eq!(frame.data_length, 8);
+        assert_eq!(frame.data_len(), 8);
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_read_by_identifier_wildcards() {
+        use super::diagnostic::{
+            ReadByIdentifierRequest, WILDCARD_FUNCTION_ID, WILDCARD_SUPPLIER_ID,
+        };
+
+        let frame = diagnostic::create_read_by_identifier_frame(
+            transport::NAD(0x7F),
+            diagnostic::Identifier::LINProductIdentification,
+            WILDCARD_SUPPLIER_ID,
+            WILDCARD_FUNCTION_ID,
+        );
+        assert_eq!(
+            frame.get_data(),
+            &[0x7F, 0x06, 0xB2, 0x00, 0xFF, 0x7F, 0xFF, 0xFF]
+        );
+        let request = ReadByIdentifierRequest::from_frame(&frame).unwrap();
+        let product_id = ProductId {
+            supplier_id: 0x00B3,
+            function_id: 0x1001,
+            variant: 0x01,
+        };
+        assert!(request.addresses(transport::NAD(0x10), &product_id));
+
+        let request = ReadByIdentifierRequest {
+            nad: transport::NAD(0x10),
+            supplier_id: 0x00B3,
+            ..request
+        };
+        assert!(request.addresses(transport::NAD(0x10), &product_id));
+        assert!(!request.addresses(transport::NAD(0x11), &product_id));
+        assert!(product_id.matches(WILDCARD_SUPPLIER_ID, 0x1001));
+        assert!(!product_id.matches(0x00B4, WILDCARD_FUNCTION_ID));
+        assert_eq!(
+            ReadByIdentifierRequest::from_frame(&diagnostic::create_go_to_sleep_frame()),
+            None
+        );
     }
 
     #[test]
+    #[cfg(feature = "ldf")]
     fn test_create_read_by_identifier_frame_from_node_attributes() {
         const LIN_ID_SERIAL_REQ_PAYLOAD: &[u8] = &[0x10, 0x06, 0xB2, 0x01, 0xB3, 0x00, 0x01, 0x10];
-        let node_attributes = NodeAttributes::with_default_timing(
+        let node_attributes = crate::ldf::NodeAttributes::with_default_timing(
             transport::NAD(0x10),
             transport::NAD(0x10),
             diagnostic::ProductId {
@@ -556,10 +2582,11 @@ mod tests {
         );
         assert_eq!(frame.get_pid(), diagnostic::MASTER_REQUEST_FRAME_PID);
         assert_eq!(frame.get_data(), LIN_ID_SERIAL_REQ_PAYLOAD);
-        assert_eq!(frame.data_length, 8);
+        assert_eq!(frame.data_len(), 8);
     }
 
     #[test]
+    #[cfg(feature = "diagnostic")]
     fn test_decode_product_id() {
         let product_id = ProductId {
             supplier_id: 0x00B3,
@@ -569,12 +2596,253 @@ mod tests {
         let data = [0xB3, 0x00, 0x01, 0x10, 0x01];
 
         assert_eq!(product_id, ProductId::from(&data[..]));
+        assert_eq!(<[u8; 5]>::from(product_id), data);
+        assert_eq!(
+            ProductId::try_from_bytes(&data[..4]),
+            Err(FrameError::InvalidPayloadLength(4))
+        );
     }
 
     #[test]
+    #[cfg(feature = "diagnostic")]
     fn test_decode_serial_number() {
         let serial_number = SerialNumber(190200009);
         let data = [0xC9, 0x38, 0x56, 0x0B];
         assert_eq!(serial_number, SerialNumber::from(&data[..]));
+        assert_eq!(serial_number.to_bytes(), data);
+        assert_eq!(
+            SerialNumber::try_from_bytes(&data[..3]),
+            Err(FrameError::InvalidPayloadLength(3))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_parse_response() {
+        let pid = SLAVE_RESPONSE_FRAME_PID;
+        let sid = READ_BY_IDENTIFIER_SID;
+        let response = |data: &[u8; 8]| Frame::from_data(pid, data);
+
+        let frame = response(&[0x10, 0x06, 0xF2, 0xB3, 0x00, 0x01, 0x10, 0x01]);
+        let pdu = parse_response(&frame, NAD(0x7F), sid).unwrap();
+        assert_eq!(pdu.nad, NAD(0x10));
+        assert_eq!(pdu.rsid, RSID(0xF2));
+        assert_eq!(pdu.data, &[0xB3, 0x00, 0x01, 0x10, 0x01]);
+
+        let frame = response(&[0x10, 0x02, 0xF2, 0x01, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(
+            parse_response(&frame, NAD(0x10), sid).unwrap().data,
+            &[0x01]
+        );
+
+        let frame = response(&[0x11, 0x02, 0xF2, 0x01, 0x00, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(
+            parse_response(&frame, NAD(0x10), sid),
+            Err(ResponseError::UnexpectedNad(NAD(0x11)))
+        );
+        let pdu = parse_response_with_policy(&frame, NAD(0x10), sid, ParsePolicy::Lenient);
+        assert_eq!(
+            pdu,
+            Ok(ResponsePdu {
+                nad: NAD(0x11),
+                rsid: RSID(0xF2),
+                data: &[0x01],
+                deviation: Some(ResponseError::UnexpectedNad(NAD(0x11))),
+            })
+        );
+        let frame = response(&[0x10, 0x09, 0xF2, 0, 0, 0, 0, 0]);
+        let pdu = parse_response_with_policy(&frame, NAD(0x10), sid, ParsePolicy::Lenient);
+        assert_eq!(pdu.unwrap().data, &[0, 0, 0, 0, 0]);
+
+        let frame = response(&[0x10, 0x03, 0x7F, 0xB2, 0x12, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(
+            parse_response(&frame, NAD(0x10), sid),
+            Err(ResponseError::Negative(0x12))
+        );
+
+        let errors = [
+            (
+                [0x11, 0x06, 0xF2, 0, 0, 0, 0, 0],
+                ResponseError::UnexpectedNad(NAD(0x11)),
+            ),
+            (
+                [0x10, 0x10, 0xF2, 0, 0, 0, 0, 0],
+                ResponseError::InvalidPci(0x10),
+            ),
+            (
+                [0x10, 0x05, 0xF2, 0, 0, 0, 0, 0],
+                ResponseError::InvalidPadding(0x00),
+            ),
+            (
+                [0x10, 0x07, 0xF2, 0, 0, 0, 0, 0],
+                ResponseError::InvalidLength(7),
+            ),
+            (
+                [0x10, 0x00, 0xF2, 0, 0, 0, 0, 0],
+                ResponseError::InvalidLength(0),
+            ),
+            (
+                [0x10, 0x06, 0xF0, 0, 0, 0, 0, 0],
+                ResponseError::UnexpectedRsid(RSID(0xF0)),
+            ),
+        ];
+        for (data, error) in errors {
+            assert_eq!(parse_response(&response(&data), NAD(0x10), sid), Err(error));
+        }
+
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x10, 0x06]);
+        assert_eq!(
+            parse_response(&frame, NAD(0x10), sid),
+            Err(ResponseError::WrongFrame(frame.get_pid()))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_receive_response() {
+        use crate::driver::loopback::Loopback;
+
+        let mut driver = Loopback::new();
+        let pid = SLAVE_RESPONSE_FRAME_PID;
+        let sid = READ_BY_IDENTIFIER_SID;
+        let mut frame = Frame::from_data(PID::from_id(0x10), &[]);
+        driver.set_response(pid, &[0x10, 0x02, 0xF2, 0x01, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let pdu = receive_response(&mut driver, &mut frame, NAD(0x10), sid).unwrap();
+        assert_eq!(pdu.unwrap().data, &[0x01]);
+        assert_eq!(frame.get_pid(), pid);
+
+        driver.set_raw_response(pid, &[0x10, 0x02]);
+        assert_eq!(
+            receive_response(&mut driver, &mut frame, NAD(0x10), sid),
+            Err(crate::Error::Partial(2))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_read_by_identifier_segmented() {
+        use crate::driver::mock::{Mock, Transaction};
+
+        let request =
+            create_read_by_identifier_frame(NAD(0x10), Identifier::UserDefined(32), 0x1234, 0x5678);
+        let response = |data| Frame::from_full_data(SLAVE_RESPONSE_FRAME_PID, data);
+        let first = response([0x10, 0x10, 0x0C, 0xF2, 1, 2, 3, 4]);
+        let second = response([0x10, 0x21, 5, 6, 7, 8, 9, 10]);
+        let last = response([0x10, 0x22, 11, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let expectations = [
+            Transaction::Header(MASTER_REQUEST_FRAME_PID),
+            Transaction::Write(request.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::Read(first.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::Read(second.get_data_with_checksum()),
+            Transaction::Header(SLAVE_RESPONSE_FRAME_PID),
+            Transaction::Read(last.get_data_with_checksum()),
+        ];
+        let mut driver = Mock::new(&expectations);
+        let mut buffer = [0u8; 16];
+        let data = read_by_identifier(
+            &mut driver,
+            NAD(0x10),
+            Identifier::UserDefined(32),
+            0x1234,
+            0x5678,
+            &mut buffer,
+        );
+        assert_eq!(data, Ok(Ok(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11][..])));
+        driver.done();
+
+        let mut buffer = [0u8; 8];
+        assert_eq!(
+            Reassembly::start(&mut buffer, &first),
+            Err(ResponseError::Overflow(12))
+        );
+        let mut buffer = [0u8; 16];
+        let mut reassembly = Reassembly::start(&mut buffer, &first).unwrap();
+        assert_eq!(reassembly.push(&last), Err(ResponseError::Sequence(2)));
+        assert_eq!(reassembly.push(&second), Ok(false));
+        assert_eq!(reassembly.message().len(), 11);
+        assert_eq!(
+            Reassembly::start(&mut [0u8; 16], &second),
+            Err(ResponseError::InvalidPci(0x21))
+        );
+        assert!(Identifier::from(40).is_user_defined());
+        assert_eq!(Identifier::SerialNumber.response_length(), Some(4));
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_receive_segmented_single_frame() {
+        use crate::driver::loopback::Loopback;
+
+        let mut driver = Loopback::new();
+        driver.set_response(
+            SLAVE_RESPONSE_FRAME_PID,
+            &[0x10, 0x03, 0xF2, 0x01, 0x02, 0xFF, 0xFF, 0xFF],
+        );
+        let mut buffer = [0u8; 4];
+        let pdu =
+            receive_segmented_response(&mut driver, &mut buffer, NAD(0x10), READ_BY_IDENTIFIER_SID)
+                .unwrap()
+                .unwrap();
+        assert_eq!(pdu.data, &[0x01, 0x02]);
+        assert_eq!(pdu.nad, NAD(0x10));
+    }
+
+    #[test]
+    #[cfg(feature = "diagnostic")]
+    fn test_diagnostic_timing() {
+        use crate::driver::loopback::Loopback;
+        use crate::ldf::{P2Min, STMin};
+        use crate::time::tests::TestClock;
+
+        let mut driver = Loopback::new();
+        let mut timer = TestClock::default();
+        // The loopback returns the written request
+        let drain = |driver: &mut Loopback| {
+            crate::driver::Master::read(driver, &mut [0; 9]).unwrap();
+        };
+        let mut timing = DiagnosticTiming::new(P2Min::from_ms(50), STMin::from_ms(80));
+        let sid = READ_BY_IDENTIFIER_SID;
+        let request = create_read_by_identifier_frame(
+            NAD(0x10),
+            Identifier::LINProductIdentification,
+            0x1234,
+            0x5678,
+        );
+        driver.set_response(
+            SLAVE_RESPONSE_FRAME_PID,
+            &[0x10, 0x02, 0xF2, 0x01, 0xFF, 0xFF, 0xFF, 0xFF],
+        );
+        timing
+            .send_request(&mut driver, &mut timer, &request)
+            .unwrap();
+        drain(&mut driver);
+        assert_eq!(timer.now, 0);
+
+        // STmin exceeds P2min, so the response waits for STmin
+        let mut frame = Frame::from_data(PID::from_id(0x10), &[]);
+        let pdu = timing
+            .receive_response(&mut driver, &mut timer, &mut frame, NAD(0x10), sid)
+            .unwrap();
+        assert_eq!(pdu.map(|pdu| pdu.data), Ok(&[0x01][..]));
+        assert_eq!(timer.now, 80_000);
+
+        timer.now = 100_000;
+        timing
+            .send_request(&mut driver, &mut timer, &request)
+            .unwrap();
+        drain(&mut driver);
+        assert_eq!(timer.now, 160_000);
+        timing = DiagnosticTiming::new(P2Min::from_ms(50), STMin::from_ms(0));
+        timing
+            .send_request(&mut driver, &mut timer, &request)
+            .unwrap();
+        drain(&mut driver);
+        timing
+            .receive_response(&mut driver, &mut timer, &mut frame, NAD(0x10), sid)
+            .unwrap()
+            .unwrap();
+        assert_eq!(timer.now, 210_000);
     }
 }
@@ -0,0 +1,132 @@
+//! Detection of a silent bus
+//!
+//! `BusIdle` is fed with the time of every bus activity, e.g. each received byte, and reports
+//! when the bus has been silent for longer than its threshold. Slaves use it with the bus idle
+//! timeout of 4 s to enter sleep mode, a master to detect a bus without traffic and
+//! `monitor::BusMonitor` to end responses of captures without idle events.
+//!
+//! ```
+//! use lin_bus::idle::BusIdle;
+//! use lin_bus::time::{Duration, Instant};
+//!
+//! let mut idle = BusIdle::sleep_timeout();
+//! idle.activity(Instant::from_micros(0));
+//! assert!(!idle.poll(Instant::from_micros(3_999_999)));
+//! assert!(idle.poll(Instant::from_micros(4_000_000)));
+//! // Reported once per silent period
+//! assert!(!idle.poll(Instant::from_micros(5_000_000)));
+//! assert_eq!(idle.idle_time(Instant::from_micros(5_000_000)), Duration::from_millis(5_000));
+//! ```
+
+use crate::iso17987::timing;
+use crate::time::{Clock, Duration, Instant};
+
+/// Bus idle tracker, see the module documentation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BusIdle {
+    threshold: Duration,
+    last_activity: Option<Instant>,
+    reported: bool,
+}
+
+impl BusIdle {
+    /// Tracker reporting silence of at least `threshold`
+    pub const fn new(threshold: Duration) -> BusIdle {
+        BusIdle {
+            threshold,
+            last_activity: None,
+            reported: false,
+        }
+    }
+
+    /// Tracker with the bus idle timeout after which slaves enter sleep mode
+    pub const fn sleep_timeout() -> BusIdle {
+        BusIdle::new(Duration::from_millis(timing::BUS_IDLE_TIMEOUT_MS as u64))
+    }
+
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Record bus activity at `at`, e.g. a received byte or break
+    pub fn activity(&mut self, at: Instant) {
+        self.last_activity = Some(at);
+        self.reported = false;
+    }
+
+    /// Record bus activity at the current time of `clock`
+    pub fn activity_now<C: Clock + ?Sized>(&mut self, clock: &mut C) {
+        self.activity(clock.now());
+    }
+
+    /// Time of the last activity, `None` before the first one
+    pub fn last_activity(&self) -> Option<Instant> {
+        self.last_activity
+    }
+
+    /// Time since the last activity, zero before the first one
+    pub fn idle_time(&self, now: Instant) -> Duration {
+        self.last_activity
+            .map_or(Duration::ZERO, |last| now.duration_since(last))
+    }
+
+    /// Whether the bus has been silent for at least the threshold. The bus counts as active
+    /// before the first activity, so the silence is measured from the first `poll`.
+    pub fn is_idle(&self, now: Instant) -> bool {
+        self.last_activity.is_some() && self.idle_time(now) >= self.threshold
+    }
+
+    /// Returns `true` once when the bus became idle, until the next activity. The first call
+    /// without any activity starts the measurement.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if self.last_activity.is_none() {
+            self.last_activity = Some(now);
+        }
+        if self.reported || !self.is_idle(now) {
+            return false;
+        }
+        self.reported = true;
+        true
+    }
+
+    /// `poll` at the current time of `clock`
+    pub fn poll_now<C: Clock + ?Sized>(&mut self, clock: &mut C) -> bool {
+        let now = clock.now();
+        self.poll(now)
+    }
+
+    /// Forget all activity
+    pub fn reset(&mut self) {
+        *self = BusIdle::new(self.threshold);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::tests::TestClock;
+
+    #[test]
+    fn test_bus_idle() {
+        let mut clock = TestClock {
+            step: 600,
+            ..Default::default()
+        };
+        let mut idle = BusIdle::new(Duration::from_millis(1));
+        assert!(!idle.is_idle(Instant::from_micros(10_000)));
+        assert!(!idle.poll_now(&mut clock));
+        assert!(!idle.poll_now(&mut clock));
+        assert!(idle.poll_now(&mut clock));
+        assert!(!idle.poll_now(&mut clock));
+
+        idle.activity_now(&mut clock);
+        assert_eq!(idle.last_activity(), Some(Instant::from_micros(3_000)));
+        assert!(!idle.poll_now(&mut clock));
+        assert!(idle.poll_now(&mut clock));
+
+        idle.reset();
+        assert_eq!(idle.last_activity(), None);
+        assert_eq!(idle.idle_time(Instant::from_micros(10_000)), Duration::ZERO);
+    }
+}
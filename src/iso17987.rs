@@ -0,0 +1,324 @@
+//! Constants of ISO 17987 (LIN), collected in one place for conformance reviews
+//!
+//! The diagnostic and transport layers, the drivers and the LDF model refer to these constants
+//! instead of repeating the values of the standard.
+
+#[cfg(feature = "transport")]
+use crate::frame::transport::{NAD, SID};
+use crate::frame::ChecksumModel;
+
+/// Frame identifiers (ISO 17987-3, 5.2.3)
+pub mod id {
+    /// Highest identifier of signal carrying frames
+    pub const MAX_SIGNAL_FRAME: u8 = 0x3B;
+    /// Master request frame of the diagnostic and node configuration services
+    pub const MASTER_REQUEST: u8 = 0x3C;
+    /// Slave response frame of the diagnostic and node configuration services
+    pub const SLAVE_RESPONSE: u8 = 0x3D;
+    /// The first of the two identifiers reserved for future use
+    pub const RESERVED_FIRST: u8 = 0x3E;
+    /// The second of the two identifiers reserved for future use
+    pub const RESERVED_LAST: u8 = 0x3F;
+
+    /// Whether the identifier is reserved for future use
+    pub const fn is_reserved(id: u8) -> bool {
+        id == RESERVED_FIRST || id == RESERVED_LAST
+    }
+
+    /// Whether the identifier is one of the diagnostic frames
+    pub const fn is_diagnostic(id: u8) -> bool {
+        id == MASTER_REQUEST || id == SLAVE_RESPONSE
+    }
+}
+
+/// Mandated data length and checksum model of the frames with the identifiers 60 to 63. The
+/// diagnostic frames always carry 8 bytes with the classic checksum, the format of the reserved
+/// frames and of all other frames is defined by the cluster, so `None` is returned for them.
+pub const fn known_format(id: u8) -> Option<(usize, ChecksumModel)> {
+    match id {
+        id::MASTER_REQUEST | id::SLAVE_RESPONSE => Some((8, ChecksumModel::Classic)),
+        _ => None,
+    }
+}
+
+/// Node addresses (ISO 17987-3, 6.2.3)
+#[cfg(feature = "transport")]
+pub mod nad {
+    use super::NAD;
+
+    /// Reserved for the go-to-sleep command
+    pub const GO_TO_SLEEP: NAD = NAD(0x00);
+    /// Lowest address of a slave node
+    pub const SLAVE_FIRST: NAD = NAD(0x01);
+    /// Highest address of a slave node
+    pub const SLAVE_LAST: NAD = NAD(0x7D);
+    /// Functional address, addressing all slave nodes, which do not respond
+    pub const FUNCTIONAL: NAD = NAD(0x7E);
+    /// Broadcast address, addressing all slave nodes
+    pub const BROADCAST: NAD = NAD(0x7F);
+
+    /// Whether the address can be assigned to a slave node
+    pub const fn is_slave(nad: NAD) -> bool {
+        nad.0 >= SLAVE_FIRST.0 && nad.0 <= SLAVE_LAST.0
+    }
+}
+
+/// Service identifiers of the node configuration and identification services
+/// (ISO 17987-3, 6.3)
+#[cfg(feature = "transport")]
+pub mod sid {
+    use super::SID;
+    use crate::frame::transport::ServiceId;
+
+    pub const ASSIGN_NAD: SID = SID(0xB0);
+    /// Obsolete since LIN 2.1
+    pub const ASSIGN_FRAME_IDENTIFIER: SID = SID(0xB1);
+    pub const READ_BY_IDENTIFIER: SID = SID(0xB2);
+    pub const CONDITIONAL_CHANGE_NAD: SID = SID(0xB3);
+    pub const DATA_DUMP: SID = SID(0xB4);
+    pub const ASSIGN_NAD_VIA_SNPD: SID = SID(0xB5);
+    pub const SAVE_CONFIGURATION: SID = SID(0xB6);
+    pub const ASSIGN_FRAME_IDENTIFIER_RANGE: SID = SID(0xB7);
+    /// Diagnostic session control of ISO 14229-1 (UDS)
+    pub const DIAGNOSTIC_SESSION_CONTROL: SID = SID(0x10);
+    /// Tester present of ISO 14229-1 (UDS), keeps a non-default session alive
+    pub const TESTER_PRESENT: SID = SID(0x3E);
+    /// Request download of ISO 14229-1 (UDS), starts a firmware transfer
+    pub const REQUEST_DOWNLOAD: SID = SID(0x34);
+    /// Transfer data of ISO 14229-1 (UDS)
+    pub const TRANSFER_DATA: SID = SID(0x36);
+    /// Request transfer exit of ISO 14229-1 (UDS)
+    pub const REQUEST_TRANSFER_EXIT: SID = SID(0x37);
+    /// Clear diagnostic information of ISO 14229-1 (UDS), clears the fault memory
+    pub const CLEAR_DIAGNOSTIC_INFORMATION: SID = SID(0x14);
+    /// Read DTC information of ISO 14229-1 (UDS), reads the fault memory
+    pub const READ_DTC_INFORMATION: SID = SID(0x19);
+    /// SID of a negative response
+    pub const NEGATIVE_RESPONSE: u8 = 0x7F;
+    /// Offset added to the SID for the RSID of a positive response
+    pub const POSITIVE_RESPONSE_OFFSET: u8 = 0x40;
+
+    /// Name of a node configuration, identification or diagnostic service, see
+    /// `ServiceId::name`
+    pub const fn name(sid: SID) -> Option<&'static str> {
+        match ServiceId::from_sid(sid) {
+            Some(service) => Some(service.name()),
+            None => None,
+        }
+    }
+}
+
+/// Negative response codes (ISO 14229-1, used by ISO 17987-3)
+pub mod nrc {
+    pub const GENERAL_REJECT: u8 = 0x10;
+    pub const SERVICE_NOT_SUPPORTED: u8 = 0x11;
+    pub const SUBFUNCTION_NOT_SUPPORTED: u8 = 0x12;
+    pub const INCORRECT_MESSAGE_LENGTH: u8 = 0x13;
+    pub const CONDITIONS_NOT_CORRECT: u8 = 0x22;
+    pub const REQUEST_OUT_OF_RANGE: u8 = 0x31;
+    pub const RESPONSE_PENDING: u8 = 0x78;
+
+    /// Name of a negative response code
+    pub const fn name(nrc: u8) -> Option<&'static str> {
+        match nrc {
+            GENERAL_REJECT => Some("general reject"),
+            SERVICE_NOT_SUPPORTED => Some("service not supported"),
+            SUBFUNCTION_NOT_SUPPORTED => Some("subfunction not supported"),
+            INCORRECT_MESSAGE_LENGTH => Some("incorrect message length"),
+            CONDITIONS_NOT_CORRECT => Some("conditions not correct"),
+            REQUEST_OUT_OF_RANGE => Some("request out of range"),
+            RESPONSE_PENDING => Some("response pending"),
+            _ => None,
+        }
+    }
+}
+
+/// Wildcards of the product identification (ISO 17987-3, 6.3.3)
+#[cfg(feature = "transport")]
+pub mod wildcard {
+    pub const NAD: super::NAD = super::NAD(0x7F);
+    pub const SUPPLIER_ID: u16 = 0x7FFF;
+    pub const FUNCTION_ID: u16 = 0xFFFF;
+}
+
+/// Timing parameters (ISO 17987-2 and -3) and the nominal and maximum durations of frames
+///
+/// The durations are calculated from the bit rate of the cluster and rounded down to µs:
+///
+/// ```
+/// use lin_bus::iso17987::timing;
+/// use lin_bus::ldf::LinSpeed;
+/// use lin_bus::time::Duration;
+///
+/// let speed = LinSpeed(20_000);
+/// assert_eq!(timing::header_time(speed), Duration::from_micros(1_700));
+/// assert_eq!(timing::response_time(2, speed), Duration::from_micros(1_500));
+/// assert_eq!(timing::max_frame_time(2, speed), Duration::from_micros(4_480));
+/// ```
+pub mod timing {
+    use crate::ldf::LinSpeed;
+    use crate::time::Duration;
+
+    /// Minimum length of the break field in bit times
+    pub const BREAK_BITS: u32 = 13;
+    /// Minimum length of the break delimiter in bit times
+    pub const BREAK_DELIMITER_BITS: u32 = 1;
+    /// Nominal length of a header in bit times
+    pub const HEADER_NOMINAL_BITS: u32 = 34;
+    /// Length of a transmitted byte in bit times, including start and stop bit
+    pub const BYTE_BITS: u32 = 10;
+    /// Maximum transmission time of a frame in percent of its nominal time
+    pub const FRAME_TOLERANCE_PERCENT: u32 = 140;
+    /// Default response tolerance in percent of the nominal response time
+    pub const RESPONSE_TOLERANCE_PERCENT: u32 = 40;
+    /// Minimum length of the wakeup signal in µs
+    pub const WAKEUP_MIN_US: u32 = 250;
+    /// Maximum length of the wakeup signal in µs
+    pub const WAKEUP_MAX_US: u32 = 5_000;
+    /// Time after a wakeup signal without a header after which the wakeup is repeated, in ms
+    pub const WAKEUP_RETRY_MS: u32 = 150;
+    /// Number of wakeup signals after which the node pauses before the next attempt
+    pub const WAKEUP_RETRIES: u32 = 3;
+    /// Pause after `WAKEUP_RETRIES` failed wakeup signals, in ms
+    pub const WAKEUP_PAUSE_MS: u32 = 1_500;
+    /// Time of bus inactivity after which the slaves enter sleep mode, in ms
+    pub const BUS_IDLE_TIMEOUT_MS: u32 = 4_000;
+    /// Default P2min, the minimum time between a master request and the slave response
+    /// header, in ms
+    pub const P2_MIN_MS: u32 = 50;
+    /// Default ST_min, the minimum time between two consecutive frames, in ms
+    pub const ST_MIN_MS: u32 = 0;
+    /// Default N_As timeout of the transport layer in ms
+    pub const N_AS_TIMEOUT_MS: u32 = 1_000;
+    /// Default N_Cr timeout of the transport layer in ms
+    pub const N_CR_TIMEOUT_MS: u32 = 1_000;
+    /// Lowest bus speed in bit/s
+    pub const MIN_SPEED: u32 = 1_000;
+    /// Highest bus speed in bit/s
+    pub const MAX_SPEED: u32 = 20_000;
+
+    /// Duration of `bits` bit times multiplied by `percent` / 100, a speed of 0 is treated as
+    /// 1 bit/s
+    const fn scaled_bits(bits: u64, percent: u64, speed: LinSpeed) -> Duration {
+        let speed = if speed.0 == 0 { 1 } else { speed.0 as u64 };
+        Duration::from_micros(bits * percent * 10_000 / speed)
+    }
+
+    /// Nominal duration of one bit in ns
+    pub const fn bit_time_ns(speed: LinSpeed) -> u32 {
+        let speed = if speed.0 == 0 { 1 } else { speed.0 };
+        1_000_000_000 / speed
+    }
+
+    /// Nominal duration of `bits` bit times
+    pub const fn bits(bits: u32, speed: LinSpeed) -> Duration {
+        scaled_bits(bits as u64, 100, speed)
+    }
+
+    /// Nominal duration of `count` bytes including start and stop bits
+    pub const fn bytes(count: usize, speed: LinSpeed) -> Duration {
+        scaled_bits(count as u64 * BYTE_BITS as u64, 100, speed)
+    }
+
+    /// Duration of the break field and the break delimiter of minimal length
+    pub const fn break_duration(speed: LinSpeed) -> Duration {
+        bits(BREAK_BITS + BREAK_DELIMITER_BITS, speed)
+    }
+
+    /// Nominal duration of a header
+    pub const fn header_time(speed: LinSpeed) -> Duration {
+        bits(HEADER_NOMINAL_BITS, speed)
+    }
+
+    /// Nominal duration of a response with `data_length` data bytes and the checksum
+    pub const fn response_time(data_length: usize, speed: LinSpeed) -> Duration {
+        bytes(data_length + 1, speed)
+    }
+
+    /// Maximum duration of a response, the nominal time plus the default response tolerance
+    pub const fn max_response_time(data_length: usize, speed: LinSpeed) -> Duration {
+        let bits = (data_length as u64 + 1) * BYTE_BITS as u64;
+        scaled_bits(bits, 100 + RESPONSE_TOLERANCE_PERCENT as u64, speed)
+    }
+
+    /// Nominal duration of a frame with `data_length` data bytes
+    pub const fn frame_time(data_length: usize, speed: LinSpeed) -> Duration {
+        let bits = HEADER_NOMINAL_BITS as u64 + (data_length as u64 + 1) * BYTE_BITS as u64;
+        scaled_bits(bits, 100, speed)
+    }
+
+    /// Maximum duration of a frame, `FRAME_TOLERANCE_PERCENT` of the nominal time, the minimum
+    /// length of a schedule slot
+    pub const fn max_frame_time(data_length: usize, speed: LinSpeed) -> Duration {
+        let bits = HEADER_NOMINAL_BITS as u64 + (data_length as u64 + 1) * BYTE_BITS as u64;
+        scaled_bits(bits, FRAME_TOLERANCE_PERCENT as u64, speed)
+    }
+
+    /// Total time available for the inter-byte spaces and the response space of a response,
+    /// i.e. the difference of its maximum and nominal duration
+    pub const fn inter_byte_budget(data_length: usize, speed: LinSpeed) -> Duration {
+        max_response_time(data_length, speed).saturating_sub(response_time(data_length, speed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges() {
+        assert!(id::is_reserved(0x3F));
+        assert!(!id::is_reserved(0x3D));
+        assert!(id::is_diagnostic(0x3D));
+    }
+
+    #[test]
+    #[cfg(feature = "transport")]
+    fn test_nad_ranges() {
+        assert!(nad::is_slave(NAD(0x7D)));
+        assert!(!nad::is_slave(nad::FUNCTIONAL));
+        assert!(!nad::is_slave(nad::GO_TO_SLEEP));
+    }
+
+    #[test]
+    fn test_timing() {
+        use crate::ldf::LinSpeed;
+        use crate::time::Duration;
+
+        let speed = LinSpeed(19200);
+        assert_eq!(timing::bit_time_ns(speed), 52_083);
+        assert_eq!(timing::bits(13, speed), Duration::from_micros(677));
+        assert_eq!(timing::break_duration(speed), Duration::from_micros(729));
+        assert_eq!(timing::header_time(speed), Duration::from_micros(1_770));
+        assert_eq!(timing::bytes(2, speed), Duration::from_micros(1_041));
+        assert_eq!(
+            timing::response_time(8, speed),
+            Duration::from_micros(4_687)
+        );
+        assert_eq!(
+            timing::max_response_time(8, speed),
+            Duration::from_micros(6_562)
+        );
+        assert_eq!(
+            timing::inter_byte_budget(8, speed),
+            Duration::from_micros(1_875)
+        );
+        assert_eq!(timing::frame_time(8, speed), Duration::from_micros(6_458));
+        assert_eq!(
+            timing::max_frame_time(8, speed),
+            Duration::from_micros(9_041)
+        );
+        assert_eq!(
+            timing::header_time(LinSpeed(0)),
+            Duration::from_micros(34_000_000)
+        );
+    }
+
+    #[test]
+    fn test_known_format() {
+        assert_eq!(known_format(0x3C), Some((8, ChecksumModel::Classic)));
+        assert_eq!(known_format(0x3D), Some((8, ChecksumModel::Classic)));
+        assert_eq!(known_format(0x3E), None);
+        assert_eq!(known_format(0x10), None);
+    }
+}
@@ -0,0 +1,207 @@
+//! SAE J2602 profile of LIN
+//!
+//! J2602 networks run at 10.417 kbit/s, use the classic checksum for all frames and derive the
+//! data length from the frame ID like LIN 1.3. The first data byte of every frame published by
+//! a slave is a status byte reporting communication errors of the slave. A `ClusterConfig`
+//! built with `with_j2602` applies these rules, so the same code can serve LIN 2.x and J2602
+//! networks.
+
+use crate::cluster::{FrameConfig, Publisher};
+use crate::frame::{Frame, PID};
+use crate::iso17987::id;
+use crate::ldf::LinSpeed;
+
+/// The fixed bus speed of J2602 networks
+pub const SPEED: LinSpeed = LinSpeed::BAUD_10417;
+
+/// Highest ID of message frames, 0x3C and 0x3D are the diagnostic frames
+pub const MAX_MESSAGE_ID: u8 = id::MAX_SIGNAL_FRAME;
+
+/// Error code in the upper three bits of the status byte, ordered by priority
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorCode {
+    NoError,
+    /// The slave was reset since the last status report
+    Reset,
+    /// Reserved codes 2 and 3
+    Reserved(u8),
+    /// A received data byte did not match the transmitted one
+    DataError,
+    ChecksumError,
+    /// A byte with an invalid stop bit was received
+    FramingError,
+    /// A header with a wrong PID parity was received
+    ParityError,
+}
+
+impl From<u8> for ErrorCode {
+    /// Decode the error code from the lower three bits of `code`
+    fn from(code: u8) -> ErrorCode {
+        match code & 0b111 {
+            0 => ErrorCode::NoError,
+            1 => ErrorCode::Reset,
+            4 => ErrorCode::DataError,
+            5 => ErrorCode::ChecksumError,
+            6 => ErrorCode::FramingError,
+            7 => ErrorCode::ParityError,
+            code => ErrorCode::Reserved(code),
+        }
+    }
+}
+
+impl ErrorCode {
+    /// The three bit code
+    pub const fn code(self) -> u8 {
+        match self {
+            ErrorCode::NoError => 0,
+            ErrorCode::Reset => 1,
+            ErrorCode::Reserved(code) => code & 0b111,
+            ErrorCode::DataError => 4,
+            ErrorCode::ChecksumError => 5,
+            ErrorCode::FramingError => 6,
+            ErrorCode::ParityError => 7,
+        }
+    }
+}
+
+impl From<ErrorCode> for u8 {
+    fn from(code: ErrorCode) -> u8 {
+        code.code()
+    }
+}
+
+/// Status byte sent by J2602 slaves as first data byte of their frames
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Status(pub u8);
+
+impl Status {
+    pub const fn new(error: ErrorCode, application_info: u8) -> Status {
+        Status(error.code() << 5 | (application_info & 0b1_1111))
+    }
+
+    /// The status byte of a slave frame, `None` if the frame has no data
+    pub fn from_frame(frame: &Frame) -> Option<Status> {
+        frame.get_data().first().map(|byte| Status(*byte))
+    }
+
+    pub fn error(self) -> ErrorCode {
+        ErrorCode::from(self.0 >> 5)
+    }
+
+    /// The application specific lower five bits
+    pub const fn application_info(self) -> u8 {
+        self.0 & 0b1_1111
+    }
+}
+
+/// Violation of the J2602 frame rules
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// The IDs 0x3E and 0x3F are reserved
+    ReservedId(u8),
+    /// The data length differs from the length encoded in the ID
+    Length { id: u8, expected: usize },
+    /// Frames published by a slave need at least the status byte
+    MissingStatus(u8),
+}
+
+/// Whether the ID may be used in a J2602 network
+pub const fn is_valid_id(id: u8) -> bool {
+    id < id::RESERVED_FIRST
+}
+
+/// Check a frame of the cluster against the J2602 rules
+pub fn check_frame(config: &FrameConfig) -> Result<(), Violation> {
+    if !is_valid_id(config.id) {
+        return Err(Violation::ReservedId(config.id));
+    }
+    let expected = PID::from_masked_id(config.id).encoded_data_length();
+    if config.id <= MAX_MESSAGE_ID && usize::from(config.data_length) != expected {
+        return Err(Violation::Length {
+            id: config.id,
+            expected,
+        });
+    }
+    if config.id <= MAX_MESSAGE_ID
+        && config.publisher != Publisher::Master
+        && config.data_length == 0
+    {
+        return Err(Violation::MissingStatus(config.id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::ClusterConfig;
+    use crate::frame::ChecksumModel;
+
+    #[test]
+    fn test_status() {
+        let status = Status::new(ErrorCode::ChecksumError, 0x03);
+        assert_eq!(status, Status(0xA3));
+        assert_eq!(status.error(), ErrorCode::ChecksumError);
+        assert_eq!(status.application_info(), 0x03);
+        assert_eq!(Status(0x40).error(), ErrorCode::Reserved(2));
+        assert_eq!(u8::from(ErrorCode::ParityError), 7);
+        assert!(ErrorCode::ParityError > ErrorCode::Reset);
+
+        let frame = Frame::from_data_with_checksum_model(
+            PID::from_id(0x10),
+            &[0x20, 0x55],
+            ChecksumModel::Classic,
+        );
+        assert_eq!(
+            Status::from_frame(&frame).map(Status::error),
+            Some(ErrorCode::Reset)
+        );
+    }
+
+    #[test]
+    fn test_check_frame() {
+        assert_eq!(
+            check_frame(&FrameConfig::new(0x10, 2, Publisher::Slave(0))),
+            Ok(())
+        );
+        assert_eq!(
+            check_frame(&FrameConfig::new(0x3C, 8, Publisher::Master)),
+            Ok(())
+        );
+        assert_eq!(
+            check_frame(&FrameConfig::new(0x3E, 8, Publisher::Master)),
+            Err(Violation::ReservedId(0x3E))
+        );
+        assert_eq!(
+            check_frame(&FrameConfig::new(0x20, 2, Publisher::Master)),
+            Err(Violation::Length {
+                id: 0x20,
+                expected: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_cluster() {
+        const FRAMES: &[FrameConfig] = &[FrameConfig::new(0x10, 2, Publisher::Slave(0))];
+        let cluster = ClusterConfig::new(FRAMES).with_j2602();
+        assert!(cluster.is_j2602());
+        assert_eq!(cluster.checksum_model(0x10), Some(ChecksumModel::Classic));
+        assert_eq!(cluster.data_length(0x30), Some(8));
+        assert_eq!(cluster.checksum_model(0x3D), Some(ChecksumModel::Classic));
+        assert_eq!(cluster.data_length(0x3E), None);
+
+        let frame = Frame::from_data_with_checksum_model(
+            PID::from_id(0x10),
+            &[0xE0, 0x00],
+            ChecksumModel::Classic,
+        );
+        assert_eq!(
+            cluster.status(&frame).map(Status::error),
+            Some(ErrorCode::ParityError)
+        );
+        let frame = Frame::from_data(PID::from_id(0x11), &[0xE0, 0x00]);
+        assert_eq!(cluster.status(&frame), None);
+    }
+}
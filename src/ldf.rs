@@ -1,74 +1,346 @@
 //! Contains structs representing data of the LDF file
 
-use crate::frame::diagnostic::ProductId;
-use crate::frame::transport::NAD;
+use core::fmt;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-#[repr(transparent)]
-pub struct P2Min(pub f32);
+use crate::frame::{ChecksumModel, PID};
+use crate::iso17987::timing;
+
+#[cfg(all(feature = "ldf", feature = "alloc"))]
+mod diff;
+#[cfg(all(feature = "ldf", feature = "alloc"))]
+pub use diff::Difference;
+#[cfg(feature = "ldf-stream")]
+mod lexer;
+#[cfg(feature = "ldf-stream")]
+pub use lexer::ParseError;
+#[cfg(all(feature = "ldf", feature = "alloc"))]
+mod model;
+#[cfg(all(feature = "ldf", feature = "alloc"))]
+pub use model::*;
+#[cfg(all(feature = "ldf", feature = "alloc"))]
+pub mod ncf;
+#[cfg(feature = "ldf")]
+mod node;
+#[cfg(feature = "ldf")]
+pub use node::*;
+#[cfg(feature = "ldf-parse")]
+mod parser;
+#[cfg(all(feature = "ldf", feature = "alloc"))]
+mod validate;
+#[cfg(all(feature = "ldf", feature = "alloc"))]
+pub use validate::Finding;
+#[cfg(all(feature = "ldf", feature = "alloc"))]
+mod writer;
+#[cfg(feature = "ldf-parse")]
+pub use parser::parse;
+#[cfg(feature = "ldf-stream")]
+pub mod stream;
+
+/// Defines a duration type of the node attributes holding integer microseconds. The LDF
+/// specifies the values as decimal milliseconds, they are converted when parsing.
+macro_rules! duration {
+    ($(#[$doc:meta])* $name:ident, $default_ms:expr) => {
+        $(#[$doc])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[repr(transparent)]
+        pub struct $name(pub u32);
+
+        impl $name {
+            /// Converts whole milliseconds, saturating at `u32::MAX` µs (about 71.6 minutes)
+            pub const fn from_ms(ms: u32) -> $name {
+                $name(ms.saturating_mul(1000))
+            }
+
+            pub const fn as_us(self) -> u32 {
+                self.0
+            }
+
+            /// Whole milliseconds, rounded down
+            pub const fn as_ms(self) -> u32 {
+                self.0 / 1000
+            }
+        }
 
-impl Default for P2Min {
-    fn default() -> P2Min {
-        P2Min(50.0)
+        impl Default for $name {
+            fn default() -> $name {
+                $name::from_ms($default_ms)
+            }
+        }
+
+        /// Formats the duration in milliseconds like the LDF, e.g. `12.5`
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt_ms(self.0, f)
+            }
+        }
+    };
+}
+
+fn fmt_ms(us: u32, f: &mut fmt::Formatter) -> fmt::Result {
+    let (ms, fraction) = (us / 1000, us % 1000);
+    match fraction {
+        0 => write!(f, "{}", ms),
+        _ if fraction % 100 == 0 => write!(f, "{}.{}", ms, fraction / 100),
+        _ if fraction % 10 == 0 => write!(f, "{}.{:02}", ms, fraction / 10),
+        _ => write!(f, "{}.{:03}", ms, fraction),
     }
 }
 
+duration!(
+    /// Minimum time between a master request and the slave response header in µs
+    P2Min,
+    timing::P2_MIN_MS
+);
+duration!(
+    /// Minimum time between two consecutive frames of a node in µs
+    STMin,
+    timing::ST_MIN_MS
+);
+duration!(
+    /// Timeout of the transmission of a frame by the transport layer in µs
+    NAsTimeout,
+    timing::N_AS_TIMEOUT_MS
+);
+duration!(
+    /// Timeout of the reception of the next consecutive frame by the transport layer in µs
+    NCrTimeout,
+    timing::N_CR_TIMEOUT_MS
+);
+
+/// Response tolerance in percent of the nominal response time
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(transparent)]
-pub struct STMin(pub f32);
+pub struct ResponseTolerance(pub f32);
 
-impl Default for STMin {
-    fn default() -> STMin {
-        STMin(0.0)
+impl Default for ResponseTolerance {
+    fn default() -> ResponseTolerance {
+        ResponseTolerance(timing::RESPONSE_TOLERANCE_PERCENT as f32)
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-#[repr(transparent)]
-pub struct NAsTimeout(pub f32);
+/// LIN protocol version of a node or cluster
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinProtocolVersion {
+    V1_3,
+    V2_0,
+    V2_1,
+    V2_2,
+    /// ISO 17987:2016
+    Iso17987,
+    /// SAE J2602
+    J2602,
+}
 
-impl Default for NAsTimeout {
-    fn default() -> NAsTimeout {
-        NAsTimeout(1000.0)
+impl Default for LinProtocolVersion {
+    fn default() -> LinProtocolVersion {
+        LinProtocolVersion::V2_2
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+impl LinProtocolVersion {
+    /// Parse the protocol version string used in LDF and NCF files, e.g. `"2.1"`
+    pub fn from_ldf_str(version: &str) -> Option<LinProtocolVersion> {
+        match version {
+            "1.3" => Some(LinProtocolVersion::V1_3),
+            "2.0" => Some(LinProtocolVersion::V2_0),
+            "2.1" => Some(LinProtocolVersion::V2_1),
+            "2.2" => Some(LinProtocolVersion::V2_2),
+            v if v.starts_with("ISO17987") => Some(LinProtocolVersion::Iso17987),
+            v if v.starts_with("J2602") => Some(LinProtocolVersion::J2602),
+            _ => None,
+        }
+    }
+
+    /// Return the checksum model used for a frame in a cluster of this protocol version. LIN 1.3
+    /// uses the classic checksum for all frames.
+    pub const fn checksum_model(self, pid: PID) -> ChecksumModel {
+        match self {
+            LinProtocolVersion::V1_3 => ChecksumModel::Classic,
+            _ => pid.checksum_model(),
+        }
+    }
+
+    /// Return the protocol version string used in LDF and NCF files
+    pub fn as_ldf_str(self) -> &'static str {
+        match self {
+            LinProtocolVersion::V1_3 => "1.3",
+            LinProtocolVersion::V2_0 => "2.0",
+            LinProtocolVersion::V2_1 => "2.1",
+            LinProtocolVersion::V2_2 => "2.2",
+            LinProtocolVersion::Iso17987 => "ISO17987:2015",
+            LinProtocolVersion::J2602 => "J2602_1_1.0",
+        }
+    }
+}
+
+/// Bus speed in bit/s
+///
+/// The field is public for speeds read from configuration files, `LinSpeed::try_new` checks
+/// the range of the standard.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(transparent)]
-pub struct NCrTimeout(pub f32);
+pub struct LinSpeed(pub u32);
 
-impl Default for NCrTimeout {
-    fn default() -> NCrTimeout {
-        NCrTimeout(1000.0)
+impl Default for LinSpeed {
+    fn default() -> LinSpeed {
+        LinSpeed(19200)
     }
 }
 
-/// Holds the most important node attributes
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub struct NodeAttributes {
-    pub configured_nad: NAD,
-    pub initial_nad: NAD,
-    pub product_id: ProductId,
-    pub p2_min: P2Min,
-    pub st_min: STMin,
-    pub n_as_timeout: NAsTimeout,
-    pub n_cr_timeout: NCrTimeout,
+/// The bus speed is the baud rate of the UART
+pub type BaudRate = LinSpeed;
+
+impl LinSpeed {
+    pub const BAUD_2400: LinSpeed = LinSpeed(2400);
+    pub const BAUD_9600: LinSpeed = LinSpeed(9600);
+    /// The speed of J2602 networks
+    pub const BAUD_10417: LinSpeed = LinSpeed(10417);
+    pub const BAUD_19200: LinSpeed = LinSpeed(19200);
+
+    /// Fails with `Error::InvalidConfig` if `bits_per_second` is outside the range of 1 to
+    /// 20 kbit/s
+    pub const fn try_new(bits_per_second: u32) -> Result<LinSpeed, crate::Error> {
+        let speed = LinSpeed(bits_per_second);
+        if !speed.is_valid() {
+            return Err(crate::Error::InvalidConfig);
+        }
+        Ok(speed)
+    }
+
+    /// Whether the speed is in the range of 1 to 20 kbit/s
+    pub const fn is_valid(self) -> bool {
+        self.0 >= timing::MIN_SPEED && self.0 <= timing::MAX_SPEED
+    }
+
+    pub const fn bits_per_second(self) -> u32 {
+        self.0
+    }
+
+    /// Nominal bit time in ns, see `timing::bit_time_ns`
+    pub const fn bit_time_ns(self) -> u32 {
+        timing::bit_time_ns(self)
+    }
+
+    /// Create the speed from kbit/s as used in LDF files, e.g. `19.2`
+    pub fn from_kbps(kbps: f32) -> LinSpeed {
+        LinSpeed((kbps * 1000.0 + 0.5) as u32)
+    }
+
+    /// Return the nominal bit time in µs
+    pub fn bit_time_us(self) -> f32 {
+        1_000_000.0 / self.0 as f32
+    }
 }
 
-impl NodeAttributes {
-    pub fn with_default_timing(
-        configured_nad: NAD,
-        initial_nad: NAD,
-        product_id: ProductId,
-    ) -> NodeAttributes {
-        NodeAttributes {
-            configured_nad,
-            initial_nad,
-            product_id,
-            p2_min: P2Min::default(),
-            st_min: STMin::default(),
-            n_as_timeout: NAsTimeout::default(),
-            n_cr_timeout: NCrTimeout::default(),
+/// Formats the speed in kbit/s without trailing zeros, e.g. `19.2` or `10.417`
+impl fmt::Display for LinSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0 / 1000)?;
+        let mut fraction = self.0 % 1000;
+        if fraction != 0 {
+            let mut digits = 3;
+            while fraction % 10 == 0 {
+                fraction /= 10;
+                digits -= 1;
+            }
+            write!(f, ".{:0width$}", fraction, width = digits)?;
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Buffer {
+        data: [u8; 16],
+        length: usize,
+    }
+
+    impl fmt::Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.data[self.length..self.length + s.len()].copy_from_slice(s.as_bytes());
+            self.length += s.len();
+            Ok(())
+        }
+    }
+
+    fn format(speed: LinSpeed) -> Buffer {
+        let mut buffer = Buffer {
+            data: [0; 16],
+            length: 0,
+        };
+        fmt::write(&mut buffer, format_args!("{}", speed)).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_lin_speed_display() {
+        let test_data: [(u32, &[u8]); 4] = [
+            (19200, b"19.2"),
+            (10417, b"10.417"),
+            (9600, b"9.6"),
+            (20000, b"20"),
+        ];
+        for (speed, text) in &test_data {
+            let buffer = format(LinSpeed(*speed));
+            assert_eq!(&buffer.data[..buffer.length], *text);
+        }
+        assert_eq!(LinSpeed::from_kbps(10.417), LinSpeed(10417));
+    }
+
+    #[test]
+    fn test_lin_speed_range() {
+        assert_eq!(LinSpeed::try_new(19200), Ok(LinSpeed::BAUD_19200));
+        assert_eq!(LinSpeed::try_new(1000), Ok(LinSpeed(1000)));
+        assert_eq!(LinSpeed::try_new(999), Err(crate::Error::InvalidConfig));
+        assert_eq!(LinSpeed::try_new(20001), Err(crate::Error::InvalidConfig));
+        assert!(LinSpeed::BAUD_2400.is_valid());
+        assert_eq!(LinSpeed::BAUD_9600.bit_time_ns(), 104_166);
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(P2Min::default(), P2Min(50_000));
+        assert_eq!(STMin::from_ms(10).as_us(), 10_000);
+        assert_eq!(NAsTimeout(1_500).as_ms(), 1);
+        assert!(NCrTimeout::from_ms(1) < NCrTimeout(1_001));
+        assert_eq!(STMin::from_ms(4_294_968), STMin(u32::MAX));
+        let test_data: [(u32, &[u8]); 4] = [
+            (100_000, b"100"),
+            (12_500, b"12.5"),
+            (250, b"0.25"),
+            (1_001, b"1.001"),
+        ];
+        for (us, text) in &test_data {
+            let mut buffer = Buffer {
+                data: [0; 16],
+                length: 0,
+            };
+            fmt::write(&mut buffer, format_args!("{}", P2Min(*us))).unwrap();
+            assert_eq!(&buffer.data[..buffer.length], *text);
+        }
+    }
+
+    #[test]
+    fn test_protocol_version_checksum_model() {
+        let pid = PID::from_id(0x10);
+        assert_eq!(
+            LinProtocolVersion::V1_3.checksum_model(pid),
+            ChecksumModel::Classic
+        );
+        assert_eq!(
+            LinProtocolVersion::V2_1.checksum_model(pid),
+            ChecksumModel::Enhanced
+        );
+        assert_eq!(
+            LinProtocolVersion::from_ldf_str("2.1"),
+            Some(LinProtocolVersion::V2_1)
+        );
     }
 }
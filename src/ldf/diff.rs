@@ -0,0 +1,336 @@
+//! Semantic comparison of two LDF data models
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::model::{Ldf, ScheduleTable, UnconditionalFrame};
+use super::LinSpeed;
+
+/// A difference between two LDFs reported by `Ldf::diff`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Difference {
+    SpeedChanged {
+        old: LinSpeed,
+        new: LinSpeed,
+    },
+    NodeAdded(String),
+    NodeRemoved(String),
+    /// The size in bits of a signal definition changed
+    SignalSizeChanged {
+        signal: String,
+        old: u8,
+        new: u8,
+    },
+    FrameAdded(String),
+    FrameRemoved(String),
+    FrameIdChanged {
+        frame: String,
+        old: u8,
+        new: u8,
+    },
+    /// The data length in bytes changed
+    FrameLengthChanged {
+        frame: String,
+        old: u8,
+        new: u8,
+    },
+    /// A signal was added to the layout of a frame
+    FrameSignalAdded {
+        frame: String,
+        signal: String,
+    },
+    /// A signal was removed from the layout of a frame
+    FrameSignalRemoved {
+        frame: String,
+        signal: String,
+    },
+    /// The bit offset of a signal within a frame changed
+    FrameSignalMoved {
+        frame: String,
+        signal: String,
+        old: u8,
+        new: u8,
+    },
+    ScheduleTableAdded(String),
+    ScheduleTableRemoved(String),
+    /// The commands of a schedule table were added, removed or reordered
+    ScheduleEntriesChanged {
+        table: String,
+    },
+    /// The delay of a schedule entry changed. Times are in ms.
+    ScheduleDelayChanged {
+        table: String,
+        entry: usize,
+        old: f32,
+        new: f32,
+    },
+}
+
+/// Report the names which are only in `old` as `removed` and only in `new` as `added`
+fn diff_names(
+    old: &[&String],
+    new: &[&String],
+    removed: fn(String) -> Difference,
+    added: fn(String) -> Difference,
+    diff: &mut Vec<Difference>,
+) {
+    for name in old.iter().filter(|name| !new.contains(name)) {
+        diff.push(removed((*name).clone()));
+    }
+    for name in new.iter().filter(|name| !old.contains(name)) {
+        diff.push(added((*name).clone()));
+    }
+}
+
+fn node_names(ldf: &Ldf) -> Vec<&String> {
+    let master = core::iter::once(&ldf.nodes.master.name);
+    master.chain(&ldf.nodes.slaves).collect()
+}
+
+fn diff_frame(old: &UnconditionalFrame, new: &UnconditionalFrame, diff: &mut Vec<Difference>) {
+    let frame = &new.name;
+    if old.id != new.id {
+        diff.push(Difference::FrameIdChanged {
+            frame: frame.clone(),
+            old: old.id,
+            new: new.id,
+        });
+    }
+    if old.length != new.length {
+        diff.push(Difference::FrameLengthChanged {
+            frame: frame.clone(),
+            old: old.length,
+            new: new.length,
+        });
+    }
+    for signal in &old.signals {
+        match new.signals.iter().find(|s| s.name == signal.name) {
+            Some(moved) if moved.offset != signal.offset => {
+                diff.push(Difference::FrameSignalMoved {
+                    frame: frame.clone(),
+                    signal: signal.name.clone(),
+                    old: signal.offset,
+                    new: moved.offset,
+                })
+            }
+            Some(_) => {}
+            None => diff.push(Difference::FrameSignalRemoved {
+                frame: frame.clone(),
+                signal: signal.name.clone(),
+            }),
+        }
+    }
+    for signal in &new.signals {
+        if !old.signals.iter().any(|s| s.name == signal.name) {
+            diff.push(Difference::FrameSignalAdded {
+                frame: frame.clone(),
+                signal: signal.name.clone(),
+            });
+        }
+    }
+}
+
+fn diff_schedule_table(old: &ScheduleTable, new: &ScheduleTable, diff: &mut Vec<Difference>) {
+    let same_commands = old.entries.len() == new.entries.len()
+        && old
+            .entries
+            .iter()
+            .zip(&new.entries)
+            .all(|(o, n)| o.command == n.command);
+    if !same_commands {
+        diff.push(Difference::ScheduleEntriesChanged {
+            table: new.name.clone(),
+        });
+        return;
+    }
+    for (entry, (o, n)) in old.entries.iter().zip(&new.entries).enumerate() {
+        if o.delay != n.delay {
+            diff.push(Difference::ScheduleDelayChanged {
+                table: new.name.clone(),
+                entry,
+                old: o.delay,
+                new: n.delay,
+            });
+        }
+    }
+}
+
+impl Ldf {
+    /// Compare this LDF with a `new` version of it and return all semantic differences. An empty
+    /// list means both describe the same cluster, formatting and ordering of frames or signals
+    /// is ignored.
+    pub fn diff(&self, new: &Ldf) -> Vec<Difference> {
+        let mut diff = Vec::new();
+        if self.speed != new.speed {
+            diff.push(Difference::SpeedChanged {
+                old: self.speed,
+                new: new.speed,
+            });
+        }
+
+        diff_names(
+            &node_names(self),
+            &node_names(new),
+            Difference::NodeRemoved,
+            Difference::NodeAdded,
+            &mut diff,
+        );
+
+        for signal in &self.signals {
+            if let Some(changed) = new.signal(&signal.name).filter(|s| s.size != signal.size) {
+                diff.push(Difference::SignalSizeChanged {
+                    signal: signal.name.clone(),
+                    old: signal.size,
+                    new: changed.size,
+                });
+            }
+        }
+
+        diff_names(
+            &self.frames.iter().map(|f| &f.name).collect::<Vec<_>>(),
+            &new.frames.iter().map(|f| &f.name).collect::<Vec<_>>(),
+            Difference::FrameRemoved,
+            Difference::FrameAdded,
+            &mut diff,
+        );
+        for frame in &self.frames {
+            if let Some(changed) = new.frame(&frame.name) {
+                diff_frame(frame, changed, &mut diff);
+            }
+        }
+
+        diff_names(
+            &self
+                .schedule_tables
+                .iter()
+                .map(|t| &t.name)
+                .collect::<Vec<_>>(),
+            &new.schedule_tables
+                .iter()
+                .map(|t| &t.name)
+                .collect::<Vec<_>>(),
+            Difference::ScheduleTableRemoved,
+            Difference::ScheduleTableAdded,
+            &mut diff,
+        );
+        for table in &self.schedule_tables {
+            if let Some(changed) = new.schedule_table(&table.name) {
+                diff_schedule_table(table, changed, &mut diff);
+            }
+        }
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ldf::model::*;
+    use alloc::vec;
+
+    fn ldf() -> Ldf {
+        Ldf {
+            nodes: Nodes {
+                master: MasterNode {
+                    name: "Gateway".into(),
+                    ..Default::default()
+                },
+                slaves: vec!["Sensor".into()],
+            },
+            signals: vec![SignalDefinition {
+                name: "Temperature".into(),
+                size: 12,
+                ..Default::default()
+            }],
+            frames: vec![UnconditionalFrame {
+                name: "SensorFrame".into(),
+                id: 0x10,
+                publisher: "Sensor".into(),
+                length: 2,
+                signals: vec![FrameSignal {
+                    name: "Temperature".into(),
+                    offset: 0,
+                }],
+            }],
+            schedule_tables: vec![ScheduleTable {
+                name: "Normal".into(),
+                entries: vec![ScheduleEntry {
+                    command: ScheduleCommand::Frame("SensorFrame".into()),
+                    delay: 10.0,
+                }],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = ldf();
+        assert_eq!(old.diff(&old), vec![]);
+
+        let mut new = ldf();
+        new.nodes.slaves = vec!["Actuator".into()];
+        new.signals[0].size = 16;
+        new.frames[0].length = 3;
+        new.frames[0].signals[0].offset = 8;
+        new.frames[0].signals.push(FrameSignal {
+            name: "Status".into(),
+            offset: 0,
+        });
+        new.frames.push(UnconditionalFrame {
+            name: "ActuatorFrame".into(),
+            id: 0x11,
+            ..Default::default()
+        });
+        new.schedule_tables[0].entries[0].delay = 20.0;
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                Difference::NodeRemoved("Sensor".into()),
+                Difference::NodeAdded("Actuator".into()),
+                Difference::SignalSizeChanged {
+                    signal: "Temperature".into(),
+                    old: 12,
+                    new: 16
+                },
+                Difference::FrameAdded("ActuatorFrame".into()),
+                Difference::FrameLengthChanged {
+                    frame: "SensorFrame".into(),
+                    old: 2,
+                    new: 3
+                },
+                Difference::FrameSignalMoved {
+                    frame: "SensorFrame".into(),
+                    signal: "Temperature".into(),
+                    old: 0,
+                    new: 8
+                },
+                Difference::FrameSignalAdded {
+                    frame: "SensorFrame".into(),
+                    signal: "Status".into()
+                },
+                Difference::ScheduleDelayChanged {
+                    table: "Normal".into(),
+                    entry: 0,
+                    old: 10.0,
+                    new: 20.0
+                },
+            ]
+        );
+
+        new.schedule_tables[0].entries.clear();
+        new.schedule_tables.push(ScheduleTable {
+            name: "Diagnostic".into(),
+            entries: vec![],
+        });
+        assert_eq!(
+            new.diff(&ldf())[7..],
+            [
+                Difference::ScheduleTableRemoved("Diagnostic".into()),
+                Difference::ScheduleEntriesChanged {
+                    table: "Normal".into()
+                }
+            ]
+        );
+    }
+}
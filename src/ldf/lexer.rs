@@ -0,0 +1,312 @@
+//! Tokenizer and recursive descent helpers shared by the LDF parsers
+
+use super::{LinProtocolVersion, LinSpeed};
+
+/// Error returned if an LDF could not be parsed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Line number (starting at 1) where the error occurred
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl core::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+pub(crate) type Result<T> = core::result::Result<T, ParseError>;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Token<'a> {
+    Ident(&'a str),
+    Number(&'a str),
+    Str(&'a str),
+    Punct(char),
+}
+
+/// Splits LDF text into tokens, skipping whitespace and comments
+pub(crate) struct Lexer<'a> {
+    input: &'a str,
+    position: usize,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(input: &'a str) -> Lexer<'a> {
+        Lexer {
+            input,
+            position: 0,
+            line: 1,
+        }
+    }
+
+    pub(crate) fn line(&self) -> usize {
+        self.line
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            let rest = self.rest();
+            if let Some(c) = rest.chars().next().filter(|c| c.is_whitespace()) {
+                if c == '\n' {
+                    self.line += 1;
+                }
+                self.position += c.len_utf8();
+            } else if rest.starts_with("//") {
+                self.position += rest.find('\n').unwrap_or(rest.len());
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let end = comment.find("*/").map_or(rest.len(), |i| i + 4);
+                self.line += rest[..end].matches('\n').count();
+                self.position += end;
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.position += end;
+        &rest[..end]
+    }
+
+    pub(crate) fn next_token(&mut self) -> Result<Option<Token<'a>>> {
+        self.skip_whitespace_and_comments();
+        let c = match self.rest().chars().next() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        let token = if c.is_ascii_alphabetic() || c == '_' {
+            Token::Ident(self.take_while(|c| c.is_ascii_alphanumeric() || c == '_'))
+        } else if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' {
+            self.position += 1;
+            let start = self.position - 1;
+            self.take_while(|c| c.is_ascii_alphanumeric() || c == '.');
+            Token::Number(&self.input[start..self.position])
+        } else if c == '"' {
+            self.position += 1;
+            let rest = self.rest();
+            let end = rest.find('"').ok_or(ParseError {
+                line: self.line,
+                message: "Unterminated string",
+            })?;
+            self.line += rest[..end].matches('\n').count();
+            self.position += end + 1;
+            Token::Str(&rest[..end])
+        } else {
+            self.position += c.len_utf8();
+            Token::Punct(c)
+        };
+        Ok(Some(token))
+    }
+}
+
+/// Recursive descent parser on top of the `Lexer`
+pub(crate) struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<Option<Token<'a>>>,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(input: &'a str) -> Parser<'a> {
+        Parser {
+            lexer: Lexer::new(input),
+            peeked: None,
+        }
+    }
+
+    pub(crate) fn error<T>(&self, message: &'static str) -> Result<T> {
+        Err(ParseError {
+            line: self.lexer.line(),
+            message,
+        })
+    }
+
+    pub(crate) fn peek(&mut self) -> Result<Option<Token<'a>>> {
+        match self.peeked {
+            Some(token) => Ok(token),
+            None => {
+                let token = self.lexer.next_token()?;
+                self.peeked = Some(token);
+                Ok(token)
+            }
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> Result<Option<Token<'a>>> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.next_token(),
+        }
+    }
+
+    pub(crate) fn is_punct(&mut self, c: char) -> Result<bool> {
+        Ok(self.peek()? == Some(Token::Punct(c)))
+    }
+
+    /// Consume the punctuation `c` if it is the next token
+    pub(crate) fn eat_punct(&mut self, c: char) -> Result<bool> {
+        let found = self.is_punct(c)?;
+        if found {
+            self.next()?;
+        }
+        Ok(found)
+    }
+
+    pub(crate) fn expect_punct(&mut self, c: char) -> Result<()> {
+        if self.eat_punct(c)? {
+            Ok(())
+        } else {
+            self.error("Unexpected token, expected punctuation")
+        }
+    }
+
+    pub(crate) fn ident(&mut self) -> Result<&'a str> {
+        match self.next()? {
+            Some(Token::Ident(ident)) => Ok(ident),
+            _ => self.error("Expected identifier"),
+        }
+    }
+
+    pub(crate) fn expect_ident(&mut self, expected: &str) -> Result<()> {
+        if self.ident()? == expected {
+            Ok(())
+        } else {
+            self.error("Unexpected identifier")
+        }
+    }
+
+    pub(crate) fn string(&mut self) -> Result<&'a str> {
+        match self.next()? {
+            Some(Token::Str(s)) => Ok(s),
+            _ => self.error("Expected string"),
+        }
+    }
+
+    pub(crate) fn number(&mut self) -> Result<&'a str> {
+        match self.next()? {
+            Some(Token::Number(n)) => Ok(n),
+            _ => self.error("Expected number"),
+        }
+    }
+
+    pub(crate) fn integer(&mut self) -> Result<u64> {
+        let number = self.number()?;
+        let parsed = if let Some(hex) = number
+            .strip_prefix("0x")
+            .or_else(|| number.strip_prefix("0X"))
+        {
+            u64::from_str_radix(hex, 16)
+        } else {
+            number.parse()
+        };
+        parsed.or_else(|_| self.error("Invalid integer"))
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8> {
+        let value = self.integer()?;
+        if value > u64::from(u8::MAX) {
+            self.error("Integer out of range")
+        } else {
+            Ok(value as u8)
+        }
+    }
+
+    pub(crate) fn float(&mut self) -> Result<f32> {
+        let number = self.number()?;
+        if number.starts_with("0x") || number.starts_with("0X") {
+            let value = u64::from_str_radix(&number[2..], 16);
+            return value
+                .map(|v| v as f32)
+                .or_else(|_| self.error("Invalid number"));
+        }
+        number.parse().or_else(|_| self.error("Invalid number"))
+    }
+
+    /// Parse a time value followed by the `ms` unit
+    pub(crate) fn milliseconds(&mut self) -> Result<f32> {
+        let value = self.float()?;
+        self.expect_ident("ms")?;
+        Ok(value)
+    }
+
+    /// Parse a time value followed by the `ms` unit into integer µs. The decimal milliseconds
+    /// are converted without a float, digits beyond the µs are rounded.
+    #[cfg(feature = "ldf-parse")]
+    pub(crate) fn microseconds(&mut self) -> Result<u32> {
+        let number = self.number()?;
+        let (ms, fraction) = number.split_once('.').unwrap_or((number, ""));
+        let ms: u64 = match ms {
+            "" => 0,
+            ms => ms.parse().or_else(|_| self.error("Invalid number"))?,
+        };
+        let mut us = ms.checked_mul(1000);
+        let mut scale = 100;
+        for (i, c) in fraction.chars().enumerate() {
+            let digit = match c.to_digit(10) {
+                Some(digit) => u64::from(digit),
+                None => return self.error("Invalid number"),
+            };
+            if i < 3 {
+                us = us.and_then(|us| us.checked_add(digit * scale));
+                scale /= 10;
+            } else if i == 3 && digit >= 5 {
+                us = us.and_then(|us| us.checked_add(1));
+            }
+        }
+        self.expect_ident("ms")?;
+        match us.and_then(|us| u32::try_from(us).ok()) {
+            Some(us) => Ok(us),
+            None => self.error("Time out of range"),
+        }
+    }
+
+    /// Skip tokens up to and including the next `;`
+    pub(crate) fn skip_statement(&mut self) -> Result<()> {
+        while !self.eat_punct(';')? {
+            if self.next()?.is_none() {
+                return self.error("Unexpected end of file");
+            }
+        }
+        Ok(())
+    }
+
+    /// Skip a block enclosed in braces, the opening brace must be the next token
+    pub(crate) fn skip_block(&mut self) -> Result<()> {
+        self.expect_punct('{')?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Some(Token::Punct('{')) => depth += 1,
+                Some(Token::Punct('}')) => depth -= 1,
+                Some(_) => {}
+                None => return self.error("Unexpected end of file"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse a speed followed by the `kbps` unit
+    pub(crate) fn kbps(&mut self) -> Result<LinSpeed> {
+        let value = self.float()?;
+        self.expect_ident("kbps")?;
+        Ok(LinSpeed::from_kbps(value))
+    }
+
+    /// Parse a protocol version string
+    pub(crate) fn protocol_version(&mut self) -> Result<LinProtocolVersion> {
+        let version = self.string()?;
+        LinProtocolVersion::from_ldf_str(version)
+            .map_or_else(|| self.error("Unknown protocol version"), Ok)
+    }
+}
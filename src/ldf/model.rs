@@ -0,0 +1,694 @@
+//! Data model of a complete LDF (LIN description file)
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{ConfigurableFrame, LinProtocolVersion, LinSpeed, NodeAttributes};
+use crate::cluster::{FrameConfig, Publisher};
+use crate::frame::diagnostic::{
+    MASTER_REQUEST_FRAME_ID, MASTER_REQUEST_FRAME_PID, SLAVE_RESPONSE_FRAME_PID,
+};
+use crate::frame::PID;
+use crate::signal;
+
+/// A complete LIN cluster description
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ldf {
+    pub protocol_version: LinProtocolVersion,
+    pub language_version: String,
+    pub speed: LinSpeed,
+    /// Channel postfix name of multi-channel descriptions (`Channel_name`)
+    pub channel_name: Option<String>,
+    pub nodes: Nodes,
+    pub node_compositions: Vec<NodeComposition>,
+    pub signals: Vec<SignalDefinition>,
+    /// Diagnostic signals, publisher and subscribers are not used
+    pub diagnostic_signals: Vec<SignalDefinition>,
+    pub frames: Vec<UnconditionalFrame>,
+    pub sporadic_frames: Vec<SporadicFrame>,
+    pub event_triggered_frames: Vec<EventTriggeredFrame>,
+    pub diagnostic_frames: Vec<DiagnosticFrame>,
+    pub node_attributes: Vec<NodeAttributesDefinition>,
+    pub schedule_tables: Vec<ScheduleTable>,
+    pub signal_encoding_types: Vec<SignalEncodingType>,
+    pub signal_representations: Vec<SignalRepresentation>,
+}
+
+/// The `Nodes` section
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct Nodes {
+    pub master: MasterNode,
+    pub slaves: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct MasterNode {
+    pub name: String,
+    /// Time base in ms
+    pub time_base: f32,
+    /// Jitter in ms
+    pub jitter: f32,
+}
+
+/// Composite node of a `NodeComposition`, grouping logical nodes of the `Nodes` section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompositeNode {
+    pub name: String,
+    pub logical_nodes: Vec<String>,
+}
+
+/// A configuration of the `Node_composition` section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeComposition {
+    /// Name of the configuration
+    pub name: String,
+    pub composite_nodes: Vec<CompositeNode>,
+}
+
+impl NodeComposition {
+    /// Find the composite node containing the logical node `node`
+    pub fn composite_of(&self, node: &str) -> Option<&CompositeNode> {
+        self.composite_nodes
+            .iter()
+            .find(|c| c.logical_nodes.iter().any(|n| n == node))
+    }
+}
+
+/// Initial value of a signal
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub enum SignalValue {
+    Scalar(u64),
+    ByteArray(Vec<u8>),
+}
+
+impl Default for SignalValue {
+    fn default() -> SignalValue {
+        SignalValue::Scalar(0)
+    }
+}
+
+/// An entry of the `Signals` section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalDefinition {
+    pub name: String,
+    /// Size in bits
+    pub size: u8,
+    pub init_value: SignalValue,
+    pub publisher: String,
+    pub subscribers: Vec<String>,
+}
+
+/// Position of a signal within a frame
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameSignal {
+    pub name: String,
+    /// Bit offset within the frame
+    pub offset: u8,
+}
+
+/// An entry of the `Frames` section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnconditionalFrame {
+    pub name: String,
+    pub id: u8,
+    pub publisher: String,
+    /// Data length in bytes
+    pub length: u8,
+    pub signals: Vec<FrameSignal>,
+}
+
+/// An entry of the `Sporadic_frames` section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct SporadicFrame {
+    pub name: String,
+    /// Associated unconditional frames in priority order
+    pub frames: Vec<String>,
+}
+
+/// An entry of the `Event_triggered_frames` section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventTriggeredFrame {
+    pub name: String,
+    /// Schedule table used to resolve collisions
+    pub collision_resolving_schedule_table: Option<String>,
+    pub id: u8,
+    /// Associated unconditional frames
+    pub frames: Vec<String>,
+}
+
+/// An entry of the `Diagnostic_frames` section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiagnosticFrame {
+    pub name: String,
+    pub id: u8,
+    pub signals: Vec<FrameSignal>,
+}
+
+/// A configurable frame of a node
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigurableFrameDefinition {
+    pub name: String,
+    pub message_id: Option<u16>,
+}
+
+/// An entry of the `Node_attributes` section
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeAttributesDefinition {
+    pub name: String,
+    /// Attributes of the node. The configurable frames are stored in `configurable_frames`,
+    /// use `node_attributes` to get attributes which reference them.
+    pub attributes: NodeAttributes<'static>,
+    pub response_error: Option<String>,
+    pub fault_state_signals: Vec<String>,
+    pub configurable_frames: Vec<ConfigurableFrameDefinition>,
+}
+
+impl NodeAttributesDefinition {
+    /// Borrow the configurable frames for use in `NodeAttributes`
+    pub fn configurable_frames(&self) -> Vec<ConfigurableFrame<'_>> {
+        self.configurable_frames
+            .iter()
+            .map(|f| ConfigurableFrame {
+                name: &f.name,
+                message_id: f.message_id,
+            })
+            .collect()
+    }
+
+    /// Get the node attributes referencing the given configurable frames, usually obtained from
+    /// `configurable_frames`
+    pub fn node_attributes<'a>(&self, frames: &'a [ConfigurableFrame<'a>]) -> NodeAttributes<'a> {
+        NodeAttributes {
+            configurable_frames: frames,
+            ..self.attributes
+        }
+    }
+}
+
+/// A command in a schedule table
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScheduleCommand {
+    /// Transmit the header of the named unconditional, sporadic or event triggered frame
+    Frame(String),
+    /// Master request frame (0x3C)
+    MasterReq,
+    /// Slave response frame (0x3D)
+    SlaveResp,
+    /// Assign the configured NAD to the node
+    AssignNAD { node: String },
+    /// Conditionally change the NAD of any node matching the condition
+    ConditionalChangeNAD {
+        nad: u8,
+        id: u8,
+        byte: u8,
+        mask: u8,
+        inv: u8,
+        new_nad: u8,
+    },
+    /// Node specific data dump
+    DataDump { node: String, data: [u8; 5] },
+    /// Make the node save its configuration
+    SaveConfiguration { node: String },
+    /// Assign the PIDs of up to four frames starting at `frame_index`. If `pids` is `None` the
+    /// PIDs from the LDF are used.
+    AssignFrameIdRange {
+        node: String,
+        frame_index: u8,
+        pids: Option<[u8; 4]>,
+    },
+    /// Master request with fixed data
+    FreeFormat([u8; 8]),
+    /// Assign the frame ID of the named frame (LIN 2.0)
+    AssignFrameId { node: String, frame: String },
+    /// Unassign the frame ID of the named frame (LIN 2.0)
+    UnassignFrameId { node: String, frame: String },
+}
+
+impl ScheduleCommand {
+    /// Return if the command is transmitted as master request frame
+    pub fn is_master_request(&self) -> bool {
+        !matches!(self, ScheduleCommand::Frame(_) | ScheduleCommand::SlaveResp)
+    }
+}
+
+/// An entry of a schedule table
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScheduleEntry {
+    pub command: ScheduleCommand,
+    /// Delay until the next entry in ms
+    pub delay: f32,
+}
+
+/// An entry of the `Schedule_tables` section
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScheduleTable {
+    pub name: String,
+    pub entries: Vec<ScheduleEntry>,
+}
+
+impl ScheduleTable {
+    /// Resolve the entries to the transmitted PIDs and the delays in ms. Entries referencing
+    /// unknown frames are skipped.
+    pub fn slots(&self, ldf: &Ldf) -> Vec<(PID, f32)> {
+        self.entries
+            .iter()
+            .filter_map(|entry| Some((ldf.schedule_command_pid(&entry.command)?, entry.delay)))
+            .collect()
+    }
+
+    /// Total duration of one cycle through the table in ms
+    pub fn cycle_time(&self) -> f32 {
+        self.entries.iter().map(|entry| entry.delay).sum()
+    }
+}
+
+/// A value definition within a signal encoding type
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub enum EncodingValue {
+    /// A raw value with a textual meaning
+    Logical { value: u64, text: Option<String> },
+    /// A raw value range with a linear physical conversion
+    Physical {
+        min: u64,
+        max: u64,
+        scale: f32,
+        offset: f32,
+        unit: Option<String>,
+    },
+    /// Binary coded decimal
+    Bcd,
+    /// ASCII characters
+    Ascii,
+}
+
+impl EncodingValue {
+    /// Return the linear scaling of a physical value
+    pub fn scaling(&self) -> Option<signal::Scaling> {
+        match self {
+            EncodingValue::Physical { scale, offset, .. } => {
+                Some(signal::Scaling::new(*scale, *offset))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A raw signal value mapped to its meaning by a `SignalEncodingType`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DecodedValue<'a> {
+    /// Logical value with its optional text
+    Logical(u64, Option<&'a str>),
+    /// Physical value with its optional unit
+    Physical(f32, Option<&'a str>),
+    /// Decimal value of a BCD encoded signal
+    Bcd(u64),
+    /// Raw value of an ASCII encoded signal, the characters are the bytes of the value
+    Ascii(u64),
+}
+
+/// An entry of the `Signal_encoding_types` section
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalEncodingType {
+    pub name: String,
+    pub values: Vec<EncodingValue>,
+}
+
+impl SignalEncodingType {
+    /// Map a raw signal value to its meaning. The first matching logical value or physical
+    /// range is used. Returns `None` if no value definition matches.
+    pub fn decode(&self, raw: u64) -> Option<DecodedValue<'_>> {
+        self.values.iter().find_map(|value| match value {
+            EncodingValue::Logical { value, text } if *value == raw => {
+                Some(DecodedValue::Logical(raw, text.as_deref()))
+            }
+            EncodingValue::Physical {
+                min,
+                max,
+                scale,
+                offset,
+                unit,
+            } if (*min..=*max).contains(&raw) => Some(DecodedValue::Physical(
+                raw as f32 * scale + offset,
+                unit.as_deref(),
+            )),
+            EncodingValue::Bcd => signal::decode_bcd(raw).map(DecodedValue::Bcd),
+            EncodingValue::Ascii => Some(DecodedValue::Ascii(raw)),
+            _ => None,
+        })
+    }
+
+    /// Convert a physical value to the raw value using the first physical range which contains
+    /// it. Returns `None` if the value is outside of all physical ranges.
+    pub fn encode_physical(&self, physical: f32) -> Option<u64> {
+        self.values.iter().find_map(|value| match value {
+            EncodingValue::Physical {
+                min,
+                max,
+                scale,
+                offset,
+                ..
+            } => {
+                let raw = (physical - offset) / scale;
+                let raw = if raw >= 0.0 { raw + 0.5 } else { raw - 0.5 };
+                if raw >= *min as f32 && raw < *max as f32 + 1.0 {
+                    Some(raw as u64)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+    }
+
+    /// Find the raw value of a logical value by its text
+    pub fn encode_logical(&self, text: &str) -> Option<u64> {
+        self.values.iter().find_map(|value| match value {
+            EncodingValue::Logical {
+                value,
+                text: Some(t),
+            } if t == text => Some(*value),
+            _ => None,
+        })
+    }
+
+    /// Return the scaling if the encoding consists of exactly one physical range
+    pub fn scaling(&self) -> Option<signal::Scaling> {
+        let mut physical = self.values.iter().filter_map(EncodingValue::scaling);
+        match (physical.next(), physical.next()) {
+            (Some(scaling), None) => Some(scaling),
+            _ => None,
+        }
+    }
+}
+
+/// An entry of the `Signal_representation` section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-alloc", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignalRepresentation {
+    pub encoding: String,
+    pub signals: Vec<String>,
+}
+
+impl Ldf {
+    /// Find a node composition configuration by name
+    pub fn node_composition(&self, configuration: &str) -> Option<&NodeComposition> {
+        self.node_compositions
+            .iter()
+            .find(|c| c.name == configuration)
+    }
+
+    /// Find a signal definition by name
+    pub fn signal(&self, name: &str) -> Option<&SignalDefinition> {
+        self.signals.iter().find(|s| s.name == name)
+    }
+
+    /// Find an unconditional frame by name
+    pub fn frame(&self, name: &str) -> Option<&UnconditionalFrame> {
+        self.frames.iter().find(|f| f.name == name)
+    }
+
+    /// Find an unconditional frame by its ID
+    pub fn frame_by_id(&self, id: u8) -> Option<&UnconditionalFrame> {
+        self.frames.iter().find(|f| f.id == id)
+    }
+
+    /// Find the node attributes of a slave node
+    pub fn node_attributes(&self, node: &str) -> Option<&NodeAttributesDefinition> {
+        self.node_attributes.iter().find(|n| n.name == node)
+    }
+
+    /// Find a schedule table by name
+    pub fn schedule_table(&self, name: &str) -> Option<&ScheduleTable> {
+        self.schedule_tables.iter().find(|t| t.name == name)
+    }
+
+    /// Find the ID of an unconditional, sporadic, event triggered or diagnostic frame by name.
+    /// Sporadic frames have no ID of their own and resolve to their first associated frame.
+    pub fn frame_id(&self, name: &str) -> Option<u8> {
+        self.frame(name)
+            .map(|f| f.id)
+            .or_else(|| {
+                self.event_triggered_frames
+                    .iter()
+                    .find(|f| f.name == name)
+                    .map(|f| f.id)
+            })
+            .or_else(|| {
+                self.diagnostic_frames
+                    .iter()
+                    .find(|f| f.name == name)
+                    .map(|f| f.id)
+            })
+            .or_else(|| {
+                self.sporadic_frames
+                    .iter()
+                    .find(|f| f.name == name)
+                    .and_then(|f| f.frames.first())
+                    .and_then(|f| self.frame(f))
+                    .map(|f| f.id)
+            })
+    }
+
+    /// Get the PID of the frame header transmitted for a schedule command
+    pub fn schedule_command_pid(&self, command: &ScheduleCommand) -> Option<PID> {
+        match command {
+            ScheduleCommand::Frame(frame) => self
+                .frame_id(frame)
+                .and_then(|id| PID::try_from_id(id).ok()),
+            ScheduleCommand::SlaveResp => Some(SLAVE_RESPONSE_FRAME_PID),
+            _ => Some(MASTER_REQUEST_FRAME_PID),
+        }
+    }
+
+    /// Find a signal encoding type by name
+    pub fn signal_encoding_type(&self, name: &str) -> Option<&SignalEncodingType> {
+        self.signal_encoding_types.iter().find(|e| e.name == name)
+    }
+
+    /// Find the encoding type used for a signal
+    pub fn encoding_of(&self, signal: &str) -> Option<&SignalEncodingType> {
+        self.signal_representations
+            .iter()
+            .find(|r| r.signals.iter().any(|s| s == signal))
+            .and_then(|r| self.signal_encoding_type(&r.encoding))
+    }
+
+    /// Build the `FrameConfig` entries of all unconditional and diagnostic frames for use in a
+    /// `cluster::ClusterConfig`. Frames of LIN 1.3 clusters use the classic checksum.
+    pub fn frame_configs(&self) -> Vec<FrameConfig> {
+        let publisher = |name: &str| {
+            self.nodes
+                .slaves
+                .iter()
+                .position(|slave| slave == name)
+                .map_or(Publisher::Master, |index| Publisher::Slave(index as u8))
+        };
+        let mut configs: Vec<FrameConfig> = self
+            .frames
+            .iter()
+            .map(|frame| {
+                FrameConfig::new(frame.id, frame.length, publisher(&frame.publisher))
+                    .with_checksum_model(
+                        self.protocol_version
+                            .checksum_model(PID::from_masked_id(frame.id)),
+                    )
+            })
+            .collect();
+        for frame in &self.diagnostic_frames {
+            // The master request is published by the master, the slave response by any slave
+            let publisher = if frame.id == MASTER_REQUEST_FRAME_ID {
+                Publisher::Master
+            } else {
+                Publisher::Slave(0)
+            };
+            configs.push(FrameConfig::new(frame.id, 8, publisher));
+        }
+        configs
+    }
+
+    /// Get the `signal::Signal` descriptor for a signal mapped into a frame
+    pub fn frame_signal(&self, frame: &str, signal: &str) -> Option<signal::Signal> {
+        let frame = self.frame(frame)?;
+        let offset = frame.signals.iter().find(|s| s.name == signal)?.offset;
+        let definition = self.signal(signal)?;
+        let descriptor = signal::Signal::new(offset, definition.size);
+        match self
+            .encoding_of(signal)
+            .and_then(SignalEncodingType::scaling)
+        {
+            Some(scaling) => Some(descriptor.with_scaling(scaling.factor, scaling.offset)),
+            None => Some(descriptor),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::ChecksumModel;
+    use alloc::vec;
+
+    #[test]
+    fn test_signal_mapping() {
+        let ldf = Ldf {
+            signals: vec![SignalDefinition {
+                name: "Temperature".into(),
+                size: 12,
+                publisher: "Sensor".into(),
+                ..Default::default()
+            }],
+            frames: vec![UnconditionalFrame {
+                name: "SensorFrame".into(),
+                id: 0x10,
+                publisher: "Sensor".into(),
+                length: 4,
+                signals: vec![FrameSignal {
+                    name: "Temperature".into(),
+                    offset: 8,
+                }],
+            }],
+            signal_encoding_types: vec![SignalEncodingType {
+                name: "TemperatureEncoding".into(),
+                values: vec![EncodingValue::Bcd],
+            }],
+            signal_representations: vec![SignalRepresentation {
+                encoding: "TemperatureEncoding".into(),
+                signals: vec!["Temperature".into()],
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ldf.frame_signal("SensorFrame", "Temperature"),
+            Some(signal::Signal::new(8, 12))
+        );
+        assert_eq!(ldf.frame_signal("SensorFrame", "Humidity"), None);
+        assert_eq!(ldf.frame_by_id(0x10).unwrap().name, "SensorFrame");
+        assert_eq!(
+            ldf.encoding_of("Temperature").unwrap().name,
+            "TemperatureEncoding"
+        );
+    }
+
+    #[test]
+    fn test_encoding_decode() {
+        let encoding = SignalEncodingType {
+            name: "TemperatureEncoding".into(),
+            values: vec![
+                EncodingValue::Physical {
+                    min: 0,
+                    max: 200,
+                    scale: 0.5,
+                    offset: -40.0,
+                    unit: Some("degC".into()),
+                },
+                EncodingValue::Logical {
+                    value: 255,
+                    text: Some("invalid".into()),
+                },
+            ],
+        };
+        assert_eq!(
+            encoding.decode(100),
+            Some(DecodedValue::Physical(10.0, Some("degC")))
+        );
+        assert_eq!(
+            encoding.decode(255),
+            Some(DecodedValue::Logical(255, Some("invalid")))
+        );
+        assert_eq!(encoding.decode(201), None);
+        assert_eq!(encoding.encode_physical(10.0), Some(100));
+        assert_eq!(encoding.encode_physical(100.0), None);
+        assert_eq!(encoding.encode_logical("invalid"), Some(255));
+        assert_eq!(encoding.scaling(), Some(signal::Scaling::new(0.5, -40.0)));
+    }
+
+    #[test]
+    fn test_schedule_table_slots() {
+        let ldf = Ldf {
+            frames: vec![UnconditionalFrame {
+                name: "SensorFrame".into(),
+                id: 0x10,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let table = ScheduleTable {
+            name: "Table".into(),
+            entries: vec![
+                ScheduleEntry {
+                    command: ScheduleCommand::Frame("SensorFrame".into()),
+                    delay: 10.0,
+                },
+                ScheduleEntry {
+                    command: ScheduleCommand::AssignNAD {
+                        node: "Sensor".into(),
+                    },
+                    delay: 20.0,
+                },
+                ScheduleEntry {
+                    command: ScheduleCommand::Frame("Unknown".into()),
+                    delay: 5.0,
+                },
+            ],
+        };
+        assert_eq!(
+            table.slots(&ldf),
+            vec![(PID::from_id(0x10), 10.0), (MASTER_REQUEST_FRAME_PID, 20.0)]
+        );
+        assert_eq!(table.cycle_time(), 35.0);
+    }
+
+    #[test]
+    fn test_frame_configs() {
+        let ldf = Ldf {
+            protocol_version: LinProtocolVersion::V2_1,
+            nodes: Nodes {
+                slaves: vec!["Sensor".into()],
+                ..Default::default()
+            },
+            frames: vec![UnconditionalFrame {
+                name: "SensorFrame".into(),
+                id: 0x10,
+                publisher: "Sensor".into(),
+                length: 4,
+                signals: vec![],
+            }],
+            ..Default::default()
+        };
+        let configs = ldf.frame_configs();
+        let cluster = crate::cluster::ClusterConfig::new(&configs);
+        assert_eq!(
+            cluster.frame(0x10),
+            Some(&FrameConfig::new(0x10, 4, Publisher::Slave(0)))
+        );
+
+        let ldf = Ldf {
+            protocol_version: LinProtocolVersion::V1_3,
+            ..ldf
+        };
+        assert_eq!(
+            ldf.frame_configs()[0].checksum_model,
+            ChecksumModel::Classic
+        );
+    }
+}
@@ -0,0 +1,570 @@
+//! Data model of an NCF (node capability file) describing a single slave node
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::model::{
+    FrameSignal, SignalDefinition, SignalEncodingType, SignalValue, UnconditionalFrame,
+};
+use super::{
+    LinProtocolVersion, LinSpeed, NAsTimeout, NCrTimeout, NodeAttributes, P2Min, ResponseTolerance,
+    STMin,
+};
+use crate::frame::diagnostic::ProductId;
+use crate::frame::transport::NAD;
+
+/// A complete node capability file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ncf {
+    pub language_version: String,
+    pub nodes: Vec<Node>,
+}
+
+/// Supported bit rates of a node
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Bitrate {
+    Fixed(LinSpeed),
+    /// Automatic bit rate detection within optional limits
+    Automatic {
+        min: Option<LinSpeed>,
+        max: Option<LinSpeed>,
+    },
+    /// One of the listed bit rates
+    Select(Vec<LinSpeed>),
+}
+
+impl Default for Bitrate {
+    fn default() -> Bitrate {
+        Bitrate::Fixed(LinSpeed::default())
+    }
+}
+
+/// The `general` section of a node
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct General {
+    pub protocol_version: LinProtocolVersion,
+    pub supplier: u16,
+    pub function: u16,
+    pub variant: u8,
+    pub bitrate: Bitrate,
+    pub sends_wake_up_signal: bool,
+}
+
+/// The `diagnostic` section of a node
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diagnostic {
+    /// Possible NADs of the node, the first one is the initial NAD
+    pub nads: Vec<u8>,
+    pub diagnostic_class: Option<u8>,
+    pub p2_min: P2Min,
+    pub st_min: STMin,
+    pub n_as_timeout: NAsTimeout,
+    pub n_cr_timeout: NCrTimeout,
+    pub supported_sids: Vec<u8>,
+    pub max_message_length: Option<u16>,
+}
+
+/// Direction of a frame as seen from the node
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Publish,
+    Subscribe,
+}
+
+/// A signal within an NCF frame
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Signal {
+    pub name: String,
+    pub size: u8,
+    pub init_value: SignalValue,
+    pub offset: u8,
+    pub encoding: Option<String>,
+}
+
+/// A frame published or subscribed by the node
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub direction: Direction,
+    pub name: String,
+    pub length: u8,
+    /// Minimal period in ms
+    pub min_period: Option<f32>,
+    /// Maximal period in ms
+    pub max_period: Option<f32>,
+    pub event_triggered_frame: Option<String>,
+    pub signals: Vec<Signal>,
+}
+
+/// The `status_management` section of a node
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatusManagement {
+    pub response_error: Option<String>,
+    pub fault_state_signals: Vec<String>,
+}
+
+/// A node described by an NCF
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub general: General,
+    pub diagnostic: Diagnostic,
+    pub frames: Vec<Frame>,
+    pub encodings: Vec<SignalEncodingType>,
+    pub status_management: StatusManagement,
+    pub free_text: Option<String>,
+}
+
+impl Node {
+    /// Return the `ProductId` of the node
+    pub fn product_id(&self) -> ProductId {
+        ProductId {
+            supplier_id: self.general.supplier,
+            function_id: self.general.function,
+            variant: self.general.variant,
+        }
+    }
+
+    /// Derive the `NodeAttributes` of the node. The first NAD of the diagnostic section is used
+    /// as initial and configured NAD. NCFs do not define configurable frames.
+    pub fn node_attributes(&self) -> NodeAttributes<'static> {
+        let nad = NAD(self.diagnostic.nads.first().copied().unwrap_or(0));
+        NodeAttributes {
+            protocol_version: self.general.protocol_version,
+            configured_nad: nad,
+            initial_nad: nad,
+            product_id: self.product_id(),
+            p2_min: self.diagnostic.p2_min,
+            st_min: self.diagnostic.st_min,
+            n_as_timeout: self.diagnostic.n_as_timeout,
+            n_cr_timeout: self.diagnostic.n_cr_timeout,
+            response_tolerance: ResponseTolerance::default(),
+            configurable_frames: &[],
+        }
+    }
+
+    /// Derive the LDF frame definitions of the node. Frame IDs are not part of an NCF and are
+    /// assigned by `assign_id` from the frame name. Subscribed frames are published by `master`.
+    pub fn frame_definitions<F>(&self, master: &str, mut assign_id: F) -> Vec<UnconditionalFrame>
+    where
+        F: FnMut(&str) -> u8,
+    {
+        self.frames
+            .iter()
+            .map(|frame| UnconditionalFrame {
+                name: frame.name.clone(),
+                id: assign_id(&frame.name),
+                publisher: self.publisher(frame, master),
+                length: frame.length,
+                signals: frame
+                    .signals
+                    .iter()
+                    .map(|s| FrameSignal {
+                        name: s.name.clone(),
+                        offset: s.offset,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Derive the LDF signal definitions of the node. Signals of subscribed frames are
+    /// published by `master`.
+    pub fn signal_definitions(&self, master: &str) -> Vec<SignalDefinition> {
+        let mut signals = Vec::new();
+        for frame in &self.frames {
+            let publisher = self.publisher(frame, master);
+            let subscriber = match frame.direction {
+                Direction::Publish => String::from(master),
+                Direction::Subscribe => self.name.clone(),
+            };
+            for signal in &frame.signals {
+                signals.push(SignalDefinition {
+                    name: signal.name.clone(),
+                    size: signal.size,
+                    init_value: signal.init_value.clone(),
+                    publisher: publisher.clone(),
+                    subscribers: alloc::vec![subscriber.clone()],
+                });
+            }
+        }
+        signals
+    }
+
+    fn publisher(&self, frame: &Frame, master: &str) -> String {
+        match frame.direction {
+            Direction::Publish => self.name.clone(),
+            Direction::Subscribe => String::from(master),
+        }
+    }
+}
+
+#[cfg(feature = "ncf-parse")]
+mod parser {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::ldf::lexer::{ParseError, Parser, Token};
+
+    type Result<T> = core::result::Result<T, ParseError>;
+
+    impl<'a> Parser<'a> {
+        /// Consume the identifier `expected` if it is the next token
+        fn eat_ident(&mut self, expected: &str) -> Result<bool> {
+            let found = self.peek()? == Some(Token::Ident(expected));
+            if found {
+                self.next()?;
+            }
+            Ok(found)
+        }
+
+        fn bitrate(&mut self) -> Result<Bitrate> {
+            let bitrate = match self.peek()? {
+                Some(Token::Ident("automatic")) => {
+                    self.next()?;
+                    let mut min = None;
+                    let mut max = None;
+                    while let Some(Token::Ident(limit)) = self.peek()? {
+                        self.next()?;
+                        match limit {
+                            "min" => min = Some(self.kbps()?),
+                            "max" => max = Some(self.kbps()?),
+                            _ => return self.error("Unknown bitrate limit"),
+                        }
+                    }
+                    Bitrate::Automatic { min, max }
+                }
+                Some(Token::Ident("select")) => {
+                    self.next()?;
+                    self.expect_punct('{')?;
+                    let mut bitrates = Vec::new();
+                    loop {
+                        bitrates.push(self.kbps()?);
+                        if !self.eat_punct(',')? {
+                            break;
+                        }
+                    }
+                    self.expect_punct('}')?;
+                    Bitrate::Select(bitrates)
+                }
+                _ => Bitrate::Fixed(self.kbps()?),
+            };
+            self.expect_punct(';')?;
+            Ok(bitrate)
+        }
+
+        fn general(&mut self) -> Result<General> {
+            let mut general = General::default();
+            self.block(|p| {
+                let field = p.ident()?;
+                p.expect_punct('=')?;
+                match field {
+                    "LIN_protocol_version" => general.protocol_version = p.protocol_version()?,
+                    "supplier" => general.supplier = p.u16()?,
+                    "function" => general.function = p.u16()?,
+                    "variant" => general.variant = p.u8()?,
+                    "bitrate" => {
+                        general.bitrate = p.bitrate()?;
+                        return Ok(());
+                    }
+                    "sends_wake_up_signal" => general.sends_wake_up_signal = p.string()? == "yes",
+                    _ => return p.skip_statement(),
+                }
+                p.expect_punct(';')
+            })?;
+            Ok(general)
+        }
+
+        fn diagnostic(&mut self) -> Result<Diagnostic> {
+            let mut diagnostic = Diagnostic::default();
+            self.block(|p| {
+                let field = p.ident()?;
+                if field == "support_sid" {
+                    p.expect_punct('{')?;
+                    loop {
+                        diagnostic.supported_sids.push(p.u8()?);
+                        if !p.eat_punct(',')? {
+                            break;
+                        }
+                    }
+                    p.expect_punct('}')?;
+                    p.eat_punct(';')?;
+                    return Ok(());
+                }
+                p.expect_punct('=')?;
+                match field {
+                    "NAD" => {
+                        let first = p.u8()?;
+                        if p.eat_ident("to")? {
+                            let last = p.u8()?;
+                            diagnostic.nads.extend(first..=last);
+                        } else {
+                            diagnostic.nads.push(first);
+                            while p.eat_punct(',')? {
+                                diagnostic.nads.push(p.u8()?);
+                            }
+                        }
+                    }
+                    "diagnostic_class" => diagnostic.diagnostic_class = Some(p.u8()?),
+                    "P2_min" => diagnostic.p2_min = P2Min(p.microseconds()?),
+                    "ST_min" => diagnostic.st_min = STMin(p.microseconds()?),
+                    "N_As_timeout" => diagnostic.n_as_timeout = NAsTimeout(p.microseconds()?),
+                    "N_Cr_timeout" => diagnostic.n_cr_timeout = NCrTimeout(p.microseconds()?),
+                    "max_message_length" => diagnostic.max_message_length = Some(p.u16()?),
+                    _ => return p.skip_statement(),
+                }
+                p.expect_punct(';')
+            })?;
+            Ok(diagnostic)
+        }
+
+        fn ncf_signal(&mut self) -> Result<Signal> {
+            let mut signal = Signal {
+                name: self.ident()?.to_string(),
+                ..Default::default()
+            };
+            self.block(|p| {
+                let field = p.ident()?;
+                if !p.eat_punct('=')? {
+                    // A bare identifier references the encoding type of the signal
+                    signal.encoding = Some(field.to_string());
+                    return p.expect_punct(';');
+                }
+                match field {
+                    "size" => signal.size = p.u8()?,
+                    "init_value" => signal.init_value = p.signal_value()?,
+                    "offset" => signal.offset = p.u8()?,
+                    _ => return p.skip_statement(),
+                }
+                p.expect_punct(';')
+            })?;
+            Ok(signal)
+        }
+
+        fn ncf_frame(&mut self, direction: Direction) -> Result<Frame> {
+            let mut frame = Frame {
+                direction,
+                name: self.ident()?.to_string(),
+                length: 0,
+                min_period: None,
+                max_period: None,
+                event_triggered_frame: None,
+                signals: Vec::new(),
+            };
+            self.block(|p| {
+                let field = p.ident()?;
+                if field == "signals" {
+                    return p.block(|p| {
+                        frame.signals.push(p.ncf_signal()?);
+                        Ok(())
+                    });
+                }
+                p.expect_punct('=')?;
+                match field {
+                    "length" => frame.length = p.u8()?,
+                    "min_period" => frame.min_period = Some(p.milliseconds()?),
+                    "max_period" => frame.max_period = Some(p.milliseconds()?),
+                    "event_triggered_frame" => {
+                        frame.event_triggered_frame = Some(p.ident()?.to_string())
+                    }
+                    _ => return p.skip_statement(),
+                }
+                p.expect_punct(';')
+            })?;
+            Ok(frame)
+        }
+
+        fn ncf_node(&mut self) -> Result<Node> {
+            let mut node = Node {
+                name: self.ident()?.to_string(),
+                ..Default::default()
+            };
+            self.block(|p| {
+                match p.ident()? {
+                    "general" => node.general = p.general()?,
+                    "diagnostic" => node.diagnostic = p.diagnostic()?,
+                    "frames" => p.block(|p| {
+                        let direction = match p.ident()? {
+                            "publish" => Direction::Publish,
+                            "subscribe" => Direction::Subscribe,
+                            _ => return p.error("Expected publish or subscribe"),
+                        };
+                        node.frames.push(p.ncf_frame(direction)?);
+                        Ok(())
+                    })?,
+                    "encoding" => p.block(|p| {
+                        node.encodings.push(p.signal_encoding_type()?);
+                        Ok(())
+                    })?,
+                    "status_management" => p.block(|p| {
+                        let field = p.ident()?;
+                        p.expect_punct('=')?;
+                        match field {
+                            "response_error" => {
+                                node.status_management.response_error =
+                                    Some(p.ident()?.to_string());
+                                p.expect_punct(';')
+                            }
+                            "fault_state_signals" => {
+                                node.status_management.fault_state_signals = p.ident_list()?;
+                                Ok(())
+                            }
+                            _ => p.skip_statement(),
+                        }
+                    })?,
+                    "free_text" => {
+                        p.expect_punct('{')?;
+                        node.free_text = Some(p.string()?.to_string());
+                        p.expect_punct('}')?;
+                    }
+                    _ => p.skip_block()?,
+                }
+                Ok(())
+            })?;
+            Ok(node)
+        }
+
+        fn parse_ncf(&mut self) -> Result<Ncf> {
+            let mut ncf = Ncf::default();
+            self.expect_ident("node_capability_file")?;
+            self.expect_punct(';')?;
+            while let Some(token) = self.next()? {
+                match token {
+                    Token::Ident("LIN_language_version") => {
+                        ncf.language_version = self.header_string()?
+                    }
+                    Token::Ident("node") => ncf.nodes.push(self.ncf_node()?),
+                    _ => return self.error("Expected node definition"),
+                }
+            }
+            Ok(ncf)
+        }
+    }
+
+    /// Parse the content of an NCF file
+    pub fn parse(input: &str) -> Result<Ncf> {
+        Parser::new(input).parse_ncf()
+    }
+}
+
+#[cfg(feature = "ncf-parse")]
+pub use parser::parse;
+
+#[cfg(all(test, feature = "ncf-parse"))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    const EXAMPLE_NCF: &str = r#"
+node_capability_file;
+LIN_language_version = "2.1";
+node Sensor {
+  general {
+    LIN_protocol_version = "2.1";
+    supplier = 0x00B3;
+    function = 0x1001;
+    variant = 1;
+    bitrate = automatic min 10 kbps max 20 kbps;
+    sends_wake_up_signal = "yes";
+  }
+  diagnostic {
+    NAD = 0x10 to 0x12;
+    diagnostic_class = 2;
+    P2_min = 100 ms;
+    ST_min = 0 ms;
+    support_sid { 0xB0, 0xB2 };
+  }
+  frames {
+    publish SensorFrame {
+      length = 2;
+      min_period = 10 ms;
+      max_period = 100 ms;
+      signals {
+        Temperature { size = 12; init_value = 0; offset = 0; TemperatureEncoding; }
+        Status { size = 4; init_value = 0xF; offset = 12; }
+      }
+    }
+    subscribe ActuatorFrame {
+      length = 1;
+      signals {
+        Command { size = 8; init_value = 0; offset = 0; }
+      }
+    }
+  }
+  encoding {
+    TemperatureEncoding {
+      physical_value, 0, 4094, 0.1, -40, "degC";
+    }
+  }
+  status_management {
+    response_error = Status;
+  }
+  free_text { "Temperature sensor" }
+}
+"#;
+
+    #[test]
+    fn test_parse_ncf() {
+        let ncf = parse(EXAMPLE_NCF).unwrap();
+        assert_eq!(ncf.language_version, "2.1");
+        let node = &ncf.nodes[0];
+        assert_eq!(node.name, "Sensor");
+        assert_eq!(
+            node.general.bitrate,
+            Bitrate::Automatic {
+                min: Some(LinSpeed(10000)),
+                max: Some(LinSpeed(20000))
+            }
+        );
+        assert!(node.general.sends_wake_up_signal);
+        assert_eq!(node.diagnostic.nads, vec![0x10, 0x11, 0x12]);
+        assert_eq!(node.diagnostic.supported_sids, vec![0xB0, 0xB2]);
+        assert_eq!(node.frames.len(), 2);
+        assert_eq!(
+            node.frames[0].signals[0].encoding.as_deref(),
+            Some("TemperatureEncoding")
+        );
+        assert_eq!(
+            node.frames[0].signals[1].init_value,
+            SignalValue::Scalar(0xF)
+        );
+        assert_eq!(node.encodings[0].name, "TemperatureEncoding");
+        assert_eq!(
+            node.status_management.response_error.as_deref(),
+            Some("Status")
+        );
+        assert_eq!(node.free_text.as_deref(), Some("Temperature sensor"));
+    }
+
+    #[test]
+    fn test_derive_from_ncf() {
+        let ncf = parse(EXAMPLE_NCF).unwrap();
+        let node = &ncf.nodes[0];
+
+        let attributes = node.node_attributes();
+        assert_eq!(attributes.initial_nad, NAD(0x10));
+        assert_eq!(attributes.product_id.supplier_id, 0x00B3);
+        assert_eq!(attributes.p2_min, P2Min::from_ms(100));
+
+        let frames =
+            node.frame_definitions(
+                "Master",
+                |name| {
+                    if name == "SensorFrame" {
+                        0x10
+                    } else {
+                        0x11
+                    }
+                },
+            );
+        assert_eq!(frames[0].publisher, "Sensor");
+        assert_eq!(frames[1].publisher, "Master");
+        assert_eq!(frames[1].id, 0x11);
+        assert_eq!(frames[0].signals[1].offset, 12);
+
+        let signals = node.signal_definitions("Master");
+        assert_eq!(signals.len(), 3);
+        assert_eq!(signals[2].publisher, "Master");
+        assert_eq!(signals[2].subscribers, vec!["Sensor"]);
+    }
+}
@@ -0,0 +1,274 @@
+//! Node attributes of the LDF and NCF
+
+use core::fmt;
+
+use super::{LinProtocolVersion, NAsTimeout, NCrTimeout, P2Min, ResponseTolerance, STMin};
+use crate::frame::diagnostic::ProductId;
+use crate::frame::transport::NAD;
+use crate::iso17987::{self, wildcard};
+
+/// A frame which can be configured in a node. Message IDs are only used by LIN 2.0 nodes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigurableFrame<'a> {
+    pub name: &'a str,
+    pub message_id: Option<u16>,
+}
+
+/// Holds the most important node attributes
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeAttributes<'a> {
+    pub protocol_version: LinProtocolVersion,
+    pub configured_nad: NAD,
+    pub initial_nad: NAD,
+    pub product_id: ProductId,
+    pub p2_min: P2Min,
+    pub st_min: STMin,
+    pub n_as_timeout: NAsTimeout,
+    pub n_cr_timeout: NCrTimeout,
+    pub response_tolerance: ResponseTolerance,
+    /// Frames in the order used by the node configuration services. They are borrowed, so they
+    /// are serialized but not deserialized, see `NodeAttributesDefinition`.
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    pub configurable_frames: &'a [ConfigurableFrame<'a>],
+}
+
+impl<'a> NodeAttributes<'a> {
+    pub fn with_default_timing(
+        configured_nad: NAD,
+        initial_nad: NAD,
+        product_id: ProductId,
+    ) -> NodeAttributes<'a> {
+        NodeAttributes {
+            protocol_version: LinProtocolVersion::default(),
+            configured_nad,
+            initial_nad,
+            product_id,
+            p2_min: P2Min::default(),
+            st_min: STMin::default(),
+            n_as_timeout: NAsTimeout::default(),
+            n_cr_timeout: NCrTimeout::default(),
+            response_tolerance: ResponseTolerance::default(),
+            configurable_frames: &[],
+        }
+    }
+
+    /// Start building node attributes for a node with the given NAD and product ID
+    pub fn builder(configured_nad: NAD, product_id: ProductId) -> NodeAttributesBuilder<'a> {
+        NodeAttributesBuilder {
+            attributes: NodeAttributes::with_default_timing(
+                configured_nad,
+                configured_nad,
+                product_id,
+            ),
+        }
+    }
+
+    /// Find the index of a configurable frame as used by the assign frame ID range service
+    pub fn configurable_frame_index(&self, name: &str) -> Option<usize> {
+        self.configurable_frames.iter().position(|f| f.name == name)
+    }
+}
+
+/// Error returned by `NodeAttributesBuilder::build` for inconsistent attributes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NodeAttributesError {
+    /// The NAD is not in the slave node range 1..=0x7D
+    InvalidNad(NAD),
+    /// The supplier ID is the wildcard 0x7FFF or has the most significant bit set
+    InvalidSupplierId(u16),
+    /// The function ID is the wildcard 0xFFFF
+    InvalidFunctionId(u16),
+    /// The response tolerance is negative or not a number
+    InvalidTiming,
+}
+
+impl fmt::Display for NodeAttributesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeAttributesError::InvalidNad(nad) => write!(f, "invalid NAD {:#04X}", nad.0),
+            NodeAttributesError::InvalidSupplierId(id) => {
+                write!(f, "invalid supplier ID {:#06X}", id)
+            }
+            NodeAttributesError::InvalidFunctionId(id) => {
+                write!(f, "invalid function ID {:#06X}", id)
+            }
+            NodeAttributesError::InvalidTiming => f.write_str("invalid timing value"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NodeAttributesError {}
+
+/// Builder for `NodeAttributes`. All fields not set explicitly use their defaults and the
+/// initial NAD defaults to the configured NAD.
+///
+/// ```
+/// use lin_bus::frame::diagnostic::ProductId;
+/// use lin_bus::frame::transport::NAD;
+/// use lin_bus::ldf::{NodeAttributes, P2Min};
+///
+/// let product_id = ProductId {
+///     supplier_id: 0x00B3,
+///     function_id: 0x1001,
+///     variant: 0,
+/// };
+/// let attributes = NodeAttributes::builder(NAD(0x10), product_id)
+///     .p2_min(P2Min::from_ms(100))
+///     .build()
+///     .unwrap();
+/// assert_eq!(attributes.initial_nad, NAD(0x10));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NodeAttributesBuilder<'a> {
+    attributes: NodeAttributes<'a>,
+}
+
+impl<'a> NodeAttributesBuilder<'a> {
+    pub fn protocol_version(mut self, protocol_version: LinProtocolVersion) -> Self {
+        self.attributes.protocol_version = protocol_version;
+        self
+    }
+
+    pub fn initial_nad(mut self, initial_nad: NAD) -> Self {
+        self.attributes.initial_nad = initial_nad;
+        self
+    }
+
+    pub fn p2_min(mut self, p2_min: P2Min) -> Self {
+        self.attributes.p2_min = p2_min;
+        self
+    }
+
+    pub fn st_min(mut self, st_min: STMin) -> Self {
+        self.attributes.st_min = st_min;
+        self
+    }
+
+    pub fn n_as_timeout(mut self, n_as_timeout: NAsTimeout) -> Self {
+        self.attributes.n_as_timeout = n_as_timeout;
+        self
+    }
+
+    pub fn n_cr_timeout(mut self, n_cr_timeout: NCrTimeout) -> Self {
+        self.attributes.n_cr_timeout = n_cr_timeout;
+        self
+    }
+
+    pub fn response_tolerance(mut self, response_tolerance: ResponseTolerance) -> Self {
+        self.attributes.response_tolerance = response_tolerance;
+        self
+    }
+
+    pub fn configurable_frames(mut self, frames: &'a [ConfigurableFrame<'a>]) -> Self {
+        self.attributes.configurable_frames = frames;
+        self
+    }
+
+    /// Validate and return the node attributes
+    pub fn build(self) -> Result<NodeAttributes<'a>, NodeAttributesError> {
+        let attributes = self.attributes;
+        for nad in [attributes.configured_nad, attributes.initial_nad] {
+            if !iso17987::nad::is_slave(nad) {
+                return Err(NodeAttributesError::InvalidNad(nad));
+            }
+        }
+        let product_id = attributes.product_id;
+        if product_id.supplier_id >= wildcard::SUPPLIER_ID {
+            return Err(NodeAttributesError::InvalidSupplierId(
+                product_id.supplier_id,
+            ));
+        }
+        if product_id.function_id == wildcard::FUNCTION_ID {
+            return Err(NodeAttributesError::InvalidFunctionId(
+                product_id.function_id,
+            ));
+        }
+        let tolerance = attributes.response_tolerance.0;
+        if tolerance.is_nan() || tolerance < 0.0 {
+            return Err(NodeAttributesError::InvalidTiming);
+        }
+        Ok(attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_attributes_builder() {
+        let product_id = ProductId {
+            supplier_id: 0x00B3,
+            function_id: 0x1001,
+            variant: 0,
+        };
+        let frames = [ConfigurableFrame {
+            name: "SensorFrame",
+            message_id: None,
+        }];
+        let attributes = NodeAttributes::builder(NAD(0x10), product_id)
+            .initial_nad(NAD(0x11))
+            .st_min(STMin::from_ms(10))
+            .configurable_frames(&frames)
+            .build()
+            .unwrap();
+        assert_eq!(attributes.configured_nad, NAD(0x10));
+        assert_eq!(attributes.initial_nad, NAD(0x11));
+        assert_eq!(attributes.st_min, STMin::from_ms(10));
+        assert_eq!(attributes.p2_min, P2Min::default());
+        assert_eq!(attributes.configurable_frame_index("SensorFrame"), Some(0));
+
+        let builder = NodeAttributes::builder(NAD(0x10), product_id);
+        assert_eq!(
+            builder.initial_nad(NAD(0x7F)).build(),
+            Err(NodeAttributesError::InvalidNad(NAD(0x7F)))
+        );
+        assert_eq!(
+            builder
+                .response_tolerance(ResponseTolerance(f32::NAN))
+                .build(),
+            Err(NodeAttributesError::InvalidTiming)
+        );
+        let wildcard = ProductId {
+            supplier_id: 0x7FFF,
+            ..product_id
+        };
+        assert_eq!(
+            NodeAttributes::builder(NAD(0x10), wildcard).build(),
+            Err(NodeAttributesError::InvalidSupplierId(0x7FFF))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let product_id = ProductId {
+            supplier_id: 0x00B3,
+            function_id: 0x1001,
+            variant: 0,
+        };
+        let frames = [ConfigurableFrame {
+            name: "SensorFrame",
+            message_id: None,
+        }];
+        let attributes = NodeAttributes {
+            configurable_frames: &frames,
+            ..NodeAttributes::with_default_timing(NAD(0x10), NAD(0x11), product_id)
+        };
+        let json = serde_json::to_string(&attributes).unwrap();
+        assert!(
+            json.contains(r#""configurable_frames":[{"name":"SensorFrame","message_id":null}]"#)
+        );
+        let deserialized: NodeAttributes = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            deserialized,
+            NodeAttributes {
+                configurable_frames: &[],
+                ..attributes
+            }
+        );
+    }
+}
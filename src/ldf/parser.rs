@@ -0,0 +1,787 @@
+//! Parser for the LDF text format
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::lexer::{Parser, Result, Token};
+use super::model::*;
+use super::{NAsTimeout, NCrTimeout, NodeAttributes, P2Min, ResponseTolerance, STMin};
+use crate::frame::diagnostic::ProductId;
+use crate::frame::transport::NAD;
+
+impl<'a> Parser<'a> {
+    pub(crate) fn u16(&mut self) -> Result<u16> {
+        let value = self.integer()?;
+        if value > u64::from(u16::MAX) {
+            self.error("Integer out of range")
+        } else {
+            Ok(value as u16)
+        }
+    }
+
+    /// Parse the entries of a block until the closing brace
+    pub(crate) fn block<F>(&mut self, mut entry: F) -> Result<()>
+    where
+        F: FnMut(&mut Self) -> Result<()>,
+    {
+        self.expect_punct('{')?;
+        while !self.eat_punct('}')? {
+            if self.peek()?.is_none() {
+                return self.error("Unexpected end of file");
+            }
+            entry(self)?;
+        }
+        Ok(())
+    }
+
+    /// Parse a comma separated list of identifiers terminated by `;`
+    pub(crate) fn ident_list(&mut self) -> Result<Vec<String>> {
+        let mut list = Vec::new();
+        loop {
+            list.push(self.ident()?.to_string());
+            if !self.eat_punct(',')? {
+                break;
+            }
+        }
+        self.expect_punct(';')?;
+        Ok(list)
+    }
+
+    /// Parse `= "<string>";`
+    pub(crate) fn header_string(&mut self) -> Result<String> {
+        self.expect_punct('=')?;
+        let value = self.string()?.to_string();
+        self.expect_punct(';')?;
+        Ok(value)
+    }
+
+    fn parse_ldf(&mut self) -> Result<Ldf> {
+        let mut ldf = Ldf::default();
+        self.expect_ident("LIN_description_file")?;
+        self.expect_punct(';')?;
+        while let Some(token) = self.next()? {
+            let keyword = match token {
+                Token::Ident(keyword) => keyword,
+                _ => return self.error("Expected section keyword"),
+            };
+            match keyword {
+                "LIN_protocol_version" => {
+                    self.expect_punct('=')?;
+                    ldf.protocol_version = self.protocol_version()?;
+                    self.expect_punct(';')?;
+                }
+                "LIN_language_version" => ldf.language_version = self.header_string()?,
+                "LIN_speed" => {
+                    self.expect_punct('=')?;
+                    ldf.speed = self.kbps()?;
+                    self.expect_punct(';')?;
+                }
+                "Channel_name" => ldf.channel_name = Some(self.header_string()?),
+                "Nodes" => ldf.nodes = self.nodes()?,
+                "Node_composition" => ldf.node_compositions = self.node_compositions()?,
+                "Signals" => ldf.signals = self.signals()?,
+                "Diagnostic_signals" => ldf.diagnostic_signals = self.diagnostic_signals()?,
+                "Frames" => ldf.frames = self.frames()?,
+                "Sporadic_frames" => ldf.sporadic_frames = self.sporadic_frames()?,
+                "Event_triggered_frames" => {
+                    ldf.event_triggered_frames = self.event_triggered_frames()?
+                }
+                "Diagnostic_frames" => ldf.diagnostic_frames = self.diagnostic_frames()?,
+                "Node_attributes" => ldf.node_attributes = self.node_attributes()?,
+                "Schedule_tables" => ldf.schedule_tables = self.schedule_tables()?,
+                "Signal_encoding_types" => {
+                    ldf.signal_encoding_types = self.signal_encoding_types()?
+                }
+                "Signal_representation" => {
+                    ldf.signal_representations = self.signal_representations()?
+                }
+                _ => {
+                    // Skip unsupported sections like `Signal_groups` or `Dynamic_frames`
+                    if self.is_punct('{')? {
+                        self.skip_block()?;
+                    } else {
+                        self.skip_statement()?;
+                    }
+                }
+            }
+        }
+        Ok(ldf)
+    }
+
+    fn nodes(&mut self) -> Result<Nodes> {
+        let mut nodes = Nodes::default();
+        self.block(|p| {
+            match p.ident()? {
+                "Master" => {
+                    p.expect_punct(':')?;
+                    nodes.master.name = p.ident()?.to_string();
+                    p.expect_punct(',')?;
+                    nodes.master.time_base = p.milliseconds()?;
+                    p.expect_punct(',')?;
+                    nodes.master.jitter = p.milliseconds()?;
+                    p.expect_punct(';')?;
+                }
+                "Slaves" => {
+                    p.expect_punct(':')?;
+                    nodes.slaves = p.ident_list()?;
+                }
+                _ => return p.error("Unknown node type"),
+            }
+            Ok(())
+        })?;
+        Ok(nodes)
+    }
+
+    fn node_compositions(&mut self) -> Result<Vec<NodeComposition>> {
+        let mut compositions = Vec::new();
+        self.block(|p| {
+            p.expect_ident("configuration")?;
+            let name = p.ident()?.to_string();
+            let mut composite_nodes = Vec::new();
+            p.block(|p| {
+                let name = p.ident()?.to_string();
+                p.expect_punct('{')?;
+                let logical_nodes = p.ident_list()?;
+                p.expect_punct('}')?;
+                composite_nodes.push(CompositeNode {
+                    name,
+                    logical_nodes,
+                });
+                Ok(())
+            })?;
+            compositions.push(NodeComposition {
+                name,
+                composite_nodes,
+            });
+            Ok(())
+        })?;
+        Ok(compositions)
+    }
+
+    pub(crate) fn signal_value(&mut self) -> Result<SignalValue> {
+        if self.eat_punct('{')? {
+            let mut bytes = Vec::new();
+            loop {
+                bytes.push(self.u8()?);
+                if !self.eat_punct(',')? {
+                    break;
+                }
+            }
+            self.expect_punct('}')?;
+            Ok(SignalValue::ByteArray(bytes))
+        } else {
+            Ok(SignalValue::Scalar(self.integer()?))
+        }
+    }
+
+    fn signals(&mut self) -> Result<Vec<SignalDefinition>> {
+        let mut signals = Vec::new();
+        self.block(|p| {
+            let name = p.ident()?.to_string();
+            p.expect_punct(':')?;
+            let size = p.u8()?;
+            p.expect_punct(',')?;
+            let init_value = p.signal_value()?;
+            p.expect_punct(',')?;
+            let mut nodes = p.ident_list()?;
+            let publisher = nodes.remove(0);
+            signals.push(SignalDefinition {
+                name,
+                size,
+                init_value,
+                publisher,
+                subscribers: nodes,
+            });
+            Ok(())
+        })?;
+        Ok(signals)
+    }
+
+    fn diagnostic_signals(&mut self) -> Result<Vec<SignalDefinition>> {
+        let mut signals = Vec::new();
+        self.block(|p| {
+            let name = p.ident()?.to_string();
+            p.expect_punct(':')?;
+            let size = p.u8()?;
+            p.expect_punct(',')?;
+            let init_value = p.signal_value()?;
+            p.expect_punct(';')?;
+            signals.push(SignalDefinition {
+                name,
+                size,
+                init_value,
+                ..Default::default()
+            });
+            Ok(())
+        })?;
+        Ok(signals)
+    }
+
+    fn frame_signals(&mut self) -> Result<Vec<FrameSignal>> {
+        let mut signals = Vec::new();
+        self.block(|p| {
+            let name = p.ident()?.to_string();
+            p.expect_punct(',')?;
+            let offset = p.u8()?;
+            p.expect_punct(';')?;
+            signals.push(FrameSignal { name, offset });
+            Ok(())
+        })?;
+        Ok(signals)
+    }
+
+    fn frames(&mut self) -> Result<Vec<UnconditionalFrame>> {
+        let mut frames = Vec::new();
+        self.block(|p| {
+            let name = p.ident()?.to_string();
+            p.expect_punct(':')?;
+            let id = p.u8()?;
+            p.expect_punct(',')?;
+            let publisher = p.ident()?.to_string();
+            p.expect_punct(',')?;
+            let length = p.u8()?;
+            let signals = p.frame_signals()?;
+            frames.push(UnconditionalFrame {
+                name,
+                id,
+                publisher,
+                length,
+                signals,
+            });
+            Ok(())
+        })?;
+        Ok(frames)
+    }
+
+    fn sporadic_frames(&mut self) -> Result<Vec<SporadicFrame>> {
+        let mut frames = Vec::new();
+        self.block(|p| {
+            let name = p.ident()?.to_string();
+            p.expect_punct(':')?;
+            let associated = p.ident_list()?;
+            frames.push(SporadicFrame {
+                name,
+                frames: associated,
+            });
+            Ok(())
+        })?;
+        Ok(frames)
+    }
+
+    fn event_triggered_frames(&mut self) -> Result<Vec<EventTriggeredFrame>> {
+        let mut frames = Vec::new();
+        self.block(|p| {
+            let name = p.ident()?.to_string();
+            p.expect_punct(':')?;
+            let collision_resolving_schedule_table = match p.peek()? {
+                Some(Token::Ident(table)) => {
+                    p.next()?;
+                    p.expect_punct(',')?;
+                    Some(table.to_string())
+                }
+                _ => None,
+            };
+            let id = p.u8()?;
+            let associated = if p.eat_punct(',')? {
+                p.ident_list()?
+            } else {
+                p.expect_punct(';')?;
+                Vec::new()
+            };
+            frames.push(EventTriggeredFrame {
+                name,
+                collision_resolving_schedule_table,
+                id,
+                frames: associated,
+            });
+            Ok(())
+        })?;
+        Ok(frames)
+    }
+
+    fn diagnostic_frames(&mut self) -> Result<Vec<DiagnosticFrame>> {
+        let mut frames = Vec::new();
+        self.block(|p| {
+            let name = p.ident()?.to_string();
+            p.expect_punct(':')?;
+            let id = p.u8()?;
+            let signals = p.frame_signals()?;
+            frames.push(DiagnosticFrame { name, id, signals });
+            Ok(())
+        })?;
+        Ok(frames)
+    }
+
+    fn node_attributes(&mut self) -> Result<Vec<NodeAttributesDefinition>> {
+        let mut nodes = Vec::new();
+        self.block(|p| {
+            let name = p.ident()?.to_string();
+            let mut definition = NodeAttributesDefinition {
+                name,
+                attributes: NodeAttributes::with_default_timing(
+                    NAD(0),
+                    NAD(0),
+                    ProductId {
+                        supplier_id: 0,
+                        function_id: 0,
+                        variant: 0,
+                    },
+                ),
+                response_error: None,
+                fault_state_signals: Vec::new(),
+                configurable_frames: Vec::new(),
+            };
+            let mut initial_nad = None;
+            p.block(|p| {
+                let attribute = p.ident()?;
+                if attribute == "configurable_frames" {
+                    return p.block(|p| {
+                        let name = p.ident()?.to_string();
+                        let message_id = if p.eat_punct('=')? {
+                            Some(p.u16()?)
+                        } else {
+                            None
+                        };
+                        definition
+                            .configurable_frames
+                            .push(ConfigurableFrameDefinition { name, message_id });
+                        p.expect_punct(';')
+                    });
+                }
+                p.expect_punct('=')?;
+                let attributes = &mut definition.attributes;
+                match attribute {
+                    "LIN_protocol" => {
+                        attributes.protocol_version = p.protocol_version()?;
+                    }
+                    "response_tolerance" => {
+                        attributes.response_tolerance = ResponseTolerance(p.float()?);
+                        p.expect_punct('%')?;
+                    }
+                    "configured_NAD" => attributes.configured_nad = NAD(p.u8()?),
+                    "initial_NAD" => initial_nad = Some(NAD(p.u8()?)),
+                    "product_id" => {
+                        attributes.product_id.supplier_id = p.u16()?;
+                        p.expect_punct(',')?;
+                        attributes.product_id.function_id = p.u16()?;
+                        if p.eat_punct(',')? {
+                            attributes.product_id.variant = p.u8()?;
+                        }
+                    }
+                    "response_error" => definition.response_error = Some(p.ident()?.to_string()),
+                    "fault_state_signals" => {
+                        definition.fault_state_signals = p.ident_list()?;
+                        return Ok(());
+                    }
+                    "P2_min" => attributes.p2_min = P2Min(p.microseconds()?),
+                    "ST_min" => attributes.st_min = STMin(p.microseconds()?),
+                    "N_As_timeout" => attributes.n_as_timeout = NAsTimeout(p.microseconds()?),
+                    "N_Cr_timeout" => attributes.n_cr_timeout = NCrTimeout(p.microseconds()?),
+                    _ => {
+                        // Skip unsupported attributes
+                        return p.skip_statement();
+                    }
+                }
+                p.expect_punct(';')
+            })?;
+            // The initial NAD defaults to the configured NAD
+            definition.attributes.initial_nad =
+                initial_nad.unwrap_or(definition.attributes.configured_nad);
+            nodes.push(definition);
+            Ok(())
+        })?;
+        Ok(nodes)
+    }
+
+    /// Parse `N` comma separated bytes
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            if i != 0 {
+                self.expect_punct(',')?;
+            }
+            *byte = self.u8()?;
+        }
+        Ok(bytes)
+    }
+
+    fn schedule_command(&mut self) -> Result<ScheduleCommand> {
+        let name = self.ident()?;
+        if !self.eat_punct('{')? {
+            return Ok(match name {
+                "MasterReq" => ScheduleCommand::MasterReq,
+                "SlaveResp" => ScheduleCommand::SlaveResp,
+                frame => ScheduleCommand::Frame(frame.to_string()),
+            });
+        }
+        let command = match name {
+            "AssignNAD" => ScheduleCommand::AssignNAD {
+                node: self.ident()?.to_string(),
+            },
+            "ConditionalChangeNAD" => {
+                let [nad, id, byte, mask, inv, new_nad] = self.bytes()?;
+                ScheduleCommand::ConditionalChangeNAD {
+                    nad,
+                    id,
+                    byte,
+                    mask,
+                    inv,
+                    new_nad,
+                }
+            }
+            "DataDump" => {
+                let node = self.ident()?.to_string();
+                self.expect_punct(',')?;
+                ScheduleCommand::DataDump {
+                    node,
+                    data: self.bytes()?,
+                }
+            }
+            "SaveConfiguration" => ScheduleCommand::SaveConfiguration {
+                node: self.ident()?.to_string(),
+            },
+            "AssignFrameIdRange" => {
+                let node = self.ident()?.to_string();
+                self.expect_punct(',')?;
+                let frame_index = self.u8()?;
+                let pids = if self.eat_punct(',')? {
+                    Some(self.bytes()?)
+                } else {
+                    None
+                };
+                ScheduleCommand::AssignFrameIdRange {
+                    node,
+                    frame_index,
+                    pids,
+                }
+            }
+            "FreeFormat" => ScheduleCommand::FreeFormat(self.bytes()?),
+            "AssignFrameId" | "UnassignFrameId" => {
+                let node = self.ident()?.to_string();
+                self.expect_punct(',')?;
+                let frame = self.ident()?.to_string();
+                if name == "AssignFrameId" {
+                    ScheduleCommand::AssignFrameId { node, frame }
+                } else {
+                    ScheduleCommand::UnassignFrameId { node, frame }
+                }
+            }
+            _ => return self.error("Unknown schedule command"),
+        };
+        self.expect_punct('}')?;
+        Ok(command)
+    }
+
+    fn schedule_tables(&mut self) -> Result<Vec<ScheduleTable>> {
+        let mut tables = Vec::new();
+        self.block(|p| {
+            let name = p.ident()?.to_string();
+            let mut entries = Vec::new();
+            p.block(|p| {
+                let command = p.schedule_command()?;
+                p.expect_ident("delay")?;
+                let delay = p.milliseconds()?;
+                p.expect_punct(';')?;
+                entries.push(ScheduleEntry { command, delay });
+                Ok(())
+            })?;
+            tables.push(ScheduleTable { name, entries });
+            Ok(())
+        })?;
+        Ok(tables)
+    }
+
+    fn signal_encoding_types(&mut self) -> Result<Vec<SignalEncodingType>> {
+        let mut encodings = Vec::new();
+        self.block(|p| {
+            encodings.push(p.signal_encoding_type()?);
+            Ok(())
+        })?;
+        Ok(encodings)
+    }
+
+    /// Parse a single named signal encoding type
+    pub(crate) fn signal_encoding_type(&mut self) -> Result<SignalEncodingType> {
+        let name = self.ident()?.to_string();
+        let mut values = Vec::new();
+        self.block(|p| {
+            let value = match p.ident()? {
+                "logical_value" => {
+                    p.expect_punct(',')?;
+                    let value = p.integer()?;
+                    let text = if p.eat_punct(',')? {
+                        Some(p.string()?.to_string())
+                    } else {
+                        None
+                    };
+                    EncodingValue::Logical { value, text }
+                }
+                "physical_value" => {
+                    p.expect_punct(',')?;
+                    let min = p.integer()?;
+                    p.expect_punct(',')?;
+                    let max = p.integer()?;
+                    p.expect_punct(',')?;
+                    let scale = p.float()?;
+                    p.expect_punct(',')?;
+                    let offset = p.float()?;
+                    let unit = if p.eat_punct(',')? {
+                        Some(p.string()?.to_string())
+                    } else {
+                        None
+                    };
+                    EncodingValue::Physical {
+                        min,
+                        max,
+                        scale,
+                        offset,
+                        unit,
+                    }
+                }
+                "bcd_value" => EncodingValue::Bcd,
+                "ascii_value" => EncodingValue::Ascii,
+                _ => return p.error("Unknown encoding value"),
+            };
+            values.push(value);
+            p.expect_punct(';')
+        })?;
+        Ok(SignalEncodingType { name, values })
+    }
+
+    fn signal_representations(&mut self) -> Result<Vec<SignalRepresentation>> {
+        let mut representations = Vec::new();
+        self.block(|p| {
+            let encoding = p.ident()?.to_string();
+            p.expect_punct(':')?;
+            let signals = p.ident_list()?;
+            representations.push(SignalRepresentation { encoding, signals });
+            Ok(())
+        })?;
+        Ok(representations)
+    }
+}
+
+/// Parse the content of an LDF file
+pub fn parse(input: &str) -> Result<Ldf> {
+    Parser::new(input).parse_ldf()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::ldf::{LinProtocolVersion, LinSpeed};
+    use alloc::vec;
+
+    pub(crate) const EXAMPLE_LDF: &str = r#"
+LIN_description_file;
+LIN_protocol_version = "2.1";
+LIN_language_version = "2.1";
+LIN_speed = 19.2 kbps;
+Channel_name = "Body";
+
+Nodes {
+  Master: Gateway, 5 ms, 0.1 ms;
+  Slaves: Sensor, Actuator;
+}
+
+Node_composition {
+  configuration Combined {
+    SensorActuator { Sensor, Actuator; }
+  }
+}
+
+/* Signal definitions */
+Signals {
+  Temperature: 12, 0, Sensor, Gateway;
+  Status: 4, 0xF, Sensor, Gateway, Actuator;
+  Position: 16, {0, 0}, Gateway, Actuator;
+}
+
+Diagnostic_signals {
+  MasterReqB0: 8, 0;
+}
+
+Frames {
+  SensorFrame: 0x10, Sensor, 2 {
+    Temperature, 0;
+    Status, 12;
+  }
+  ActuatorFrame: 0x11, Gateway, 2 {
+    Position, 0; // little endian
+  }
+}
+
+Sporadic_frames {
+  SporadicFrame: ActuatorFrame;
+}
+
+Event_triggered_frames {
+  EventFrame: CollisionTable, 0x3A, SensorFrame;
+}
+
+Diagnostic_frames {
+  MasterReq: 0x3C {
+    MasterReqB0, 0;
+  }
+}
+
+Node_attributes {
+  Sensor {
+    LIN_protocol = "2.1";
+    configured_NAD = 0x10;
+    product_id = 0x00B3, 0x1001, 1;
+    response_error = Status;
+    P2_min = 100 ms;
+    ST_min = 0.0125 ms;
+    configurable_frames {
+      SensorFrame;
+      ActuatorFrame = 0x1234;
+    }
+    response_tolerance = 38 %;
+  }
+}
+
+Schedule_tables {
+  Normal {
+    SensorFrame delay 10 ms;
+    ActuatorFrame delay 10 ms;
+    MasterReq delay 20 ms;
+  }
+  Configuration {
+    AssignNAD { Sensor } delay 10 ms;
+    ConditionalChangeNAD { 0x7F, 0x01, 0x03, 0x01, 0xFF, 0x01 } delay 10 ms;
+    DataDump { Sensor, 0x10, 0x80, 0x00, 0xFF, 0xFF } delay 10 ms;
+    SaveConfiguration { Sensor } delay 10 ms;
+    AssignFrameIdRange { Sensor, 0 } delay 10 ms;
+    AssignFrameIdRange { Sensor, 0, 0x50, 0x11, 0xFF, 0xFF } delay 10 ms;
+    FreeFormat { 0x7F, 0x06, 0xB5, 0xFF, 0x7F, 0xFF, 0xFF, 0xFF } delay 10 ms;
+    AssignFrameId { Sensor, SensorFrame } delay 10 ms;
+    UnassignFrameId { Sensor, SensorFrame } delay 10 ms;
+  }
+}
+
+Signal_encoding_types {
+  TemperatureEncoding {
+    physical_value, 0, 4094, 0.1, -40, "degC";
+    logical_value, 4095, "invalid";
+  }
+}
+
+Signal_representation {
+  TemperatureEncoding: Temperature;
+}
+"#;
+
+    #[test]
+    fn test_parse_ldf() {
+        let ldf = parse(EXAMPLE_LDF).unwrap();
+        assert_eq!(ldf.protocol_version, LinProtocolVersion::V2_1);
+        assert_eq!(ldf.speed, LinSpeed(19200));
+        assert_eq!(ldf.nodes.master.name, "Gateway");
+        assert_eq!(ldf.nodes.master.jitter, 0.1);
+        assert_eq!(ldf.nodes.slaves, vec!["Sensor", "Actuator"]);
+        assert_eq!(ldf.channel_name.as_deref(), Some("Body"));
+        let composition = ldf.node_composition("Combined").unwrap();
+        assert_eq!(
+            composition
+                .composite_of("Actuator")
+                .map(|c| c.name.as_str()),
+            Some("SensorActuator")
+        );
+        assert_eq!(composition.composite_of("Gateway"), None);
+        assert_eq!(ldf.signals.len(), 3);
+        assert_eq!(ldf.signals[1].init_value, SignalValue::Scalar(0xF));
+        assert_eq!(ldf.signals[1].subscribers, vec!["Gateway", "Actuator"]);
+        assert_eq!(
+            ldf.signals[2].init_value,
+            SignalValue::ByteArray(vec![0, 0])
+        );
+        assert_eq!(ldf.diagnostic_signals[0].name, "MasterReqB0");
+        assert_eq!(ldf.frame_by_id(0x10).unwrap().signals[1].offset, 12);
+        assert_eq!(ldf.sporadic_frames[0].frames, vec!["ActuatorFrame"]);
+        assert_eq!(
+            ldf.event_triggered_frames[0]
+                .collision_resolving_schedule_table
+                .as_deref(),
+            Some("CollisionTable")
+        );
+        assert_eq!(ldf.diagnostic_frames[0].id, 0x3C);
+
+        let sensor = ldf.node_attributes("Sensor").unwrap();
+        assert_eq!(sensor.attributes.configured_nad, NAD(0x10));
+        assert_eq!(sensor.attributes.initial_nad, NAD(0x10));
+        assert_eq!(sensor.attributes.product_id.variant, 1);
+        assert_eq!(sensor.attributes.p2_min, P2Min::from_ms(100));
+        assert_eq!(sensor.attributes.st_min, STMin(13));
+        assert_eq!(sensor.response_error.as_deref(), Some("Status"));
+        assert_eq!(sensor.attributes.protocol_version, LinProtocolVersion::V2_1);
+        assert_eq!(
+            sensor.attributes.response_tolerance,
+            ResponseTolerance(38.0)
+        );
+        let frames = sensor.configurable_frames();
+        let attributes = sensor.node_attributes(&frames);
+        assert_eq!(
+            attributes.configurable_frame_index("ActuatorFrame"),
+            Some(1)
+        );
+        assert_eq!(attributes.configurable_frames[1].message_id, Some(0x1234));
+
+        let table = ldf.schedule_table("Normal").unwrap();
+        assert_eq!(table.entries.len(), 3);
+        assert_eq!(table.entries[2].command, ScheduleCommand::MasterReq);
+        assert_eq!(table.entries[2].delay, 20.0);
+
+        let table = ldf.schedule_table("Configuration").unwrap();
+        assert_eq!(table.entries.len(), 9);
+        assert_eq!(
+            table.entries[1].command,
+            ScheduleCommand::ConditionalChangeNAD {
+                nad: 0x7F,
+                id: 0x01,
+                byte: 0x03,
+                mask: 0x01,
+                inv: 0xFF,
+                new_nad: 0x01
+            }
+        );
+        assert_eq!(
+            table.entries[5].command,
+            ScheduleCommand::AssignFrameIdRange {
+                node: "Sensor".to_string(),
+                frame_index: 0,
+                pids: Some([0x50, 0x11, 0xFF, 0xFF])
+            }
+        );
+        assert_eq!(
+            table.entries[8].command,
+            ScheduleCommand::UnassignFrameId {
+                node: "Sensor".to_string(),
+                frame: "SensorFrame".to_string()
+            }
+        );
+
+        assert_eq!(
+            ldf.encoding_of("Temperature").unwrap().values[1],
+            EncodingValue::Logical {
+                value: 4095,
+                text: Some("invalid".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let error = parse("LIN_description_file;\nNodes {\n  Master Gateway;\n}").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(parse("").unwrap_err().message, "Expected identifier");
+    }
+
+    #[test]
+    fn test_parse_time_out_of_range() {
+        let ldf = EXAMPLE_LDF.replace("P2_min = 100 ms;", "P2_min = 18446744073709552.9 ms;");
+        assert_eq!(parse(&ldf).unwrap_err().message, "Time out of range");
+        let ldf = EXAMPLE_LDF.replace("P2_min = 100 ms;", "P2_min = 4294967.2955 ms;");
+        assert_eq!(parse(&ldf).unwrap_err().message, "Time out of range");
+    }
+}
@@ -0,0 +1,552 @@
+//! Pull based LDF parser which works without an allocator
+//!
+//! The `StreamParser` reads an LDF from a byte slice, e.g. a blob stored in flash, and emits one
+//! `Event` per node, signal, frame and schedule entry. The caller folds the events into its own
+//! fixed-capacity structures. All names borrow from the input.
+//!
+//! Only the header, `Nodes`, `Signals`, `Frames` and `Schedule_tables` sections produce events,
+//! all other sections are skipped.
+//!
+//! ```
+//! use lin_bus::ldf::stream::{Event, StreamParser};
+//!
+//! let ldf = b"LIN_description_file;
+//! Frames {
+//!   SensorFrame: 0x10, Sensor, 2 {
+//!     Temperature, 0;
+//!   }
+//! }";
+//!
+//! let mut ids = [0u8; 4];
+//! let mut count = 0;
+//! for event in StreamParser::new(ldf).unwrap() {
+//!     if let Event::Frame { id, .. } = event.unwrap() {
+//!         ids[count] = id;
+//!         count += 1;
+//!     }
+//! }
+//! assert_eq!(&ids[..count], &[0x10]);
+//! ```
+
+use super::lexer::{ParseError, Parser, Result, Token};
+use super::{LinProtocolVersion, LinSpeed};
+
+/// Initial value of a signal
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InitValue {
+    Scalar(u64),
+    /// Initial value of a byte array signal, only the first `len` bytes are used
+    ByteArray {
+        bytes: [u8; 8],
+        len: usize,
+    },
+}
+
+impl InitValue {
+    /// Bytes of a byte array initial value
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            InitValue::Scalar(_) => None,
+            InitValue::ByteArray { bytes, len } => Some(&bytes[..*len]),
+        }
+    }
+}
+
+/// Item emitted by the `StreamParser`, in the order of the LDF text
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// `LIN_protocol_version`
+    ProtocolVersion(LinProtocolVersion),
+    /// `LIN_language_version`
+    LanguageVersion(&'a str),
+    /// `LIN_speed`
+    Speed(LinSpeed),
+    /// Master node with time base and jitter in ms
+    Master {
+        name: &'a str,
+        time_base: f32,
+        jitter: f32,
+    },
+    /// Slave node
+    Slave(&'a str),
+    /// Signal definition, followed by one `Subscriber` event per subscribing node
+    Signal {
+        name: &'a str,
+        size: u8,
+        init_value: InitValue,
+        publisher: &'a str,
+    },
+    /// Node subscribing to the preceding signal
+    Subscriber { signal: &'a str, node: &'a str },
+    /// Unconditional frame, followed by one `FrameSignal` event per signal
+    Frame {
+        name: &'a str,
+        id: u8,
+        publisher: &'a str,
+        length: u8,
+    },
+    /// Signal placed at bit `offset` of the preceding frame
+    FrameSignal {
+        frame: &'a str,
+        signal: &'a str,
+        offset: u8,
+    },
+    /// Schedule table, followed by one `ScheduleEntry` event per entry
+    ScheduleTable(&'a str),
+    /// Entry of the preceding schedule table with its delay in ms. `command` is the frame name,
+    /// `MasterReq`, `SlaveResp` or the name of a node configuration command, whose arguments are
+    /// skipped.
+    ScheduleEntry {
+        table: &'a str,
+        command: &'a str,
+        delay: f32,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State<'a> {
+    Start,
+    Top,
+    Nodes,
+    Slaves,
+    Signals,
+    Subscribers { signal: &'a str },
+    Frames,
+    FrameSignals { frame: &'a str },
+    ScheduleTables,
+    ScheduleTable { table: &'a str },
+    Done,
+}
+
+/// Parser emitting `Event`s without allocating, see the module documentation
+pub struct StreamParser<'a> {
+    parser: Parser<'a>,
+    state: State<'a>,
+}
+
+impl<'a> StreamParser<'a> {
+    /// Create a parser for the LDF in `input`, fails if it is not valid UTF-8
+    pub fn new(input: &'a [u8]) -> core::result::Result<StreamParser<'a>, ParseError> {
+        core::str::from_utf8(input)
+            .map(StreamParser::from_text)
+            .map_err(|e| ParseError {
+                line: input[..e.valid_up_to()]
+                    .iter()
+                    .filter(|b| **b == b'\n')
+                    .count()
+                    + 1,
+                message: "Invalid UTF-8",
+            })
+    }
+
+    /// Create a parser for the LDF text in `input`
+    pub fn from_text(input: &'a str) -> StreamParser<'a> {
+        StreamParser {
+            parser: Parser::new(input),
+            state: State::Start,
+        }
+    }
+
+    fn init_value(&mut self) -> Result<InitValue> {
+        let p = &mut self.parser;
+        if !p.eat_punct('{')? {
+            return Ok(InitValue::Scalar(p.integer()?));
+        }
+        let mut bytes = [0u8; 8];
+        let mut len = 0;
+        loop {
+            if len == bytes.len() {
+                return p.error("Too many bytes");
+            }
+            bytes[len] = p.u8()?;
+            len += 1;
+            if !p.eat_punct(',')? {
+                break;
+            }
+        }
+        p.expect_punct('}')?;
+        Ok(InitValue::ByteArray { bytes, len })
+    }
+
+    /// Consume `}` and switch to `state` if the current block ends
+    fn end_of_block(&mut self, state: State<'a>) -> Result<bool> {
+        let end = self.parser.eat_punct('}')?;
+        if end {
+            self.state = state;
+        }
+        Ok(end)
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event<'a>>> {
+        loop {
+            match self.state {
+                State::Start => {
+                    self.parser.expect_ident("LIN_description_file")?;
+                    self.parser.expect_punct(';')?;
+                    self.state = State::Top;
+                }
+                State::Top => {
+                    let p = &mut self.parser;
+                    let keyword = match p.next()? {
+                        Some(Token::Ident(keyword)) => keyword,
+                        Some(_) => return p.error("Expected section keyword"),
+                        None => {
+                            self.state = State::Done;
+                            return Ok(None);
+                        }
+                    };
+                    let event = match keyword {
+                        "LIN_protocol_version" => {
+                            p.expect_punct('=')?;
+                            Event::ProtocolVersion(p.protocol_version()?)
+                        }
+                        "LIN_language_version" => {
+                            p.expect_punct('=')?;
+                            Event::LanguageVersion(p.string()?)
+                        }
+                        "LIN_speed" => {
+                            p.expect_punct('=')?;
+                            Event::Speed(p.kbps()?)
+                        }
+                        "Nodes" | "Signals" | "Frames" | "Schedule_tables" => {
+                            p.expect_punct('{')?;
+                            self.state = match keyword {
+                                "Nodes" => State::Nodes,
+                                "Signals" => State::Signals,
+                                "Frames" => State::Frames,
+                                _ => State::ScheduleTables,
+                            };
+                            continue;
+                        }
+                        _ => {
+                            if p.is_punct('{')? {
+                                p.skip_block()?;
+                            } else {
+                                p.skip_statement()?;
+                            }
+                            continue;
+                        }
+                    };
+                    p.expect_punct(';')?;
+                    return Ok(Some(event));
+                }
+                State::Nodes => {
+                    if self.end_of_block(State::Top)? {
+                        continue;
+                    }
+                    let p = &mut self.parser;
+                    match p.ident()? {
+                        "Master" => {
+                            p.expect_punct(':')?;
+                            let name = p.ident()?;
+                            p.expect_punct(',')?;
+                            let time_base = p.milliseconds()?;
+                            p.expect_punct(',')?;
+                            let jitter = p.milliseconds()?;
+                            p.expect_punct(';')?;
+                            return Ok(Some(Event::Master {
+                                name,
+                                time_base,
+                                jitter,
+                            }));
+                        }
+                        "Slaves" => {
+                            p.expect_punct(':')?;
+                            self.state = State::Slaves;
+                        }
+                        _ => return p.error("Unknown node type"),
+                    }
+                }
+                State::Slaves => {
+                    let p = &mut self.parser;
+                    let name = p.ident()?;
+                    if !p.eat_punct(',')? {
+                        p.expect_punct(';')?;
+                        self.state = State::Nodes;
+                    }
+                    return Ok(Some(Event::Slave(name)));
+                }
+                State::Signals => {
+                    if self.end_of_block(State::Top)? {
+                        continue;
+                    }
+                    let name = self.parser.ident()?;
+                    self.parser.expect_punct(':')?;
+                    let size = self.parser.u8()?;
+                    self.parser.expect_punct(',')?;
+                    let init_value = self.init_value()?;
+                    let p = &mut self.parser;
+                    p.expect_punct(',')?;
+                    let publisher = p.ident()?;
+                    if p.eat_punct(',')? {
+                        self.state = State::Subscribers { signal: name };
+                    } else {
+                        p.expect_punct(';')?;
+                    }
+                    return Ok(Some(Event::Signal {
+                        name,
+                        size,
+                        init_value,
+                        publisher,
+                    }));
+                }
+                State::Subscribers { signal } => {
+                    let p = &mut self.parser;
+                    let node = p.ident()?;
+                    if !p.eat_punct(',')? {
+                        p.expect_punct(';')?;
+                        self.state = State::Signals;
+                    }
+                    return Ok(Some(Event::Subscriber { signal, node }));
+                }
+                State::Frames => {
+                    if self.end_of_block(State::Top)? {
+                        continue;
+                    }
+                    let p = &mut self.parser;
+                    let name = p.ident()?;
+                    p.expect_punct(':')?;
+                    let id = p.u8()?;
+                    p.expect_punct(',')?;
+                    let publisher = p.ident()?;
+                    p.expect_punct(',')?;
+                    let length = p.u8()?;
+                    p.expect_punct('{')?;
+                    self.state = State::FrameSignals { frame: name };
+                    return Ok(Some(Event::Frame {
+                        name,
+                        id,
+                        publisher,
+                        length,
+                    }));
+                }
+                State::FrameSignals { frame } => {
+                    if self.end_of_block(State::Frames)? {
+                        continue;
+                    }
+                    let p = &mut self.parser;
+                    let signal = p.ident()?;
+                    p.expect_punct(',')?;
+                    let offset = p.u8()?;
+                    p.expect_punct(';')?;
+                    return Ok(Some(Event::FrameSignal {
+                        frame,
+                        signal,
+                        offset,
+                    }));
+                }
+                State::ScheduleTables => {
+                    if self.end_of_block(State::Top)? {
+                        continue;
+                    }
+                    let p = &mut self.parser;
+                    let name = p.ident()?;
+                    p.expect_punct('{')?;
+                    self.state = State::ScheduleTable { table: name };
+                    return Ok(Some(Event::ScheduleTable(name)));
+                }
+                State::ScheduleTable { table } => {
+                    if self.end_of_block(State::ScheduleTables)? {
+                        continue;
+                    }
+                    let p = &mut self.parser;
+                    let command = p.ident()?;
+                    if p.is_punct('{')? {
+                        p.skip_block()?;
+                    }
+                    p.expect_ident("delay")?;
+                    let delay = p.milliseconds()?;
+                    p.expect_punct(';')?;
+                    return Ok(Some(Event::ScheduleEntry {
+                        table,
+                        command,
+                        delay,
+                    }));
+                }
+                State::Done => return Ok(None),
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for StreamParser<'a> {
+    type Item = core::result::Result<Event<'a>, ParseError>;
+
+    /// Returns the next event, iteration ends after the first error
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.next_event();
+        if event.is_err() {
+            self.state = State::Done;
+        }
+        event.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LDF: &[u8] = br#"
+LIN_description_file;
+LIN_protocol_version = "2.1";
+LIN_language_version = "2.1";
+LIN_speed = 19.2 kbps;
+Nodes {
+  Master: Gateway, 5 ms, 0.1 ms;
+  Slaves: Sensor, Actuator;
+}
+Signals {
+  Temperature: 12, 0, Sensor, Gateway;
+  Position: 16, {1, 2}, Gateway, Actuator, Sensor;
+}
+Diagnostic_signals {
+  MasterReqB0: 8, 0;
+}
+Frames {
+  SensorFrame: 0x10, Sensor, 2 {
+    Temperature, 0;
+  }
+}
+Schedule_tables {
+  Normal {
+    SensorFrame delay 10 ms;
+    AssignNAD { Sensor } delay 20 ms;
+  }
+}
+"#;
+
+    #[test]
+    fn test_events() {
+        let mut events = StreamParser::new(LDF).unwrap();
+        let mut next = || events.next().unwrap().unwrap();
+        assert_eq!(next(), Event::ProtocolVersion(LinProtocolVersion::V2_1));
+        assert_eq!(next(), Event::LanguageVersion("2.1"));
+        assert_eq!(next(), Event::Speed(LinSpeed(19200)));
+        assert_eq!(
+            next(),
+            Event::Master {
+                name: "Gateway",
+                time_base: 5.0,
+                jitter: 0.1
+            }
+        );
+        assert_eq!(next(), Event::Slave("Sensor"));
+        assert_eq!(next(), Event::Slave("Actuator"));
+        assert_eq!(
+            next(),
+            Event::Signal {
+                name: "Temperature",
+                size: 12,
+                init_value: InitValue::Scalar(0),
+                publisher: "Sensor"
+            }
+        );
+        assert_eq!(
+            next(),
+            Event::Subscriber {
+                signal: "Temperature",
+                node: "Gateway"
+            }
+        );
+        match next() {
+            Event::Signal { init_value, .. } => assert_eq!(init_value.bytes(), Some(&[1, 2][..])),
+            event => panic!("Unexpected event {:?}", event),
+        }
+        assert_eq!(
+            next(),
+            Event::Subscriber {
+                signal: "Position",
+                node: "Actuator"
+            }
+        );
+        assert_eq!(
+            next(),
+            Event::Subscriber {
+                signal: "Position",
+                node: "Sensor"
+            }
+        );
+        assert_eq!(
+            next(),
+            Event::Frame {
+                name: "SensorFrame",
+                id: 0x10,
+                publisher: "Sensor",
+                length: 2
+            }
+        );
+        assert_eq!(
+            next(),
+            Event::FrameSignal {
+                frame: "SensorFrame",
+                signal: "Temperature",
+                offset: 0
+            }
+        );
+        assert_eq!(next(), Event::ScheduleTable("Normal"));
+        assert_eq!(
+            next(),
+            Event::ScheduleEntry {
+                table: "Normal",
+                command: "SensorFrame",
+                delay: 10.0
+            }
+        );
+        assert_eq!(
+            next(),
+            Event::ScheduleEntry {
+                table: "Normal",
+                command: "AssignNAD",
+                delay: 20.0
+            }
+        );
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            StreamParser::new(b"LIN_description_file;\n\xFF").err(),
+            Some(ParseError {
+                line: 2,
+                message: "Invalid UTF-8"
+            })
+        );
+
+        let mut events = StreamParser::from_text("LIN_description_file;\nFrames {\n  F: 0x10;\n}");
+        assert_eq!(
+            events.next(),
+            Some(Err(ParseError {
+                line: 3,
+                message: "Unexpected token, expected punctuation"
+            }))
+        );
+        assert!(events.next().is_none());
+    }
+
+    #[cfg(feature = "ldf-parse")]
+    #[test]
+    fn test_matches_parse() {
+        let text = crate::ldf::parser::tests::EXAMPLE_LDF;
+        let ldf = crate::ldf::parse(text).unwrap();
+        let mut frames = 0;
+        let mut entries = 0;
+        for event in StreamParser::from_text(text) {
+            match event.unwrap() {
+                Event::Frame { name, id, .. } => {
+                    assert_eq!(ldf.frame(name).map(|f| f.id), Some(id));
+                    frames += 1;
+                }
+                Event::ScheduleEntry { table, delay, .. } => {
+                    let table = ldf.schedule_table(table).unwrap();
+                    assert!(table.entries.iter().any(|e| e.delay == delay));
+                    entries += 1;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(frames, ldf.frames.len());
+        let total: usize = ldf.schedule_tables.iter().map(|t| t.entries.len()).sum();
+        assert_eq!(entries, total);
+    }
+}
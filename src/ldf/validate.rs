@@ -0,0 +1,306 @@
+//! Consistency checks of the LDF data model
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::model::{Ldf, ScheduleCommand};
+use super::LinSpeed;
+use crate::frame::PID;
+use crate::iso17987::{id, timing};
+
+/// A problem found by `Ldf::validate`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Finding {
+    /// Two frames share the same frame ID
+    DuplicateFrameId {
+        id: u8,
+        first: String,
+        second: String,
+    },
+    /// The frame ID is outside of the range allowed for the frame type
+    InvalidFrameId { frame: String, id: u8 },
+    /// A PID in the LDF has wrong parity bits
+    InvalidPid { table: String, pid: u8 },
+    /// The frame length is not within 1..=8 bytes
+    InvalidFrameLength { frame: String, length: u8 },
+    /// A frame maps a signal which is not defined in the `Signals` section
+    UnknownSignal { frame: String, signal: String },
+    /// A signal does not fit into its frame
+    SignalExceedsFrame { frame: String, signal: String },
+    /// A schedule table references an unknown frame
+    UnknownFrame { table: String, frame: String },
+    /// A schedule slot is shorter than the maximal frame time. Times are in ms.
+    SlotTooShort {
+        table: String,
+        entry: usize,
+        delay: f32,
+        minimum: f32,
+    },
+}
+
+/// Maximal time of a frame with `data_length` bytes in ms, see `timing::max_frame_time`
+fn max_frame_time(data_length: u8, speed: LinSpeed) -> f32 {
+    timing::max_frame_time(usize::from(data_length), speed).as_micros() as f32 / 1000.0
+}
+
+impl Ldf {
+    /// Check the LDF for inconsistencies and return all findings. An empty list means no problems
+    /// were found.
+    pub fn validate(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        self.validate_frame_ids(&mut findings);
+        self.validate_frames(&mut findings);
+        self.validate_schedule_tables(&mut findings);
+        findings
+    }
+
+    fn validate_frame_ids(&self, findings: &mut Vec<Finding>) {
+        let frames = self.frames.iter().map(|f| (&f.name, f.id, 0..=59));
+        let event_triggered_frames = self
+            .event_triggered_frames
+            .iter()
+            .map(|f| (&f.name, f.id, 0..=59));
+        let diagnostic_frames = self
+            .diagnostic_frames
+            .iter()
+            .map(|f| (&f.name, f.id, id::MASTER_REQUEST..=id::SLAVE_RESPONSE));
+        let all: Vec<_> = frames
+            .chain(event_triggered_frames)
+            .chain(diagnostic_frames)
+            .collect();
+        for (i, (name, id, range)) in all.iter().enumerate() {
+            if !range.contains(id) {
+                findings.push(Finding::InvalidFrameId {
+                    frame: (*name).clone(),
+                    id: *id,
+                });
+            }
+            if let Some((first, _, _)) = all[..i].iter().find(|(_, other, _)| other == id) {
+                findings.push(Finding::DuplicateFrameId {
+                    id: *id,
+                    first: (*first).clone(),
+                    second: (*name).clone(),
+                });
+            }
+        }
+    }
+
+    fn validate_frames(&self, findings: &mut Vec<Finding>) {
+        for frame in &self.frames {
+            if frame.length == 0 || frame.length > 8 {
+                findings.push(Finding::InvalidFrameLength {
+                    frame: frame.name.clone(),
+                    length: frame.length,
+                });
+            }
+            for mapped in &frame.signals {
+                match self.signal(&mapped.name) {
+                    None => findings.push(Finding::UnknownSignal {
+                        frame: frame.name.clone(),
+                        signal: mapped.name.clone(),
+                    }),
+                    Some(signal) => {
+                        let end = usize::from(mapped.offset) + usize::from(signal.size);
+                        if end > usize::from(frame.length) * 8 {
+                            findings.push(Finding::SignalExceedsFrame {
+                                frame: frame.name.clone(),
+                                signal: mapped.name.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn validate_schedule_tables(&self, findings: &mut Vec<Finding>) {
+        for table in &self.schedule_tables {
+            for (index, entry) in table.entries.iter().enumerate() {
+                let data_length = match &entry.command {
+                    ScheduleCommand::Frame(name) => {
+                        if self.frame_id(name).is_none() {
+                            findings.push(Finding::UnknownFrame {
+                                table: table.name.clone(),
+                                frame: name.clone(),
+                            });
+                            continue;
+                        }
+                        // Sporadic and event triggered frames use the longest associated frame
+                        let associated = self
+                            .sporadic_frames
+                            .iter()
+                            .map(|f| (&f.name, &f.frames))
+                            .chain(
+                                self.event_triggered_frames
+                                    .iter()
+                                    .map(|f| (&f.name, &f.frames)),
+                            )
+                            .find(|(n, _)| *n == name)
+                            .map(|(_, frames)| frames);
+                        match associated {
+                            Some(frames) => frames
+                                .iter()
+                                .filter_map(|f| self.frame(f))
+                                .map(|f| f.length)
+                                .max()
+                                .unwrap_or(8),
+                            None => self.frame(name).map_or(8, |f| f.length),
+                        }
+                    }
+                    ScheduleCommand::AssignFrameIdRange {
+                        pids: Some(pids), ..
+                    } => {
+                        for pid in pids {
+                            // 0xFF marks an unassigned frame
+                            if *pid != 0xFF && PID::new(*pid).is_err() {
+                                findings.push(Finding::InvalidPid {
+                                    table: table.name.clone(),
+                                    pid: *pid,
+                                });
+                            }
+                        }
+                        8
+                    }
+                    _ => 8,
+                };
+                if self.speed.0 != 0 {
+                    let minimum = max_frame_time(data_length, self.speed);
+                    if entry.delay < minimum {
+                        findings.push(Finding::SlotTooShort {
+                            table: table.name.clone(),
+                            entry: index,
+                            delay: entry.delay,
+                            minimum,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ldf::model::*;
+    use alloc::vec;
+
+    fn frame(name: &str, id: u8, length: u8, signals: Vec<FrameSignal>) -> UnconditionalFrame {
+        UnconditionalFrame {
+            name: name.into(),
+            id,
+            publisher: "Sensor".into(),
+            length,
+            signals,
+        }
+    }
+
+    #[test]
+    fn test_max_frame_time() {
+        // 1.4 * (34 + 90) bit at 19.2 kbit/s
+        assert!((max_frame_time(8, LinSpeed(19200)) - 9.041).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut ldf = Ldf {
+            speed: LinSpeed(19200),
+            signals: vec![SignalDefinition {
+                name: "Temperature".into(),
+                size: 12,
+                ..Default::default()
+            }],
+            frames: vec![frame(
+                "SensorFrame",
+                0x10,
+                2,
+                vec![FrameSignal {
+                    name: "Temperature".into(),
+                    offset: 0,
+                }],
+            )],
+            schedule_tables: vec![ScheduleTable {
+                name: "Normal".into(),
+                entries: vec![ScheduleEntry {
+                    command: ScheduleCommand::Frame("SensorFrame".into()),
+                    delay: 10.0,
+                }],
+            }],
+            ..Default::default()
+        };
+        assert_eq!(ldf.validate(), vec![]);
+
+        ldf.frames.push(frame(
+            "Duplicate",
+            0x10,
+            9,
+            vec![
+                FrameSignal {
+                    name: "Temperature".into(),
+                    offset: 64,
+                },
+                FrameSignal {
+                    name: "Humidity".into(),
+                    offset: 0,
+                },
+            ],
+        ));
+        ldf.frames.push(frame("Reserved", 0x3E, 1, vec![]));
+        ldf.schedule_tables[0].entries.push(ScheduleEntry {
+            command: ScheduleCommand::AssignFrameIdRange {
+                node: "Sensor".into(),
+                frame_index: 0,
+                pids: Some([0x50, 0x10, 0xFF, 0xFF]),
+            },
+            delay: 5.0,
+        });
+        ldf.schedule_tables[0].entries.push(ScheduleEntry {
+            command: ScheduleCommand::Frame("Unknown".into()),
+            delay: 10.0,
+        });
+
+        let findings = ldf.validate();
+        assert_eq!(
+            findings[..6],
+            [
+                Finding::DuplicateFrameId {
+                    id: 0x10,
+                    first: "SensorFrame".into(),
+                    second: "Duplicate".into()
+                },
+                Finding::InvalidFrameId {
+                    frame: "Reserved".into(),
+                    id: 0x3E
+                },
+                Finding::InvalidFrameLength {
+                    frame: "Duplicate".into(),
+                    length: 9
+                },
+                Finding::SignalExceedsFrame {
+                    frame: "Duplicate".into(),
+                    signal: "Temperature".into()
+                },
+                Finding::UnknownSignal {
+                    frame: "Duplicate".into(),
+                    signal: "Humidity".into()
+                },
+                Finding::InvalidPid {
+                    table: "Normal".into(),
+                    pid: 0x10
+                },
+            ]
+        );
+        assert!(matches!(
+            findings[6],
+            Finding::SlotTooShort { entry: 1, .. }
+        ));
+        assert_eq!(
+            findings[7],
+            Finding::UnknownFrame {
+                table: "Normal".into(),
+                frame: "Unknown".into()
+            }
+        );
+        assert_eq!(findings.len(), 8);
+    }
+}
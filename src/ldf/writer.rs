@@ -0,0 +1,340 @@
+//! Serializes the LDF data model to the LDF text format
+
+use core::fmt::{self, Display, Formatter, Write};
+
+use super::model::*;
+
+fn write_list<W: Write>(w: &mut W, list: &[alloc::string::String]) -> fmt::Result {
+    for (i, item) in list.iter().enumerate() {
+        if i != 0 {
+            w.write_str(", ")?;
+        }
+        w.write_str(item)?;
+    }
+    Ok(())
+}
+
+fn write_signal_value<W: Write>(w: &mut W, value: &SignalValue) -> fmt::Result {
+    match value {
+        SignalValue::Scalar(value) => write!(w, "{}", value),
+        SignalValue::ByteArray(bytes) => {
+            w.write_char('{')?;
+            for (i, byte) in bytes.iter().enumerate() {
+                if i != 0 {
+                    w.write_str(", ")?;
+                }
+                write!(w, "{}", byte)?;
+            }
+            w.write_char('}')
+        }
+    }
+}
+
+fn write_frame_signals<W: Write>(w: &mut W, signals: &[FrameSignal]) -> fmt::Result {
+    writeln!(w, " {{")?;
+    for signal in signals {
+        writeln!(w, "    {}, {};", signal.name, signal.offset)?;
+    }
+    writeln!(w, "  }}")
+}
+
+fn write_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> fmt::Result {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i != 0 {
+            w.write_str(", ")?;
+        }
+        write!(w, "0x{:02X}", byte)?;
+    }
+    Ok(())
+}
+
+fn write_schedule_command<W: Write>(w: &mut W, command: &ScheduleCommand) -> fmt::Result {
+    match command {
+        ScheduleCommand::Frame(frame) => w.write_str(frame),
+        ScheduleCommand::MasterReq => w.write_str("MasterReq"),
+        ScheduleCommand::SlaveResp => w.write_str("SlaveResp"),
+        ScheduleCommand::AssignNAD { node } => write!(w, "AssignNAD {{ {} }}", node),
+        ScheduleCommand::ConditionalChangeNAD {
+            nad,
+            id,
+            byte,
+            mask,
+            inv,
+            new_nad,
+        } => {
+            w.write_str("ConditionalChangeNAD { ")?;
+            write_bytes(w, &[*nad, *id, *byte, *mask, *inv, *new_nad])?;
+            w.write_str(" }")
+        }
+        ScheduleCommand::DataDump { node, data } => {
+            write!(w, "DataDump {{ {}, ", node)?;
+            write_bytes(w, data)?;
+            w.write_str(" }")
+        }
+        ScheduleCommand::SaveConfiguration { node } => {
+            write!(w, "SaveConfiguration {{ {} }}", node)
+        }
+        ScheduleCommand::AssignFrameIdRange {
+            node,
+            frame_index,
+            pids,
+        } => {
+            write!(w, "AssignFrameIdRange {{ {}, {}", node, frame_index)?;
+            if let Some(pids) = pids {
+                w.write_str(", ")?;
+                write_bytes(w, pids)?;
+            }
+            w.write_str(" }")
+        }
+        ScheduleCommand::FreeFormat(data) => {
+            w.write_str("FreeFormat { ")?;
+            write_bytes(w, data)?;
+            w.write_str(" }")
+        }
+        ScheduleCommand::AssignFrameId { node, frame } => {
+            write!(w, "AssignFrameId {{ {}, {} }}", node, frame)
+        }
+        ScheduleCommand::UnassignFrameId { node, frame } => {
+            write!(w, "UnassignFrameId {{ {}, {} }}", node, frame)
+        }
+    }
+}
+
+impl Display for Ldf {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "LIN_description_file;")?;
+        writeln!(
+            f,
+            "LIN_protocol_version = \"{}\";",
+            self.protocol_version.as_ldf_str()
+        )?;
+        writeln!(f, "LIN_language_version = \"{}\";", self.language_version)?;
+        writeln!(f, "LIN_speed = {} kbps;", self.speed)?;
+        if let Some(channel_name) = &self.channel_name {
+            writeln!(f, "Channel_name = \"{}\";", channel_name)?;
+        }
+
+        writeln!(f, "\nNodes {{")?;
+        let master = &self.nodes.master;
+        writeln!(
+            f,
+            "  Master: {}, {} ms, {} ms;",
+            master.name, master.time_base, master.jitter
+        )?;
+        if !self.nodes.slaves.is_empty() {
+            f.write_str("  Slaves: ")?;
+            write_list(f, &self.nodes.slaves)?;
+            writeln!(f, ";")?;
+        }
+        writeln!(f, "}}")?;
+
+        if !self.node_compositions.is_empty() {
+            writeln!(f, "\nNode_composition {{")?;
+            for composition in &self.node_compositions {
+                writeln!(f, "  configuration {} {{", composition.name)?;
+                for composite in &composition.composite_nodes {
+                    write!(f, "    {} {{ ", composite.name)?;
+                    write_list(f, &composite.logical_nodes)?;
+                    writeln!(f, "; }}")?;
+                }
+                writeln!(f, "  }}")?;
+            }
+            writeln!(f, "}}")?;
+        }
+
+        writeln!(f, "\nSignals {{")?;
+        for signal in &self.signals {
+            write!(f, "  {}: {}, ", signal.name, signal.size)?;
+            write_signal_value(f, &signal.init_value)?;
+            write!(f, ", {}", signal.publisher)?;
+            for subscriber in &signal.subscribers {
+                write!(f, ", {}", subscriber)?;
+            }
+            writeln!(f, ";")?;
+        }
+        writeln!(f, "}}")?;
+
+        if !self.diagnostic_signals.is_empty() {
+            writeln!(f, "\nDiagnostic_signals {{")?;
+            for signal in &self.diagnostic_signals {
+                write!(f, "  {}: {}, ", signal.name, signal.size)?;
+                write_signal_value(f, &signal.init_value)?;
+                writeln!(f, ";")?;
+            }
+            writeln!(f, "}}")?;
+        }
+
+        writeln!(f, "\nFrames {{")?;
+        for frame in &self.frames {
+            write!(
+                f,
+                "  {}: 0x{:02X}, {}, {}",
+                frame.name, frame.id, frame.publisher, frame.length
+            )?;
+            write_frame_signals(f, &frame.signals)?;
+        }
+        writeln!(f, "}}")?;
+
+        if !self.sporadic_frames.is_empty() {
+            writeln!(f, "\nSporadic_frames {{")?;
+            for frame in &self.sporadic_frames {
+                write!(f, "  {}: ", frame.name)?;
+                write_list(f, &frame.frames)?;
+                writeln!(f, ";")?;
+            }
+            writeln!(f, "}}")?;
+        }
+
+        if !self.event_triggered_frames.is_empty() {
+            writeln!(f, "\nEvent_triggered_frames {{")?;
+            for frame in &self.event_triggered_frames {
+                write!(f, "  {}: ", frame.name)?;
+                if let Some(table) = &frame.collision_resolving_schedule_table {
+                    write!(f, "{}, ", table)?;
+                }
+                write!(f, "0x{:02X}", frame.id)?;
+                for associated in &frame.frames {
+                    write!(f, ", {}", associated)?;
+                }
+                writeln!(f, ";")?;
+            }
+            writeln!(f, "}}")?;
+        }
+
+        if !self.diagnostic_frames.is_empty() {
+            writeln!(f, "\nDiagnostic_frames {{")?;
+            for frame in &self.diagnostic_frames {
+                write!(f, "  {}: 0x{:02X}", frame.name, frame.id)?;
+                write_frame_signals(f, &frame.signals)?;
+            }
+            writeln!(f, "}}")?;
+        }
+
+        if !self.node_attributes.is_empty() {
+            writeln!(f, "\nNode_attributes {{")?;
+            for node in &self.node_attributes {
+                let attributes = &node.attributes;
+                writeln!(f, "  {} {{", node.name)?;
+                writeln!(
+                    f,
+                    "    LIN_protocol = \"{}\";",
+                    attributes.protocol_version.as_ldf_str()
+                )?;
+                writeln!(
+                    f,
+                    "    configured_NAD = 0x{:02X};",
+                    attributes.configured_nad.0
+                )?;
+                writeln!(f, "    initial_NAD = 0x{:02X};", attributes.initial_nad.0)?;
+                writeln!(
+                    f,
+                    "    product_id = 0x{:04X}, 0x{:04X}, {};",
+                    attributes.product_id.supplier_id,
+                    attributes.product_id.function_id,
+                    attributes.product_id.variant
+                )?;
+                if let Some(response_error) = &node.response_error {
+                    writeln!(f, "    response_error = {};", response_error)?;
+                }
+                if !node.fault_state_signals.is_empty() {
+                    f.write_str("    fault_state_signals = ")?;
+                    write_list(f, &node.fault_state_signals)?;
+                    writeln!(f, ";")?;
+                }
+                writeln!(f, "    P2_min = {} ms;", attributes.p2_min)?;
+                writeln!(f, "    ST_min = {} ms;", attributes.st_min)?;
+                writeln!(f, "    N_As_timeout = {} ms;", attributes.n_as_timeout)?;
+                writeln!(f, "    N_Cr_timeout = {} ms;", attributes.n_cr_timeout)?;
+                if !node.configurable_frames.is_empty() {
+                    writeln!(f, "    configurable_frames {{")?;
+                    for frame in &node.configurable_frames {
+                        match frame.message_id {
+                            Some(id) => writeln!(f, "      {} = 0x{:04X};", frame.name, id)?,
+                            None => writeln!(f, "      {};", frame.name)?,
+                        }
+                    }
+                    writeln!(f, "    }}")?;
+                }
+                writeln!(
+                    f,
+                    "    response_tolerance = {} %;",
+                    attributes.response_tolerance.0
+                )?;
+                writeln!(f, "  }}")?;
+            }
+            writeln!(f, "}}")?;
+        }
+
+        if !self.schedule_tables.is_empty() {
+            writeln!(f, "\nSchedule_tables {{")?;
+            for table in &self.schedule_tables {
+                writeln!(f, "  {} {{", table.name)?;
+                for entry in &table.entries {
+                    f.write_str("    ")?;
+                    write_schedule_command(f, &entry.command)?;
+                    writeln!(f, " delay {} ms;", entry.delay)?;
+                }
+                writeln!(f, "  }}")?;
+            }
+            writeln!(f, "}}")?;
+        }
+
+        if !self.signal_encoding_types.is_empty() {
+            writeln!(f, "\nSignal_encoding_types {{")?;
+            for encoding in &self.signal_encoding_types {
+                writeln!(f, "  {} {{", encoding.name)?;
+                for value in &encoding.values {
+                    f.write_str("    ")?;
+                    match value {
+                        EncodingValue::Logical { value, text } => {
+                            write!(f, "logical_value, {}", value)?;
+                            if let Some(text) = text {
+                                write!(f, ", \"{}\"", text)?;
+                            }
+                        }
+                        EncodingValue::Physical {
+                            min,
+                            max,
+                            scale,
+                            offset,
+                            unit,
+                        } => {
+                            write!(f, "physical_value, {}, {}, {}, {}", min, max, scale, offset)?;
+                            if let Some(unit) = unit {
+                                write!(f, ", \"{}\"", unit)?;
+                            }
+                        }
+                        EncodingValue::Bcd => f.write_str("bcd_value")?,
+                        EncodingValue::Ascii => f.write_str("ascii_value")?,
+                    }
+                    writeln!(f, ";")?;
+                }
+                writeln!(f, "  }}")?;
+            }
+            writeln!(f, "}}")?;
+        }
+
+        if !self.signal_representations.is_empty() {
+            writeln!(f, "\nSignal_representation {{")?;
+            for representation in &self.signal_representations {
+                write!(f, "  {}: ", representation.encoding)?;
+                write_list(f, &representation.signals)?;
+                writeln!(f, ";")?;
+            }
+            writeln!(f, "}}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "ldf-parse"))]
+mod tests {
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_roundtrip() {
+        let ldf = crate::ldf::parse(crate::ldf::parser::tests::EXAMPLE_LDF).unwrap();
+        let text = ldf.to_string();
+        assert_eq!(crate::ldf::parse(&text).unwrap(), ldf);
+    }
+}
@@ -1,16 +1,154 @@
 #![no_std]
+#![cfg_attr(
+    all(feature = "panic-free", not(test)),
+    deny(
+        clippy::panic,
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::unreachable,
+        clippy::todo,
+        clippy::unimplemented
+    )
+)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+// Instrumentation forwarding to the `log` crate with the `log` feature. Without it the
+// arguments are only type checked, so no code is generated.
+macro_rules! log {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        ::log::$level!($($arg)+);
+        #[cfg(not(feature = "log"))]
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    }};
+}
+
+#[cfg(feature = "can")]
+pub mod can;
+pub mod cluster;
+#[cfg(feature = "diagnostic")]
+pub mod diag;
 pub mod driver;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod frame;
+pub mod idle;
+pub mod iso17987;
+pub mod j2602;
 pub mod ldf;
 pub mod master;
+pub mod monitor;
+#[cfg(feature = "diagnostic")]
+pub mod nm;
+#[cfg(feature = "schedule")]
+pub mod schedule;
+pub mod signal;
+pub mod time;
+pub mod trace;
 
-pub use crate::frame::{checksum, classic_checksum, Frame, PID};
+pub use crate::frame::{checksum, classic_checksum, ChecksumModel, Frame, ParsePolicy, PID};
+#[cfg(feature = "async")]
+pub use crate::master::AsyncMaster;
 pub use crate::master::Master;
+pub use crate::signal::Signal;
+
+use core::fmt;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     Timeout,
     PhysicalBus,
-    Checksum,
+    /// The checksum of a received response is wrong
+    Checksum(frame::ChecksumMismatch),
+    /// The frame is not part of the cluster configuration
+    UnknownFrame,
+    /// The bus is permanently at the dominant level
+    BusStuckDominant,
+    /// The bus line is shorted to ground
+    ShortToGround,
+    /// The bus line is shorted to the battery supply
+    ShortToBattery,
+    /// The transceiver has no bus supply (VBAT)
+    NoBusSupply,
+    /// No byte of the response was received
+    NoResponse,
+    /// A byte was received with a framing or parity error
+    Framing,
+    /// The read back of a transmitted byte differs, e.g. because another node transmitted at the
+    /// same time
+    Collision,
+    /// The response is incomplete, only the given number of bytes was received
+    Partial(usize),
+    /// Invalid frame parameters, e.g. a data length differing from the length mandated for the
+    /// frame ID. More than 8 data bytes are only reported with the `panic-free` feature, which
+    /// replaces the assertion of the length.
+    Frame(frame::FrameError),
+    /// A configuration parameter is outside the range allowed by the standard, e.g. the length
+    /// of the wakeup signal
+    InvalidConfig,
+}
+
+impl Error {
+    /// Whether the error is a physical fault of the bus, which persists until it is repaired
+    pub fn is_bus_fault(&self) -> bool {
+        matches!(
+            self,
+            Error::BusStuckDominant
+                | Error::ShortToGround
+                | Error::ShortToBattery
+                | Error::NoBusSupply
+        )
+    }
+
+    /// The error for a response which ended after `received` bytes, `Error::NoResponse` or
+    /// `Error::Partial`
+    pub fn incomplete_response(received: usize) -> Error {
+        match received {
+            0 => Error::NoResponse,
+            received => Error::Partial(received),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Timeout => f.write_str("timeout"),
+            Error::PhysicalBus => f.write_str("physical bus error"),
+            Error::Checksum(mismatch) => write!(
+                f,
+                "checksum mismatch, expected {:#04X} but received {:#04X}",
+                mismatch.expected, mismatch.received
+            ),
+            Error::UnknownFrame => f.write_str("frame is not part of the cluster configuration"),
+            Error::BusStuckDominant => f.write_str("bus stuck at the dominant level"),
+            Error::ShortToGround => f.write_str("bus shorted to ground"),
+            Error::ShortToBattery => f.write_str("bus shorted to battery"),
+            Error::NoBusSupply => f.write_str("no bus supply"),
+            Error::NoResponse => f.write_str("no response"),
+            Error::Framing => f.write_str("framing or parity error"),
+            Error::Collision => f.write_str("read back differs from transmitted byte"),
+            Error::Partial(received) => {
+                write!(f, "incomplete response, received {} bytes", received)
+            }
+            Error::Frame(e) => write!(f, "invalid frame: {}", e),
+            Error::InvalidConfig => f.write_str("configuration outside of the allowed range"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl From<frame::FrameError> for Error {
+    fn from(e: frame::FrameError) -> Error {
+        Error::Frame(e)
+    }
 }
@@ -1,14 +1,236 @@
 //! LIN bus master implementation
+use crate::cluster::ClusterConfig;
 use crate::driver;
-use crate::frame::Frame;
+use crate::frame::{self, ChecksumAccumulator, ChecksumModel, Frame, FrameError};
+use crate::time::{Duration, Instant};
 use crate::PID;
-use crate::{checksum, classic_checksum};
+use core::task::Poll;
 
 pub trait Master {
-    type Error;
+    /// Convertible from and into `driver::Error` like the errors of the drivers, so the
+    /// default methods can report errors
+    type Error: Into<driver::Error> + From<driver::Error>;
     fn send_wakeup(&mut self) -> Result<(), Self::Error>;
     fn write_frame(&mut self, frame: &Frame) -> Result<(), Self::Error>;
     fn read_frame(&mut self, pid: PID, data_lengh: usize) -> Result<Frame, Self::Error>;
+    /// Read a frame like `read_frame` into `frame` instead of returning it, so no temporary
+    /// frame is copied up the call stack. The content of `frame` is unspecified on errors.
+    fn read_frame_into(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+        frame: &mut Frame,
+    ) -> Result<(), Self::Error> {
+        *frame = self.read_frame(pid, data_length)?;
+        Ok(())
+    }
+    /// Read a frame verifying the checksum with the given model instead of the default model of
+    /// the PID. Needed for LIN 1.3 slaves which use the classic checksum for all frames.
+    ///
+    /// The default suits adapters verifying the checksum themselves: it reads the frame with
+    /// `read_frame` and returns its data with the checksum of `checksum_model`.
+    fn read_frame_with_checksum_model(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+        checksum_model: ChecksumModel,
+    ) -> Result<Frame, Self::Error> {
+        let frame = self.read_frame(pid, data_length)?;
+        if frame.get_checksum_model() == checksum_model {
+            return Ok(frame);
+        }
+        Frame::try_from_data_with_checksum_model(pid, frame.get_data(), checksum_model)
+            .map_err(|e| driver::Error::Frame(e).into())
+    }
+    /// Read a frame taking the data length and checksum model from the cluster configuration.
+    /// Fails with `Error::UnknownFrame` if the frame is not configured.
+    fn read_configured_frame(
+        &mut self,
+        pid: PID,
+        cluster: &ClusterConfig,
+    ) -> Result<Frame, Self::Error> {
+        let unknown = || Self::Error::from(driver::Error::UnknownFrame);
+        let data_length = cluster.data_length(pid.get_id()).ok_or_else(unknown)?;
+        let checksum_model = cluster.checksum_model(pid.get_id()).ok_or_else(unknown)?;
+        self.read_frame_with_checksum_model(pid, data_length, checksum_model)
+    }
+    /// Read a frame like `read_frame`, but return a missing or incomplete response as
+    /// `Response` instead of failing, so diagnostics can tell an absent slave from one
+    /// answering with the wrong length
+    ///
+    /// The default reads the frame with `read_frame` and returns `Error::NoResponse` as
+    /// `Response::NoResponse`. `Error::Partial` remains an error, as the received bytes are
+    /// unknown.
+    fn read_response(&mut self, pid: PID, data_length: usize) -> Result<Response, Self::Error> {
+        match self.read_frame(pid, data_length).map_err(Into::into) {
+            Ok(frame) => Ok(Response::Complete(frame)),
+            Err(driver::Error::NoResponse) => Ok(Response::NoResponse),
+            Err(e) => Err(e.into()),
+        }
+    }
+    /// Publish `data` followed by `checksum` without copying them into a `Frame`, e.g. to
+    /// forward a payload received on another bus. The checksum is sent as it is, so it can be
+    /// calculated separately with `ChecksumModel::calculate`. The default copies them into a
+    /// frame for `write_frame`.
+    fn write_payload(&mut self, pid: PID, data: &[u8], checksum: u8) -> Result<(), Self::Error> {
+        check_response_length(pid, data.len())?;
+        let mut frame = Frame {
+            pid,
+            data_length: data.len() as u8,
+            buffer: [0u8; 9],
+            checksum_model: pid.checksum_model(),
+        };
+        frame.buffer[..data.len()].copy_from_slice(data);
+        frame.buffer[data.len()] = checksum;
+        self.write_frame(&frame)
+    }
+}
+
+/// Response to a header, see `Master::read_response`
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Response {
+    /// The complete response with a correct checksum
+    Complete(Frame),
+    /// The slave sent less bytes than requested
+    Partial(PartialResponse),
+    /// No slave responded
+    NoResponse,
+}
+
+/// Bytes of an incomplete response
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PartialResponse {
+    pid: PID,
+    bytes: [u8; 9],
+    received: usize,
+}
+
+impl PartialResponse {
+    pub fn new(pid: PID, bytes: &[u8]) -> PartialResponse {
+        let received = bytes.len().min(9);
+        let mut response = PartialResponse {
+            pid,
+            bytes: [0u8; 9],
+            received,
+        };
+        response.bytes[..received].copy_from_slice(&bytes[..received]);
+        response
+    }
+
+    pub fn pid(&self) -> PID {
+        self.pid
+    }
+
+    /// The received bytes, the last one is not necessarily the checksum
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.received]
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PartialResponse {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "PartialResponse {{ pid: {}, bytes: {=[u8]:02x} }}",
+            self.pid,
+            self.bytes()
+        )
+    }
+}
+
+/// Assert that a requested response fits into a frame, the `panic-free` feature turns the
+/// assertion into an error
+pub(crate) fn check_data_length(data_length: usize) -> Result<(), driver::Error> {
+    #[cfg(not(feature = "panic-free"))]
+    assert!(data_length <= 8, "Maximum data length is 8 bytes");
+    if data_length > 8 {
+        return Err(driver::Error::Frame(FrameError::DataTooLong(data_length)));
+    }
+    Ok(())
+}
+
+/// Check the requested response of `pid`, like `check_data_length`, and fail if the length
+/// differs from the length mandated for the ID
+pub(crate) fn check_response_length(pid: PID, data_length: usize) -> Result<(), driver::Error> {
+    check_data_length(data_length)?;
+    frame::check_known_length(pid, data_length)?;
+    Ok(())
+}
+
+fn log_header(pid: PID) {
+    log!(trace, "header {:#04x} sent", pid.get_id());
+}
+
+/// Verify the checksum of a received frame unless the driver already did
+fn check_frame<E: From<driver::Error>>(frame: &Frame, verified: bool) -> Result<(), E> {
+    let result = match verified {
+        true => Ok(()),
+        false => frame.check_checksum(),
+    };
+    match result {
+        Ok(()) => {
+            log!(
+                debug,
+                "response {:02x?} to {:#04x} received",
+                frame.get_data(),
+                frame.pid.get_id()
+            );
+            Ok(())
+        }
+        Err(mismatch) => {
+            log!(
+                warn,
+                "checksum error in frame {:#04x}: expected {:#04x}, received {:#04x}",
+                mismatch.pid.get_id(),
+                mismatch.expected,
+                mismatch.received
+            );
+            Err(E::from(driver::Error::Checksum(mismatch)))
+        }
+    }
+}
+
+fn into_frame<E: From<driver::Error>>(frame: Frame, verified: bool) -> Result<Frame, E> {
+    check_frame(&frame, verified).map(|()| frame)
+}
+
+/// Prepare `frame` for receiving `data_length` bytes of `pid` in place
+fn reset_frame(frame: &mut Frame, pid: PID, data_length: usize, checksum_model: ChecksumModel) {
+    frame.pid = pid;
+    frame.data_length = data_length as u8;
+    frame.checksum_model = checksum_model;
+}
+
+/// Turn the result of reading the response into `frame` into a `Response`. Drivers report
+/// incomplete responses with `Error::Partial` and leave the received bytes in the buffer.
+fn into_response<E>(frame: Frame, result: Result<bool, E>) -> Result<Response, E>
+where
+    E: Into<driver::Error> + From<driver::Error>,
+{
+    match result.map_err(Into::into) {
+        Ok(verified) => into_frame(frame, verified).map(Response::Complete),
+        Err(driver::Error::NoResponse) => {
+            log!(debug, "no response to {:#04x}", frame.pid.get_id());
+            Ok(Response::NoResponse)
+        }
+        Err(driver::Error::Partial(received)) => {
+            let received = received.min(frame.data_len());
+            log!(
+                debug,
+                "incomplete response to {:#04x}: {} of {} bytes",
+                frame.pid.get_id(),
+                received,
+                frame.data_len() + 1
+            );
+            Ok(Response::Partial(PartialResponse::new(
+                frame.pid,
+                &frame.buffer[..received],
+            )))
+        }
+        Err(e) => Err(E::from(e)),
+    }
 }
 
 impl<Driver> Master for Driver
@@ -23,34 +245,376 @@ where
 
     fn write_frame(&mut self, frame: &Frame) -> Result<(), Driver::Error> {
         self.send_header(frame.get_pid())?;
+        log_header(frame.get_pid());
         self.write(frame.get_data_with_checksum())
     }
 
     fn read_frame(&mut self, pid: PID, data_length: usize) -> Result<Frame, Driver::Error> {
-        assert!(data_length <= 8, "Maximum data length is 8 bytes");
+        self.read_frame_with_checksum_model(pid, data_length, pid.checksum_model())
+    }
+
+    fn read_frame_into(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+        frame: &mut Frame,
+    ) -> Result<(), Driver::Error> {
+        check_response_length(pid, data_length)?;
         self.send_header(pid)?;
+        log_header(pid);
+        reset_frame(frame, pid, data_length, pid.checksum_model());
+        let accumulator = ChecksumAccumulator::new(pid);
+        let verified = self.read_verified(&mut frame.buffer[0..=data_length], accumulator)?;
+        check_frame(frame, verified)
+    }
+
+    fn read_frame_with_checksum_model(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+        checksum_model: ChecksumModel,
+    ) -> Result<Frame, Driver::Error> {
+        check_response_length(pid, data_length)?;
+        self.send_header(pid)?;
+        log_header(pid);
         let mut frame = Frame {
             pid,
-            data_length,
+            data_length: data_length as u8,
             buffer: [0u8; 9],
+            checksum_model,
         };
-        self.read(&mut frame.buffer[0..=data_length])?;
+        let accumulator = ChecksumAccumulator::with_checksum_model(pid, checksum_model);
+        let verified = self.read_verified(&mut frame.buffer[0..=data_length], accumulator)?;
+        into_frame(frame, verified)
+    }
 
-        let checksum = {
-            if pid.uses_classic_checksum() {
-                classic_checksum(&frame.buffer[0..data_length])
-            } else {
-                checksum(pid, &frame.buffer[0..data_length])
-            }
+    /// Errors of the driver are converted to `driver::Error` to detect incomplete responses
+    fn read_response(&mut self, pid: PID, data_length: usize) -> Result<Response, Driver::Error> {
+        check_response_length(pid, data_length)?;
+        self.send_header(pid)?;
+        log_header(pid);
+        let mut frame = Frame {
+            pid,
+            data_length: data_length as u8,
+            buffer: [0u8; 9],
+            checksum_model: pid.checksum_model(),
+        };
+        let accumulator = ChecksumAccumulator::new(pid);
+        let result = self.read_verified(&mut frame.buffer[0..=data_length], accumulator);
+        into_response(frame, result)
+    }
+
+    fn write_payload(&mut self, pid: PID, data: &[u8], checksum: u8) -> Result<(), Driver::Error> {
+        check_response_length(pid, data.len())?;
+        self.send_header(pid)?;
+        log_header(pid);
+        self.write(data)?;
+        self.write(&[checksum])
+    }
+}
+
+/// Start of the header and end of the response of a frame, see `driver::Timestamping`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameTimestamps {
+    /// Start of the break
+    pub header_start: Option<Instant>,
+    /// End of the last response byte
+    pub response_end: Option<Instant>,
+}
+
+impl FrameTimestamps {
+    /// Duration from the start of the header to the end of the response, `None` if a
+    /// timestamp is missing or the response ended before the header
+    pub fn frame_time(&self) -> Option<Duration> {
+        let (start, end) = (self.header_start?, self.response_end?);
+        (end >= start).then(|| end - start)
+    }
+}
+
+/// `Master` with timestamps, implemented for all drivers supporting `driver::Timestamping`
+pub trait TimestampedMaster: Master {
+    /// Write a frame and return when it was transmitted
+    fn write_frame_timestamped(&mut self, frame: &Frame) -> Result<FrameTimestamps, Self::Error>;
+    /// Read a frame together with the time it was received
+    fn read_frame_timestamped(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+    ) -> Result<(Frame, FrameTimestamps), Self::Error>;
+}
+
+impl<Driver> TimestampedMaster for Driver
+where
+    Driver: driver::Master + driver::Timestamping,
+{
+    fn write_frame_timestamped(&mut self, frame: &Frame) -> Result<FrameTimestamps, Driver::Error> {
+        self.write_frame(frame)?;
+        Ok(FrameTimestamps {
+            header_start: self.header_timestamp(),
+            response_end: self.response_timestamp(),
+        })
+    }
+
+    fn read_frame_timestamped(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+    ) -> Result<(Frame, FrameTimestamps), Driver::Error> {
+        let frame = self.read_frame(pid, data_length)?;
+        let timestamps = FrameTimestamps {
+            header_start: self.header_timestamp(),
+            response_end: self.response_timestamp(),
+        };
+        Ok((frame, timestamps))
+    }
+}
+
+/// Write of a frame started on a `driver::SplitMaster`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PendingWrite;
+
+impl PendingWrite {
+    /// Start writing `frame`
+    pub fn start<Driver: driver::SplitMaster>(
+        driver: &mut Driver,
+        frame: &Frame,
+    ) -> Result<PendingWrite, Driver::Error> {
+        driver.start_write(frame.get_pid(), frame.get_data_with_checksum())?;
+        Ok(PendingWrite)
+    }
+
+    /// Check whether the frame was transmitted
+    pub fn poll<Driver: driver::SplitMaster>(
+        &self,
+        driver: &mut Driver,
+    ) -> Poll<Result<(), Driver::Error>> {
+        driver.poll_complete(&mut [])
+    }
+}
+
+/// Read of a frame started on a `driver::SplitMaster`. The checksum is verified when the
+/// transfer completes, so the application is free to do other work while the driver receives
+/// the response.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PendingRead {
+    pid: PID,
+    data_length: usize,
+    checksum_model: ChecksumModel,
+}
+
+impl PendingRead {
+    /// Start reading a frame with the default checksum model of the PID
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data_length` exceeds 8 bytes, with the `panic-free` feature it fails with
+    /// `Error::Frame` instead.
+    pub fn start<Driver: driver::SplitMaster>(
+        driver: &mut Driver,
+        pid: PID,
+        data_length: usize,
+    ) -> Result<PendingRead, Driver::Error> {
+        PendingRead::start_with_checksum_model(driver, pid, data_length, pid.checksum_model())
+    }
+
+    /// Start reading a frame, see `Master::read_frame_with_checksum_model`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data_length` exceeds 8 bytes, with the `panic-free` feature it fails with
+    /// `Error::Frame` instead.
+    pub fn start_with_checksum_model<Driver: driver::SplitMaster>(
+        driver: &mut Driver,
+        pid: PID,
+        data_length: usize,
+        checksum_model: ChecksumModel,
+    ) -> Result<PendingRead, Driver::Error> {
+        check_response_length(pid, data_length)?;
+        driver.start_read(pid, data_length + 1)?;
+        Ok(PendingRead {
+            pid,
+            data_length,
+            checksum_model,
+        })
+    }
+
+    /// Check whether the response was received and return the frame once it is complete
+    pub fn poll<Driver: driver::SplitMaster>(
+        &self,
+        driver: &mut Driver,
+    ) -> Poll<Result<Frame, Driver::Error>> {
+        let mut frame = Frame {
+            pid: self.pid,
+            data_length: self.data_length as u8,
+            buffer: [0u8; 9],
+            checksum_model: self.checksum_model,
         };
-        if checksum != frame.buffer[data_length] {
-            Err(Driver::Error::from(driver::Error::Checksum))
-        } else {
-            Ok(frame)
+        match driver.poll_complete(&mut frame.buffer[0..=self.data_length]) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Ready(Ok(())) => Poll::Ready(
+                frame
+                    .verify_checksum()
+                    .map_err(|mismatch| Driver::Error::from(driver::Error::Checksum(mismatch))),
+            ),
         }
     }
 }
 
+/// Asynchronous variant of `Master`, implemented for all `driver::AsyncMaster` drivers
+#[cfg(feature = "async")]
+#[cfg_attr(feature = "async", allow(async_fn_in_trait))]
+pub trait AsyncMaster {
+    type Error;
+    async fn send_wakeup(&mut self) -> Result<(), Self::Error>;
+    async fn write_frame(&mut self, frame: &Frame) -> Result<(), Self::Error>;
+    async fn read_frame(&mut self, pid: PID, data_length: usize) -> Result<Frame, Self::Error>;
+    /// See `Master::read_frame_into`
+    async fn read_frame_into(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+        frame: &mut Frame,
+    ) -> Result<(), Self::Error> {
+        *frame = self.read_frame(pid, data_length).await?;
+        Ok(())
+    }
+    /// See `Master::read_frame_with_checksum_model`
+    async fn read_frame_with_checksum_model(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+        checksum_model: ChecksumModel,
+    ) -> Result<Frame, Self::Error>;
+    /// See `Master::read_configured_frame`
+    async fn read_configured_frame(
+        &mut self,
+        pid: PID,
+        cluster: &ClusterConfig<'_>,
+    ) -> Result<Frame, Self::Error>;
+    /// See `Master::read_response`
+    async fn read_response(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+    ) -> Result<Response, Self::Error>;
+    /// See `Master::write_payload`
+    async fn write_payload(
+        &mut self,
+        pid: PID,
+        data: &[u8],
+        checksum: u8,
+    ) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "async")]
+impl<Driver> AsyncMaster for Driver
+where
+    Driver: driver::AsyncMaster,
+{
+    type Error = Driver::Error;
+
+    async fn send_wakeup(&mut self) -> Result<(), Driver::Error> {
+        Driver::send_wakeup(self).await
+    }
+
+    async fn write_frame(&mut self, frame: &Frame) -> Result<(), Driver::Error> {
+        self.send_header(frame.get_pid()).await?;
+        log_header(frame.get_pid());
+        self.write(frame.get_data_with_checksum()).await
+    }
+
+    async fn read_frame(&mut self, pid: PID, data_length: usize) -> Result<Frame, Driver::Error> {
+        self.read_frame_with_checksum_model(pid, data_length, pid.checksum_model())
+            .await
+    }
+
+    async fn read_frame_into(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+        frame: &mut Frame,
+    ) -> Result<(), Driver::Error> {
+        check_response_length(pid, data_length)?;
+        self.send_header(pid).await?;
+        log_header(pid);
+        reset_frame(frame, pid, data_length, pid.checksum_model());
+        let accumulator = ChecksumAccumulator::new(pid);
+        let verified = self
+            .read_verified(&mut frame.buffer[0..=data_length], accumulator)
+            .await?;
+        check_frame(frame, verified)
+    }
+
+    async fn read_frame_with_checksum_model(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+        checksum_model: ChecksumModel,
+    ) -> Result<Frame, Driver::Error> {
+        check_response_length(pid, data_length)?;
+        self.send_header(pid).await?;
+        log_header(pid);
+        let mut frame = Frame {
+            pid,
+            data_length: data_length as u8,
+            buffer: [0u8; 9],
+            checksum_model,
+        };
+        let accumulator = ChecksumAccumulator::with_checksum_model(pid, checksum_model);
+        let verified = self
+            .read_verified(&mut frame.buffer[0..=data_length], accumulator)
+            .await?;
+        into_frame(frame, verified)
+    }
+
+    async fn read_configured_frame(
+        &mut self,
+        pid: PID,
+        cluster: &ClusterConfig<'_>,
+    ) -> Result<Frame, Driver::Error> {
+        let unknown = || Driver::Error::from(driver::Error::UnknownFrame);
+        let data_length = cluster.data_length(pid.get_id()).ok_or_else(unknown)?;
+        let checksum_model = cluster.checksum_model(pid.get_id()).ok_or_else(unknown)?;
+        self.read_frame_with_checksum_model(pid, data_length, checksum_model)
+            .await
+    }
+
+    async fn read_response(
+        &mut self,
+        pid: PID,
+        data_length: usize,
+    ) -> Result<Response, Driver::Error> {
+        check_response_length(pid, data_length)?;
+        self.send_header(pid).await?;
+        log_header(pid);
+        let mut frame = Frame {
+            pid,
+            data_length: data_length as u8,
+            buffer: [0u8; 9],
+            checksum_model: pid.checksum_model(),
+        };
+        let accumulator = ChecksumAccumulator::new(pid);
+        let result = self
+            .read_verified(&mut frame.buffer[0..=data_length], accumulator)
+            .await;
+        into_response(frame, result)
+    }
+
+    async fn write_payload(
+        &mut self,
+        pid: PID,
+        data: &[u8],
+        checksum: u8,
+    ) -> Result<(), Driver::Error> {
+        check_response_length(pid, data.len())?;
+        self.send_header(pid).await?;
+        log_header(pid);
+        self.write(data).await?;
+        self.write(&[checksum]).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,6 +633,7 @@ mod tests {
                 pid: PID::new(0xDD).unwrap(),
                 buffer: [0x01, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
                 data_length: 1,
+                checksum_model: ChecksumModel::Enhanced,
             },
         }];
         for d in &test_data {
@@ -112,9 +677,362 @@ mod tests {
         }
     }
 
+    /// Driver advancing a clock by 1 ms per call and responding with a fixed response
+    struct Clocked {
+        now: u64,
+        header: Option<u64>,
+        response: Option<u64>,
+    }
+
+    impl driver::Master for Clocked {
+        type Error = crate::Error;
+
+        fn send_wakeup(&mut self) -> Result<(), crate::Error> {
+            Ok(())
+        }
+
+        fn send_header(&mut self, _pid: PID) -> Result<(), crate::Error> {
+            self.now += 1000;
+            self.header = Some(self.now);
+            Ok(())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<(), crate::Error> {
+            buf.copy_from_slice(&[0x01, 0xAE][..buf.len()]);
+            self.now += 1000;
+            self.response = Some(self.now);
+            Ok(())
+        }
+
+        fn write(&mut self, _data: &[u8]) -> Result<(), crate::Error> {
+            self.now += 1000;
+            self.response = Some(self.now);
+            Ok(())
+        }
+    }
+
+    impl driver::Timestamping for Clocked {
+        fn header_timestamp(&self) -> Option<Instant> {
+            self.header.map(Instant::from_micros)
+        }
+
+        fn response_timestamp(&self) -> Option<Instant> {
+            self.response.map(Instant::from_micros)
+        }
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let mut driver = Clocked {
+            now: 0,
+            header: None,
+            response: None,
+        };
+        let (frame, timestamps) = driver
+            .read_frame_timestamped(PID::from_id(0x10), 1)
+            .unwrap();
+        assert_eq!(frame.get_data(), &[0x01]);
+        assert_eq!(
+            timestamps,
+            FrameTimestamps {
+                header_start: Some(Instant::from_micros(1000)),
+                response_end: Some(Instant::from_micros(2000))
+            }
+        );
+        let timestamps = driver.write_frame_timestamped(&frame).unwrap();
+        assert_eq!(timestamps.header_start, Some(Instant::from_micros(3000)));
+        assert_eq!(timestamps.frame_time(), Some(Duration::from_micros(1000)));
+    }
+
+    /// DMA driver completing a transfer on the second poll, replying with a prepared response
+    struct Dma {
+        polls: usize,
+        started: Option<(PID, usize)>,
+        response: [u8; 2],
+    }
+
+    impl driver::SplitMaster for Dma {
+        type Error = driver::Error;
+
+        fn start_write(&mut self, pid: PID, data: &[u8]) -> Result<(), driver::Error> {
+            self.started = Some((pid, data.len()));
+            Ok(())
+        }
+
+        fn start_read(&mut self, pid: PID, length: usize) -> Result<(), driver::Error> {
+            self.started = Some((pid, length));
+            Ok(())
+        }
+
+        fn poll_complete(&mut self, buf: &mut [u8]) -> Poll<Result<(), driver::Error>> {
+            self.polls += 1;
+            if self.polls < 2 {
+                return Poll::Pending;
+            }
+            self.polls = 0;
+            buf.copy_from_slice(&self.response[..buf.len()]);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_split_transfer() {
+        let pid = PID::from_id(0x10);
+        let mut driver = Dma {
+            polls: 0,
+            started: None,
+            response: [0x01, 0xAE],
+        };
+        let read = PendingRead::start(&mut driver, pid, 1).unwrap();
+        assert_eq!(driver.started, Some((pid, 2)));
+        assert_eq!(read.poll(&mut driver), Poll::Pending);
+        let frame = Frame::from_data(pid, &[0x01]);
+        assert_eq!(
+            read.poll(&mut driver),
+            Poll::Ready(Ok(Frame::from_data(pid, &[0x01])))
+        );
+
+        let write = PendingWrite::start(&mut driver, &frame).unwrap();
+        assert_eq!(write.poll(&mut driver), Poll::Pending);
+        assert_eq!(write.poll(&mut driver), Poll::Ready(Ok(())));
+
+        driver.response = [0x01, 0x00];
+        let read = PendingRead::start(&mut driver, pid, 1).unwrap();
+        assert_eq!(read.poll(&mut driver), Poll::Pending);
+        assert!(matches!(
+            read.poll(&mut driver),
+            Poll::Ready(Err(driver::Error::Checksum(_)))
+        ));
+    }
+
     #[test]
     fn test_data_decode_all_bits() {
         let frame = Frame::from_data(PID::new(80).unwrap(), &[0x55, 0xDD]);
         assert_eq!(frame.decode::<u16>(0, 16), 0xdd55);
     }
+
+    #[test]
+    fn test_id_encoded_length() {
+        let cluster = ClusterConfig::new(&[]).with_id_encoded_length();
+        let pid = PID::from_id(0x20);
+        let mut driver = crate::driver::loopback::Loopback::new();
+        driver.set_response_with_checksum_model(pid, &[1, 2, 3, 4], ChecksumModel::Classic);
+        let frame = driver.read_configured_frame(pid, &cluster).unwrap();
+        assert_eq!(frame.get_data(), &[1, 2, 3, 4]);
+        assert_eq!(
+            driver.read_configured_frame(pid, &ClusterConfig::new(&[])),
+            Err(driver::Error::UnknownFrame)
+        );
+    }
+
+    #[test]
+    fn test_known_length() {
+        let mut driver = crate::driver::loopback::Loopback::new();
+        assert_eq!(
+            driver.read_frame(PID::from_id(0x3D), 3),
+            Err(driver::Error::Frame(FrameError::WrongLength {
+                id: 0x3D,
+                expected: 8
+            }))
+        );
+        assert!(driver.last_pid().is_none(), "No header must be sent");
+    }
+
+    #[test]
+    fn test_write_payload() {
+        let mut driver = crate::driver::loopback::Loopback::new();
+        let pid = PID::from_id(0x11);
+        let data = [0x01, 0x02];
+        let checksum = ChecksumModel::Enhanced.calculate(pid, &data);
+        driver.write_payload(pid, &data, checksum).unwrap();
+        assert_eq!(driver.last_pid(), Some(pid));
+        assert_eq!(
+            driver.pending(),
+            Frame::from_data(pid, &data).get_data_with_checksum()
+        );
+        assert_eq!(
+            driver.write_payload(PID::from_id(0x3C), &data, checksum),
+            Err(driver::Error::Frame(FrameError::WrongLength {
+                id: 0x3C,
+                expected: 8
+            }))
+        );
+    }
+
+    /// Frame based adapter implementing only the required methods
+    #[derive(Default)]
+    struct Adapter {
+        written: Option<Frame>,
+        response: Option<Frame>,
+    }
+
+    impl Master for Adapter {
+        type Error = driver::Error;
+
+        fn send_wakeup(&mut self) -> Result<(), driver::Error> {
+            Ok(())
+        }
+
+        fn write_frame(&mut self, frame: &Frame) -> Result<(), driver::Error> {
+            self.written = Some(Frame { ..*frame });
+            Ok(())
+        }
+
+        fn read_frame(&mut self, pid: PID, _data_length: usize) -> Result<Frame, driver::Error> {
+            match self.response.take() {
+                Some(frame) if frame.get_pid() == pid => Ok(frame),
+                _ => Err(driver::Error::NoResponse),
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_read_frame_with_checksum_model() {
+        let pid = PID::from_id(0x10);
+        let mut adapter = Adapter {
+            response: Some(Frame::from_data(pid, &[0x01])),
+            ..Adapter::default()
+        };
+        let frame = adapter
+            .read_frame_with_checksum_model(pid, 1, ChecksumModel::Classic)
+            .unwrap();
+        assert_eq!(frame.get_data(), &[0x01]);
+        assert_eq!(frame.get_checksum_model(), ChecksumModel::Classic);
+        assert_eq!(frame.get_checksum(), 0xFE);
+        assert_eq!(
+            adapter.read_frame_with_checksum_model(pid, 1, ChecksumModel::Classic),
+            Err(driver::Error::NoResponse)
+        );
+    }
+
+    #[test]
+    fn test_default_read_configured_frame() {
+        use crate::cluster::{FrameConfig, Publisher};
+        const CLUSTER: ClusterConfig =
+            ClusterConfig::new(&[FrameConfig::new(0x10, 1, Publisher::Slave(0))
+                .with_checksum_model(ChecksumModel::Classic)]);
+        let pid = PID::from_id(0x10);
+        let mut adapter = Adapter {
+            response: Some(Frame::from_data(pid, &[0x01])),
+            ..Adapter::default()
+        };
+        let frame = adapter.read_configured_frame(pid, &CLUSTER).unwrap();
+        assert_eq!(frame.get_checksum_model(), ChecksumModel::Classic);
+        assert_eq!(
+            adapter.read_configured_frame(PID::from_id(0x11), &CLUSTER),
+            Err(driver::Error::UnknownFrame)
+        );
+    }
+
+    #[test]
+    fn test_default_read_response() {
+        let pid = PID::from_id(0x10);
+        let mut adapter = Adapter {
+            response: Some(Frame::from_data(pid, &[0x01])),
+            ..Adapter::default()
+        };
+        assert_eq!(
+            adapter.read_response(pid, 1),
+            Ok(Response::Complete(Frame::from_data(pid, &[0x01])))
+        );
+        assert_eq!(adapter.read_response(pid, 1), Ok(Response::NoResponse));
+    }
+
+    #[test]
+    fn test_default_write_payload() {
+        let mut adapter = Adapter::default();
+        let pid = PID::from_id(0x11);
+        adapter.write_payload(pid, &[0x01, 0x02], 0xAB).unwrap();
+        let frame = adapter.written.take().unwrap();
+        assert_eq!(frame.get_data(), &[0x01, 0x02]);
+        assert_eq!(frame.get_checksum(), 0xAB);
+        assert_eq!(
+            adapter.write_payload(PID::from_id(0x3C), &[0x01], 0xAB),
+            Err(driver::Error::Frame(FrameError::WrongLength {
+                id: 0x3C,
+                expected: 8
+            }))
+        );
+        assert!(adapter.written.is_none());
+    }
+
+    #[test]
+    #[cfg_attr(
+        not(feature = "panic-free"),
+        should_panic(expected = "Maximum data length is 8 bytes")
+    )]
+    fn test_data_length_check() {
+        let mut driver = crate::driver::loopback::Loopback::new();
+        assert_eq!(
+            driver.read_frame(PID::from_id(0x10), 9),
+            Err(driver::Error::Frame(FrameError::DataTooLong(9)))
+        );
+    }
+
+    #[cfg(feature = "async")]
+    mod asynchronous {
+        use super::*;
+        use core::future::Future;
+        use core::pin::pin;
+        use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+        /// Poll a future which never returns `Pending` to completion
+        fn block_on<F: Future>(future: F) -> F::Output {
+            const VTABLE: RawWakerVTable = RawWakerVTable::new(
+                |_| RawWaker::new(core::ptr::null(), &VTABLE),
+                |_| {},
+                |_| {},
+                |_| {},
+            );
+            let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) };
+            match pin!(future).poll(&mut Context::from_waker(&waker)) {
+                Poll::Ready(output) => output,
+                Poll::Pending => panic!("Future is pending"),
+            }
+        }
+
+        /// Driver responding with a fixed response
+        struct Responder {
+            response: &'static [u8],
+            header: Option<PID>,
+        }
+
+        impl driver::AsyncMaster for Responder {
+            type Error = crate::Error;
+
+            async fn send_wakeup(&mut self) -> Result<(), crate::Error> {
+                Ok(())
+            }
+
+            async fn send_header(&mut self, pid: PID) -> Result<(), crate::Error> {
+                self.header = Some(pid);
+                Ok(())
+            }
+
+            async fn read(&mut self, buf: &mut [u8]) -> Result<(), crate::Error> {
+                buf.copy_from_slice(&self.response[..buf.len()]);
+                Ok(())
+            }
+
+            async fn write(&mut self, _data: &[u8]) -> Result<(), crate::Error> {
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn test_read_frame() {
+            let mut driver = Responder {
+                response: &[0x01, 0x02, 0xAC],
+                header: None,
+            };
+            let pid = PID::from_id(0x10);
+            let frame = block_on(AsyncMaster::read_frame(&mut driver, pid, 2)).unwrap();
+            assert_eq!(driver.header, Some(pid));
+            assert_eq!(frame.get_data(), &[0x01, 0x02]);
+            assert!(matches!(
+                block_on(AsyncMaster::read_frame(&mut driver, pid, 1)),
+                Err(crate::Error::Checksum(_))
+            ));
+        }
+    }
 }
@@ -0,0 +1,624 @@
+//! Passive bus monitor reconstructing frames from the observed byte stream
+//!
+//! The `BusMonitor` is fed with the `driver::BusEvent`s of a receive-only driver and reports
+//! every frame seen on the bus, including frames with a missing response or a wrong checksum.
+//! Without a cluster configuration, the end of a response is detected by the next break or an
+//! idle bus. With a `ClusterConfig`, the data length and checksum model of configured frames
+//! are taken from it. With `ParsePolicy::Strict` only valid frames are reported.
+//!
+//! Sniffers that also need the errors in between frames, e.g. for raw captures, use
+//! `BusMonitor::analyze`, which segments the events into headers and responses and reports
+//! sync bytes and bytes outside of a frame.
+//!
+//! Events pushed with their time of reception with `push_at` or `analyze_at` are used to
+//! measure the inter-byte space of the responses, see `MonitoredFrame::inter_byte_space`. With
+//! `BusMonitor::with_inter_byte_space` a strict monitor drops responses with longer spaces.
+//! For drivers and captures without `BusEvent::Idle`, `BusMonitor::with_idle_timeout` detects
+//! the idle bus from these timestamps, see `BusMonitor::poll_idle`.
+//!
+//! The master request and slave response frames of the monitor are decoded for display with
+//! `Frame::decode_pdu` of the transport layer.
+//!
+//! ```
+//! use lin_bus::driver::{BusEvent, SYNC_BYTE};
+//! use lin_bus::monitor::{BusMonitor, Segment};
+//!
+//! let capture = [
+//!     BusEvent::Break,
+//!     BusEvent::Byte(0x54),
+//!     BusEvent::Break,
+//!     BusEvent::Byte(SYNC_BYTE),
+//!     BusEvent::Byte(0x50),
+//!     BusEvent::Byte(0x01),
+//!     BusEvent::Byte(0xAE),
+//!     BusEvent::Idle,
+//! ];
+//! let mut monitor = BusMonitor::new();
+//! let mut segments = capture.iter().filter_map(|event| monitor.analyze(*event));
+//! assert_eq!(segments.next(), Some(Segment::SyncError(0x54)));
+//! assert!(matches!(segments.next(), Some(Segment::Header { parity_error: false, .. })));
+//! assert!(matches!(segments.next(), Some(Segment::Response(frame)) if frame.frame().is_some()));
+//! ```
+use crate::cluster::ClusterConfig;
+use crate::driver::{self, BusEvent, SYNC_BYTE};
+use crate::frame::{ChecksumModel, Frame, ParsePolicy, PID};
+use crate::idle::BusIdle;
+use crate::iso17987::{id, timing};
+use crate::ldf::LinSpeed;
+use crate::time::{Duration, Instant};
+
+/// Result of checking an observed frame
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Verdict {
+    /// The response is complete and the checksum is correct
+    Valid,
+    /// The checksum of the response is wrong
+    Checksum,
+    /// No slave responded to the header
+    NoResponse,
+    /// The response is shorter than configured in the cluster
+    Incomplete,
+    /// The parity bits of the PID are wrong, the response was ignored
+    Parity,
+}
+
+/// Frame observed on the bus
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MonitoredFrame {
+    pid: PID,
+    response: [u8; 9],
+    length: usize,
+    checksum_model: ChecksumModel,
+    verdict: Verdict,
+    inter_byte_space: Option<Duration>,
+}
+
+impl MonitoredFrame {
+    /// The PID of the header, for `Verdict::Parity` the PID with the corrected parity
+    pub fn pid(&self) -> PID {
+        self.pid
+    }
+
+    pub fn verdict(&self) -> Verdict {
+        self.verdict
+    }
+
+    /// The checksum model the response was checked with
+    pub fn checksum_model(&self) -> ChecksumModel {
+        self.checksum_model
+    }
+
+    /// All received response bytes including the checksum
+    pub fn response(&self) -> &[u8] {
+        &self.response[..self.length]
+    }
+
+    /// The response data without the checksum
+    pub fn data(&self) -> &[u8] {
+        &self.response[..self.length.saturating_sub(1)]
+    }
+
+    /// The longest space between two response bytes, `None` if the bytes were pushed without
+    /// timestamps. Without the bus speed, see `BusMonitor::with_inter_byte_space`, it is the
+    /// time between the receptions of the bytes including the byte time.
+    pub fn inter_byte_space(&self) -> Option<Duration> {
+        self.inter_byte_space
+    }
+
+    /// The frame if the response is valid
+    pub fn frame(&self) -> Option<Frame> {
+        if self.verdict != Verdict::Valid {
+            return None;
+        }
+        Some(Frame {
+            pid: self.pid,
+            buffer: self.response,
+            data_length: (self.length - 1) as u8,
+            checksum_model: self.checksum_model,
+        })
+    }
+}
+
+/// Part of the byte stream reported by `BusMonitor::analyze`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    /// A break followed by the sync byte and the PID, `pid` has the corrected parity
+    Header { pid: PID, parity_error: bool },
+    /// The response to the last header, ended by its expected length, a break or an idle bus
+    Response(MonitoredFrame),
+    /// A break was followed by the given byte instead of the sync byte
+    SyncError(u8),
+    /// A byte outside of a frame, e.g. after a complete response
+    Unexpected(u8),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    WaitForBreak,
+    Sync,
+    Pid,
+    Response {
+        pid: PID,
+        parity_error: bool,
+        expected_length: Option<usize>,
+        checksum_model: ChecksumModel,
+    },
+}
+
+/// Frame reconstruction engine, see the module documentation
+pub struct BusMonitor<'a> {
+    cluster: Option<ClusterConfig<'a>>,
+    policy: ParsePolicy,
+    state: State,
+    response: [u8; 9],
+    length: usize,
+    speed: Option<LinSpeed>,
+    max_inter_byte_space: Option<Duration>,
+    last_byte: Option<Instant>,
+    inter_byte_space: Option<Duration>,
+    idle: Option<BusIdle>,
+}
+
+impl<'a> BusMonitor<'a> {
+    /// Create a monitor for an unknown cluster
+    pub fn new() -> BusMonitor<'a> {
+        BusMonitor {
+            cluster: None,
+            policy: ParsePolicy::Lenient,
+            state: State::WaitForBreak,
+            response: [0u8; 9],
+            length: 0,
+            speed: None,
+            max_inter_byte_space: None,
+            last_byte: None,
+            inter_byte_space: None,
+            idle: None,
+        }
+    }
+
+    /// Create a monitor taking data lengths and checksum models from `cluster`
+    pub fn with_cluster(cluster: ClusterConfig<'a>) -> BusMonitor<'a> {
+        BusMonitor {
+            cluster: Some(cluster),
+            ..BusMonitor::new()
+        }
+    }
+
+    /// Returns the monitor with the given policy, `ParsePolicy::Lenient` by default. A strict
+    /// monitor drops frames with an invalid response or PID parity and frames with a reserved
+    /// ID.
+    pub fn with_policy(self, policy: ParsePolicy) -> BusMonitor<'a> {
+        BusMonitor { policy, ..self }
+    }
+
+    /// Returns the monitor measuring the inter-byte space of a bus running at `speed`. A
+    /// strict monitor drops responses with a space longer than `max`.
+    pub fn with_inter_byte_space(self, speed: LinSpeed, max: Duration) -> BusMonitor<'a> {
+        BusMonitor {
+            speed: Some(speed),
+            max_inter_byte_space: Some(max),
+            ..self
+        }
+    }
+
+    /// Returns the monitor treating a bus without events for `timeout` as idle, see
+    /// `poll_idle`
+    pub fn with_idle_timeout(self, timeout: Duration) -> BusMonitor<'a> {
+        BusMonitor {
+            idle: Some(BusIdle::new(timeout)),
+            ..self
+        }
+    }
+
+    /// Process a `BusEvent::Idle` if no event was pushed with `push_at` or `analyze_at` for the
+    /// idle timeout until `now`. Call it before pushing the next timestamped event and
+    /// periodically while the driver receives nothing.
+    pub fn poll_idle(&mut self, now: Instant) -> Option<Segment> {
+        let idle = self.idle.as_mut().map_or(false, |idle| idle.poll(now));
+        if idle {
+            self.analyze(BusEvent::Idle)
+        } else {
+            None
+        }
+    }
+
+    /// Process the next event, returns a frame if the event completed one
+    pub fn push(&mut self, event: BusEvent) -> Option<MonitoredFrame> {
+        let segment = self.analyze(event);
+        self.filter(segment)
+    }
+
+    /// Process the next event received at `timestamp` like `push`
+    pub fn push_at(&mut self, event: BusEvent, timestamp: Instant) -> Option<MonitoredFrame> {
+        let segment = self.analyze_at(event, timestamp);
+        self.filter(segment)
+    }
+
+    fn filter(&self, segment: Option<Segment>) -> Option<MonitoredFrame> {
+        match segment {
+            Some(Segment::Response(frame))
+                if self.policy == ParsePolicy::Lenient
+                    || (frame.verdict == Verdict::Valid
+                        && !id::is_reserved(frame.pid.get_id())
+                        && !self.exceeds_inter_byte_space(&frame)) =>
+            {
+                Some(frame)
+            }
+            _ => None,
+        }
+    }
+
+    fn exceeds_inter_byte_space(&self, frame: &MonitoredFrame) -> bool {
+        match (frame.inter_byte_space, self.max_inter_byte_space) {
+            (Some(space), Some(max)) => space > max,
+            _ => false,
+        }
+    }
+
+    /// Process the next event received at `timestamp` like `analyze`, measuring the
+    /// inter-byte space of responses
+    pub fn analyze_at(&mut self, event: BusEvent, timestamp: Instant) -> Option<Segment> {
+        if let Some(idle) = &mut self.idle {
+            idle.activity(timestamp);
+        }
+        if let (State::Response { .. }, BusEvent::Byte(_)) = (self.state, event) {
+            if let Some(last_byte) = self.last_byte {
+                let byte_time = self
+                    .speed
+                    .map_or(Duration::ZERO, |speed| timing::bytes(1, speed));
+                let space = (timestamp - last_byte).saturating_sub(byte_time);
+                self.inter_byte_space = Some(self.inter_byte_space.map_or(space, |s| s.max(space)));
+            }
+            self.last_byte = Some(timestamp);
+        }
+        self.analyze(event)
+    }
+
+    /// Process the next event, returns the segment completed by it. Unlike `push`, responses
+    /// are reported regardless of the policy.
+    pub fn analyze(&mut self, event: BusEvent) -> Option<Segment> {
+        match (self.state, event) {
+            (State::Response { .. }, BusEvent::Break) => {
+                let frame = self.finish();
+                self.state = State::Sync;
+                frame.map(Segment::Response)
+            }
+            (State::Response { .. }, BusEvent::Idle) => {
+                let frame = self.finish();
+                self.state = State::WaitForBreak;
+                frame.map(Segment::Response)
+            }
+            (
+                State::Response {
+                    expected_length, ..
+                },
+                BusEvent::Byte(byte),
+            ) => {
+                self.response[self.length] = byte;
+                self.length += 1;
+                let complete = expected_length.map_or(self.response.len(), |length| length + 1);
+                if self.length >= complete.min(self.response.len()) {
+                    let frame = self.finish();
+                    self.state = State::WaitForBreak;
+                    frame.map(Segment::Response)
+                } else {
+                    None
+                }
+            }
+            (_, BusEvent::Break) => {
+                self.state = State::Sync;
+                None
+            }
+            (State::Sync, BusEvent::Byte(SYNC_BYTE)) => {
+                self.state = State::Pid;
+                None
+            }
+            (State::Sync, BusEvent::Byte(byte)) => {
+                self.state = State::WaitForBreak;
+                Some(Segment::SyncError(byte))
+            }
+            (State::Pid, BusEvent::Byte(byte)) => {
+                let pid = PID::from_masked_id(byte);
+                let parity_error = pid.get() != byte;
+                let cluster = self.cluster;
+                self.state = State::Response {
+                    pid,
+                    parity_error,
+                    expected_length: cluster.and_then(|cluster| cluster.data_length(pid.get_id())),
+                    checksum_model: cluster
+                        .and_then(|cluster| cluster.checksum_model(pid.get_id()))
+                        .unwrap_or_else(|| pid.checksum_model()),
+                };
+                self.length = 0;
+                self.last_byte = None;
+                self.inter_byte_space = None;
+                Some(Segment::Header { pid, parity_error })
+            }
+            (State::WaitForBreak, BusEvent::Byte(byte)) => Some(Segment::Unexpected(byte)),
+            _ => {
+                self.state = State::WaitForBreak;
+                None
+            }
+        }
+    }
+
+    /// Feed the events of `monitor` to the engine and report every frame to `callback` until
+    /// the driver fails
+    pub fn run<M, F>(&mut self, monitor: &mut M, mut callback: F) -> M::Error
+    where
+        M: driver::Monitor,
+        F: FnMut(&MonitoredFrame),
+    {
+        loop {
+            match monitor.next_event() {
+                Ok(event) => {
+                    if let Some(frame) = self.push(event) {
+                        callback(&frame);
+                    }
+                }
+                Err(e) => return e,
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Option<MonitoredFrame> {
+        let (pid, parity_error, expected_length, checksum_model) = match self.state {
+            State::Response {
+                pid,
+                parity_error,
+                expected_length,
+                checksum_model,
+            } => (pid, parity_error, expected_length, checksum_model),
+            _ => return None,
+        };
+        let verdict = if parity_error {
+            Verdict::Parity
+        } else if self.length == 0 {
+            Verdict::NoResponse
+        } else if expected_length.map_or(false, |length| self.length < length + 1) {
+            Verdict::Incomplete
+        } else {
+            let (data, checksum) = self.response[..self.length].split_at(self.length - 1);
+            if checksum_model.calculate(pid, data) == checksum[0] {
+                Verdict::Valid
+            } else {
+                Verdict::Checksum
+            }
+        };
+        Some(MonitoredFrame {
+            pid,
+            response: self.response,
+            length: self.length,
+            checksum_model,
+            verdict,
+            inter_byte_space: self.inter_byte_space,
+        })
+    }
+}
+
+impl Default for BusMonitor<'_> {
+    fn default() -> Self {
+        BusMonitor::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::{FrameConfig, Publisher};
+
+    fn push_all(monitor: &mut BusMonitor, events: &[BusEvent]) -> Option<MonitoredFrame> {
+        events
+            .iter()
+            .filter_map(|event| monitor.push(*event))
+            .last()
+    }
+
+    fn header(id: u8) -> [BusEvent; 3] {
+        [
+            BusEvent::Break,
+            BusEvent::Byte(SYNC_BYTE),
+            BusEvent::Byte(PID::from_id(id).get()),
+        ]
+    }
+
+    #[test]
+    fn test_unknown_cluster() {
+        let mut monitor = BusMonitor::new();
+        assert_eq!(push_all(&mut monitor, &header(0x10)), None);
+        assert_eq!(monitor.push(BusEvent::Byte(0x01)), None);
+        assert_eq!(monitor.push(BusEvent::Byte(0xAE)), None);
+        let frame = push_all(&mut monitor, &header(0x11)).unwrap();
+        assert_eq!(frame.verdict(), Verdict::Valid);
+        assert_eq!(frame.data(), &[0x01]);
+        assert_eq!(
+            frame.frame(),
+            Some(Frame::from_data(PID::from_id(0x10), &[0x01]))
+        );
+
+        let frame = monitor.push(BusEvent::Idle).unwrap();
+        assert_eq!(frame.pid(), PID::from_id(0x11));
+        assert_eq!(frame.verdict(), Verdict::NoResponse);
+        assert_eq!(monitor.push(BusEvent::Idle), None);
+    }
+
+    #[test]
+    fn test_cluster() {
+        const FRAMES: &[FrameConfig] = &[FrameConfig::new(0x10, 1, Publisher::Slave(0))];
+        let mut monitor = BusMonitor::with_cluster(ClusterConfig::new(FRAMES));
+        push_all(&mut monitor, &header(0x10));
+        assert_eq!(monitor.push(BusEvent::Byte(0x01)), None);
+        let frame = monitor.push(BusEvent::Byte(0xAF)).unwrap();
+        assert_eq!(frame.verdict(), Verdict::Checksum);
+        assert_eq!(frame.response(), &[0x01, 0xAF]);
+        assert_eq!(frame.frame(), None);
+
+        push_all(&mut monitor, &header(0x10));
+        let frame = monitor.push(BusEvent::Idle).unwrap();
+        assert_eq!(frame.verdict(), Verdict::NoResponse);
+
+        push_all(&mut monitor, &header(0x10));
+        monitor.push(BusEvent::Byte(0x01));
+        let frame = monitor.push(BusEvent::Idle).unwrap();
+        assert_eq!(frame.verdict(), Verdict::Incomplete);
+    }
+
+    #[test]
+    fn test_header_errors() {
+        let mut monitor = BusMonitor::new();
+        let events = [
+            BusEvent::Break,
+            BusEvent::Byte(0x10),
+            BusEvent::Byte(0x50),
+            BusEvent::Idle,
+        ];
+        assert_eq!(push_all(&mut monitor, &events), None);
+        let events = [
+            BusEvent::Break,
+            BusEvent::Byte(SYNC_BYTE),
+            BusEvent::Byte(0x10),
+            BusEvent::Idle,
+        ];
+        let frame = push_all(&mut monitor, &events).unwrap();
+        assert_eq!(frame.verdict(), Verdict::Parity);
+    }
+
+    #[test]
+    fn test_inter_byte_space() {
+        let max = Duration::from_micros(50);
+        let speed = LinSpeed(19200);
+        let mut lenient = BusMonitor::new().with_inter_byte_space(speed, max);
+        let mut strict = BusMonitor::new()
+            .with_inter_byte_space(speed, max)
+            .with_policy(ParsePolicy::Strict);
+        // A byte takes 520 µs at 19200 bit/s
+        let events = [
+            (BusEvent::Byte(0x01), 1_000),
+            (BusEvent::Byte(0x02), 1_620),
+            (BusEvent::Byte(0xAC), 2_140),
+            (BusEvent::Idle, 5_000),
+        ];
+        for monitor in [&mut lenient, &mut strict] {
+            push_all(monitor, &header(0x10));
+        }
+        let mut frames = events.iter().map(|(event, us)| {
+            let at = Instant::from_micros(*us);
+            (lenient.push_at(*event, at), strict.push_at(*event, at))
+        });
+        let (frame, dropped) = frames.nth(3).unwrap();
+        let frame = frame.unwrap();
+        assert_eq!(frame.verdict(), Verdict::Valid);
+        assert_eq!(frame.inter_byte_space(), Some(Duration::from_micros(100)));
+        assert_eq!(dropped, None);
+
+        let mut monitor = BusMonitor::new();
+        push_all(&mut monitor, &header(0x10));
+        monitor.push(BusEvent::Byte(0x01));
+        monitor.push(BusEvent::Byte(0xAE));
+        let frame = monitor.push(BusEvent::Idle).unwrap();
+        assert_eq!(frame.inter_byte_space(), None);
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        let mut monitor = BusMonitor::new().with_idle_timeout(Duration::from_millis(2));
+        let at = Instant::from_micros;
+        for (i, event) in header(0x10).iter().enumerate() {
+            monitor.analyze_at(*event, at(i as u64 * 500));
+        }
+        monitor.analyze_at(BusEvent::Byte(0x01), at(2_000));
+        assert_eq!(monitor.poll_idle(at(3_000)), None);
+        match monitor.poll_idle(at(4_000)) {
+            Some(Segment::Response(frame)) => assert_eq!(frame.verdict(), Verdict::Checksum),
+            segment => panic!("unexpected segment {:?}", segment),
+        }
+        assert_eq!(monitor.poll_idle(at(6_000)), None);
+    }
+
+    #[test]
+    fn test_strict_policy() {
+        let mut monitor = BusMonitor::new().with_policy(ParsePolicy::Strict);
+        push_all(&mut monitor, &header(0x10));
+        monitor.push(BusEvent::Byte(0x01));
+        assert_eq!(monitor.push(BusEvent::Byte(0xAF)), None);
+        assert_eq!(monitor.push(BusEvent::Idle), None);
+
+        push_all(&mut monitor, &header(0x3E));
+        monitor.push(BusEvent::Byte(0x01));
+        let checksum = ChecksumModel::Enhanced.calculate(PID::from_id(0x3E), &[0x01]);
+        monitor.push(BusEvent::Byte(checksum));
+        assert_eq!(monitor.push(BusEvent::Idle), None);
+
+        push_all(&mut monitor, &header(0x10));
+        monitor.push(BusEvent::Byte(0x01));
+        monitor.push(BusEvent::Byte(0xAE));
+        let frame = monitor.push(BusEvent::Idle).unwrap();
+        assert_eq!(frame.verdict(), Verdict::Valid);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let mut monitor = BusMonitor::new().with_policy(ParsePolicy::Strict);
+        assert_eq!(
+            monitor.analyze(BusEvent::Byte(0x01)),
+            Some(Segment::Unexpected(0x01))
+        );
+        assert_eq!(monitor.analyze(BusEvent::Break), None);
+        assert_eq!(
+            monitor.analyze(BusEvent::Byte(0x54)),
+            Some(Segment::SyncError(0x54))
+        );
+        assert_eq!(
+            monitor.analyze(BusEvent::Byte(0x50)),
+            Some(Segment::Unexpected(0x50))
+        );
+
+        monitor.analyze(BusEvent::Break);
+        monitor.analyze(BusEvent::Byte(SYNC_BYTE));
+        assert_eq!(
+            monitor.analyze(BusEvent::Byte(0x10)),
+            Some(Segment::Header {
+                pid: PID::from_id(0x10),
+                parity_error: true
+            })
+        );
+        monitor.analyze(BusEvent::Byte(0x01));
+        match monitor.analyze(BusEvent::Break) {
+            Some(Segment::Response(frame)) => assert_eq!(frame.verdict(), Verdict::Parity),
+            segment => panic!("Unexpected segment {:?}", segment),
+        }
+    }
+
+    /// Driver replaying a recorded event sequence
+    struct Replay(&'static [BusEvent]);
+
+    impl driver::Monitor for Replay {
+        type Error = driver::Error;
+
+        fn next_event(&mut self) -> Result<BusEvent, driver::Error> {
+            let (event, rest) = self.0.split_first().ok_or(driver::Error::Timeout)?;
+            self.0 = rest;
+            Ok(*event)
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let mut driver = Replay(&[
+            BusEvent::Break,
+            BusEvent::Byte(SYNC_BYTE),
+            BusEvent::Byte(0x50),
+            BusEvent::Byte(0x01),
+            BusEvent::Byte(0xAE),
+            BusEvent::Idle,
+        ]);
+        let mut frames = 0;
+        let error = BusMonitor::new().run(&mut driver, |frame| {
+            assert_eq!(frame.verdict(), Verdict::Valid);
+            frames += 1;
+        });
+        assert_eq!(error, driver::Error::Timeout);
+        assert_eq!(frames, 1);
+    }
+}
@@ -0,0 +1,427 @@
+//! Network management of a LIN master, i.e. putting the cluster to sleep and waking it up
+//! again while driving the transceiver accordingly
+//!
+//! Applications coordinating the power management with the rest of the system get notified of
+//! every transition, either by a listener called immediately or by polling the queued events
+//! with `NetworkManagement::poll_event`.
+use crate::driver::{self, Transceiver};
+use crate::frame::diagnostic::GO_TO_SLEEP_FRAME;
+use crate::idle::BusIdle;
+use crate::iso17987::timing;
+use crate::master::Master;
+use crate::time::{Duration, Instant, Timer};
+
+/// State of the cluster
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum State {
+    Operational,
+    Sleep,
+}
+
+/// Network management event, see the module documentation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// The cluster is going to sleep, the transceiver enters sleep mode next
+    GoingToSleep,
+    /// The master sent a wakeup signal
+    WakeupSent,
+    /// A wakeup signal of a slave was detected on the bus
+    WakeupReceived,
+    /// The transceiver is back in normal mode after the wakeup by a slave
+    WokenUpBySlave,
+}
+
+/// Queue of the last `EventQueue::CAPACITY` events, older events are dropped
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EventQueue {
+    events: [Event; EventQueue::CAPACITY],
+    head: usize,
+    len: usize,
+}
+
+impl EventQueue {
+    pub const CAPACITY: usize = 8;
+
+    pub const fn new() -> EventQueue {
+        EventQueue {
+            events: [Event::GoingToSleep; EventQueue::CAPACITY],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Queue `event`, dropping the oldest event if the queue is full
+    pub fn push(&mut self, event: Event) {
+        let tail = (self.head + self.len) % Self::CAPACITY;
+        self.events[tail] = event;
+        if self.len == Self::CAPACITY {
+            self.head = (self.head + 1) % Self::CAPACITY;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// Remove the oldest event
+    pub fn pop(&mut self) -> Option<Event> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.head];
+        self.head = (self.head + 1) % Self::CAPACITY;
+        self.len -= 1;
+        Some(event)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        EventQueue::new()
+    }
+}
+
+/// Network management state machine of the master node
+pub struct NetworkManagement<T> {
+    transceiver: T,
+    state: State,
+    events: EventQueue,
+    listener: Option<fn(Event)>,
+}
+
+impl<T: Transceiver> NetworkManagement<T> {
+    /// Take control of the transceiver and switch it to normal mode
+    pub fn new(mut transceiver: T) -> Result<NetworkManagement<T>, driver::Error> {
+        transceiver.enable()?;
+        Ok(NetworkManagement {
+            transceiver,
+            state: State::Operational,
+            events: EventQueue::new(),
+            listener: None,
+        })
+    }
+
+    /// Returns the state machine calling `listener` for every event, which is queued for
+    /// `poll_event` as well
+    pub fn with_listener(self, listener: fn(Event)) -> NetworkManagement<T> {
+        NetworkManagement {
+            listener: Some(listener),
+            ..self
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// The oldest event which was not polled yet
+    pub fn poll_event(&mut self) -> Option<Event> {
+        self.events.pop()
+    }
+
+    fn notify(&mut self, event: Event) {
+        log!(debug, "network management event {:?}", event);
+        self.events.push(event);
+        if let Some(listener) = self.listener {
+            listener(event);
+        }
+    }
+
+    /// Send the go-to-sleep command and switch the transceiver to sleep mode
+    pub fn go_to_sleep<M: Master>(&mut self, master: &mut M) -> Result<(), M::Error> {
+        master.write_frame(&GO_TO_SLEEP_FRAME)?;
+        self.notify(Event::GoingToSleep);
+        self.transceiver.sleep()?;
+        log!(info, "cluster entered sleep mode");
+        self.state = State::Sleep;
+        Ok(())
+    }
+
+    /// Wake up the transceiver because of a local request and send the wakeup signal to the
+    /// cluster
+    pub fn wake_up<M: Master>(&mut self, master: &mut M) -> Result<(), M::Error> {
+        self.transceiver.wake()?;
+        log!(info, "cluster woken up by the master");
+        self.state = State::Operational;
+        master.send_wakeup()?;
+        self.notify(Event::WakeupSent);
+        Ok(())
+    }
+
+    /// Wake up the cluster like `wake_up` and check with `is_awake` whether it responds, e.g.
+    /// by reading a slave frame. The wakeup signal is repeated after `timing::WAKEUP_RETRY_MS`
+    /// up to `timing::WAKEUP_RETRIES` times. If the cluster does not wake up, the function
+    /// waits `timing::WAKEUP_PAUSE_MS` before it returns `false`, so a further attempt can
+    /// follow immediately.
+    pub fn wake_up_with_retries<M, C, F>(
+        &mut self,
+        master: &mut M,
+        timer: &mut C,
+        mut is_awake: F,
+    ) -> Result<bool, M::Error>
+    where
+        M: Master,
+        C: Timer + ?Sized,
+        F: FnMut(&mut M) -> bool,
+    {
+        self.wake_up(master)?;
+        for attempt in 1..=timing::WAKEUP_RETRIES {
+            if attempt > 1 {
+                log!(debug, "repeating wakeup signal, attempt {}", attempt);
+                master.send_wakeup()?;
+                self.notify(Event::WakeupSent);
+            }
+            timer.delay(Duration::from_millis(u64::from(timing::WAKEUP_RETRY_MS)));
+            if is_awake(master) {
+                return Ok(true);
+            }
+        }
+        log!(warn, "cluster did not wake up");
+        timer.delay(Duration::from_millis(u64::from(timing::WAKEUP_PAUSE_MS)));
+        Ok(false)
+    }
+
+    /// Switch the transceiver to normal mode after a slave woke up the cluster
+    pub fn bus_wakeup(&mut self) -> Result<(), driver::Error> {
+        self.notify(Event::WakeupReceived);
+        self.transceiver.enable()?;
+        log!(info, "cluster woken up by a slave");
+        self.state = State::Operational;
+        self.notify(Event::WokenUpBySlave);
+        Ok(())
+    }
+
+    /// Switch the transceiver to sleep mode without sending the go-to-sleep command once
+    /// `idle` reports the bus idle at `now`, like the slaves do after the bus idle timeout of
+    /// `BusIdle::sleep_timeout`. Returns whether the cluster entered sleep mode.
+    pub fn sleep_if_idle(
+        &mut self,
+        idle: &mut BusIdle,
+        now: Instant,
+    ) -> Result<bool, driver::Error> {
+        if self.state == State::Sleep || !idle.poll(now) {
+            return Ok(false);
+        }
+        self.notify(Event::GoingToSleep);
+        self.transceiver.sleep()?;
+        log!(
+            info,
+            "cluster entered sleep mode after {} ms of silence",
+            idle.idle_time(now).as_millis()
+        );
+        self.state = State::Sleep;
+        Ok(true)
+    }
+
+    /// Check the transceiver for physical bus faults
+    pub fn check_fault(&mut self) -> Result<(), driver::Error> {
+        self.transceiver.check_fault()
+    }
+
+    /// Release the transceiver
+    pub fn free(self) -> T {
+        self.transceiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PID;
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    enum Mode {
+        Normal,
+        Sleep,
+    }
+
+    struct Mock {
+        mode: Mode,
+        written_pid: Option<u8>,
+        wakeups: usize,
+        powered: bool,
+    }
+
+    impl Transceiver for Mock {
+        fn enable(&mut self) -> Result<(), driver::Error> {
+            self.mode = Mode::Normal;
+            Ok(())
+        }
+
+        fn sleep(&mut self) -> Result<(), driver::Error> {
+            self.mode = Mode::Sleep;
+            Ok(())
+        }
+
+        fn wake(&mut self) -> Result<(), driver::Error> {
+            self.enable()
+        }
+
+        fn check_fault(&mut self) -> Result<(), driver::Error> {
+            if self.powered {
+                Ok(())
+            } else {
+                Err(driver::Error::NoBusSupply)
+            }
+        }
+    }
+
+    impl driver::Master for Mock {
+        type Error = driver::Error;
+
+        fn send_wakeup(&mut self) -> Result<(), driver::Error> {
+            self.wakeups += 1;
+            Ok(())
+        }
+
+        fn send_header(&mut self, pid: PID) -> Result<(), driver::Error> {
+            self.written_pid = Some(pid.get_id());
+            Ok(())
+        }
+
+        fn read(&mut self, _buf: &mut [u8]) -> Result<(), driver::Error> {
+            Err(driver::Error::Timeout)
+        }
+
+        fn write(&mut self, _data: &[u8]) -> Result<(), driver::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sleep_and_wakeup() {
+        let transceiver = Mock {
+            mode: Mode::Sleep,
+            written_pid: None,
+            wakeups: 0,
+            powered: true,
+        };
+        let mut nm = NetworkManagement::new(transceiver).unwrap();
+        assert_eq!(nm.transceiver.mode, Mode::Normal);
+
+        let mut master = Mock {
+            mode: Mode::Normal,
+            written_pid: None,
+            wakeups: 0,
+            powered: true,
+        };
+        nm.go_to_sleep(&mut master).unwrap();
+        assert_eq!(master.written_pid, Some(0x3C));
+        assert_eq!(nm.state(), State::Sleep);
+        assert_eq!(nm.transceiver.mode, Mode::Sleep);
+
+        nm.wake_up(&mut master).unwrap();
+        assert_eq!(master.wakeups, 1);
+        assert_eq!(nm.state(), State::Operational);
+        assert_eq!(nm.free().mode, Mode::Normal);
+    }
+
+    #[test]
+    fn test_events() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+        static NOTIFIED: AtomicUsize = AtomicUsize::new(0);
+
+        let mock = || Mock {
+            mode: Mode::Normal,
+            written_pid: None,
+            wakeups: 0,
+            powered: true,
+        };
+        let mut nm = NetworkManagement::new(mock()).unwrap().with_listener(|_| {
+            NOTIFIED.fetch_add(1, Ordering::Relaxed);
+        });
+        let mut master = mock();
+        nm.go_to_sleep(&mut master).unwrap();
+        nm.bus_wakeup().unwrap();
+        nm.wake_up(&mut master).unwrap();
+        assert_eq!(nm.poll_event(), Some(Event::GoingToSleep));
+        assert_eq!(nm.poll_event(), Some(Event::WakeupReceived));
+        assert_eq!(nm.poll_event(), Some(Event::WokenUpBySlave));
+        assert_eq!(nm.poll_event(), Some(Event::WakeupSent));
+        assert_eq!(nm.poll_event(), None);
+        assert_eq!(NOTIFIED.load(Ordering::Relaxed), 4);
+
+        let mut queue = EventQueue::new();
+        for _ in 0..EventQueue::CAPACITY {
+            queue.push(Event::WakeupSent);
+        }
+        queue.push(Event::GoingToSleep);
+        assert_eq!(queue.len(), EventQueue::CAPACITY);
+        assert_eq!(queue.pop(), Some(Event::WakeupSent));
+        while queue.len() > 1 {
+            queue.pop();
+        }
+        assert_eq!(queue.pop(), Some(Event::GoingToSleep));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_wakeup_retries() {
+        let mock = || Mock {
+            mode: Mode::Sleep,
+            written_pid: None,
+            wakeups: 0,
+            powered: true,
+        };
+        let mut nm = NetworkManagement::new(mock()).unwrap();
+        let mut master = mock();
+        let mut timer = crate::time::tests::TestClock::default();
+        let awake = nm.wake_up_with_retries(&mut master, &mut timer, |master| master.wakeups == 2);
+        assert_eq!(awake, Ok(true));
+        assert_eq!(timer.now, 300_000);
+
+        let mut master = mock();
+        let mut timer = crate::time::tests::TestClock::default();
+        assert_eq!(
+            nm.wake_up_with_retries(&mut master, &mut timer, |_| false),
+            Ok(false)
+        );
+        assert_eq!(master.wakeups, 3);
+        assert_eq!(timer.now, 1_950_000);
+    }
+
+    #[test]
+    fn test_sleep_if_idle() {
+        let transceiver = Mock {
+            mode: Mode::Normal,
+            written_pid: None,
+            wakeups: 0,
+            powered: true,
+        };
+        let mut nm = NetworkManagement::new(transceiver).unwrap();
+        let mut idle = BusIdle::sleep_timeout();
+        idle.activity(Instant::from_micros(0));
+        assert_eq!(
+            nm.sleep_if_idle(&mut idle, Instant::from_micros(1_000_000)),
+            Ok(false)
+        );
+        assert_eq!(
+            nm.sleep_if_idle(&mut idle, Instant::from_micros(4_000_000)),
+            Ok(true)
+        );
+        assert_eq!(nm.state(), State::Sleep);
+        assert_eq!(nm.transceiver.mode, Mode::Sleep);
+    }
+
+    #[test]
+    fn test_fault() {
+        let transceiver = Mock {
+            mode: Mode::Normal,
+            written_pid: None,
+            wakeups: 0,
+            powered: false,
+        };
+        let mut nm = NetworkManagement::new(transceiver).unwrap();
+        let error = nm.check_fault().unwrap_err();
+        assert_eq!(error, driver::Error::NoBusSupply);
+        assert!(error.is_bus_fault());
+        assert!(!driver::Error::Timeout.is_bus_fault());
+    }
+}
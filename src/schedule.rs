@@ -0,0 +1,521 @@
+//! Execution of schedule tables
+//!
+//! The schedulers are generic over the master and the `Handler` of the application and do not
+//! allocate, the schedule tables are `const` slices of `Slot`s. `Scheduler` runs a table on a
+//! blocking `Master`. The `dyn-schedule` feature adds `DynScheduler`, which takes trait objects
+//! instead, e.g. to select handlers at runtime without monomorphizing the scheduler for each.
+//!
+//! `PipelinedSchedule` runs a schedule table on a `driver::SplitMaster`, e.g. a DMA driver.
+//! The response of the following slot is prepared in a second buffer while the transfer of
+//! the current slot is in flight, so the work at the start of a slot is limited to handing a
+//! ready buffer to the driver. This keeps the CPU load flat also at a high bus utilization.
+//!
+//! The application calls `start_slot` from the slot timer and `poll` from the transfer
+//! complete interrupt or its main loop:
+//!
+//! ```
+//! use lin_bus::cluster::{FrameConfig, Publisher};
+//! use lin_bus::schedule::{PipelinedSchedule, Slot};
+//!
+//! const SLOTS: &[Slot] = &[
+//!     Slot::new(FrameConfig::new(0x10, 2, Publisher::Master), 10_000),
+//!     Slot::new(FrameConfig::new(0x11, 4, Publisher::Slave(0)), 10_000),
+//! ];
+//! let schedule = PipelinedSchedule::new(SLOTS);
+//! assert_eq!(schedule.slots().len(), 2);
+//! ```
+
+use core::task::Poll;
+
+use crate::cluster::{FrameConfig, Publisher};
+use crate::driver;
+use crate::frame::{Frame, PID};
+use crate::master::check_response_length;
+use crate::time::{Duration, Timer};
+use crate::Master;
+
+/// Entry of a schedule table
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Slot {
+    pub frame: FrameConfig,
+    /// Time from the start of this slot to the start of the next slot in µs
+    pub delay_us: u32,
+}
+
+impl Slot {
+    pub const fn new(frame: FrameConfig, delay_us: u32) -> Slot {
+        Slot { frame, delay_us }
+    }
+
+    /// Time from the start of this slot to the start of the next slot
+    pub const fn delay(&self) -> Duration {
+        Duration::from_micros(self.delay_us as u64)
+    }
+
+    /// PID of the transmitted header
+    pub const fn pid(&self) -> PID {
+        self.frame.pid()
+    }
+
+    const fn data_length(&self) -> usize {
+        let length = self.frame.data_length as usize;
+        if length > 8 {
+            8
+        } else {
+            length
+        }
+    }
+}
+
+/// Application side of a schedule, called for the frames of the executed slots
+pub trait Handler {
+    /// Fill the data of a frame published by the master
+    fn publish(&mut self, pid: PID, data: &mut [u8]);
+
+    /// Called with every frame received from a slave
+    fn receive(&mut self, _frame: &Frame) {}
+}
+
+impl<F: FnMut(PID, &mut [u8])> Handler for F {
+    fn publish(&mut self, pid: PID, data: &mut [u8]) {
+        self(pid, data)
+    }
+}
+
+/// Execution of a schedule table on a blocking `Master`
+#[derive(Clone, Debug)]
+pub struct Scheduler<'a> {
+    slots: &'a [Slot],
+    next: usize,
+}
+
+impl<'a> Scheduler<'a> {
+    pub const fn new(slots: &'a [Slot]) -> Scheduler<'a> {
+        Scheduler { slots, next: 0 }
+    }
+
+    pub fn slots(&self) -> &'a [Slot] {
+        self.slots
+    }
+
+    /// Index of the slot executed by the next call of `run_slot`
+    pub fn next_slot(&self) -> usize {
+        self.next
+    }
+
+    /// Execute the next slot and return its delay in µs, after which `run_slot` is due again.
+    /// Received frames are passed to `handler`, a failed slot is skipped like on the bus.
+    pub fn run_slot<M, H>(&mut self, master: &mut M, handler: &mut H) -> Result<u32, M::Error>
+    where
+        M: Master + ?Sized,
+        H: Handler + ?Sized,
+    {
+        let slot = match self.slots.get(self.next) {
+            Some(slot) => *slot,
+            None => return Ok(0),
+        };
+        log!(
+            trace,
+            "schedule slot {} ({:#04x})",
+            self.next,
+            slot.frame.id
+        );
+        self.next = (self.next + 1) % self.slots.len();
+        let length = slot.data_length();
+        let checksum_model = slot.frame.checksum_model;
+        match slot.frame.publisher {
+            Publisher::Master => {
+                let mut data = [0u8; 8];
+                handler.publish(slot.pid(), &mut data[..length]);
+                let checksum = checksum_model.calculate(slot.pid(), &data[..length]);
+                master.write_payload(slot.pid(), &data[..length], checksum)?;
+            }
+            Publisher::Slave(_) => {
+                let frame =
+                    master.read_frame_with_checksum_model(slot.pid(), length, checksum_model)?;
+                handler.receive(&frame);
+            }
+        }
+        Ok(slot.delay_us)
+    }
+
+    /// Execute the next slot like `run_slot` and wait with `timer` until the slot ended, also
+    /// if it failed, so calling it in a loop runs the schedule table with the slot timing
+    pub fn run_slot_timed<M, H, T>(
+        &mut self,
+        master: &mut M,
+        handler: &mut H,
+        timer: &mut T,
+    ) -> Result<(), M::Error>
+    where
+        M: Master + ?Sized,
+        H: Handler + ?Sized,
+        T: Timer + ?Sized,
+    {
+        let start = timer.now();
+        let delay = self
+            .slots
+            .get(self.next)
+            .map_or(Duration::ZERO, Slot::delay);
+        let result = self.run_slot(master, handler);
+        timer.delay_until(start + delay);
+        result.map(|_| ())
+    }
+}
+
+/// `Scheduler` working on trait objects, so one instance of the code serves all masters with
+/// the same error type and all handlers. The handler can be replaced at runtime.
+#[cfg(feature = "dyn-schedule")]
+pub struct DynScheduler<'a> {
+    scheduler: Scheduler<'a>,
+    handler: &'a mut dyn Handler,
+}
+
+#[cfg(feature = "dyn-schedule")]
+impl<'a> DynScheduler<'a> {
+    pub fn new(slots: &'a [Slot], handler: &'a mut dyn Handler) -> DynScheduler<'a> {
+        DynScheduler {
+            scheduler: Scheduler::new(slots),
+            handler,
+        }
+    }
+
+    pub fn set_handler(&mut self, handler: &'a mut dyn Handler) {
+        self.handler = handler;
+    }
+
+    /// Index of the slot executed by the next call of `run_slot`
+    pub fn next_slot(&self) -> usize {
+        self.scheduler.next_slot()
+    }
+
+    /// See `Scheduler::run_slot`
+    pub fn run_slot<E>(&mut self, master: &mut dyn Master<Error = E>) -> Result<u32, E>
+    where
+        E: Into<driver::Error> + From<driver::Error>,
+    {
+        self.scheduler.run_slot(master, self.handler)
+    }
+}
+
+/// Double buffered execution of a schedule table, see the module documentation
+#[derive(Clone, Debug)]
+pub struct PipelinedSchedule<'a> {
+    slots: &'a [Slot],
+    /// Index of the slot started next
+    next: usize,
+    /// Buffer holding the prepared response of the next slot
+    prepared: Option<usize>,
+    buffers: [[u8; 9]; 2],
+    /// Index of the slot whose transfer is in flight
+    in_flight: Option<usize>,
+}
+
+impl<'a> PipelinedSchedule<'a> {
+    pub const fn new(slots: &'a [Slot]) -> PipelinedSchedule<'a> {
+        PipelinedSchedule {
+            slots,
+            next: 0,
+            prepared: None,
+            buffers: [[0u8; 9]; 2],
+            in_flight: None,
+        }
+    }
+
+    pub fn slots(&self) -> &'a [Slot] {
+        self.slots
+    }
+
+    /// Index of the slot started by the next call of `start_slot`
+    pub fn next_slot(&self) -> usize {
+        self.next
+    }
+
+    /// Start the transfer of the next slot and prepare the response of the following slot.
+    /// `handler` fills the data of frames published by the master, it is called one slot
+    /// ahead of the transmission. Returns the delay of the started slot in µs, after which
+    /// `start_slot` is due again.
+    ///
+    /// Fails with `Error::Timeout` if the transfer of the previous slot is still in flight,
+    /// i.e. `poll` did not report its completion.
+    pub fn start_slot<Driver, H>(
+        &mut self,
+        driver: &mut Driver,
+        handler: &mut H,
+    ) -> Result<u32, Driver::Error>
+    where
+        Driver: driver::SplitMaster,
+        H: Handler + ?Sized,
+    {
+        if self.in_flight.is_some() {
+            return Err(driver::Error::Timeout.into());
+        }
+        let slot = match self.slots.get(self.next) {
+            Some(slot) => *slot,
+            None => return Ok(0),
+        };
+        let buffer = match self.prepared {
+            Some(buffer) => buffer,
+            None => self.prepare(self.next, 0, handler),
+        };
+        let length = slot.data_length();
+        check_response_length(slot.pid(), length)?;
+        log!(
+            trace,
+            "schedule slot {} ({:#04x})",
+            self.next,
+            slot.frame.id
+        );
+        match slot.frame.publisher {
+            Publisher::Master => {
+                driver.start_write(slot.pid(), &self.buffers[buffer][..=length])?;
+            }
+            Publisher::Slave(_) => driver.start_read(slot.pid(), length + 1)?,
+        }
+        self.in_flight = Some(self.next);
+        // The next response is prepared while the transfer is in flight
+        self.next = (self.next + 1) % self.slots.len();
+        self.prepared = Some(self.prepare(self.next, 1 - buffer, handler));
+        Ok(slot.delay_us)
+    }
+
+    /// Check whether the transfer of the current slot is complete. Returns the received frame
+    /// for frames published by a slave and `None` for frames published by the master or if no
+    /// transfer is in flight.
+    pub fn poll<Driver>(
+        &mut self,
+        driver: &mut Driver,
+    ) -> Poll<Result<Option<Frame>, Driver::Error>>
+    where
+        Driver: driver::SplitMaster,
+    {
+        let slot = match self.in_flight.and_then(|index| self.slots.get(index)) {
+            Some(slot) => *slot,
+            None => return Poll::Ready(Ok(None)),
+        };
+        let length = slot.data_length();
+        let mut frame = Frame {
+            pid: slot.pid(),
+            buffer: [0u8; 9],
+            data_length: length as u8,
+            checksum_model: slot.frame.checksum_model,
+        };
+        let buf = match slot.frame.publisher {
+            Publisher::Master => &mut frame.buffer[..0],
+            Publisher::Slave(_) => &mut frame.buffer[..=length],
+        };
+        let result = match driver.poll_complete(buf) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(result) => result,
+        };
+        self.in_flight = None;
+        Poll::Ready(result.and_then(|()| {
+            match slot.frame.publisher {
+                Publisher::Master => Ok(None),
+                Publisher::Slave(_) => frame
+                    .verify_checksum()
+                    .map(Some)
+                    .map_err(|mismatch| driver::Error::Checksum(mismatch).into()),
+            }
+        }))
+    }
+
+    /// Fill `buffer` with the response of the slot at `index` if the master publishes it and
+    /// return the buffer index
+    fn prepare<H: Handler + ?Sized>(
+        &mut self,
+        index: usize,
+        buffer: usize,
+        handler: &mut H,
+    ) -> usize {
+        if let Some(slot) = self.slots.get(index) {
+            if slot.frame.publisher == Publisher::Master {
+                let length = slot.data_length();
+                let bytes = &mut self.buffers[buffer];
+                handler.publish(slot.pid(), &mut bytes[..length]);
+                bytes[length] = slot
+                    .frame
+                    .checksum_model
+                    .calculate(slot.pid(), &bytes[..length]);
+            }
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::loopback::Loopback;
+
+    /// Handler publishing the ID of the frame in all bytes and recording the last received
+    /// frame
+    #[derive(Default)]
+    struct Recorder(Option<Frame>);
+
+    impl Handler for Recorder {
+        fn publish(&mut self, pid: PID, data: &mut [u8]) {
+            data.fill(pid.get_id());
+        }
+
+        fn receive(&mut self, frame: &Frame) {
+            self.0 = Some(Frame::from_data(frame.get_pid(), frame.get_data()));
+        }
+    }
+
+    /// DMA driver recording started transfers and completing them on the first poll
+    #[derive(Default)]
+    struct Dma {
+        started: Option<(PID, [u8; 9], usize)>,
+        response: &'static [u8],
+    }
+
+    impl driver::SplitMaster for Dma {
+        type Error = driver::Error;
+
+        fn start_write(&mut self, pid: PID, data: &[u8]) -> Result<(), driver::Error> {
+            let mut bytes = [0u8; 9];
+            bytes[..data.len()].copy_from_slice(data);
+            self.started = Some((pid, bytes, data.len()));
+            Ok(())
+        }
+
+        fn start_read(&mut self, pid: PID, length: usize) -> Result<(), driver::Error> {
+            self.started = Some((pid, [0u8; 9], length));
+            Ok(())
+        }
+
+        fn poll_complete(&mut self, buf: &mut [u8]) -> Poll<Result<(), driver::Error>> {
+            buf.copy_from_slice(&self.response[..buf.len()]);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    const SLOTS: &[Slot] = &[
+        Slot::new(FrameConfig::new(0x11, 2, Publisher::Master), 5_000),
+        Slot::new(FrameConfig::new(0x10, 1, Publisher::Slave(0)), 10_000),
+    ];
+
+    #[test]
+    fn test_scheduler() {
+        let mut driver = Loopback::new();
+        driver.set_response(PID::from_id(0x10), &[0x05]);
+        let mut scheduler = Scheduler::new(SLOTS);
+        let mut handler = Recorder::default();
+
+        assert_eq!(scheduler.run_slot(&mut driver, &mut handler), Ok(5_000));
+        let frame = Frame::from_data(PID::from_id(0x11), &[0x11, 0x11]);
+        assert_eq!(driver.pending(), frame.get_data_with_checksum());
+        let mut buf = [0u8; 3];
+        crate::driver::Master::read(&mut driver, &mut buf).unwrap();
+
+        assert_eq!(scheduler.run_slot(&mut driver, &mut handler), Ok(10_000));
+        assert_eq!(scheduler.next_slot(), 0);
+        assert_eq!(
+            handler.0,
+            Some(Frame::from_data(PID::from_id(0x10), &[0x05]))
+        );
+
+        driver.clear_response(PID::from_id(0x10));
+        scheduler.run_slot(&mut driver, &mut handler).unwrap();
+        crate::driver::Master::read(&mut driver, &mut buf).unwrap();
+        assert_eq!(
+            scheduler.run_slot(&mut driver, &mut handler),
+            Err(driver::Error::NoResponse)
+        );
+        assert_eq!(scheduler.next_slot(), 0);
+    }
+
+    #[test]
+    fn test_run_slot_timed() {
+        let mut driver = Loopback::new();
+        let mut scheduler = Scheduler::new(SLOTS);
+        let mut handler = Recorder::default();
+        let mut timer = crate::time::tests::TestClock {
+            step: 1_000,
+            ..Default::default()
+        };
+        scheduler
+            .run_slot_timed(&mut driver, &mut handler, &mut timer)
+            .unwrap();
+        assert_eq!(timer.now, 6_000);
+        crate::driver::Master::read(&mut driver, &mut [0u8; 3]).unwrap();
+        assert_eq!(
+            scheduler.run_slot_timed(&mut driver, &mut handler, &mut timer),
+            Err(driver::Error::NoResponse)
+        );
+        assert_eq!(timer.now, 17_000);
+    }
+
+    #[test]
+    #[cfg(feature = "dyn-schedule")]
+    fn test_dyn_scheduler() {
+        let mut driver = Loopback::new();
+        driver.set_response(PID::from_id(0x10), &[0x05]);
+        let mut handler = Recorder::default();
+        let mut scheduler = DynScheduler::new(&SLOTS[1..], &mut handler);
+        let master: &mut dyn Master<Error = driver::Error> = &mut driver;
+        assert_eq!(scheduler.run_slot(master), Ok(10_000));
+        assert_eq!(
+            handler.0,
+            Some(Frame::from_data(PID::from_id(0x10), &[0x05]))
+        );
+    }
+
+    #[test]
+    fn test_pipelined_schedule() {
+        let mut driver = Dma {
+            response: &[0x01, 0xAE],
+            ..Default::default()
+        };
+        let mut schedule = PipelinedSchedule::new(SLOTS);
+        let mut counter = 0;
+        let mut publish = |pid: PID, data: &mut [u8]| {
+            assert_eq!(pid, PID::from_id(0x11));
+            counter += 1;
+            data.fill(counter);
+        };
+
+        assert_eq!(schedule.start_slot(&mut driver, &mut publish), Ok(5_000));
+        let frame = Frame::from_data(PID::from_id(0x11), &[1, 1]);
+        let (pid, bytes, length) = driver.started.unwrap();
+        assert_eq!(pid, PID::from_id(0x11));
+        assert_eq!(&bytes[..length], frame.get_data_with_checksum());
+        assert_eq!(
+            schedule.start_slot(&mut driver, &mut publish),
+            Err(driver::Error::Timeout)
+        );
+        assert_eq!(schedule.poll(&mut driver), Poll::Ready(Ok(None)));
+
+        assert_eq!(schedule.start_slot(&mut driver, &mut publish), Ok(10_000));
+        assert_eq!(
+            driver.started.map(|(pid, _, length)| (pid, length)),
+            Some((PID::from_id(0x10), 2))
+        );
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01]);
+        assert_eq!(schedule.poll(&mut driver), Poll::Ready(Ok(Some(frame))));
+
+        // The response of the first slot was prepared while the second slot was in flight
+        assert_eq!(schedule.next_slot(), 0);
+        assert_eq!(schedule.start_slot(&mut driver, &mut publish), Ok(5_000));
+        let (_, bytes, _) = driver.started.unwrap();
+        assert_eq!(&bytes[..2], &[2, 2]);
+    }
+
+    #[test]
+    fn test_checksum_error() {
+        let mut driver = Dma {
+            response: &[0x01, 0x00],
+            ..Default::default()
+        };
+        let mut schedule = PipelinedSchedule::new(&SLOTS[1..]);
+        schedule
+            .start_slot(&mut driver, &mut |_: PID, _: &mut [u8]| {})
+            .unwrap();
+        assert!(matches!(
+            schedule.poll(&mut driver),
+            Poll::Ready(Err(driver::Error::Checksum(_)))
+        ));
+        assert_eq!(schedule.poll(&mut driver), Poll::Ready(Ok(None)));
+    }
+}
@@ -0,0 +1,530 @@
+//! Signal descriptors describing where a signal lives inside a frame and how to interpret it
+
+pub mod interface;
+
+use crate::frame::{Frame, FrameError, PID};
+
+/// Byte order of a signal within the frame data
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Least significant byte first. This is the byte order defined by the LIN specification.
+    LittleEndian,
+    /// Most significant byte first. Only supported for byte aligned signals with a length which
+    /// is a multiple of 8.
+    BigEndian,
+}
+
+/// Signedness of the raw signal value
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Signedness {
+    Unsigned,
+    /// Two's complement signed value
+    Signed,
+}
+
+/// Linear conversion between the raw value and the physical value.
+/// `physical = raw * factor + offset`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Scaling {
+    pub factor: f32,
+    pub offset: f32,
+}
+
+impl Scaling {
+    pub const fn new(factor: f32, offset: f32) -> Scaling {
+        Scaling { factor, offset }
+    }
+}
+
+/// Decode a binary coded decimal value, returns `None` if a nibble is not a decimal digit
+pub const fn decode_bcd(raw: u64) -> Option<u64> {
+    let mut value = 0;
+    let mut factor = 1;
+    let mut raw = raw;
+    while raw != 0 {
+        let digit = raw & 0xF;
+        if digit > 9 {
+            return None;
+        }
+        value += digit * factor;
+        factor *= 10;
+        raw >>= 4;
+    }
+    Some(value)
+}
+
+/// Maximal value encodable as binary coded decimal, which has 16 digits
+pub const MAX_BCD: u64 = 9_999_999_999_999_999;
+
+/// Encode a value as binary coded decimal, returns `None` if the value exceeds `MAX_BCD`
+pub const fn encode_bcd(value: u64) -> Option<u64> {
+    if value > MAX_BCD {
+        return None;
+    }
+    let mut raw = 0;
+    let mut shift = 0;
+    let mut value = value;
+    while value != 0 {
+        raw |= (value % 10) << shift;
+        shift += 4;
+        value /= 10;
+    }
+    Some(raw)
+}
+
+/// Describes the layout and encoding of a signal within a frame. Intended to be stored in const
+/// tables, e.g.
+///
+/// ```
+/// use lin_bus::signal::Signal;
+/// const TEMPERATURE: Signal = Signal::new(8, 12).with_scaling(0.1, -40.0);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Signal {
+    /// Bit offset of the least significant bit within the frame data
+    pub offset: u8,
+    /// Length in bits (1..=64)
+    pub length: u8,
+    pub byte_order: ByteOrder,
+    pub signedness: Signedness,
+    /// Physical encoding, `None` if the raw value is used as is
+    pub scaling: Option<Scaling>,
+}
+
+impl Signal {
+    /// Creates an unsigned little endian signal without scaling
+    pub const fn new(offset: u8, length: u8) -> Signal {
+        Signal {
+            offset,
+            length,
+            byte_order: ByteOrder::LittleEndian,
+            signedness: Signedness::Unsigned,
+            scaling: None,
+        }
+    }
+
+    /// Returns the signal with the given byte order
+    pub const fn with_byte_order(self, byte_order: ByteOrder) -> Signal {
+        Signal { byte_order, ..self }
+    }
+
+    /// Returns the signal as two's complement signed signal
+    pub const fn signed(self) -> Signal {
+        Signal {
+            signedness: Signedness::Signed,
+            ..self
+        }
+    }
+
+    /// Returns the signal with a linear physical encoding
+    pub const fn with_scaling(self, factor: f32, offset: f32) -> Signal {
+        Signal {
+            scaling: Some(Scaling::new(factor, offset)),
+            ..self
+        }
+    }
+
+    /// Return the first bit after the signal
+    pub const fn end(&self) -> usize {
+        self.offset as usize + self.length as usize
+    }
+
+    /// Check if the signal is well formed and fits into a frame of `data_length` bytes
+    pub const fn fits(&self, data_length: usize) -> bool {
+        let aligned = match self.byte_order {
+            ByteOrder::LittleEndian => true,
+            ByteOrder::BigEndian => self.offset % 8 == 0 && self.length % 8 == 0,
+        };
+        aligned && self.length > 0 && self.length <= 64 && self.end() <= data_length * 8
+    }
+
+    /// Bit mask of the raw value
+    pub const fn mask(&self) -> u64 {
+        if self.length >= 64 {
+            u64::MAX
+        } else {
+            (1u64 << self.length) - 1
+        }
+    }
+
+    /// Interpret the raw value according to the signedness of the signal
+    pub const fn to_i64(&self, raw: u64) -> i64 {
+        match self.signedness {
+            Signedness::Unsigned => raw as i64,
+            Signedness::Signed => {
+                let shift = 64 - self.length as u32;
+                ((raw << shift) as i64) >> shift
+            }
+        }
+    }
+
+    /// Convert a raw value to the physical value
+    pub fn to_physical(&self, raw: u64) -> f32 {
+        let value = self.to_i64(raw) as f32;
+        match self.scaling {
+            Some(scaling) => value * scaling.factor + scaling.offset,
+            None => value,
+        }
+    }
+
+    /// Convert a physical value to the raw value. The value is rounded to the nearest raw value
+    /// and saturated to the range of the signal.
+    pub fn from_physical(&self, physical: f32) -> u64 {
+        let value = match self.scaling {
+            Some(scaling) => (physical - scaling.offset) / scaling.factor,
+            None => physical,
+        };
+        let value = if value >= 0.0 {
+            value + 0.5
+        } else {
+            value - 0.5
+        };
+        let raw = match self.signedness {
+            Signedness::Unsigned => {
+                let max = self.mask();
+                if value <= 0.0 {
+                    0
+                } else if value >= max as f32 {
+                    max
+                } else {
+                    value as u64
+                }
+            }
+            Signedness::Signed => {
+                let max = (self.mask() >> 1) as i64;
+                let min = -max - 1;
+                let value = if value <= min as f32 {
+                    min
+                } else if value >= max as f32 {
+                    max
+                } else {
+                    value as i64
+                };
+                value as u64
+            }
+        };
+        raw & self.mask()
+    }
+
+    /// Extract the raw value from frame data, fails if the signal does not fit into the data.
+    /// Usable in constant expressions, e.g. for the expected raw values of test vectors:
+    ///
+    /// ```
+    /// use lin_bus::signal::Signal;
+    /// const RAW: u64 = match Signal::new(4, 8).decode(&[0xB0, 0x0A]) {
+    ///     Ok(raw) => raw,
+    ///     Err(_) => panic!("Signal does not fit"),
+    /// };
+    /// assert_eq!(RAW, 0xAB);
+    /// ```
+    pub const fn decode(&self, data: &[u8]) -> Result<u64, FrameError> {
+        if data.len() > 8 || !self.fits(data.len()) {
+            return Err(FrameError::OutOfRange);
+        }
+        let mut bytes = [0u8; 8];
+        let mut i = 0;
+        while i < data.len() {
+            bytes[i] = data[i];
+            i += 1;
+        }
+        Ok(self.decode_unchecked(bytes))
+    }
+
+    /// Insert the raw value into frame data and return the data, fails if the signal does not
+    /// fit into the data. Usable in constant expressions like `decode`.
+    pub const fn encode<const N: usize>(
+        &self,
+        data: [u8; N],
+        raw: u64,
+    ) -> Result<[u8; N], FrameError> {
+        if N > 8 || !self.fits(N) {
+            return Err(FrameError::OutOfRange);
+        }
+        let mut bytes = [0u8; 8];
+        let mut i = 0;
+        while i < N {
+            bytes[i] = data[i];
+            i += 1;
+        }
+        let bytes = self.encode_unchecked(bytes, raw);
+        let mut data = data;
+        let mut i = 0;
+        while i < N {
+            data[i] = bytes[i];
+            i += 1;
+        }
+        Ok(data)
+    }
+
+    /// Insert the raw value into frame data
+    pub(crate) fn insert(&self, data: &mut [u8], raw: u64) -> Result<(), FrameError> {
+        if data.len() > 8 || !self.fits(data.len()) {
+            return Err(FrameError::OutOfRange);
+        }
+        let mut bytes = [0u8; 8];
+        bytes[0..data.len()].copy_from_slice(data);
+        let bytes = self.encode_unchecked(bytes, raw);
+        data.copy_from_slice(&bytes[0..data.len()]);
+        Ok(())
+    }
+
+    /// Extract the raw value from 8 bytes of data, which the signal has to fit into
+    const fn decode_unchecked(&self, bytes: [u8; 8]) -> u64 {
+        let raw = match self.byte_order {
+            ByteOrder::LittleEndian => u64::from_le_bytes(bytes) >> self.offset,
+            ByteOrder::BigEndian => {
+                let mut raw = 0u64;
+                let mut i = self.offset as usize / 8;
+                // `fits` limits the end to the 8 bytes, checking it again removes a panic path
+                while i < self.end() / 8 && i < bytes.len() {
+                    raw = (raw << 8) | bytes[i] as u64;
+                    i += 1;
+                }
+                raw
+            }
+        };
+        raw & self.mask()
+    }
+
+    /// Insert the raw value into 8 bytes of data, which the signal has to fit into
+    const fn encode_unchecked(&self, bytes: [u8; 8], raw: u64) -> [u8; 8] {
+        let raw = raw & self.mask();
+        match self.byte_order {
+            ByteOrder::LittleEndian => {
+                let num = u64::from_le_bytes(bytes);
+                let num = (num & !(self.mask() << self.offset)) | (raw << self.offset);
+                num.to_le_bytes()
+            }
+            ByteOrder::BigEndian => {
+                let mut bytes = bytes;
+                let mut raw = raw;
+                let mut i = self.end() / 8;
+                while i > self.offset as usize / 8 {
+                    i -= 1;
+                    bytes[i] = raw as u8;
+                    raw >>= 8;
+                }
+                bytes
+            }
+        }
+    }
+}
+
+/// A signal of interest, identified by the PID of the frame carrying it and its descriptor
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WatchedSignal {
+    pub pid: PID,
+    pub signal: Signal,
+}
+
+impl WatchedSignal {
+    pub const fn new(pid: PID, signal: Signal) -> WatchedSignal {
+        WatchedSignal { pid, signal }
+    }
+}
+
+/// Tracks the values of a fixed set of signals and detects changes when frames are received.
+///
+/// Changes can either be handled with a callback in `update_with` or polled later through the
+/// dirty flags with `take_changed`.
+#[derive(Clone, Debug)]
+pub struct SignalWatcher<const N: usize> {
+    signals: [WatchedSignal; N],
+    values: [Option<u64>; N],
+    dirty: [bool; N],
+}
+
+impl<const N: usize> SignalWatcher<N> {
+    pub const fn new(signals: [WatchedSignal; N]) -> SignalWatcher<N> {
+        SignalWatcher {
+            signals,
+            values: [None; N],
+            dirty: [false; N],
+        }
+    }
+
+    /// Update the watched signals from a received frame. Returns the number of changed signals.
+    pub fn update(&mut self, frame: &Frame) -> usize {
+        self.update_with(frame, |_, _, _| {})
+    }
+
+    /// Update the watched signals from a received frame and call `on_change` with the index of
+    /// the signal, the previous value (`None` on first reception) and the new raw value for every
+    /// changed signal. Returns the number of changed signals.
+    pub fn update_with<F>(&mut self, frame: &Frame, mut on_change: F) -> usize
+    where
+        F: FnMut(usize, Option<u64>, u64),
+    {
+        let mut changed = 0;
+        for (index, watched) in self.signals.iter().enumerate() {
+            if watched.pid != frame.get_pid() {
+                continue;
+            }
+            let value = match frame.try_read_signal(&watched.signal) {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            let previous = self.values[index];
+            if previous != Some(value) {
+                self.values[index] = Some(value);
+                self.dirty[index] = true;
+                changed += 1;
+                on_change(index, previous, value);
+            }
+        }
+        changed
+    }
+
+    /// Get the last received raw value of the signal at `index`
+    pub fn value(&self, index: usize) -> Option<u64> {
+        self.values[index]
+    }
+
+    /// Check if the signal at `index` changed since the last call to `take_changed`
+    pub fn is_changed(&self, index: usize) -> bool {
+        self.dirty[index]
+    }
+
+    /// Return the raw value of the signal at `index` if it changed and clear the dirty flag
+    pub fn take_changed(&mut self, index: usize) -> Option<u64> {
+        if core::mem::replace(&mut self.dirty[index], false) {
+            self.values[index]
+        } else {
+            None
+        }
+    }
+
+    /// Iterate over the indices of all changed signals
+    pub fn changed(&self) -> impl Iterator<Item = usize> + '_ {
+        self.dirty
+            .iter()
+            .enumerate()
+            .filter(|(_, dirty)| **dirty)
+            .map(|(index, _)| index)
+    }
+
+    /// Get the watched signal at `index`
+    pub fn signal(&self, index: usize) -> &WatchedSignal {
+        &self.signals[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_signal() {
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x55, 0xDD, 0x0F, 0xF0]);
+        assert_eq!(frame.read_signal(&Signal::new(0, 16)), 0xDD55);
+        assert_eq!(frame.read_signal(&Signal::new(4, 8)), 0xD5);
+        assert_eq!(frame.read_signal(&Signal::new(20, 8)), 0x00);
+        assert_eq!(
+            frame.read_signal(&Signal::new(0, 16).with_byte_order(ByteOrder::BigEndian)),
+            0x55DD
+        );
+    }
+
+    #[test]
+    fn test_write_signal() {
+        let signal = Signal::new(4, 8);
+        let mut frame = Frame::from_data(PID::from_id(0x10), &[0x00, 0x00]);
+        frame.write_signal(&signal, 0xAB);
+        assert_eq!(frame.get_data(), &[0xB0, 0x0A]);
+        assert_eq!(frame.read_signal(&signal), 0xAB);
+        assert_eq!(
+            frame,
+            Frame::from_data(PID::from_id(0x10), &[0xB0, 0x0A]),
+            "Checksum must be updated"
+        );
+
+        let signal = Signal::new(8, 16).with_byte_order(ByteOrder::BigEndian);
+        let mut frame = Frame::from_data(PID::from_id(0x10), &[0x00, 0x00, 0x00]);
+        frame.write_signal(&signal, 0x1234);
+        assert_eq!(frame.get_data(), &[0x00, 0x12, 0x34]);
+
+        let signal = Signal::new(16, 16);
+        assert_eq!(frame.try_read_signal(&signal), Err(FrameError::OutOfRange));
+        assert_eq!(
+            frame.try_write_signal(&signal, 0x1234),
+            Err(FrameError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_const_signals() {
+        const SIGNAL: Signal = Signal::new(4, 8);
+        const DATA: [u8; 2] = match SIGNAL.encode([0x00, 0x00], 0xAB) {
+            Ok(data) => data,
+            Err(_) => panic!("Signal does not fit"),
+        };
+        const RAW: Result<u64, FrameError> = SIGNAL.decode(&DATA);
+        assert_eq!(DATA, [0xB0, 0x0A]);
+        assert_eq!(RAW, Ok(0xAB));
+
+        const BIG_ENDIAN: Signal = Signal::new(8, 16).with_byte_order(ByteOrder::BigEndian);
+        assert_eq!(BIG_ENDIAN.encode([0u8; 3], 0x1234), Ok([0x00, 0x12, 0x34]));
+        assert_eq!(BIG_ENDIAN.decode(&[0x00, 0x12, 0x34]), Ok(0x1234));
+        assert_eq!(
+            BIG_ENDIAN.encode([0u8; 2], 0x1234),
+            Err(FrameError::OutOfRange)
+        );
+        assert_eq!(SIGNAL.decode(&[0u8; 9]), Err(FrameError::OutOfRange));
+    }
+
+    #[test]
+    fn test_signed_physical() {
+        const SIGNAL: Signal = Signal::new(0, 8).signed().with_scaling(0.5, 10.0);
+        assert_eq!(SIGNAL.to_i64(0xFF), -1);
+        assert_eq!(SIGNAL.to_physical(0xFE), 9.0);
+        assert_eq!(SIGNAL.from_physical(9.0), 0xFE);
+        assert_eq!(SIGNAL.from_physical(1000.0), 0x7F);
+        assert_eq!(SIGNAL.from_physical(-1000.0), 0x80);
+    }
+
+    #[test]
+    fn test_bcd() {
+        assert_eq!(decode_bcd(0x1234), Some(1234));
+        assert_eq!(decode_bcd(0x1A), None);
+        assert_eq!(encode_bcd(1234), Some(0x1234));
+        assert_eq!(encode_bcd(0), Some(0));
+        assert_eq!(encode_bcd(MAX_BCD), Some(0x9999_9999_9999_9999));
+        assert_eq!(encode_bcd(MAX_BCD + 1), None);
+        assert_eq!(decode_bcd(0x9999_9999_9999_9999), Some(MAX_BCD));
+    }
+
+    #[test]
+    fn test_signal_fits() {
+        assert!(Signal::new(0, 64).fits(8));
+        assert!(!Signal::new(1, 64).fits(8));
+        assert!(!Signal::new(0, 0).fits(8));
+        assert!(!Signal::new(4, 8)
+            .with_byte_order(ByteOrder::BigEndian)
+            .fits(8));
+    }
+
+    #[test]
+    fn test_signal_watcher() {
+        const PID_A: PID = PID::from_id(0x10);
+        let mut watcher = SignalWatcher::new([
+            WatchedSignal::new(PID_A, Signal::new(0, 8)),
+            WatchedSignal::new(PID_A, Signal::new(8, 8)),
+            WatchedSignal::new(PID::from_id(0x11), Signal::new(0, 8)),
+        ]);
+
+        assert_eq!(watcher.update(&Frame::from_data(PID_A, &[1, 2])), 2);
+        assert_eq!(watcher.changed().count(), 2);
+        assert_eq!(watcher.take_changed(0), Some(1));
+        assert_eq!(watcher.take_changed(0), None);
+
+        let mut changes = [None; 3];
+        let changed = watcher.update_with(&Frame::from_data(PID_A, &[1, 3]), |i, old, new| {
+            changes[i] = Some((old, new))
+        });
+        assert_eq!(changed, 1);
+        assert_eq!(changes, [None, Some((Some(2), 3)), None]);
+        assert_eq!(watcher.value(1), Some(3));
+        assert_eq!(watcher.value(2), None);
+        assert!(!watcher.is_changed(2));
+    }
+}
@@ -0,0 +1,266 @@
+//! Signal interface of the application
+//!
+//! `SignalInterface` keeps a struct of typed signal values of the application in sync with the
+//! bus. The struct implements `SignalSet` to unpack its fields from the received frames and
+//! pack them into the frames published by the node, usually with the signal descriptors of
+//! the frames. Received frames update the fields, writes of the application with
+//! `SignalInterface::write` mark the published frames whose data changed as dirty. The dirty
+//! frames are sent in the next slot publishing them, as the interface implements the
+//! `schedule::Handler`, or taken with `take_dirty` by masters sending frames on demand.
+//!
+//! ```
+//! use lin_bus::cluster::{FrameConfig, Publisher};
+//! use lin_bus::signal::interface::{SignalInterface, SignalSet};
+//! use lin_bus::signal::Signal;
+//! use lin_bus::Frame;
+//!
+//! const LIGHTS: FrameConfig = FrameConfig::new(0x10, 1, Publisher::Master);
+//! const SWITCHES: FrameConfig = FrameConfig::new(0x11, 1, Publisher::Slave(0));
+//! const HEADLIGHT: Signal = Signal::new(0, 1);
+//! const SWITCH: Signal = Signal::new(0, 2);
+//!
+//! #[derive(Default)]
+//! struct Signals {
+//!     headlight: bool,
+//!     switch: u8,
+//! }
+//!
+//! impl SignalSet for Signals {
+//!     fn unpack(&mut self, frame: &Frame) {
+//!         if frame.get_pid().get_id() == SWITCHES.id {
+//!             self.switch = frame.try_read_signal(&SWITCH).unwrap_or(0) as u8;
+//!         }
+//!     }
+//!
+//!     fn pack(&self, frame: &mut Frame) {
+//!         if frame.get_pid().get_id() == LIGHTS.id {
+//!             let _ = frame.try_write_signal(&HEADLIGHT, self.headlight as u64);
+//!         }
+//!     }
+//! }
+//!
+//! let mut interface =
+//!     SignalInterface::new(Signals::default(), Publisher::Master, [LIGHTS, SWITCHES]);
+//! assert_eq!(interface.write(|signals| signals.headlight = true), 1);
+//! let frame = interface.take_dirty().unwrap();
+//! assert_eq!(frame.get_data(), [0x01]);
+//! ```
+
+use crate::cluster::{FrameConfig, Publisher};
+use crate::frame::{Frame, PID};
+#[cfg(feature = "schedule")]
+use crate::schedule::Handler;
+
+/// Application struct of signal values, see the module documentation
+pub trait SignalSet {
+    /// Update the fields from a received frame subscribed by the node
+    fn unpack(&mut self, frame: &Frame);
+
+    /// Write the fields into a frame published by the node, whose data holds the values
+    /// packed last
+    fn pack(&self, frame: &mut Frame);
+}
+
+/// Synchronization of a `SignalSet` with the frames of the cluster, see the module
+/// documentation
+#[derive(Clone, Debug)]
+pub struct SignalInterface<S, const N: usize> {
+    values: S,
+    node: Publisher,
+    frames: [FrameConfig; N],
+    /// Data of the published frames packed last
+    data: [[u8; 8]; N],
+    dirty: [bool; N],
+    received: [bool; N],
+}
+
+impl<S: SignalSet, const N: usize> SignalInterface<S, N> {
+    /// Interface of the node `node` to `frames`, which it publishes or subscribes to. The
+    /// published frames are packed from the initial `values`.
+    pub fn new(values: S, node: Publisher, frames: [FrameConfig; N]) -> SignalInterface<S, N> {
+        let mut interface = SignalInterface {
+            values,
+            node,
+            frames,
+            data: [[0; 8]; N],
+            dirty: [false; N],
+            received: [false; N],
+        };
+        interface.pack();
+        interface
+    }
+
+    pub fn values(&self) -> &S {
+        &self.values
+    }
+
+    /// Update the values with `write` and mark the published frames whose data changed as
+    /// dirty. Returns the number of frames marked.
+    pub fn write<F: FnOnce(&mut S)>(&mut self, write: F) -> usize {
+        write(&mut self.values);
+        self.pack()
+    }
+
+    fn pack(&mut self) -> usize {
+        let mut marked = 0;
+        for (index, config) in self.frames.iter().enumerate() {
+            if config.publisher != self.node {
+                continue;
+            }
+            let mut frame = match frame(config, &self.data[index]) {
+                Some(frame) => frame,
+                None => continue,
+            };
+            self.values.pack(&mut frame);
+            let data = frame.get_data();
+            if data != &self.data[index][..data.len()] {
+                self.data[index][..data.len()].copy_from_slice(data);
+                marked += usize::from(!self.dirty[index]);
+                self.dirty[index] = true;
+            }
+        }
+        marked
+    }
+
+    /// Update the values from a received frame. Returns `false` if the node does not
+    /// subscribe to the frame.
+    pub fn receive(&mut self, frame: &Frame) -> bool {
+        let index = match self.index(frame.get_pid()) {
+            Some(index) if self.frames[index].publisher != self.node => index,
+            _ => return false,
+        };
+        self.values.unpack(frame);
+        self.received[index] = true;
+        true
+    }
+
+    /// Check if the frame is published by the node and changed since it was sent last
+    pub fn is_dirty(&self, pid: PID) -> bool {
+        self.index(pid).map_or(false, |index| self.dirty[index])
+    }
+
+    /// Return whether the frame was received since the last call and clear the flag
+    pub fn take_received(&mut self, pid: PID) -> bool {
+        self.index(pid).map_or(false, |index| {
+            core::mem::replace(&mut self.received[index], false)
+        })
+    }
+
+    /// The next dirty frame, which is considered sent
+    pub fn take_dirty(&mut self) -> Option<Frame> {
+        let index = self.dirty.iter().position(|dirty| *dirty)?;
+        self.dirty[index] = false;
+        frame(&self.frames[index], &self.data[index])
+    }
+
+    fn index(&self, pid: PID) -> Option<usize> {
+        self.frames
+            .iter()
+            .position(|config| config.id == pid.get_id())
+    }
+}
+
+fn frame(config: &FrameConfig, data: &[u8; 8]) -> Option<Frame> {
+    let length = usize::from(config.data_length).min(8);
+    Frame::try_from_data_with_checksum_model(config.pid(), &data[..length], config.checksum_model)
+        .ok()
+}
+
+/// Publishes the packed data of the frames published by the node and unpacks the received
+/// frames
+#[cfg(feature = "schedule")]
+impl<S: SignalSet, const N: usize> Handler for SignalInterface<S, N> {
+    fn publish(&mut self, pid: PID, data: &mut [u8]) {
+        let index = match self.index(pid) {
+            Some(index) if self.frames[index].publisher == self.node => index,
+            _ => return,
+        };
+        let length = data.len().min(8);
+        data[..length].copy_from_slice(&self.data[index][..length]);
+        self.dirty[index] = false;
+    }
+
+    fn receive(&mut self, frame: &Frame) {
+        SignalInterface::receive(self, frame);
+    }
+}
+
+#[cfg(all(test, feature = "schedule"))]
+mod tests {
+    use super::*;
+    use crate::signal::Signal;
+
+    const LIGHTS: FrameConfig = FrameConfig::new(0x10, 2, Publisher::Master);
+    const WIPER: FrameConfig = FrameConfig::new(0x11, 1, Publisher::Master);
+    const STATUS: FrameConfig = FrameConfig::new(0x12, 2, Publisher::Slave(0));
+    const HEADLIGHT: Signal = Signal::new(0, 1);
+    const DIMMING: Signal = Signal::new(8, 8);
+    const WIPER_SPEED: Signal = Signal::new(0, 3);
+    const TEMPERATURE: Signal = Signal::new(0, 16).signed();
+
+    #[derive(Default)]
+    struct Signals {
+        headlight: bool,
+        dimming: u8,
+        wiper_speed: u8,
+        temperature: i16,
+    }
+
+    impl SignalSet for Signals {
+        fn unpack(&mut self, frame: &Frame) {
+            if frame.get_pid().get_id() == STATUS.id {
+                if let Ok(raw) = frame.try_read_signal(&TEMPERATURE) {
+                    self.temperature = TEMPERATURE.to_i64(raw) as i16;
+                }
+            }
+        }
+
+        fn pack(&self, frame: &mut Frame) {
+            match frame.get_pid().get_id() {
+                id if id == LIGHTS.id => {
+                    frame.write_signal(&HEADLIGHT, self.headlight as u64);
+                    frame.write_signal(&DIMMING, self.dimming.into());
+                }
+                id if id == WIPER.id => frame.write_signal(&WIPER_SPEED, self.wiper_speed.into()),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_signal_interface() {
+        let mut interface = SignalInterface::new(
+            Signals {
+                dimming: 0x80,
+                ..Signals::default()
+            },
+            Publisher::Master,
+            [LIGHTS, WIPER, STATUS],
+        );
+        let mut data = [0xFF; 2];
+        interface.publish(LIGHTS.pid(), &mut data);
+        assert_eq!(data, [0x00, 0x80]);
+        assert!(interface.take_dirty().is_none());
+
+        assert_eq!(interface.write(|signals| signals.wiper_speed = 2), 1);
+        assert_eq!(interface.write(|signals| signals.wiper_speed = 2), 0);
+        assert_eq!(interface.write(|signals| signals.headlight = true), 1);
+        assert!(interface.is_dirty(LIGHTS.pid()) && interface.is_dirty(WIPER.pid()));
+
+        interface.publish(LIGHTS.pid(), &mut data);
+        assert_eq!(data, [0x01, 0x80]);
+        assert!(!interface.is_dirty(LIGHTS.pid()));
+        let frame = interface.take_dirty().unwrap();
+        assert_eq!(frame.get_pid(), WIPER.pid());
+        assert_eq!(frame.get_data(), [0x02]);
+        assert!(interface.take_dirty().is_none());
+
+        let status = Frame::from_data(STATUS.pid(), &[0xF6, 0xFF]);
+        assert!(interface.receive(&status));
+        assert_eq!(interface.values().temperature, -10);
+        assert!(interface.take_received(STATUS.pid()));
+        assert!(!interface.take_received(STATUS.pid()));
+        // Frames published by the node are not unpacked
+        assert!(!interface.receive(&Frame::from_data(LIGHTS.pid(), &[0x00, 0x00])));
+    }
+}
@@ -0,0 +1,284 @@
+//! Time base of the crate: `Instant`, `Duration` and the `Clock` and `Delay` traits
+//!
+//! Timing of the protocol, e.g. read timeouts, schedule slots and wakeup retries, is expressed
+//! with these types instead of leaving it to each driver. Platforms implement `Clock` with a
+//! free running timer and `Delay` with a blocking wait, both in µs, a type implementing both
+//! is a `Timer`. `StdClock` implements them with the standard library (feature `std`),
+//! `HalDelay` adapts an `embedded-hal` delay (feature `hal`).
+//!
+//! ```
+//! use lin_bus::time::{Clock, Duration, Instant};
+//!
+//! struct Timer(u64);
+//!
+//! impl Clock for Timer {
+//!     fn now(&mut self) -> Instant {
+//!         self.0 += 100;
+//!         Instant::from_micros(self.0)
+//!     }
+//! }
+//!
+//! let mut timer = Timer(0);
+//! let start = timer.now();
+//! assert_eq!(timer.now() - start, Duration::from_micros(100));
+//! assert!(timer.now() < start + Duration::from_millis(1));
+//! ```
+
+use core::ops::{Add, AddAssign, Sub};
+
+/// Time span with µs resolution
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Duration(u64);
+
+impl Duration {
+    pub const ZERO: Duration = Duration(0);
+
+    pub const fn from_micros(us: u64) -> Duration {
+        Duration(us)
+    }
+
+    pub const fn from_millis(ms: u64) -> Duration {
+        Duration(ms * 1_000)
+    }
+
+    pub const fn as_micros(self) -> u64 {
+        self.0
+    }
+
+    /// The duration in ms, rounded down
+    pub const fn as_millis(self) -> u64 {
+        self.0 / 1_000
+    }
+
+    pub const fn checked_add(self, other: Duration) -> Option<Duration> {
+        match self.0.checked_add(other.0) {
+            Some(us) => Some(Duration(us)),
+            None => None,
+        }
+    }
+
+    pub const fn saturating_sub(self, other: Duration) -> Duration {
+        Duration(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, other: Duration) -> Duration {
+        Duration(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, other: Duration) {
+        self.0 += other.0;
+    }
+}
+
+/// Saturates at zero like `Instant - Instant`
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, other: Duration) -> Duration {
+        self.saturating_sub(other)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Duration> for std::time::Duration {
+    fn from(duration: Duration) -> std::time::Duration {
+        std::time::Duration::from_micros(duration.0)
+    }
+}
+
+/// Point in time of a `Clock` in µs since an arbitrary epoch, e.g. the start of the timer
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Instant(u64);
+
+impl Instant {
+    pub const fn from_micros(us: u64) -> Instant {
+        Instant(us)
+    }
+
+    /// Time since the epoch of the clock in µs, like the timestamps of `trace::TimestampedFrame`
+    pub const fn as_micros(self) -> u64 {
+        self.0
+    }
+
+    /// Time elapsed since `earlier`, zero if `earlier` is later
+    pub const fn duration_since(self, earlier: Instant) -> Duration {
+        Duration(self.0.saturating_sub(earlier.0))
+    }
+
+    pub const fn checked_add(self, duration: Duration) -> Option<Instant> {
+        match self.0.checked_add(duration.0) {
+            Some(us) => Some(Instant(us)),
+            None => None,
+        }
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0 + duration.0)
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        self.0 += duration.0;
+    }
+}
+
+/// Time elapsed since `earlier`, zero if `earlier` is later
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// Monotonic clock
+pub trait Clock {
+    fn now(&mut self) -> Instant;
+}
+
+/// Blocking wait
+pub trait Delay {
+    fn delay(&mut self, duration: Duration);
+}
+
+/// `Clock` with a `Delay`, e.g. a hardware timer, implemented for all types implementing both
+pub trait Timer: Clock + Delay {
+    /// Wait until the clock reaches `deadline`, returns immediately if it has passed
+    fn delay_until(&mut self, deadline: Instant) {
+        let remaining = deadline - self.now();
+        if remaining > Duration::ZERO {
+            self.delay(remaining);
+        }
+    }
+}
+
+impl<T: Clock + Delay + ?Sized> Timer for T {}
+
+impl<C: Clock + ?Sized> Clock for &mut C {
+    fn now(&mut self) -> Instant {
+        (**self).now()
+    }
+}
+
+impl<D: Delay + ?Sized> Delay for &mut D {
+    fn delay(&mut self, duration: Duration) {
+        (**self).delay(duration)
+    }
+}
+
+/// `Clock` and `Delay` of the standard library, the epoch is the creation of the clock
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug)]
+pub struct StdClock {
+    epoch: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl StdClock {
+    pub fn new() -> StdClock {
+        StdClock {
+            epoch: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        StdClock::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now(&mut self) -> Instant {
+        Instant(self.epoch.elapsed().as_micros() as u64)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Delay for StdClock {
+    fn delay(&mut self, duration: Duration) {
+        std::thread::sleep(duration.into());
+    }
+}
+
+/// `Delay` of an `embedded-hal` delay provider
+#[cfg(feature = "hal")]
+pub struct HalDelay<D>(pub D);
+
+#[cfg(feature = "hal")]
+impl<D: embedded_hal::delay::DelayNs> Delay for HalDelay<D> {
+    fn delay(&mut self, duration: Duration) {
+        let mut remaining = duration.as_micros();
+        while remaining > 0 {
+            let step = remaining.min(u64::from(u32::MAX));
+            self.0.delay_us(step as u32);
+            remaining -= step;
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Simulated time, advanced by the delays and by `step` on every reading of the clock
+    #[derive(Clone, Debug, Default)]
+    pub(crate) struct TestClock {
+        pub now: u64,
+        pub step: u64,
+        pub delays: u32,
+    }
+
+    impl Clock for TestClock {
+        fn now(&mut self) -> Instant {
+            self.now += self.step;
+            Instant(self.now)
+        }
+    }
+
+    impl Delay for TestClock {
+        fn delay(&mut self, duration: Duration) {
+            self.now += duration.as_micros();
+            self.delays += 1;
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let start = Instant::from_micros(1_000);
+        let end = start + Duration::from_millis(2);
+        assert_eq!(end.as_micros(), 3_000);
+        assert_eq!(end - start, Duration::from_micros(2_000));
+        assert_eq!(start - end, Duration::ZERO);
+        assert_eq!(Duration(1) - Duration(2), Duration::ZERO);
+        assert_eq!(Duration::from_micros(2_500).as_millis(), 2);
+        assert_eq!(
+            Duration::from_micros(u64::MAX).checked_add(Duration(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_delay_until() {
+        let mut timer = TestClock::default();
+        timer.delay_until(Instant(500));
+        assert_eq!((timer.now, timer.delays), (500, 1));
+        timer.delay_until(Instant(500));
+        assert_eq!(timer.delays, 1);
+    }
+}
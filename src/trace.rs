@@ -0,0 +1,168 @@
+//! Trace files of the bus traffic
+//!
+//! A `TimestampedFrame` holds a transmitted or received frame with its timestamp, direction and
+//! the result of the reception. It is the common envelope of the frames of a master, a
+//! `monitor::BusMonitor` and imported logs, used by all writers and readers as well as by
+//! `replay` and `statistics`. The writers store the frames in the file formats of common
+//! analysis tools:
+//!
+//! * `pcapng::PcapngWriter` for Wireshark (feature `std`)
+//! * `asc::AscWriter` and `asc::AscReader` for the ASCII logs of Vector tools (feature `std`)
+//! * `csv::CsvWriter` for spreadsheets, writing to any `core::fmt::Write`
+//!
+//! Logs recorded by other tools are read with `asc::AscReader` and `blf::BlfReader`, the
+//! reader of the binary logs of Vector tools (feature `std`). `replay::Replay` replays the
+//! events of a trace through a master. `statistics::Statistics` computes the bus load, frame
+//! rates, response times and error ratios of live or imported records.
+
+use crate::frame::{ChecksumModel, Frame, PID};
+use crate::monitor::{MonitoredFrame, Verdict};
+
+#[cfg(feature = "std")]
+pub mod asc;
+#[cfg(feature = "std")]
+pub mod blf;
+pub mod csv;
+#[cfg(feature = "std")]
+mod inflate;
+#[cfg(feature = "std")]
+pub mod pcapng;
+pub mod replay;
+pub mod statistics;
+
+/// Direction of a traced frame, seen from the tracing node
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// The node published the response
+    Tx,
+    /// The response was received from another node or observed on the bus
+    Rx,
+}
+
+/// Frame with its timestamp, direction and verdict, e.g. an entry of a trace
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimestampedFrame {
+    /// Start of the frame in µs
+    pub timestamp_us: u64,
+    pub direction: Direction,
+    pub pid: PID,
+    pub checksum_model: ChecksumModel,
+    pub verdict: Verdict,
+    response: [u8; 9],
+    length: usize,
+}
+
+impl TimestampedFrame {
+    /// A complete frame
+    pub fn from_frame(timestamp_us: u64, direction: Direction, frame: &Frame) -> TimestampedFrame {
+        let bytes = frame.get_data_with_checksum();
+        let mut response = [0u8; 9];
+        response[..bytes.len()].copy_from_slice(bytes);
+        TimestampedFrame {
+            timestamp_us,
+            direction,
+            pid: frame.get_pid(),
+            checksum_model: frame.get_checksum_model(),
+            verdict: Verdict::Valid,
+            response,
+            length: bytes.len(),
+        }
+    }
+
+    /// A frame observed by a `monitor::BusMonitor`
+    pub fn from_monitored(timestamp_us: u64, frame: &MonitoredFrame) -> TimestampedFrame {
+        let bytes = frame.response();
+        let mut response = [0u8; 9];
+        response[..bytes.len()].copy_from_slice(bytes);
+        TimestampedFrame {
+            timestamp_us,
+            direction: Direction::Rx,
+            pid: frame.pid(),
+            checksum_model: frame.checksum_model(),
+            verdict: frame.verdict(),
+            response,
+            length: bytes.len(),
+        }
+    }
+
+    /// The frame if the response is valid
+    pub fn frame(&self) -> Option<Frame> {
+        match (self.verdict, self.checksum()) {
+            (Verdict::Valid, Some(_)) => {
+                Frame::try_from_data_with_checksum_model(self.pid, self.data(), self.checksum_model)
+                    .ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// All received bytes of the response
+    pub fn response(&self) -> &[u8] {
+        &self.response[..self.length]
+    }
+
+    /// The response data, all received bytes if the response is incomplete
+    pub fn data(&self) -> &[u8] {
+        match self.checksum() {
+            Some(_) => &self.response[..self.length - 1],
+            None => self.response(),
+        }
+    }
+
+    /// The checksum if the response is complete
+    pub fn checksum(&self) -> Option<u8> {
+        match self.verdict {
+            Verdict::Valid | Verdict::Checksum if self.length > 0 => {
+                Some(self.response[self.length - 1])
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Entry of a trace
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Frame(TimestampedFrame),
+    /// The cluster entered sleep mode, e.g. after a go-to-sleep command
+    Sleep {
+        timestamp_us: u64,
+    },
+    /// A wakeup signal was sent or received
+    Wakeup {
+        timestamp_us: u64,
+        direction: Direction,
+    },
+}
+
+impl Event {
+    pub fn timestamp_us(&self) -> u64 {
+        match self {
+            Event::Frame(record) => record.timestamp_us,
+            Event::Sleep { timestamp_us } | Event::Wakeup { timestamp_us, .. } => *timestamp_us,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        let record = TimestampedFrame::from_frame(100, Direction::Tx, &frame);
+        assert_eq!(record.data(), &[0x01, 0x02]);
+        assert_eq!(record.checksum(), Some(frame.get_checksum()));
+        assert_eq!(record.response(), frame.get_data_with_checksum());
+        assert_eq!(record.frame(), Some(frame));
+
+        let record = TimestampedFrame {
+            verdict: Verdict::Incomplete,
+            ..record
+        };
+        assert_eq!(record.data(), record.response());
+        assert_eq!(record.checksum(), None);
+        assert_eq!(record.frame(), None);
+    }
+}
@@ -0,0 +1,459 @@
+//! Vector ASCII logs (.asc) of LIN traffic, as written and read by CANoe and CANalyzer
+//!
+//! The following lines are supported, `<time>` is in seconds and `<ch>` the channel number:
+//!
+//! ```text
+//! <time> Li<ch> <id> <Tx|Rx> <dlc> <data...> checksum = <cs> CSM = <classic|enhanced>
+//! <time> Li<ch> CSErr: id = <id> <dlc> <data...> checksum = <cs>
+//! <time> Li<ch> TransmErr: id = <id>
+//! <time> Li<ch> RcvError: id = <id> <count> <bytes...>
+//! <time> Li<ch> SleepModeEvent: entering sleep mode
+//! <time> Li<ch> WakeupFrame: <Tx|Rx>
+//! ```
+//!
+//! Identifiers and data are hexadecimal unless the header declares `base dec`, relative
+//! timestamps are accumulated. Other lines, e.g. CAN frames or unknown LIN events, are
+//! skipped when reading. A response with a wrong PID parity is written as receive error, so
+//! it is read back as `Verdict::Incomplete`.
+//!
+//! ```
+//! use lin_bus::trace::asc::{AscReader, AscWriter};
+//! use lin_bus::trace::{Direction, Event, TimestampedFrame};
+//! use lin_bus::{Frame, PID};
+//!
+//! let frame = Frame::try_from_data(PID::TABLE[0x10], &[0x01, 0x02]).unwrap();
+//! let event = Event::Frame(TimestampedFrame::from_frame(1_500, Direction::Rx, &frame));
+//! let mut writer = AscWriter::new(Vec::new(), 1).unwrap();
+//! writer.write(&event).unwrap();
+//! let log = writer.finish().unwrap();
+//!
+//! let mut reader = AscReader::new(log.as_slice());
+//! assert_eq!(reader.next().unwrap().unwrap(), event);
+//! ```
+
+use core::fmt;
+use std::io::{self, BufRead, Write};
+use std::string::String;
+use std::vec::Vec;
+
+use super::{Direction, Event, TimestampedFrame};
+use crate::frame::{ChecksumModel, PID};
+use crate::monitor::Verdict;
+
+/// Error reading an ASC log
+#[derive(Debug)]
+pub enum AscError {
+    Io(io::Error),
+    /// A LIN line of the log is malformed
+    Syntax {
+        line: usize,
+        message: &'static str,
+    },
+}
+
+impl fmt::Display for AscError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AscError::Io(e) => write!(f, "{}", e),
+            AscError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for AscError {}
+
+impl From<io::Error> for AscError {
+    fn from(e: io::Error) -> AscError {
+        AscError::Io(e)
+    }
+}
+
+/// Writer of an ASC log with hexadecimal values and absolute timestamps
+pub struct AscWriter<W: Write> {
+    writer: W,
+    channel: u8,
+}
+
+impl<W: Write> AscWriter<W> {
+    /// Write the header of the log. The events are logged on the LIN channel `channel`.
+    pub fn new(mut writer: W, channel: u8) -> io::Result<AscWriter<W>> {
+        writeln!(writer, "base hex  timestamps absolute")?;
+        writeln!(writer, "no internal events logged")?;
+        writeln!(writer, "Begin Triggerblock")?;
+        Ok(AscWriter { writer, channel })
+    }
+
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        let timestamp = event.timestamp_us();
+        write!(
+            self.writer,
+            "{:>4}.{:06} Li{} ",
+            timestamp / 1_000_000,
+            timestamp % 1_000_000,
+            self.channel
+        )?;
+        match event {
+            Event::Frame(record) => self.write_record(record),
+            Event::Sleep { .. } => writeln!(self.writer, "SleepModeEvent: entering sleep mode"),
+            Event::Wakeup { direction, .. } => {
+                writeln!(self.writer, "WakeupFrame: {}", direction_name(*direction))
+            }
+        }
+    }
+
+    fn write_record(&mut self, record: &TimestampedFrame) -> io::Result<()> {
+        let id = record.pid.get_id();
+        match record.verdict {
+            Verdict::Valid => write!(
+                self.writer,
+                "{:02x} {} {}",
+                id,
+                direction_name(record.direction),
+                record.data().len()
+            )?,
+            Verdict::Checksum => write!(
+                self.writer,
+                "CSErr: id = {:02x} {}",
+                id,
+                record.data().len()
+            )?,
+            Verdict::NoResponse => return writeln!(self.writer, "TransmErr: id = {:02x}", id),
+            Verdict::Incomplete | Verdict::Parity => {
+                write!(
+                    self.writer,
+                    "RcvError: id = {:02x} {}",
+                    id,
+                    record.response().len()
+                )?;
+                for byte in record.response() {
+                    write!(self.writer, " {:02x}", byte)?;
+                }
+                return writeln!(self.writer);
+            }
+        }
+        for byte in record.data() {
+            write!(self.writer, " {:02x}", byte)?;
+        }
+        let checksum_model = match record.checksum_model {
+            ChecksumModel::Classic => "classic",
+            ChecksumModel::Enhanced => "enhanced",
+        };
+        writeln!(
+            self.writer,
+            " checksum = {:02x} CSM = {}",
+            record.checksum().unwrap_or(0),
+            checksum_model
+        )
+    }
+
+    /// Write the end of the log and return the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        writeln!(self.writer, "End TriggerBlock")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Tx => "Tx",
+        Direction::Rx => "Rx",
+    }
+}
+
+/// Reader of the LIN events of an ASC log, see the module documentation
+pub struct AscReader<R: BufRead> {
+    reader: R,
+    line: String,
+    number: usize,
+    radix: u32,
+    relative: bool,
+    time: u64,
+}
+
+impl<R: BufRead> AscReader<R> {
+    pub fn new(reader: R) -> AscReader<R> {
+        AscReader {
+            reader,
+            line: String::new(),
+            number: 0,
+            radix: 16,
+            relative: false,
+            time: 0,
+        }
+    }
+
+    fn error<T>(&self, message: &'static str) -> Result<T, AscError> {
+        Err(AscError::Syntax {
+            line: self.number,
+            message,
+        })
+    }
+
+    /// Parse the current line, `None` if it is no LIN event
+    fn parse_line(&mut self) -> Result<Option<Event>, AscError> {
+        let line = core::mem::take(&mut self.line);
+        let result = self.parse(&line);
+        self.line = line;
+        result
+    }
+
+    fn parse(&mut self, line: &str) -> Result<Option<Event>, AscError> {
+        let mut tokens = line.split_whitespace();
+        let first = match tokens.next() {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        if first == "base" {
+            self.radix = if tokens.next() == Some("dec") { 10 } else { 16 };
+            self.relative = line.contains("timestamps relative");
+            return Ok(None);
+        }
+        let timestamp = match parse_seconds(first) {
+            Some(timestamp) if self.relative => {
+                self.time += timestamp;
+                self.time
+            }
+            Some(timestamp) => timestamp,
+            None => return Ok(None),
+        };
+        match tokens.next() {
+            Some(channel) if channel.starts_with("Li") => {}
+            _ => return Ok(None),
+        }
+        let tokens: Vec<&str> = tokens.collect();
+        match tokens.as_slice() {
+            ["SleepModeEvent:", .., "sleep", "mode"] => Ok(Some(Event::Sleep {
+                timestamp_us: timestamp,
+            })),
+            ["WakeupFrame:", direction, ..] => Ok(Some(Event::Wakeup {
+                timestamp_us: timestamp,
+                direction: self.direction(direction)?,
+            })),
+            ["CSErr:", "id", "=", id, rest @ ..] => {
+                self.frame(timestamp, id, Direction::Rx, Verdict::Checksum, rest)
+            }
+            ["TransmErr:", "id", "=", id, ..] => {
+                self.frame(timestamp, id, Direction::Rx, Verdict::NoResponse, &["0"])
+            }
+            ["RcvError:", "id", "=", id, rest @ ..] => {
+                self.frame(timestamp, id, Direction::Rx, Verdict::Incomplete, rest)
+            }
+            [event, ..] if event.ends_with(':') => Ok(None),
+            [id, direction, rest @ ..] => {
+                let direction = self.direction(direction)?;
+                self.frame(timestamp, id, direction, Verdict::Valid, rest)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn direction(&self, token: &str) -> Result<Direction, AscError> {
+        match token {
+            "Tx" | "TX" => Ok(Direction::Tx),
+            "Rx" | "RX" => Ok(Direction::Rx),
+            _ => self.error("invalid direction"),
+        }
+    }
+
+    fn number(&self, token: &str) -> Result<u8, AscError> {
+        u8::from_str_radix(token, self.radix).or_else(|_| self.error("invalid number"))
+    }
+
+    /// Parse `<length> <bytes...>` and the optional checksum of a frame
+    fn frame(
+        &self,
+        timestamp_us: u64,
+        id: &str,
+        direction: Direction,
+        verdict: Verdict,
+        tokens: &[&str],
+    ) -> Result<Option<Event>, AscError> {
+        let pid = match PID::TABLE.get(usize::from(self.number(id)?)) {
+            Some(pid) => *pid,
+            None => return self.error("invalid ID"),
+        };
+        let (length, tokens) = match tokens.split_first() {
+            Some((length, tokens)) => (usize::from(self.number(length)?), tokens),
+            None => return self.error("missing length"),
+        };
+        if length > 9 || tokens.len() < length {
+            return self.error("invalid length");
+        }
+        let mut response = [0u8; 9];
+        for (byte, token) in response.iter_mut().zip(&tokens[..length]) {
+            *byte = self.number(token)?;
+        }
+        let mut record = TimestampedFrame {
+            timestamp_us,
+            direction,
+            pid,
+            checksum_model: pid.checksum_model(),
+            verdict,
+            response,
+            length,
+        };
+        let mut tokens = tokens[length..].iter();
+        while let Some(token) = tokens.next() {
+            match (*token, tokens.next(), tokens.next()) {
+                ("checksum", Some(&"="), Some(checksum)) if verdict != Verdict::Incomplete => {
+                    if length > 8 {
+                        return self.error("invalid length");
+                    }
+                    record.response[length] = self.number(checksum)?;
+                    record.length = length + 1;
+                }
+                ("CSM", Some(&"="), Some(&"classic")) => {
+                    record.checksum_model = ChecksumModel::Classic;
+                }
+                ("CSM", Some(&"="), Some(&"enhanced")) => {
+                    record.checksum_model = ChecksumModel::Enhanced;
+                }
+                _ => break,
+            }
+        }
+        if record.verdict == Verdict::Valid && record.checksum().is_none() {
+            return self.error("missing checksum");
+        }
+        Ok(Some(Event::Frame(record)))
+    }
+}
+
+impl<R: BufRead> Iterator for AscReader<R> {
+    type Item = Result<Event, AscError>;
+
+    fn next(&mut self) -> Option<Result<Event, AscError>> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => self.number += 1,
+                Err(e) => return Some(Err(e.into())),
+            }
+            match self.parse_line() {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Parse a timestamp in seconds with up to 6 decimals into µs
+fn parse_seconds(token: &str) -> Option<u64> {
+    let (seconds, fraction) = token.split_once('.').unwrap_or((token, ""));
+    if seconds.is_empty()
+        || fraction.len() > 6
+        || !token.bytes().all(|b| b.is_ascii_digit() || b == b'.')
+    {
+        return None;
+    }
+    let seconds: u64 = seconds.parse().ok()?;
+    let mut us = 0;
+    for (i, digit) in fraction.bytes().enumerate() {
+        us += u64::from(digit - b'0') * 10u64.pow(5 - i as u32);
+    }
+    Some(seconds * 1_000_000 + us)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frame;
+
+    const LOG: &str = "date Wed Jun 12 10:00:00.000 am 2019
+base hex  timestamps absolute
+internal events logged
+Begin Triggerblock Wed Jun 12 10:00:00.000 am 2019
+   0.000000 Start of measurement
+   0.010000 1  123             Rx   d 2 01 02
+   1.000000 Li1 10 Rx 2 01 02 checksum = ac header time = 40, full time = 80
+   1.010000 Li1 CSErr: id = 11 1 01 checksum = 00
+   1.020000 Li1 TransmErr: id = 12
+   1.030000 Li1 RcvError: id = 13 1 05
+   1.040000 Li1 SpikeEvent: Rx
+   2.000000 Li1 SleepModeEvent: entering sleep mode
+   2.500000 Li1 WakeupFrame: Rx signal = 80
+End TriggerBlock
+";
+
+    #[test]
+    fn test_read() {
+        let events: Vec<Event> = AscReader::new(LOG.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 6);
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        assert_eq!(
+            events[0],
+            Event::Frame(TimestampedFrame::from_frame(
+                1_000_000,
+                Direction::Rx,
+                &frame
+            ))
+        );
+        match events[1] {
+            Event::Frame(record) => {
+                assert_eq!(record.verdict, Verdict::Checksum);
+                assert_eq!(record.data(), &[0x01]);
+                assert_eq!(record.checksum(), Some(0x00));
+            }
+            event => panic!("Unexpected {:?}", event),
+        }
+        match events[2] {
+            Event::Frame(record) => assert_eq!(record.verdict, Verdict::NoResponse),
+            event => panic!("Unexpected {:?}", event),
+        }
+        match events[3] {
+            Event::Frame(record) => assert_eq!(record.response(), &[0x05]),
+            event => panic!("Unexpected {:?}", event),
+        }
+        assert_eq!(
+            events[4],
+            Event::Sleep {
+                timestamp_us: 2_000_000
+            }
+        );
+        assert_eq!(
+            events[5],
+            Event::Wakeup {
+                timestamp_us: 2_500_000,
+                direction: Direction::Rx
+            }
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let events: Vec<Event> = AscReader::new(LOG.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let mut writer = AscWriter::new(Vec::new(), 2).unwrap();
+        for event in &events {
+            writer.write(event).unwrap();
+        }
+        let log = writer.finish().unwrap();
+        let text = std::str::from_utf8(&log).unwrap();
+        assert!(text.contains("   1.000000 Li2 10 Rx 2 01 02 checksum = ac CSM = enhanced\n"));
+        assert!(text.contains("   1.020000 Li2 TransmErr: id = 12\n"));
+
+        let read: Vec<Event> = AscReader::new(log.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, events);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut reader = AscReader::new("base dec\n1.0 Li1 70 Rx 1 1 checksum = 1\n".as_bytes());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(AscError::Syntax { line: 2, .. }))
+        ));
+        let mut reader = AscReader::new("1.0 Li1 10 Rx 2 01\n".as_bytes());
+        assert!(matches!(reader.next(), Some(Err(AscError::Syntax { .. }))));
+        let reader = AscReader::new("base dec timestamps relative\n1.0 Li1 16 Rx 1 1 checksum = 1\n0.5 Li1 16 Rx 1 1 checksum = 1\n".as_bytes());
+        let times: Vec<u64> = reader.map(|event| event.unwrap().timestamp_us()).collect();
+        assert_eq!(times, [1_000_000, 1_500_000]);
+    }
+}
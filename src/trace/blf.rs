@@ -0,0 +1,621 @@
+//! Reader of the LIN objects of Vector binary logging files (.blf)
+//!
+//! The objects of a BLF file are stored in log containers, which are usually zlib compressed.
+//! The reader decompresses the containers and yields the following objects, other objects, e.g.
+//! CAN frames or LIN statistics, are skipped:
+//!
+//! * `LIN_MESSAGE`, `LIN_MESSAGE2`: `Verdict::Valid`
+//! * `LIN_CRC_ERROR`, `LIN_CRC_ERROR2`: `Verdict::Checksum`
+//! * `LIN_SND_ERROR`, `LIN_SND_ERROR2`: `Verdict::NoResponse`
+//! * `LIN_RCV_ERROR`, `LIN_RCV_ERROR2`: `Verdict::Incomplete`, without the received bytes
+//! * `LIN_SLEEP`: `Event::Sleep` when the channel enters sleep mode
+//! * `LIN_WAKEUP`, `LIN_WAKEUP2`: `Event::Wakeup`
+//!
+//! The channel of the objects is ignored, timestamps are relative to the start of the
+//! measurement.
+//!
+//! ```no_run
+//! use lin_bus::trace::blf::BlfReader;
+//! use lin_bus::trace::Event;
+//! use std::io::BufReader;
+//!
+//! let file = std::fs::File::open("measurement.blf").unwrap();
+//! for event in BlfReader::new(BufReader::new(file)).unwrap() {
+//!     if let Event::Frame(record) = event.unwrap() {
+//!         println!("{} {:?} {:02x?}", record.timestamp_us, record.pid, record.data());
+//!     }
+//! }
+//! ```
+
+use core::fmt;
+use std::io::{self, Read};
+use std::vec::Vec;
+
+use super::{inflate, Direction, Event, TimestampedFrame};
+use crate::frame::{ChecksumModel, PID};
+use crate::monitor::Verdict;
+
+const FILE_SIGNATURE: &[u8; 4] = b"LOGG";
+const OBJECT_SIGNATURE: &[u8; 4] = b"LOBJ";
+const BASE_HEADER_SIZE: usize = 16;
+/// Size of the object headers of version 1 and 2 up to the timestamp
+const HEADER_SIZE: usize = 32;
+
+const TIME_TEN_MICS: u32 = 1;
+const TIME_ONE_NANS: u32 = 2;
+
+const COMPRESSION_NONE: u16 = 0;
+const COMPRESSION_ZLIB: u16 = 2;
+const CONTAINER_HEADER_SIZE: usize = 16;
+
+const LOG_CONTAINER: u32 = 10;
+const LIN_MESSAGE: u32 = 11;
+const LIN_CRC_ERROR: u32 = 12;
+const LIN_RCV_ERROR: u32 = 14;
+const LIN_SND_ERROR: u32 = 15;
+const LIN_SLEEP: u32 = 20;
+const LIN_WAKEUP: u32 = 21;
+const LIN_MESSAGE2: u32 = 57;
+const LIN_SND_ERROR2: u32 = 58;
+const LIN_CRC_ERROR2: u32 = 60;
+const LIN_RCV_ERROR2: u32 = 61;
+const LIN_WAKEUP2: u32 = 62;
+
+/// Flag of `LIN_SLEEP`, set if the channel is awake after the event
+const SLEEP_IS_AWAKE: u8 = 0x02;
+
+/// Error reading a BLF file
+#[derive(Debug)]
+pub enum BlfError {
+    Io(io::Error),
+    /// The file is malformed or truncated
+    Format(&'static str),
+}
+
+impl fmt::Display for BlfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlfError::Io(e) => write!(f, "{}", e),
+            BlfError::Format(message) => write!(f, "invalid BLF file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for BlfError {}
+
+impl From<io::Error> for BlfError {
+    fn from(e: io::Error) -> BlfError {
+        BlfError::Io(e)
+    }
+}
+
+/// Reader of the LIN events of a BLF file, see the module documentation
+pub struct BlfReader<R: Read> {
+    reader: R,
+    /// Decompressed objects, an object may span several containers
+    stream: Vec<u8>,
+    position: usize,
+    /// Padding after the last object which is not yet skipped
+    padding: usize,
+    failed: bool,
+}
+
+impl<R: Read> BlfReader<R> {
+    /// Read the file header
+    pub fn new(mut reader: R) -> Result<BlfReader<R>, BlfError> {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != FILE_SIGNATURE {
+            return Err(BlfError::Format("missing file signature"));
+        }
+        let header_size = u64::from(u32_at(&header, 4));
+        if header_size < 8 {
+            return Err(BlfError::Format("invalid file header size"));
+        }
+        let skipped = io::copy(&mut reader.by_ref().take(header_size - 8), &mut io::sink())?;
+        if skipped != header_size - 8 {
+            return Err(BlfError::Format("truncated file header"));
+        }
+        Ok(BlfReader {
+            reader,
+            stream: Vec::new(),
+            position: 0,
+            padding: 0,
+            failed: false,
+        })
+    }
+
+    /// Append the next object of the file to the stream, decompressing log containers.
+    /// Returns `false` at the end of the file.
+    fn read_object(&mut self) -> Result<bool, BlfError> {
+        let mut object = Vec::new();
+        self.reader
+            .by_ref()
+            .take(BASE_HEADER_SIZE as u64)
+            .read_to_end(&mut object)?;
+        match object.len() {
+            0 => return Ok(false),
+            BASE_HEADER_SIZE => {}
+            _ => return Err(BlfError::Format("truncated object")),
+        }
+        let size = object_size(&object)?;
+        self.reader
+            .by_ref()
+            .take((size - BASE_HEADER_SIZE) as u64)
+            .read_to_end(&mut object)?;
+        if object.len() < size {
+            return Err(BlfError::Format("truncated object"));
+        }
+        // The padding of the last object may be missing
+        let mut padding = Vec::new();
+        self.reader
+            .by_ref()
+            .take((size % 4) as u64)
+            .read_to_end(&mut padding)?;
+
+        self.stream.drain(..self.position);
+        self.position = 0;
+        if u32_at(&object, 12) != LOG_CONTAINER {
+            self.stream.extend_from_slice(&object);
+            self.stream.extend_from_slice(&padding);
+            return Ok(true);
+        }
+        let data = object
+            .get(BASE_HEADER_SIZE + CONTAINER_HEADER_SIZE..)
+            .ok_or(BlfError::Format("truncated container"))?;
+        match u16_at(&object, BASE_HEADER_SIZE) {
+            COMPRESSION_NONE => self.stream.extend_from_slice(data),
+            COMPRESSION_ZLIB => {
+                inflate::zlib_decompress(data, &mut self.stream).map_err(BlfError::Format)?
+            }
+            _ => return Err(BlfError::Format("unsupported compression")),
+        }
+        Ok(true)
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, BlfError> {
+        loop {
+            let available = self.stream.len() - self.position;
+            let skipped = self.padding.min(available);
+            self.position += skipped;
+            self.padding -= skipped;
+
+            let objects = &self.stream[self.position..];
+            if objects.len() >= BASE_HEADER_SIZE {
+                let size = object_size(objects)?;
+                if objects.len() >= size {
+                    let event = parse_object(&objects[..size])?;
+                    self.position += size;
+                    self.padding = size % 4;
+                    if event.is_some() {
+                        return Ok(event);
+                    }
+                    continue;
+                }
+            }
+            if !self.read_object()? {
+                return match self.position == self.stream.len() {
+                    true => Ok(None),
+                    false => Err(BlfError::Format("truncated object")),
+                };
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for BlfReader<R> {
+    type Item = Result<Event, BlfError>;
+
+    fn next(&mut self) -> Option<Result<Event, BlfError>> {
+        if self.failed {
+            return None;
+        }
+        // After an error the position of the next object is unknown
+        let result = self.read_event();
+        self.failed = result.is_err();
+        result.transpose()
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(value)
+}
+
+/// Size of the object starting with the base header `header`
+fn object_size(header: &[u8]) -> Result<usize, BlfError> {
+    if &header[..4] != OBJECT_SIGNATURE {
+        return Err(BlfError::Format("missing object signature"));
+    }
+    match u32_at(header, 8) as usize {
+        size if size >= BASE_HEADER_SIZE => Ok(size),
+        _ => Err(BlfError::Format("invalid object size")),
+    }
+}
+
+/// The event of a LIN object, `None` for other objects
+fn parse_object(object: &[u8]) -> Result<Option<Event>, BlfError> {
+    let object_type = u32_at(object, 12);
+    if !matches!(
+        object_type,
+        LIN_MESSAGE
+            | LIN_CRC_ERROR
+            | LIN_RCV_ERROR
+            | LIN_SND_ERROR
+            | LIN_SLEEP
+            | LIN_WAKEUP
+            | LIN_MESSAGE2
+            | LIN_SND_ERROR2
+            | LIN_CRC_ERROR2
+            | LIN_RCV_ERROR2
+            | LIN_WAKEUP2
+    ) {
+        return Ok(None);
+    }
+    let header_size = usize::from(u16_at(object, 4));
+    if header_size < HEADER_SIZE || header_size > object.len() {
+        return Err(BlfError::Format("invalid object header size"));
+    }
+    let timestamp = u64_at(object, 24);
+    let timestamp_us = match u32_at(object, 16) {
+        TIME_TEN_MICS => timestamp
+            .checked_mul(10)
+            .ok_or(BlfError::Format("timestamp out of range"))?,
+        TIME_ONE_NANS => timestamp / 1000,
+        _ => return Err(BlfError::Format("unknown timestamp unit")),
+    };
+    let body = &object[header_size..];
+    let fields = |length: usize| {
+        body.get(..length)
+            .ok_or(BlfError::Format("object too short"))
+    };
+
+    let event = match object_type {
+        LIN_MESSAGE | LIN_CRC_ERROR => {
+            let body = fields(19)?;
+            let response = Response {
+                id: body[2],
+                dlc: body[3],
+                data: &body[4..12],
+                checksum: body[16],
+            };
+            let verdict = match object_type {
+                LIN_MESSAGE => Verdict::Valid,
+                _ => Verdict::Checksum,
+            };
+            let record = response.record(timestamp_us, body[18], None, verdict)?;
+            Event::Frame(record)
+        }
+        LIN_MESSAGE2 | LIN_CRC_ERROR2 => {
+            let body = fields(123)?;
+            let response = Response {
+                id: body[37],
+                dlc: body[38],
+                data: &body[112..120],
+                checksum: body[120],
+            };
+            let checksum_model = match body[39] {
+                0 => Some(ChecksumModel::Classic),
+                1 => Some(ChecksumModel::Enhanced),
+                _ => None,
+            };
+            let verdict = match object_type {
+                LIN_MESSAGE2 => Verdict::Valid,
+                _ => Verdict::Checksum,
+            };
+            let record = response.record(timestamp_us, body[122], checksum_model, verdict)?;
+            Event::Frame(record)
+        }
+        LIN_RCV_ERROR | LIN_SND_ERROR => {
+            let verdict = match object_type {
+                LIN_RCV_ERROR => Verdict::Incomplete,
+                _ => Verdict::NoResponse,
+            };
+            Event::Frame(header_record(timestamp_us, fields(3)?[2], verdict)?)
+        }
+        LIN_RCV_ERROR2 | LIN_SND_ERROR2 => {
+            let verdict = match object_type {
+                LIN_RCV_ERROR2 => Verdict::Incomplete,
+                _ => Verdict::NoResponse,
+            };
+            Event::Frame(header_record(timestamp_us, fields(38)?[37], verdict)?)
+        }
+        LIN_SLEEP => match fields(4)?[3] & SLEEP_IS_AWAKE {
+            0 => Event::Sleep { timestamp_us },
+            _ => return Ok(None),
+        },
+        _ => {
+            let external = match object_type {
+                LIN_WAKEUP => fields(4)?[3],
+                _ => fields(19)?[18],
+            };
+            Event::Wakeup {
+                timestamp_us,
+                direction: match external {
+                    0 => Direction::Tx,
+                    _ => Direction::Rx,
+                },
+            }
+        }
+    };
+    Ok(Some(event))
+}
+
+fn pid(id: u8) -> Result<PID, BlfError> {
+    PID::TABLE
+        .get(usize::from(id))
+        .copied()
+        .ok_or(BlfError::Format("invalid ID"))
+}
+
+/// Record of a header without a complete response
+fn header_record(
+    timestamp_us: u64,
+    id: u8,
+    verdict: Verdict,
+) -> Result<TimestampedFrame, BlfError> {
+    let pid = pid(id)?;
+    Ok(TimestampedFrame {
+        timestamp_us,
+        direction: Direction::Rx,
+        pid,
+        checksum_model: pid.checksum_model(),
+        verdict,
+        response: [0u8; 9],
+        length: 0,
+    })
+}
+
+/// Fields of a message or checksum error object
+struct Response<'a> {
+    id: u8,
+    dlc: u8,
+    data: &'a [u8],
+    checksum: u8,
+}
+
+impl Response<'_> {
+    fn record(
+        &self,
+        timestamp_us: u64,
+        direction: u8,
+        checksum_model: Option<ChecksumModel>,
+        verdict: Verdict,
+    ) -> Result<TimestampedFrame, BlfError> {
+        let pid = pid(self.id)?;
+        let length = usize::from(self.dlc);
+        if length > 8 {
+            return Err(BlfError::Format("invalid DLC"));
+        }
+        let mut response = [0u8; 9];
+        response[..length].copy_from_slice(&self.data[..length]);
+        response[length] = self.checksum;
+        Ok(TimestampedFrame {
+            timestamp_us,
+            // 0 is a received response, 1 a transmission and 2 a transmission request
+            direction: match direction {
+                0 => Direction::Rx,
+                _ => Direction::Tx,
+            },
+            pid,
+            checksum_model: checksum_model.unwrap_or_else(|| pid.checksum_model()),
+            verdict,
+            response,
+            length: length + 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Frame, PID};
+
+    fn object(object_type: u32, flags: u32, timestamp: u64, body: &[u8]) -> Vec<u8> {
+        let size = (HEADER_SIZE + body.len()) as u32;
+        let mut object = Vec::new();
+        object.extend_from_slice(OBJECT_SIGNATURE);
+        object.extend_from_slice(&(HEADER_SIZE as u16).to_le_bytes());
+        object.extend_from_slice(&1u16.to_le_bytes());
+        object.extend_from_slice(&size.to_le_bytes());
+        object.extend_from_slice(&object_type.to_le_bytes());
+        object.extend_from_slice(&flags.to_le_bytes());
+        object.extend_from_slice(&[0; 4]);
+        object.extend_from_slice(&timestamp.to_le_bytes());
+        object.extend_from_slice(body);
+        object.resize(object.len() + size as usize % 4, 0);
+        object
+    }
+
+    fn container(compression: u16, data: &[u8]) -> Vec<u8> {
+        let size = (BASE_HEADER_SIZE + CONTAINER_HEADER_SIZE + data.len()) as u32;
+        let mut object = Vec::new();
+        object.extend_from_slice(OBJECT_SIGNATURE);
+        object.extend_from_slice(&(BASE_HEADER_SIZE as u16).to_le_bytes());
+        object.extend_from_slice(&1u16.to_le_bytes());
+        object.extend_from_slice(&size.to_le_bytes());
+        object.extend_from_slice(&LOG_CONTAINER.to_le_bytes());
+        object.extend_from_slice(&compression.to_le_bytes());
+        object.extend_from_slice(&[0; 6]);
+        object.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        object.extend_from_slice(&[0; 4]);
+        object.extend_from_slice(data);
+        object.resize(object.len() + size as usize % 4, 0);
+        object
+    }
+
+    /// zlib stream with a single stored block, the checksum is not verified by the reader
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let length = data.len() as u16;
+        let mut stream = std::vec![0x78, 0x01, 0x01];
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(data);
+        stream.extend_from_slice(&[0; 4]);
+        stream
+    }
+
+    fn message(id: u8, data: &[u8], checksum: u8, direction: u8) -> Vec<u8> {
+        let mut body = std::vec![0u8; 24];
+        body[2] = id;
+        body[3] = data.len() as u8;
+        body[4..4 + data.len()].copy_from_slice(data);
+        body[16] = checksum;
+        body[18] = direction;
+        body
+    }
+
+    fn file(containers: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = std::vec![0u8; 144];
+        file[..4].copy_from_slice(FILE_SIGNATURE);
+        file[4..8].copy_from_slice(&144u32.to_le_bytes());
+        for container in containers {
+            file.extend_from_slice(container);
+        }
+        file
+    }
+
+    #[test]
+    fn test_read() {
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        let mut message2 = std::vec![0u8; 128];
+        message2[37] = 0x3C;
+        message2[38] = 8;
+        message2[39] = 1;
+        message2[112..120].copy_from_slice(&[0x7F; 8]);
+        message2[120] = 0x55;
+        message2[122] = 1;
+        let mut wakeup2 = std::vec![0u8; 20];
+        wakeup2[18] = 1;
+
+        let mut raw = object(
+            LIN_MESSAGE,
+            TIME_TEN_MICS,
+            100,
+            &message(0x10, &[1, 2], 0xAC, 0),
+        );
+        raw.extend(object(1, TIME_ONE_NANS, 0, &[0; 16]));
+        let crc_error = object(
+            LIN_CRC_ERROR,
+            TIME_ONE_NANS,
+            2_000_000,
+            &message(0x11, &[3], 0, 1),
+        );
+        let (first, second) = crc_error.split_at(20);
+        raw.extend_from_slice(first);
+
+        let mut compressed = second.to_vec();
+        compressed.extend(object(
+            LIN_SND_ERROR,
+            TIME_ONE_NANS,
+            3_000_000,
+            &[0, 0, 0x20, 0],
+        ));
+        compressed.extend(object(LIN_MESSAGE2, TIME_ONE_NANS, 4_000_000, &message2));
+        compressed.extend(object(
+            LIN_SLEEP,
+            TIME_ONE_NANS,
+            5_000_000,
+            &[0, 0, 0, 0x01],
+        ));
+        compressed.extend(object(
+            LIN_SLEEP,
+            TIME_ONE_NANS,
+            5_500_000,
+            &[0, 0, 0, 0x03],
+        ));
+        compressed.extend(object(LIN_WAKEUP2, TIME_ONE_NANS, 6_000_000, &wakeup2));
+        let file = file(&[
+            container(COMPRESSION_NONE, &raw),
+            container(COMPRESSION_ZLIB, &zlib_stored(&compressed)),
+        ]);
+
+        let events: Vec<Event> = BlfReader::new(file.as_slice())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(events.len(), 6);
+        assert_eq!(
+            events[0],
+            Event::Frame(TimestampedFrame::from_frame(1_000, Direction::Rx, &frame))
+        );
+        match events[1] {
+            Event::Frame(record) => {
+                assert_eq!(record.timestamp_us, 2_000);
+                assert_eq!(record.direction, Direction::Tx);
+                assert_eq!(record.verdict, Verdict::Checksum);
+                assert_eq!(record.data(), &[3]);
+                assert_eq!(record.checksum(), Some(0));
+            }
+            _ => panic!("expected a frame"),
+        }
+        match events[2] {
+            Event::Frame(record) => {
+                assert_eq!(record.pid, PID::from_id(0x20));
+                assert_eq!(record.verdict, Verdict::NoResponse);
+                assert!(record.response().is_empty());
+            }
+            _ => panic!("expected a frame"),
+        }
+        match events[3] {
+            Event::Frame(record) => {
+                assert_eq!(record.timestamp_us, 4_000);
+                assert_eq!(record.pid, PID::from_id(0x3C));
+                assert_eq!(record.checksum_model, ChecksumModel::Enhanced);
+                assert_eq!(record.data(), &[0x7F; 8]);
+                assert_eq!(record.checksum(), Some(0x55));
+            }
+            _ => panic!("expected a frame"),
+        }
+        assert_eq!(
+            events[4],
+            Event::Sleep {
+                timestamp_us: 5_000
+            }
+        );
+        assert_eq!(
+            events[5],
+            Event::Wakeup {
+                timestamp_us: 6_000,
+                direction: Direction::Rx
+            }
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            BlfReader::new(&b"LOGX\x08\0\0\0"[..]),
+            Err(BlfError::Format(_))
+        ));
+
+        let mut truncated = file(&[container(COMPRESSION_NONE, &[0x4C, 0x4F, 0x42, 0x4A])]);
+        let mut reader = BlfReader::new(truncated.as_slice()).unwrap();
+        assert!(matches!(reader.next(), Some(Err(BlfError::Format(_)))));
+        assert!(reader.next().is_none());
+
+        truncated.truncate(150);
+        let mut reader = BlfReader::new(truncated.as_slice()).unwrap();
+        assert!(matches!(reader.next(), Some(Err(BlfError::Format(_)))));
+
+        let message = message(0x10, &[1, 2], 0xAC, 0);
+        let overflow = object(LIN_MESSAGE, TIME_TEN_MICS, u64::MAX / 10 + 1, &message);
+        let file = file(&[container(COMPRESSION_NONE, &overflow)]);
+        let mut reader = BlfReader::new(file.as_slice()).unwrap();
+        assert!(matches!(
+            reader.next(),
+            Some(Err(BlfError::Format("timestamp out of range")))
+        ));
+    }
+}
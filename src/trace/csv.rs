@@ -0,0 +1,150 @@
+//! CSV trace of frames for spreadsheets and test reports
+//!
+//! The sink writes to any `core::fmt::Write`, e.g. a `String`, a fixed buffer or a debug
+//! UART, so it is available without `std`. Every record is one line:
+//!
+//! ```text
+//! timestamp_us,id,pid,dlc,data,checksum,verdict
+//! 1500,0x10,0x50,2,01 02,0xac,valid
+//! 2500,0x20,0x20,0,,,no_response
+//! ```
+//!
+//! The data bytes are separated by spaces, the checksum is empty if the response is
+//! incomplete. Frames of a `monitor::BusMonitor` are logged from its callback:
+//!
+//! ```no_run
+//! # fn example<M: lin_bus::driver::Monitor, W: core::fmt::Write>(driver: &mut M, output: W) {
+//! use lin_bus::monitor::BusMonitor;
+//! use lin_bus::trace::csv::CsvWriter;
+//! use lin_bus::trace::TimestampedFrame;
+//!
+//! let mut csv = CsvWriter::new(output).unwrap();
+//! let mut time_us = 0;
+//! BusMonitor::new().run(driver, |frame| {
+//!     time_us += 10_000;
+//!     csv.write(&TimestampedFrame::from_monitored(time_us, frame)).unwrap();
+//! });
+//! # }
+//! ```
+//!
+//! Frames of a master are logged with `TimestampedFrame::from_frame`, with the timestamps of
+//! a `master::TimestampedMaster` if the driver supports them.
+
+use core::fmt::{self, Write};
+
+use super::TimestampedFrame;
+use crate::monitor::Verdict;
+
+/// Header line of the trace
+pub const HEADER: &str = "timestamp_us,id,pid,dlc,data,checksum,verdict";
+
+/// Name of `verdict` in the verdict column
+pub fn verdict_name(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::Valid => "valid",
+        Verdict::Checksum => "checksum",
+        Verdict::NoResponse => "no_response",
+        Verdict::Incomplete => "incomplete",
+        Verdict::Parity => "parity",
+    }
+}
+
+/// Writer of a CSV trace
+pub struct CsvWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Write the header line
+    pub fn new(mut writer: W) -> Result<CsvWriter<W>, fmt::Error> {
+        writer.write_str(HEADER)?;
+        writer.write_char('\n')?;
+        Ok(CsvWriter { writer })
+    }
+
+    pub fn write(&mut self, record: &TimestampedFrame) -> fmt::Result {
+        write!(
+            self.writer,
+            "{},0x{:02x},0x{:02x},{},",
+            record.timestamp_us,
+            record.pid.get_id(),
+            record.pid.get(),
+            record.data().len()
+        )?;
+        for (i, byte) in record.data().iter().enumerate() {
+            if i > 0 {
+                self.writer.write_char(' ')?;
+            }
+            write!(self.writer, "{:02x}", byte)?;
+        }
+        self.writer.write_char(',')?;
+        if let Some(checksum) = record.checksum() {
+            write!(self.writer, "0x{:02x}", checksum)?;
+        }
+        writeln!(self.writer, ",{}", verdict_name(record.verdict))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::Direction;
+    use crate::{Frame, PID};
+
+    struct Buffer {
+        data: [u8; 128],
+        length: usize,
+    }
+
+    impl Write for Buffer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let end = self.length + s.len();
+            self.data
+                .get_mut(self.length..end)
+                .ok_or(fmt::Error)?
+                .copy_from_slice(s.as_bytes());
+            self.length = end;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_csv() {
+        let buffer = Buffer {
+            data: [0; 128],
+            length: 0,
+        };
+        let mut writer = CsvWriter::new(buffer).unwrap();
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        let record = TimestampedFrame::from_frame(1_500, Direction::Tx, &frame);
+        writer.write(&record).unwrap();
+        let record = TimestampedFrame {
+            timestamp_us: 2_500,
+            pid: PID::from_id(0x20),
+            verdict: Verdict::NoResponse,
+            length: 0,
+            ..record
+        };
+        writer.write(&record).unwrap();
+        let buffer = writer.into_inner();
+        assert_eq!(
+            core::str::from_utf8(&buffer.data[..buffer.length]).unwrap(),
+            "timestamp_us,id,pid,dlc,data,checksum,verdict\n\
+             1500,0x10,0x50,2,01 02,0xac,valid\n\
+             2500,0x20,0x20,0,,,no_response\n"
+        );
+    }
+
+    #[test]
+    fn test_buffer_full() {
+        let buffer = Buffer {
+            data: [0; 128],
+            length: 100,
+        };
+        assert!(CsvWriter::new(buffer).is_err());
+    }
+}
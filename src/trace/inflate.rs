@@ -0,0 +1,310 @@
+//! Decompression of zlib streams (RFC 1950 and 1951), as used by the containers of BLF files
+//!
+//! The decoder follows the canonical Huffman decoding of zlib's `puff.c`, it is small rather
+//! than fast.
+
+use std::vec::Vec;
+
+const MAX_BITS: usize = 15;
+
+/// Base lengths and extra bits of the length codes 257..=285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// Base distances and extra bits of the distance codes 0..=29
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order of the code length code lengths in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+type Result<T> = core::result::Result<T, &'static str>;
+
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    fn bits(&mut self, n: u32) -> Result<u32> {
+        while self.count < n {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or("unexpected end of data")?;
+            self.position += 1;
+            self.buffer |= u32::from(byte) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+/// Canonical Huffman code given by the number of codes per length and the symbols ordered by
+/// code
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: [u16; 288],
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut huffman = Huffman {
+            counts: [0; MAX_BITS + 1],
+            symbols: [0; 288],
+        };
+        for length in lengths {
+            huffman.counts[usize::from(*length)] += 1;
+        }
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + huffman.counts[length];
+        }
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                let offset = &mut offsets[usize::from(*length)];
+                huffman.symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+        huffman
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..=MAX_BITS {
+            code |= bits.bits(1)? as i32;
+            let count = i32::from(self.counts[length]);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code")
+    }
+}
+
+/// Decompress a zlib stream, the checksum is not verified
+pub(crate) fn zlib_decompress(data: &[u8], output: &mut Vec<u8>) -> Result<()> {
+    match data {
+        [cmf, flg, rest @ ..]
+            if cmf & 0x0F == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0 =>
+        {
+            if flg & 0x20 != 0 {
+                return Err("preset dictionary not supported");
+            }
+            inflate(rest, output)
+        }
+        _ => Err("invalid zlib header"),
+    }
+}
+
+/// Decompress raw deflate data
+pub(crate) fn inflate(data: &[u8], output: &mut Vec<u8>) -> Result<()> {
+    let mut bits = Bits {
+        data,
+        position: 0,
+        buffer: 0,
+        count: 0,
+    };
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => stored(&mut bits, output)?,
+            1 => {
+                let mut lengths = [0u8; 288 + 30];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                lengths[288..].fill(5);
+                let literals = Huffman::new(&lengths[..288]);
+                let distances = Huffman::new(&lengths[288..]);
+                codes(&mut bits, output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic(&mut bits)?;
+                codes(&mut bits, output, &literals, &distances)?;
+            }
+            _ => return Err("invalid block type"),
+        }
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+fn stored(bits: &mut Bits, output: &mut Vec<u8>) -> Result<()> {
+    bits.align();
+    let header = bits
+        .data
+        .get(bits.position..bits.position + 4)
+        .ok_or("unexpected end of data")?;
+    let length = usize::from(u16::from_le_bytes([header[0], header[1]]));
+    if u16::from_le_bytes([header[2], header[3]]) != !(length as u16) {
+        return Err("invalid stored block length");
+    }
+    bits.position += 4;
+    let block = bits
+        .data
+        .get(bits.position..bits.position + length)
+        .ok_or("unexpected end of data")?;
+    output.extend_from_slice(block);
+    bits.position += length;
+    Ok(())
+}
+
+fn dynamic(bits: &mut Bits) -> Result<(Huffman, Huffman)> {
+    let literal_count = bits.bits(5)? as usize + 257;
+    let distance_count = bits.bits(5)? as usize + 1;
+    let code_length_count = bits.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err("invalid code counts");
+    }
+    let mut lengths = [0u8; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        lengths[*index] = bits.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&lengths);
+
+    let mut lengths = [0u8; 286 + 30];
+    let count = literal_count + distance_count;
+    let mut index = 0;
+    while index < count {
+        let symbol = code_lengths.decode(bits)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 if index > 0 => (lengths[index - 1], 3 + bits.bits(2)?),
+            17 => (0, 3 + bits.bits(3)?),
+            18 => (0, 11 + bits.bits(7)?),
+            _ => return Err("invalid code length repeat"),
+        };
+        let end = index + repeat as usize;
+        if end > count {
+            return Err("too many code lengths");
+        }
+        lengths[index..end].fill(length);
+        index = end;
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..count]),
+    ))
+}
+
+fn codes(
+    bits: &mut Bits,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<()> {
+    loop {
+        let symbol = usize::from(literals.decode(bits)?);
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let code = symbol - 257;
+                let length = usize::from(LENGTH_BASE[code])
+                    + bits.bits(u32::from(LENGTH_EXTRA[code]))? as usize;
+                let code = usize::from(distances.decode(bits)?);
+                if code >= DISTANCE_BASE.len() {
+                    return Err("invalid distance code");
+                }
+                let distance = usize::from(DISTANCE_BASE[code])
+                    + bits.bits(u32::from(DISTANCE_EXTRA[code]))? as usize;
+                if distance > output.len() {
+                    return Err("distance too far back");
+                }
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+            _ => return Err("invalid literal or length code"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored() {
+        let mut output = Vec::new();
+        inflate(
+            &[0x01, 0x03, 0x00, 0xFC, 0xFF, b'L', b'I', b'N'],
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(output, b"LIN");
+    }
+
+    #[test]
+    fn test_fixed() {
+        let data = [
+            0x78, 0xDA, 0xF3, 0xF1, 0xF4, 0x53, 0xF0, 0x81, 0xE2, 0xA4, 0xD2, 0x62, 0x00, 0x20,
+            0x26, 0x04, 0x54,
+        ];
+        let mut output = Vec::new();
+        zlib_decompress(&data, &mut output).unwrap();
+        assert_eq!(output, b"LIN LIN LIN bus");
+        assert!(zlib_decompress(&data[1..], &mut output).is_err());
+    }
+
+    #[test]
+    fn test_dynamic() {
+        let data = [
+            0x78, 0xDA, 0x75, 0x92, 0x39, 0x12, 0x82, 0x50, 0x10, 0x05, 0x73, 0x4E, 0xC1, 0x11,
+            0x80, 0x41, 0xC0, 0xD4, 0x0D, 0xD7, 0x52, 0x11, 0xD7, 0x8C, 0x2A, 0x25, 0x33, 0xB1,
+            0xBC, 0x7F, 0x09, 0xCC, 0x7B, 0xC8, 0xF2, 0xE9, 0x6C, 0xFA, 0x27, 0x34, 0xF5, 0xF2,
+            0x4F, 0xF6, 0x7E, 0xD9, 0x8E, 0xFD, 0xCC, 0xBE, 0x99, 0x6D, 0xE5, 0xD5, 0xE5, 0xEA,
+            0x35, 0x51, 0x20, 0x3D, 0x95, 0x53, 0x9C, 0xA2, 0xE7, 0x0C, 0xC0, 0xFA, 0x6A, 0xE7,
+            0x73, 0xDC, 0x23, 0xBD, 0x17, 0x04, 0x3A, 0x50, 0x1D, 0xC7, 0x31, 0x44, 0xA8, 0x62,
+            0x59, 0x03, 0x1F, 0xA9, 0x5F, 0x15, 0xC0, 0x8C, 0xD5, 0xAC, 0xFF, 0xF0, 0xA3, 0xD1,
+            0xB0, 0x29, 0xA1, 0x43, 0xC9, 0xB6, 0x01, 0x9F, 0xD0, 0xB3, 0xAB, 0xA0, 0x94, 0xF6,
+            0x7F, 0x40, 0xCE, 0x5E, 0xA1, 0x45, 0xD4, 0x81, 0x37, 0x6A, 0x8E, 0x80, 0x1A, 0x4D,
+            0x49, 0x42, 0x81, 0x98, 0x13, 0xA1, 0x47, 0x52, 0x9A, 0xA6, 0xFC, 0xD5, 0x48, 0x39,
+            0xD7, 0xF0, 0x01, 0x3D, 0x97, 0x02, 0x2A, 0x74, 0x5C, 0xFF, 0xF0, 0x05, 0x31, 0xB7,
+            0x12, 0x3A, 0x14, 0xDD, 0x1B, 0xF0, 0x09, 0x59, 0x8F, 0x0A, 0xCA, 0xA0, 0xF5, 0x43,
+            0xBC, 0xD0, 0xB8, 0x8C, 0xA8, 0x3D, 0x0D, 0x6F, 0x6C, 0xDC, 0x86, 0x38, 0x9D, 0x71,
+            0x88, 0x6B, 0x5E, 0x87, 0x78, 0xDD, 0x79, 0x88, 0x0C, 0xEC, 0x43, 0xFC, 0xDE, 0x40,
+            0x64, 0x34, 0xB4, 0x10, 0x09, 0xFA, 0x0B, 0x91, 0x70, 0x70, 0x21, 0x12, 0x19, 0x16,
+            0x22, 0x68, 0xB3, 0x7E, 0x1A, 0xC5, 0xF6, 0x56,
+        ];
+        let mut expected = Vec::new();
+        for i in 0..40u8 {
+            expected.extend_from_slice(std::format!("frame {} data ", i).as_bytes());
+            expected.extend((0..(usize::from(i) * 7) % 13).map(|_| b'A' + i % 26));
+            expected.push(b'\n');
+        }
+        let mut output = Vec::new();
+        zlib_decompress(&data, &mut output).unwrap();
+        assert_eq!(output, expected);
+        assert!(zlib_decompress(&data[..100], &mut output).is_err());
+    }
+}
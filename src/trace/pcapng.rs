@@ -0,0 +1,221 @@
+//! Writer of pcapng files with the LIN link type, which Wireshark decodes
+//!
+//! ```
+//! use lin_bus::trace::pcapng::PcapngWriter;
+//! use lin_bus::trace::{Direction, TimestampedFrame};
+//! use lin_bus::{Frame, PID};
+//!
+//! let mut writer = PcapngWriter::new(Vec::new()).unwrap();
+//! let frame = Frame::try_from_data(PID::TABLE[0x10], &[0x01, 0x02]).unwrap();
+//! writer
+//!     .write(&TimestampedFrame::from_frame(1_000, Direction::Tx, &frame))
+//!     .unwrap();
+//! let file = writer.into_inner();
+//! ```
+
+use std::io::{self, Write};
+
+use super::{Direction, TimestampedFrame};
+use crate::frame::ChecksumModel;
+use crate::monitor::Verdict;
+
+/// Link type of LIN in pcap and pcapng files
+pub const LINKTYPE_LIN: u16 = 212;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const ENHANCED_PACKET_BLOCK: u32 = 6;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const OPTION_END: u16 = 0;
+const OPTION_IF_TSRESOL: u16 = 9;
+const OPTION_EPB_FLAGS: u16 = 2;
+/// Timestamps in µs
+const TSRESOL_MICROSECONDS: u8 = 6;
+
+/// Revision of the pseudo header preceding the data of a LIN packet
+const LIN_REVISION: u8 = 1;
+const LIN_MESSAGE_TYPE_FRAME: u8 = 0;
+
+/// Error flags of the LIN pseudo header
+pub mod error {
+    pub const NO_SLAVE_RESPONSE: u8 = 0x01;
+    pub const FRAMING: u8 = 0x02;
+    pub const PARITY: u8 = 0x04;
+    pub const CHECKSUM: u8 = 0x08;
+    pub const INVALID_ID: u8 = 0x10;
+    pub const OVERFLOW: u8 = 0x20;
+}
+
+/// Error flags of the pseudo header for `verdict`. An incomplete response is reported as
+/// framing error, like LIN controllers do when the response times out.
+pub fn error_flags(verdict: Verdict) -> u8 {
+    match verdict {
+        Verdict::Valid => 0,
+        Verdict::Checksum => error::CHECKSUM,
+        Verdict::NoResponse => error::NO_SLAVE_RESPONSE,
+        Verdict::Incomplete => error::FRAMING,
+        Verdict::Parity => error::PARITY,
+    }
+}
+
+/// Writer of a pcapng file with one LIN interface
+pub struct PcapngWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Write the section header and the description of the LIN interface
+    pub fn new(mut writer: W) -> io::Result<PcapngWriter<W>> {
+        let mut block = Block::new(SECTION_HEADER_BLOCK);
+        block.u32(BYTE_ORDER_MAGIC);
+        block.u16(1);
+        block.u16(0);
+        // The length of the section is not known in advance
+        block.bytes(&(-1i64).to_le_bytes());
+        block.write(&mut writer)?;
+
+        let mut block = Block::new(INTERFACE_DESCRIPTION_BLOCK);
+        block.u16(LINKTYPE_LIN);
+        block.u16(0);
+        // No snapshot length limit
+        block.u32(0);
+        block.option(OPTION_IF_TSRESOL, &[TSRESOL_MICROSECONDS]);
+        block.option(OPTION_END, &[]);
+        block.write(&mut writer)?;
+        Ok(PcapngWriter { writer })
+    }
+
+    /// Write a record as enhanced packet block
+    pub fn write(&mut self, record: &TimestampedFrame) -> io::Result<()> {
+        let data = record.data();
+        let checksum_type = match record.checksum_model {
+            ChecksumModel::Classic => 0,
+            ChecksumModel::Enhanced => 1,
+        };
+        let mut packet = [0u8; 16];
+        packet[0] = LIN_REVISION;
+        packet[4] = (data.len() as u8) << 4 | LIN_MESSAGE_TYPE_FRAME << 2 | checksum_type;
+        packet[5] = record.pid.get();
+        packet[6] = record.checksum().unwrap_or(0);
+        packet[7] = error_flags(record.verdict);
+        packet[8..8 + data.len()].copy_from_slice(data);
+        let packet = &packet[..8 + data.len()];
+
+        let mut block = Block::new(ENHANCED_PACKET_BLOCK);
+        // Interface ID
+        block.u32(0);
+        block.u32((record.timestamp_us >> 32) as u32);
+        block.u32(record.timestamp_us as u32);
+        block.u32(packet.len() as u32);
+        block.u32(packet.len() as u32);
+        block.padded(packet);
+        let flags: u32 = match record.direction {
+            Direction::Rx => 1,
+            Direction::Tx => 2,
+        };
+        block.option(OPTION_EPB_FLAGS, &flags.to_le_bytes());
+        block.option(OPTION_END, &[]);
+        block.write(&mut self.writer)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Block under construction, the body is small enough for a fixed buffer
+struct Block {
+    block_type: u32,
+    body: [u8; 64],
+    length: usize,
+}
+
+impl Block {
+    fn new(block_type: u32) -> Block {
+        Block {
+            block_type,
+            body: [0u8; 64],
+            length: 0,
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.body[self.length..self.length + bytes.len()].copy_from_slice(bytes);
+        self.length += bytes.len();
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    /// Append `bytes` padded to 32 bits
+    fn padded(&mut self, bytes: &[u8]) {
+        self.bytes(bytes);
+        self.length = (self.length + 3) & !3;
+    }
+
+    fn option(&mut self, code: u16, value: &[u8]) {
+        self.u16(code);
+        self.u16(value.len() as u16);
+        self.padded(value);
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let total = (self.length + 12) as u32;
+        writer.write_all(&self.block_type.to_le_bytes())?;
+        writer.write_all(&total.to_le_bytes())?;
+        writer.write_all(&self.body[..self.length])?;
+        writer.write_all(&total.to_le_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Frame, PID};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_pcapng() {
+        let mut writer = PcapngWriter::new(Vec::new()).unwrap();
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02, 0x03]);
+        let record = TimestampedFrame::from_frame(0x1_0000_0002, Direction::Rx, &frame);
+        writer.write(&record).unwrap();
+        let file = writer.into_inner();
+
+        let (header, rest) = file.split_at(28);
+        assert_eq!(
+            &header[..12],
+            &[0x0A, 0x0D, 0x0D, 0x0A, 28, 0, 0, 0, 0x4D, 0x3C, 0x2B, 0x1A]
+        );
+        let (interface, packet) = rest.split_at(32);
+        assert_eq!(&interface[8..10], &[212, 0]);
+        assert_eq!(&interface[16..24], &[9, 0, 1, 0, 6, 0, 0, 0]);
+
+        assert_eq!(packet.len(), 56);
+        assert_eq!(&packet[..8], &[6, 0, 0, 0, 56, 0, 0, 0]);
+        assert_eq!(&packet[12..20], &[1, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(&packet[20..28], &[11, 0, 0, 0, 11, 0, 0, 0]);
+        assert_eq!(
+            &packet[28..40],
+            &[1, 0, 0, 0, 0x31, 0x50, frame.get_checksum(), 0, 1, 2, 3, 0]
+        );
+        assert_eq!(&packet[40..48], &[2, 0, 4, 0, 1, 0, 0, 0]);
+        assert_eq!(&packet[52..], &[56, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_error_flags() {
+        assert_eq!(error_flags(Verdict::Valid), 0);
+        assert_eq!(error_flags(Verdict::NoResponse), error::NO_SLAVE_RESPONSE);
+        assert_eq!(error_flags(Verdict::Checksum), error::CHECKSUM);
+    }
+}
@@ -0,0 +1,242 @@
+//! Replay of recorded traffic through a `Master`, e.g. to reproduce a field issue against real
+//! slaves on the bench
+//!
+//! The events of a trace, e.g. read with `asc::AscReader` or `blf::BlfReader`, are replayed one
+//! by one with `Replay::step`, which returns the delay until the next event like
+//! `schedule::Scheduler::run_slot`, so the caller waits with the timer of its platform:
+//!
+//! * A frame published by the tracing node is written with the recorded data and checksum,
+//!   so also corrupted checksums are reproduced
+//! * For a frame received from a slave, the header is sent and the response is returned for
+//!   the comparison with the recorded one
+//! * A sent wakeup signal is sent again, other events are skipped
+//!
+//! ```
+//! use lin_bus::trace::replay::Replay;
+//! use lin_bus::trace::{Direction, Event, TimestampedFrame};
+//! use lin_bus::{Frame, Master, PID};
+//!
+//! fn replay<M: Master>(master: &mut M) -> Result<(), M::Error> {
+//!     let frame = Frame::try_from_data(PID::TABLE[0x10], &[0x01, 0x02]).unwrap();
+//!     let events = [
+//!         Event::Frame(TimestampedFrame::from_frame(0, Direction::Tx, &frame)),
+//!         Event::Frame(TimestampedFrame::from_frame(10_000, Direction::Tx, &frame)),
+//!     ];
+//!     // Replay twice as fast as recorded
+//!     let mut replay = Replay::new(events).with_speed_percent(200);
+//!     while let Some(step) = replay.step(master)? {
+//!         // Wait for `step.delay_us`
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use super::{Direction, Event, TimestampedFrame};
+use crate::cluster::ClusterConfig;
+use crate::iso17987;
+use crate::master::{Master, Response};
+
+/// Result of replaying one event
+#[derive(Debug, PartialEq, Eq)]
+pub struct Step {
+    /// The replayed event
+    pub event: Event,
+    /// The response to the header of a received frame
+    pub response: Option<Response>,
+    /// Delay until the next event is due in µs, scaled by the speed
+    pub delay_us: u64,
+}
+
+/// Replay of a sequence of events, see the module documentation
+#[derive(Clone, Debug)]
+pub struct Replay<'a, I: Iterator<Item = Event>> {
+    events: core::iter::Peekable<I>,
+    cluster: Option<ClusterConfig<'a>>,
+    speed_percent: u32,
+}
+
+impl<'a, I: Iterator<Item = Event>> Replay<'a, I> {
+    pub fn new<E: IntoIterator<IntoIter = I>>(events: E) -> Replay<'a, I> {
+        Replay {
+            events: events.into_iter().peekable(),
+            cluster: None,
+            speed_percent: 100,
+        }
+    }
+
+    /// Take the expected data length of received frames from `cluster`. Otherwise the length is
+    /// that of the recorded response, which is too short for incomplete responses, except for
+    /// frames with a length mandated by the standard.
+    pub fn with_cluster(self, cluster: ClusterConfig<'a>) -> Replay<'a, I> {
+        Replay {
+            cluster: Some(cluster),
+            ..self
+        }
+    }
+
+    /// Scale the timing, 100 replays the original timing, 200 twice as fast and 0 without
+    /// delays
+    pub fn with_speed_percent(self, speed_percent: u32) -> Replay<'a, I> {
+        Replay {
+            speed_percent,
+            ..self
+        }
+    }
+
+    /// Replay the next event, `None` if all events are replayed. Errors of the master, e.g. a
+    /// checksum error of the response, are returned after the event is consumed, so the replay
+    /// can continue with the next event.
+    pub fn step<M: Master + ?Sized>(&mut self, master: &mut M) -> Result<Option<Step>, M::Error> {
+        let event = match self.events.next() {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        let delay_us = match self.events.peek() {
+            Some(next) if self.speed_percent > 0 => {
+                let delay = next.timestamp_us().saturating_sub(event.timestamp_us());
+                delay.saturating_mul(100) / u64::from(self.speed_percent)
+            }
+            _ => 0,
+        };
+        let response = match &event {
+            Event::Frame(record) => self.replay_frame(master, record)?,
+            Event::Wakeup {
+                direction: Direction::Tx,
+                ..
+            } => {
+                master.send_wakeup()?;
+                None
+            }
+            Event::Wakeup { .. } | Event::Sleep { .. } => None,
+        };
+        Ok(Some(Step {
+            event,
+            response,
+            delay_us,
+        }))
+    }
+
+    fn replay_frame<M: Master + ?Sized>(
+        &self,
+        master: &mut M,
+        record: &TimestampedFrame,
+    ) -> Result<Option<Response>, M::Error> {
+        let pid = record.pid;
+        if let (Direction::Tx, Some(checksum)) = (record.direction, record.checksum()) {
+            master.write_payload(pid, record.data(), checksum)?;
+            return Ok(None);
+        }
+        let id = pid.get_id();
+        let data_length = self
+            .cluster
+            .and_then(|cluster| cluster.data_length(id))
+            .or_else(|| iso17987::known_format(id).map(|(length, _)| length))
+            .unwrap_or_else(|| record.data().len());
+        let response = if record.checksum_model == pid.checksum_model() {
+            master.read_response(pid, data_length)?
+        } else {
+            let frame =
+                master.read_frame_with_checksum_model(pid, data_length, record.checksum_model)?;
+            Response::Complete(frame)
+        };
+        Ok(Some(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::loopback::Loopback;
+    use crate::driver::Master as _;
+    use crate::master::PartialResponse;
+    use crate::monitor::Verdict;
+    use crate::{ChecksumModel, Frame, PID};
+
+    #[test]
+    fn test_replay() {
+        let written = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        let received = Frame::from_data(PID::from_id(0x11), &[0x03, 0x04]);
+        let corrupted = TimestampedFrame {
+            response: [0x01, 0x02, 0x00, 0, 0, 0, 0, 0, 0],
+            verdict: Verdict::Checksum,
+            ..TimestampedFrame::from_frame(3_000, Direction::Tx, &written)
+        };
+        let events = [
+            Event::Frame(TimestampedFrame::from_frame(1_000, Direction::Tx, &written)),
+            Event::Frame(TimestampedFrame::from_frame(
+                2_000,
+                Direction::Rx,
+                &received,
+            )),
+            Event::Frame(corrupted),
+            Event::Wakeup {
+                timestamp_us: 7_000,
+                direction: Direction::Tx,
+            },
+            Event::Frame(TimestampedFrame {
+                verdict: Verdict::Incomplete,
+                length: 1,
+                ..TimestampedFrame::from_frame(8_000, Direction::Rx, &received)
+            }),
+        ];
+        let mut driver = Loopback::new();
+        let cluster_frames = [crate::cluster::FrameConfig::new(
+            0x11,
+            2,
+            crate::cluster::Publisher::Slave(0),
+        )];
+        let mut replay = Replay::new(events)
+            .with_cluster(ClusterConfig::new(&cluster_frames))
+            .with_speed_percent(50);
+
+        let step = replay.step(&mut driver).unwrap().unwrap();
+        assert_eq!(step.delay_us, 2_000);
+        assert_eq!(step.response, None);
+        assert_eq!(driver.pending(), written.get_data_with_checksum());
+        driver.read(&mut [0; 3]).unwrap();
+
+        driver.set_response(received.get_pid(), received.get_data());
+        let step = replay.step(&mut driver).unwrap().unwrap();
+        assert_eq!(step.response, Some(Response::Complete(received)));
+
+        replay.step(&mut driver).unwrap().unwrap();
+        assert_eq!(driver.pending(), &[0x01, 0x02, 0x00]);
+        driver.read(&mut [0; 3]).unwrap();
+
+        let step = replay.step(&mut driver).unwrap().unwrap();
+        assert_eq!(step.delay_us, 2_000);
+        assert_eq!(driver.wakeups(), 1);
+
+        driver.set_raw_response(PID::from_id(0x11), &[0x03]);
+        let step = replay.step(&mut driver).unwrap().unwrap();
+        assert_eq!(step.delay_us, 0);
+        assert_eq!(
+            step.response,
+            Some(Response::Partial(PartialResponse::new(
+                PID::from_id(0x11),
+                &[0x03]
+            )))
+        );
+        assert_eq!(replay.step(&mut driver).unwrap(), None);
+    }
+
+    #[test]
+    fn test_checksum_model() {
+        let frame = Frame::from_data_with_checksum_model(
+            PID::from_id(0x12),
+            &[0x05],
+            ChecksumModel::Classic,
+        );
+        let mut driver = Loopback::new();
+        driver.set_response_with_checksum_model(frame.get_pid(), &[0x05], ChecksumModel::Classic);
+        let mut replay = Replay::new([Event::Frame(TimestampedFrame::from_frame(
+            0,
+            Direction::Rx,
+            &frame,
+        ))])
+        .with_speed_percent(0);
+        let step = replay.step(&mut driver).unwrap().unwrap();
+        assert_eq!(step.response, Some(Response::Complete(frame)));
+        assert_eq!(step.delay_us, 0);
+    }
+}
@@ -0,0 +1,290 @@
+//! Bus load, frame rates, response times and error ratios of a stream of records
+//!
+//! `Statistics` is an accumulator without allocations, so it runs on the node itself, fed
+//! with the frames of the master or a `monitor::BusMonitor`, as well as on a host analyzing an
+//! imported trace. Records must be pushed in the order of their timestamps.
+//!
+//! ```
+//! use lin_bus::ldf::LinSpeed;
+//! use lin_bus::trace::statistics::Statistics;
+//! use lin_bus::trace::{Direction, TimestampedFrame};
+//! use lin_bus::{Frame, PID};
+//!
+//! let frame = Frame::try_from_data(PID::TABLE[0x10], &[0x01, 0x02]).unwrap();
+//! let mut statistics = Statistics::new(LinSpeed(19200));
+//! for i in 0..10 {
+//!     statistics.push(&TimestampedFrame::from_frame(i * 10_000, Direction::Rx, &frame));
+//! }
+//! assert_eq!(statistics.id(0x10).period_us(), Some(10_000));
+//! // A frame of 3.3 ms every 10 ms
+//! let load = statistics.bus_load().unwrap();
+//! assert!(load > 0.3 && load < 0.4);
+//! ```
+
+use super::TimestampedFrame;
+use crate::iso17987::timing;
+use crate::ldf::LinSpeed;
+use crate::master::FrameTimestamps;
+use crate::monitor::Verdict;
+
+/// Minimum, average and maximum of the measured frame times of an ID in µs
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ResponseTimes {
+    pub min_us: u64,
+    pub avg_us: u64,
+    pub max_us: u64,
+}
+
+/// Statistics of the frames of one ID
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct IdStatistics {
+    frames: u32,
+    /// Frames per `Verdict`, in the order of the variants
+    verdicts: [u32; 5],
+    first_us: u64,
+    last_us: u64,
+    timed: u32,
+    total_time_us: u64,
+    min_time_us: u64,
+    max_time_us: u64,
+}
+
+impl IdStatistics {
+    /// Number of headers of the ID
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Number of frames with the given verdict
+    pub fn frames_with(&self, verdict: Verdict) -> u32 {
+        self.verdicts[verdict_index(verdict)]
+    }
+
+    /// Number of frames without a valid response
+    pub fn errors(&self) -> u32 {
+        self.frames - self.frames_with(Verdict::Valid)
+    }
+
+    /// Share of the frames without a valid response, 0 without frames
+    pub fn error_ratio(&self) -> f32 {
+        ratio(self.errors(), self.frames)
+    }
+
+    /// Average time between two headers of the ID, `None` before the second frame
+    pub fn period_us(&self) -> Option<u64> {
+        match self.frames {
+            0 | 1 => None,
+            frames => Some((self.last_us - self.first_us) / u64::from(frames - 1)),
+        }
+    }
+
+    /// The measured frame times, `None` if no frame was pushed with timestamps
+    pub fn response_times(&self) -> Option<ResponseTimes> {
+        match self.timed {
+            0 => None,
+            timed => Some(ResponseTimes {
+                min_us: self.min_time_us,
+                avg_us: self.total_time_us / u64::from(timed),
+                max_us: self.max_time_us,
+            }),
+        }
+    }
+}
+
+/// Incremental statistics of the bus traffic, see the module documentation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Statistics {
+    speed: LinSpeed,
+    ids: [IdStatistics; 64],
+    first_us: Option<u64>,
+    /// End of the last frame
+    end_us: u64,
+    busy_us: u64,
+}
+
+impl Statistics {
+    /// Statistics of a cluster with the bit rate `speed`, which is used for the nominal time
+    /// of frames pushed without timestamps
+    pub fn new(speed: LinSpeed) -> Statistics {
+        Statistics {
+            speed,
+            ids: [IdStatistics::default(); 64],
+            first_us: None,
+            end_us: 0,
+            busy_us: 0,
+        }
+    }
+
+    /// Add a record, the bus is assumed to be busy for the nominal time of the header and the
+    /// response
+    pub fn push(&mut self, record: &TimestampedFrame) {
+        let frame_time =
+            timing::header_time(self.speed) + timing::bytes(record.response().len(), self.speed);
+        self.add(record, frame_time.as_micros());
+    }
+
+    /// Add a record with the timestamps measured by the driver, e.g. of
+    /// `master::TimestampedMaster`. Without both timestamps the nominal time is used like in
+    /// `push`.
+    pub fn push_with_timestamps(
+        &mut self,
+        record: &TimestampedFrame,
+        timestamps: &FrameTimestamps,
+    ) {
+        let frame_time_us = match timestamps.frame_time() {
+            Some(frame_time) => frame_time.as_micros(),
+            None => return self.push(record),
+        };
+        self.add(record, frame_time_us);
+        let id = &mut self.ids[usize::from(record.pid.get_id())];
+        if id.timed == 0 || frame_time_us < id.min_time_us {
+            id.min_time_us = frame_time_us;
+        }
+        id.max_time_us = id.max_time_us.max(frame_time_us);
+        id.total_time_us += frame_time_us;
+        id.timed += 1;
+    }
+
+    fn add(&mut self, record: &TimestampedFrame, frame_time_us: u64) {
+        let start = record.timestamp_us;
+        self.first_us.get_or_insert(start);
+        self.end_us = self.end_us.max(start + frame_time_us);
+        self.busy_us += frame_time_us;
+
+        let id = &mut self.ids[usize::from(record.pid.get_id())];
+        if id.frames == 0 {
+            id.first_us = start;
+        }
+        id.last_us = start;
+        id.frames += 1;
+        id.verdicts[verdict_index(record.verdict)] += 1;
+    }
+
+    /// Statistics of the frames with the ID `id`, IDs above 63 are masked
+    pub fn id(&self, id: u8) -> &IdStatistics {
+        &self.ids[usize::from(id & 0x3F)]
+    }
+
+    /// Statistics of all IDs with at least one frame
+    pub fn ids(&self) -> impl Iterator<Item = (u8, &IdStatistics)> {
+        (0u8..)
+            .zip(self.ids.iter())
+            .filter(|(_, statistics)| statistics.frames > 0)
+    }
+
+    /// Number of frames of all IDs
+    pub fn frames(&self) -> u32 {
+        self.ids.iter().map(|id| id.frames).sum()
+    }
+
+    /// Number of frames without a valid response of all IDs
+    pub fn errors(&self) -> u32 {
+        self.ids.iter().map(IdStatistics::errors).sum()
+    }
+
+    /// Share of the frames without a valid response, 0 without frames
+    pub fn error_ratio(&self) -> f32 {
+        ratio(self.errors(), self.frames())
+    }
+
+    /// Time from the start of the first to the end of the last frame in µs
+    pub fn duration_us(&self) -> u64 {
+        self.first_us.map_or(0, |first| self.end_us - first)
+    }
+
+    /// Share of the time the bus was busy with frames, `None` without frames
+    pub fn bus_load(&self) -> Option<f32> {
+        match self.duration_us() {
+            0 => None,
+            duration => Some(self.busy_us as f32 / duration as f32),
+        }
+    }
+
+    /// Forget all frames, e.g. to start a new measurement interval
+    pub fn reset(&mut self) {
+        *self = Statistics::new(self.speed);
+    }
+}
+
+fn verdict_index(verdict: Verdict) -> usize {
+    match verdict {
+        Verdict::Valid => 0,
+        Verdict::Checksum => 1,
+        Verdict::NoResponse => 2,
+        Verdict::Incomplete => 3,
+        Verdict::Parity => 4,
+    }
+}
+
+fn ratio(count: u32, total: u32) -> f32 {
+    match total {
+        0 => 0.0,
+        total => count as f32 / total as f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::Instant;
+    use crate::trace::Direction;
+    use crate::{Frame, PID};
+
+    #[test]
+    fn test_statistics() {
+        let mut statistics = Statistics::new(LinSpeed(20000));
+        assert_eq!(statistics.bus_load(), None);
+        let frame = Frame::from_data(PID::from_id(0x10), &[0x01, 0x02]);
+        // 34 header bits and 3 bytes of 10 bits at 20 kbit/s
+        for i in 0..4 {
+            statistics.push(&TimestampedFrame::from_frame(
+                i * 10_000,
+                Direction::Rx,
+                &frame,
+            ));
+        }
+        let missing = TimestampedFrame {
+            verdict: Verdict::NoResponse,
+            length: 0,
+            ..TimestampedFrame::from_frame(40_000, Direction::Rx, &frame)
+        };
+        statistics.push(&missing);
+
+        let id = statistics.id(0x10);
+        assert_eq!(id.frames(), 5);
+        assert_eq!(id.frames_with(Verdict::NoResponse), 1);
+        assert_eq!(id.error_ratio(), 0.2);
+        assert_eq!(id.period_us(), Some(10_000));
+        assert_eq!(id.response_times(), None);
+        assert_eq!(statistics.duration_us(), 40_000 + 1_700);
+        assert_eq!(
+            statistics.bus_load(),
+            Some((4 * 3_200 + 1_700) as f32 / 41_700.0)
+        );
+        assert_eq!(statistics.ids().count(), 1);
+
+        let timestamps = |start, end| FrameTimestamps {
+            header_start: Some(Instant::from_micros(start)),
+            response_end: Some(Instant::from_micros(end)),
+        };
+        let frame = Frame::from_data(PID::from_id(0x20), &[0x01]);
+        let record = TimestampedFrame::from_frame(50_000, Direction::Tx, &frame);
+        statistics.push_with_timestamps(&record, &timestamps(50_000, 53_000));
+        let record = TimestampedFrame::from_frame(60_000, Direction::Tx, &frame);
+        statistics.push_with_timestamps(&record, &timestamps(60_000, 62_000));
+        assert_eq!(
+            statistics.id(0x20).response_times(),
+            Some(ResponseTimes {
+                min_us: 2_000,
+                avg_us: 2_500,
+                max_us: 3_000
+            })
+        );
+        assert_eq!(statistics.frames(), 7);
+        assert_eq!(statistics.errors(), 1);
+        assert_eq!(statistics.duration_us(), 62_000);
+
+        statistics.reset();
+        assert_eq!(statistics.frames(), 0);
+    }
+}